@@ -0,0 +1,176 @@
+//! Finding values that most tracks of an album share, to fill in on the tracks
+//! that are missing them.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::path::PathBuf;
+
+use crate::consts::FIELD_SEP_STR;
+use crate::consts::GROUP_BY_KEY;
+use crate::data::Record;
+
+/// A value that most tracks of an album have for a field, and the files of
+/// the tracks without the field.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct AlbumFill {
+    /// The album the tracks are on.
+    pub album: String,
+    /// The metadata key of the field.
+    pub meta_key: String,
+    /// The values to fill in.
+    pub values: Vec<String>,
+    /// The files of the tracks that are missing the field.
+    pub file_paths: Vec<PathBuf>,
+}
+
+impl Display for AlbumFill {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f, "{}: {} = {}, on {} track(s)",
+            self.album, self.meta_key, self.values.join(FIELD_SEP_STR), self.file_paths.len(),
+        )
+    }
+}
+
+/// The values of a field of a record, with the key spelled in any case, and
+/// the key as the record spells it.
+fn get_meta_any_case<'a>(record: &'a Record, meta_key: &str) -> Option<(&'a str, &'a [String])> {
+    let found = record.meta_keys().find(|k| k.eq_ignore_ascii_case(meta_key))?;
+    record.get_meta(found).map(|values| (found, values))
+}
+
+/// Works out the fills for the albums of some records. A value is shared
+/// when more than half of the tracks of an album have exactly it, and it is
+/// only filled into tracks without the field, leaving other values alone.
+/// Keys are matched whatever their case, and filled in as most tracks with
+/// the value spell them. Read-only tracks are left out.
+pub fn plan(records: &[Record], indices: &[usize]) -> Vec<AlbumFill> {
+    let mut albums = Vec::<&[String]>::new();
+
+    for album in indices.iter().filter_map(|&i| records[i].get_meta(GROUP_BY_KEY)) {
+        if !albums.contains(&album) {
+            albums.push(album);
+        }
+    }
+
+    let mut fills = Vec::new();
+
+    for album in albums {
+        let tracks = records.iter().filter(|r| r.get_meta(GROUP_BY_KEY) == Some(album)).collect::<Vec<_>>();
+
+        let mut meta_keys =
+            tracks.iter()
+            .flat_map(|r| r.meta_keys())
+            .map(str::to_ascii_uppercase)
+            .filter(|k| k != GROUP_BY_KEY)
+            .collect::<Vec<_>>()
+        ;
+        meta_keys.sort_unstable();
+        meta_keys.dedup();
+
+        for meta_key in meta_keys {
+            // Each value, with how many tracks have it under each spelling of the key.
+            let mut counts = Vec::<(&[String], Vec<(&str, usize)>)>::new();
+
+            for (spelling, values) in tracks.iter().filter_map(|r| get_meta_any_case(r, &meta_key)) {
+                let spellings = match counts.iter_mut().find(|(vs, _)| *vs == values) {
+                    Some((_, spellings)) => spellings,
+                    None => {
+                        counts.push((values, Vec::new()));
+                        &mut counts.last_mut().unwrap().1
+                    },
+                };
+
+                match spellings.iter_mut().find(|(s, _)| *s == spelling) {
+                    Some((_, count)) => *count += 1,
+                    None => spellings.push((spelling, 1)),
+                }
+            }
+
+            let total = |spellings: &[(&str, usize)]| spellings.iter().map(|(_, count)| count).sum::<usize>();
+
+            let (values, spellings) = match counts.into_iter().max_by_key(|(_, spellings)| total(spellings)) {
+                Some(most) => most,
+                None => continue,
+            };
+
+            if total(&spellings) * 2 <= tracks.len() {
+                continue;
+            }
+
+            let spelling = spellings.iter().max_by_key(|(_, count)| *count).map_or(meta_key.as_str(), |(s, _)| *s);
+
+            let file_paths =
+                tracks.iter()
+                .filter(|r| !r.read_only && get_meta_any_case(r, &meta_key).is_none())
+                .map(|r| r.file_path.clone())
+                .collect::<Vec<_>>()
+            ;
+
+            if !file_paths.is_empty() {
+                fills.push(AlbumFill {
+                    album: album.join(FIELD_SEP_STR),
+                    meta_key: spelling.to_string(),
+                    values: values.to_vec(),
+                    file_paths,
+                });
+            }
+        }
+    }
+
+    fills
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    fn track(n: usize, tags: &[(&str, &str)]) -> Record {
+        let mut metadata = hashmap! { str!("ALBUM") => vec![str!("Album")] };
+
+        for (meta_key, value) in tags {
+            metadata.insert(meta_key.to_string(), vec![value.to_string()]);
+        }
+
+        Record::new(metadata, PathBuf::from(format!("{:02}.flac", n)))
+    }
+
+    #[test]
+    fn plan() {
+        let records = vec![
+            track(1, &[("DATE", "2001"), ("GENRE", "Rock"), ("LABEL", "A")]),
+            track(2, &[("DATE", "2001"), ("GENRE", "Rock"), ("LABEL", "B")]),
+            track(3, &[("DATE", "2001"), ("GENRE", "Pop")]),
+            track(4, &[("Genre", "Rock")]),
+            track(5, &[]),
+        ];
+
+        let fills = super::plan(&records, &[0]);
+
+        // Keys match whatever their case, and other values are kept.
+        assert_eq!(fills, vec![
+            AlbumFill {
+                album: str!("Album"),
+                meta_key: str!("DATE"),
+                values: vec![str!("2001")],
+                file_paths: vec![PathBuf::from("04.flac"), PathBuf::from("05.flac")],
+            },
+            AlbumFill {
+                album: str!("Album"),
+                meta_key: str!("GENRE"),
+                values: vec![str!("Rock")],
+                file_paths: vec![PathBuf::from("05.flac")],
+            },
+        ]);
+        assert_eq!(fills[0].to_string(), "Album: DATE = 2001, on 2 track(s)");
+
+        let mut read_only = records;
+        read_only[3].read_only = true;
+        read_only[4].read_only = true;
+        assert!(super::plan(&read_only, &[0, 1]).is_empty());
+    }
+}
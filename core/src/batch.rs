@@ -0,0 +1,232 @@
+//! Editing and reading the tags of a library from the command line, without
+//! the terminal UI.
+
+use std::borrow::Cow;
+
+use unicode_width::UnicodeWidthStr;
+
+use crate::clipboard::row_json;
+use crate::clipboard::row_tsv;
+use crate::data::Align;
+use crate::data::Column;
+use crate::data::ColumnKey;
+use crate::consts::ELLIPSIS_STR;
+use crate::data::InfoKind;
+use crate::data::Record;
+use crate::data::Sizing;
+use crate::filter::Filter;
+use crate::model::Model;
+use crate::script::TagChanges;
+use crate::util::TrimStatus;
+use crate::util::Util;
+
+/// What columns are separated by in printed tables.
+const TABLE_SEP: &str = "  ";
+
+/// Reads `KEY=value` assignments given on the command line. A key given more
+/// than once gets each value, and `KEY=` removes the tag.
+pub fn parse_assignments(args: &[String]) -> Result<TagChanges, String> {
+    let mut changes: TagChanges = Vec::new();
+
+    for arg in args {
+        let (meta_key, value) = arg.split_once('=').ok_or_else(|| format!("expected KEY=value, got: {}", arg))?;
+        let meta_key = meta_key.trim().to_uppercase();
+
+        if meta_key.is_empty() {
+            return Err(format!("expected a tag name before '=' in: {}", arg));
+        }
+
+        let values = match changes.iter_mut().find(|(k, _)| *k == meta_key) {
+            Some((_, values)) => values,
+            None => {
+                changes.push((meta_key, None));
+                &mut changes.last_mut().unwrap().1
+            },
+        };
+
+        if !value.is_empty() {
+            values.get_or_insert_with(Vec::new).push(value.to_string());
+        }
+    }
+
+    Ok(changes)
+}
+
+/// The indices of the records that match a filter, or of all records.
+fn matching_indices(model: &Model, filter: Option<&Filter>) -> Vec<usize> {
+    model.data.records.iter().enumerate()
+    .filter(|(_, record)| filter.is_none_or(|filter| filter.matches(record)))
+    .map(|(i, _)| i)
+    .collect()
+}
+
+/// Stages tag changes on the records that match a filter, as one edit.
+/// Returns how many records changed out of how many matched.
+pub fn set(model: &mut Model, changes: &TagChanges, filter: Option<&Filter>) -> (usize, usize) {
+    let indices = matching_indices(model, filter);
+
+    let edited = model.edit_records("Set tags", |records| {
+        indices.iter()
+        .filter(|&&i| {
+            changes.iter().fold(false, |changed, (meta_key, values)| records[i].set_meta(meta_key, values.clone()) | changed)
+        })
+        .count()
+    });
+
+    (edited, indices.len())
+}
+
+/// One line for each record that matches a filter, with the path of its file
+/// followed by the values of some tags, either tab-separated or as a JSON
+/// object.
+pub fn get(model: &Model, meta_keys: &[String], filter: Option<&Filter>, json: bool) -> Vec<String> {
+    let column = |key: ColumnKey, title: &str| Column {
+        key,
+        title: title.to_string(),
+        sizing: Sizing::Auto,
+        align: Align::default(),
+        kind: None,
+        separator: None,
+    };
+
+    let columns =
+        std::iter::once(column(ColumnKey::Info(InfoKind::FilePath), "Path"))
+        .chain(meta_keys.iter().map(|meta_key| column(ColumnKey::Meta(meta_key.to_uppercase()), meta_key)))
+        .collect::<Vec<_>>()
+    ;
+
+    matching_indices(model, filter).into_iter()
+    .map(|i| {
+        let record = &model.data.records[i];
+        if json { row_json(record, &columns) } else { row_tsv(record, &columns) }
+    })
+    .collect()
+}
+
+/// Fits a value into a column of a width, cutting it off with an ellipsis
+/// if it is too wide.
+fn fit(text: &str, width: usize, align: Align) -> String {
+    let trim_output = Util::trim_display_str_elided(text, width, ELLIPSIS_STR.width());
+
+    let fitted = match trim_output.trim_status {
+        TrimStatus::Untrimmed => {
+            let offset = align.offset(trim_output.full_real_width, width);
+            format!("{}{}", " ".repeat(offset), text)
+        },
+        TrimStatus::Trimmed(padding, emit_ellipsis) => {
+            let ellipsis = if emit_ellipsis { ELLIPSIS_STR } else { "" };
+            format!("{}{}{}", trim_output.display_str, " ".repeat(padding), ellipsis)
+        },
+    };
+
+    let padding = width.saturating_sub(fitted.width());
+    format!("{}{}", fitted, " ".repeat(padding))
+}
+
+/// The text of a cell as shown in a table, with multiple values on one line.
+fn cell_display(record: &Record, column: &Column) -> String {
+    match &column.key {
+        ColumnKey::Meta(meta_key) => record.get_meta(meta_key).map(|vals| vals.join(column.separator())),
+        ColumnKey::Info(info_kind) => record.get_info(info_kind).map(Cow::into_owned),
+        ColumnKey::Command(command) => record.get_command_output(command).map(String::from),
+        ColumnKey::Computed(template) => record.get_computed(template),
+    }
+    .unwrap_or_default()
+    .replace(['\n', '\r'], " ")
+}
+
+/// The records under the configured columns, as lines of a plain text table
+/// sized the same way as in the UI, or as one JSON object per record. Column
+/// commands are run first, and waited for.
+pub fn print_table(model: &mut Model, json: bool) -> Vec<String> {
+    let jobs = model.take_command_jobs();
+    let outputs = jobs.into_iter().map(|job| { let output = job.run(); (job, output) }).collect();
+    model.set_command_outputs(outputs);
+
+    if json {
+        return model.data.records.iter().map(|record| row_json(record, &model.data.columns)).collect();
+    }
+
+    model.recache();
+    let widths = model.cached_content_widths.clone();
+    let columns = &model.data.columns;
+
+    let line = |cells: Vec<String>| cells.join(TABLE_SEP).trim_end().to_string();
+
+    let header = line(columns.iter().zip(&widths).map(|(column, &width)| fit(&column.title, width, column.align)).collect());
+    let rule = line(widths.iter().map(|&width| "-".repeat(width)).collect());
+
+    let rows = model.data.records.iter().map(|record| {
+        line(columns.iter().zip(&widths).map(|(column, &width)| fit(&cell_display(record, column), width, column.align)).collect())
+    });
+
+    vec![header, rule].into_iter().chain(rows).collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use str_macro::str;
+
+    use crate::config::Config;
+    use crate::fixtures::MemorySource;
+
+    fn demo_model() -> Model {
+        Model::load(Config::default().columns, Box::new(MemorySource::demo())).unwrap()
+    }
+
+    #[test]
+    fn assignments() {
+        let args = [str!("artist=A"), str!("GENRE=Rock"), str!("ARTIST=B"), str!("comment=")];
+
+        assert_eq!(
+            parse_assignments(&args),
+            Ok(vec![
+                (str!("ARTIST"), Some(vec![str!("A"), str!("B")])),
+                (str!("GENRE"), Some(vec![str!("Rock")])),
+                (str!("COMMENT"), None),
+            ]),
+        );
+        assert!(parse_assignments(&[str!("ARTIST")]).is_err());
+        assert!(parse_assignments(&[str!("=A")]).is_err());
+    }
+
+    #[test]
+    fn setting_and_getting() {
+        let mut model = demo_model();
+        let filter = "TITLE~second".parse::<Filter>().unwrap();
+        let changes = parse_assignments(&[str!("GENRE=Jazz")]).unwrap();
+
+        assert_eq!(set(&mut model, &changes, Some(&filter)), (4, 4));
+        assert_eq!(set(&mut model, &changes, Some(&filter)), (0, 4));
+
+        let filter = "GENRE=Jazz".parse::<Filter>().unwrap();
+        let lines = get(&model, &[str!("genre"), str!("DATE")], Some(&filter), false);
+
+        assert_eq!(lines.len(), 4);
+        assert!(lines.iter().all(|line| line.split('\t').nth(1) == Some("Jazz")));
+
+        let lines = get(&model, &[str!("GENRE")], Some(&filter), true);
+        assert!(lines[0].contains("\"GENRE\":[\"Jazz\"]"));
+        assert_eq!(get(&model, &[], None, false).len(), model.data.records.len());
+    }
+
+    #[test]
+    fn printing() {
+        let mut model = demo_model();
+        model.data.columns.truncate(2);
+        model.data.columns[0].sizing = Sizing::Fixed(6);
+        model.data.columns[1].align = Align::Right;
+
+        let lines = print_table(&mut model, false);
+
+        assert_eq!(lines.len(), model.data.records.len() + 2);
+        assert_eq!(lines[0], "Artist                   Title");
+        assert_eq!(lines[1], "------  ----------------------");
+        assert_eq!(lines[2], "Artis⋯     Song 1 of Album One");
+
+        let lines = print_table(&mut model, true);
+        assert!(lines[0].starts_with("{\"ARTIST\":[\"Artist A\"]"));
+    }
+}
@@ -0,0 +1,235 @@
+//! Copying cell values to the system clipboard and pasting them back.
+
+use std::borrow::Cow;
+
+use serde_json::Map as JsonMap;
+use serde_json::Value as JsonValue;
+
+use crate::consts::*;
+use crate::data::Column;
+use crate::data::ColumnKey;
+use crate::data::InfoKind;
+use crate::data::Record;
+use crate::value::Value;
+
+/// The system clipboard, falling back to an in-process register when there is
+/// no system clipboard available, such as over SSH or on a bare TTY.
+pub struct Clipboard {
+    system: Option<arboard::Clipboard>,
+    register: Option<String>,
+}
+
+impl Clipboard {
+    /// Connects to the system clipboard, if there is one.
+    pub fn new() -> Self {
+        Self {
+            system: arboard::Clipboard::new().ok(),
+            register: None,
+        }
+    }
+
+    /// Copies text, returning `true` if it made it to the system clipboard.
+    pub fn set_text(&mut self, text: String) -> bool {
+        let copied_to_system =
+            self.system.as_mut()
+            .map(|system| system.set_text(text.clone()).is_ok())
+            .unwrap_or(false)
+        ;
+
+        self.register = Some(text);
+
+        copied_to_system
+    }
+
+    /// Reads text from the system clipboard, falling back to the register.
+    pub fn get_text(&mut self) -> Option<String> {
+        self.system.as_mut()
+        .and_then(|system| system.get_text().ok())
+        .or_else(|| self.register.clone())
+    }
+}
+
+impl Default for Clipboard {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+fn info_key_name(info_kind: &InfoKind) -> &'static str {
+    match info_kind {
+        InfoKind::FileName => "file_name",
+        InfoKind::FilePath => "file_path",
+        InfoKind::FileSize => "file_size",
+        InfoKind::Duration => "duration",
+        InfoKind::Modified => "modified",
+        InfoKind::SourceRoot => "source_root",
+    }
+}
+
+/// The values of a cell, one per line.
+pub fn cell_text(record: &Record, column: &Column) -> Option<String> {
+    match &column.key {
+        ColumnKey::Meta(meta_key) => record.get_meta(meta_key).map(|vals| vals.join("\n")),
+        ColumnKey::Info(info_kind) => record.get_info(info_kind).map(Cow::into_owned),
+        ColumnKey::Command(command) => record.get_command_output(command).map(String::from),
+        ColumnKey::Computed(template) => record.get_computed(template),
+    }
+}
+
+/// Splits pasted text into field values, one per line. Returns `None` if
+/// there is nothing to paste, which would otherwise remove the field.
+pub fn paste_values(text: &str) -> Option<Vec<String>> {
+    let values = text.lines().map(String::from).collect::<Vec<_>>();
+
+    Some(values).filter(|vs| vs.iter().any(|v| !v.is_empty()))
+}
+
+/// The values of a row as a single line of tab-separated text.
+pub fn row_tsv(record: &Record, columns: &[Column]) -> String {
+    columns.iter()
+    .map(|column| {
+        let text = match &column.key {
+            ColumnKey::Meta(meta_key) => record.get_meta(meta_key).map(|vals| vals.join(FIELD_SEP_STR)),
+            ColumnKey::Info(info_kind) => record.get_info(info_kind).map(Cow::into_owned),
+            ColumnKey::Command(command) => record.get_command_output(command).map(String::from),
+            ColumnKey::Computed(template) => record.get_computed(template),
+        };
+
+        // Tabs and newlines would break the row structure.
+        text.unwrap_or_default().replace(['\t', '\n', '\r'], " ")
+    })
+    .collect::<Vec<_>>()
+    .join("\t")
+}
+
+/// A typed value as JSON, if it is a number or a boolean. Whole numbers with
+/// more to them, such as `3/12`, are left as text so that nothing is lost.
+fn typed_json(value: &Value, raw: &str) -> Option<JsonValue> {
+    match value {
+        Value::Integer(n) if raw.trim() == n.to_string() => Some(JsonValue::from(*n)),
+        Value::Duration(secs) => Some(JsonValue::from(*secs)),
+        Value::Bool(b) => Some(JsonValue::from(*b)),
+        _ => None,
+    }
+}
+
+/// The values of a row as a JSON object, keyed by column key. Numbers and
+/// booleans are written as such, going by the kind of their column, while
+/// other metadata values are arrays, to preserve multiple values.
+pub fn row_json(record: &Record, columns: &[Column]) -> String {
+    let mut object = JsonMap::new();
+
+    for column in columns {
+        let kind = column.value_kind();
+
+        let (key, value) = match &column.key {
+            ColumnKey::Meta(meta_key) => {
+                let value = record.get_meta(meta_key).map(|vals| {
+                    record.get_meta_value(meta_key, kind)
+                    .and_then(|value| typed_json(&value, &vals[0]))
+                    .unwrap_or_else(|| JsonValue::from(vals.to_vec()))
+                });
+
+                (meta_key.as_str(), value)
+            },
+            ColumnKey::Info(info_kind) => {
+                let value = record.get_info(info_kind).map(|val| {
+                    match record.get_info_value(info_kind, kind) {
+                        Some(Value::Integer(n)) => JsonValue::from(n),
+                        Some(Value::Duration(secs)) => JsonValue::from(secs),
+                        _ => JsonValue::from(val.into_owned()),
+                    }
+                });
+
+                (info_key_name(info_kind), value)
+            },
+            ColumnKey::Command(command) => {
+                let value = record.get_command_output(command).map(|val| {
+                    typed_json(&Value::parse(val, kind), val).unwrap_or_else(|| JsonValue::from(val))
+                });
+
+                (command.as_str(), value)
+            },
+            ColumnKey::Computed(template) => {
+                let value = record.get_computed(template).map(|val| {
+                    typed_json(&Value::parse(&val, kind), &val).unwrap_or_else(|| JsonValue::from(val))
+                });
+
+                (template.as_str(), value)
+            },
+        };
+
+        object.insert(key.to_string(), value.unwrap_or(JsonValue::Null));
+    }
+
+    JsonValue::Object(object).to_string()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    use crate::config::Config;
+
+    #[test]
+    fn row_formats() {
+        let record = Record::new(
+            hashmap! {
+                str!("ARTIST") => vec![str!("Foo"), str!("Bar")],
+                str!("TITLE") => vec![str!("Tab\there")],
+            },
+            PathBuf::from("music/a.flac"),
+        );
+
+        let columns = Config::default().columns;
+
+        assert_eq!(cell_text(&record, &columns[0]), Some(str!("Foo\nBar")));
+        assert_eq!(cell_text(&record, &columns[2]), None);
+        assert_eq!(row_tsv(&record, &columns), "Foo|Bar\tTab here\t\ta.flac");
+        assert_eq!(
+            row_json(&record, &columns),
+            r#"{"ALBUM":null,"ARTIST":["Foo","Bar"],"TITLE":["Tab\there"],"file_name":"a.flac"}"#,
+        );
+    }
+
+    #[test]
+    fn typed_row_json() {
+        use crate::data::Sizing;
+
+        let mut record = Record::new(
+            hashmap! {
+                str!("TRACKNUMBER") => vec![str!("3")],
+                str!("DISCNUMBER") => vec![str!("1/2")],
+                str!("COMPILATION") => vec![str!("1")],
+            },
+            PathBuf::from("music/a.flac"),
+        );
+        record.file_size = Some(2048);
+
+        let column = |key: ColumnKey| Column { key, title: str!(""), sizing: Sizing::Auto, align: Default::default(), kind: None, separator: None };
+        let columns = vec![
+            column(ColumnKey::Meta(str!("TRACKNUMBER"))),
+            column(ColumnKey::Meta(str!("DISCNUMBER"))),
+            column(ColumnKey::Meta(str!("COMPILATION"))),
+            column(ColumnKey::Info(InfoKind::FileSize)),
+        ];
+
+        assert_eq!(
+            row_json(&record, &columns),
+            r#"{"COMPILATION":true,"DISCNUMBER":["1/2"],"TRACKNUMBER":3,"file_size":2048}"#,
+        );
+    }
+
+    #[test]
+    fn paste() {
+        assert_eq!(paste_values("Foo\r\nBar\n"), Some(vec![str!("Foo"), str!("Bar")]));
+        assert_eq!(paste_values("Foo"), Some(vec![str!("Foo")]));
+        assert_eq!(paste_values("\n"), None);
+        assert_eq!(paste_values(""), None);
+    }
+}
@@ -0,0 +1,818 @@
+//! The commands that keys are bound to, and the keymap that binds them.
+
+use std::collections::HashMap;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::str::FromStr;
+
+use cursive::event::Event;
+use cursive::event::Key;
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::json;
+
+/// An action that can be bound to a key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub enum Command {
+    /// Edits the values of the current field.
+    EditField,
+    /// Edits the values of the current field in `$EDITOR`, one per line.
+    EditExternally,
+    /// Sorts by the current column, descending.
+    SortDescending,
+    /// Sorts by the current column, ascending.
+    SortAscending,
+    /// Groups records by album, or stops grouping.
+    ToggleGrouping,
+    /// Groups records by the current column, or stops grouping by it.
+    GroupByColumn,
+    /// Collapses the current group to its header, or expands it.
+    ToggleCollapse,
+    /// Collapses all groups, or expands them if any is collapsed.
+    ToggleCollapseAll,
+    /// Saves the column layout and sort to a preset file.
+    ExportPreset,
+    /// Loads the column layout and sort from a preset file.
+    ImportPreset,
+    /// Stages tag values from a CSV file as edits.
+    ImportCsv,
+    /// Writes all pending edits to their files.
+    Save,
+    /// Writes the pending edits of the current record to its file.
+    SaveCurrent,
+    /// Re-reads all files from disk.
+    Reload,
+    /// Re-reads the config files and applies them to every tab, keeping pending
+    /// edits.
+    ReloadConfig,
+    /// Renames the files of the target records from a tag template.
+    RenameFiles,
+    /// Moves the files of the target records into a chosen directory.
+    MoveFiles,
+    /// Copies the files of the target records into a chosen directory, without
+    /// pending edits.
+    CopyFiles,
+    /// Moves the files of the target records to the trash.
+    DeleteFiles,
+    /// Fills in missing tags of the target records from their file names.
+    TagsFromNames,
+    /// Writes the current tags to a snapshot file.
+    ExportSnapshot,
+    /// Writes the records in their current order to an M3U, M3U8, or PLS
+    /// playlist.
+    ExportPlaylist,
+    /// Compares against a snapshot file, and restores fields.
+    CompareSnapshot,
+    /// Copies the current cell to the clipboard.
+    CopyCell,
+    /// Copies the current row to the clipboard as tab-separated text.
+    CopyRow,
+    /// Copies the current row to the clipboard as JSON.
+    CopyRowJson,
+    /// Pastes the clipboard into the current column of the target records.
+    Paste,
+    /// Chooses the register for the next yank or put.
+    ChooseRegister,
+    /// Yanks the current cell into a register.
+    Yank,
+    /// Puts a register into the current column of the target records.
+    Put,
+    /// Sets the current column of the target records to an entered value.
+    SetValue,
+    /// Removes the current field from the target records, rather than leaving
+    /// it empty.
+    RemoveField,
+    /// Adds a field with an entered value to the target records, and a column
+    /// for it if there is none.
+    AddField,
+    /// Moves the values of a field to another one, in every loaded record.
+    RenameKey,
+    /// Undoes the last edit.
+    Undo,
+    /// Redoes the last undone edit.
+    Redo,
+    /// Lists the edits that can be undone or redone, and goes back or forward
+    /// to one of them.
+    ShowHistory,
+    /// Copies chosen tags from the current record to the target records.
+    CopyTags,
+    /// Numbers the target records in their current order, per album.
+    NumberTracks,
+    /// Raises the rating of the target records by a star.
+    RateUp,
+    /// Lowers the rating of the target records by a star.
+    RateDown,
+    /// Analyzes the loudness of the target records, and tags their track and
+    /// album ReplayGain.
+    ReplayGain,
+    /// Looks up the current album on MusicBrainz, and applies its tags.
+    LookUpAlbum,
+    /// Shows the lyrics of the current record, to edit or fetch them.
+    ShowLyrics,
+    /// Plays the file of the current record, or stops playing it.
+    TogglePlayback,
+    /// Runs a command from the config on the target records, and shows its
+    /// output.
+    RunHook,
+    /// Transforms the tags of the target records with a script from the config.
+    RunScript,
+    /// Sets the tags of a preset from the config on the target records, and
+    /// shows its columns.
+    ApplyTagPreset,
+    /// Fills in fields that most tracks of the albums of the target records
+    /// share, on the tracks missing them.
+    FillAlbumFields,
+    /// Cleans up stray whitespace, empty values, and invisible characters in
+    /// the target records.
+    CleanTags,
+    /// Removes every tag of the target records, except for the fields in
+    /// `strip_keep`.
+    StripTags,
+    /// Changes the case of the current cell, the target records, or the whole
+    /// column.
+    ConvertCase,
+    /// Previews and converts values or file names in the current column to
+    /// plain ASCII.
+    ConvertToAscii,
+    /// Cycles which records batch operations apply to.
+    CycleScope,
+    /// Selects or deselects the current record.
+    ToggleSelection,
+    /// Selects all records.
+    SelectAll,
+    /// Deselects all records.
+    ClearSelection,
+    /// Moves the cursor up.
+    MoveUp,
+    /// Moves the cursor down.
+    MoveDown,
+    /// Moves the cursor left.
+    MoveLeft,
+    /// Moves the cursor right.
+    MoveRight,
+    /// Moves the cursor up by a page.
+    PageUp,
+    /// Moves the cursor down by a page.
+    PageDown,
+    /// Moves the cursor up by half a page.
+    HalfPageUp,
+    /// Moves the cursor down by half a page.
+    HalfPageDown,
+    /// Switches between highlighting a cell and the whole row.
+    ToggleRowMode,
+    /// Shows or hides every tag of the record under the cursor.
+    ToggleDetail,
+    /// Shows or hides markers for spaces at the ends, tabs, and other hard to
+    /// see characters.
+    ToggleInvisible,
+    /// Switches between shrinking columns to fit the view and showing them at
+    /// full width.
+    ToggleFitWidth,
+    /// Loads or leaves out files whose names start with a dot.
+    ToggleHidden,
+    /// Lists the files that could not be read and were left out, and why.
+    ShowSkipped,
+    /// Switches to the next directory tab.
+    NextTab,
+    /// Switches to the previous directory tab.
+    PreviousTab,
+    /// Opens another directory in a new tab.
+    OpenTab,
+    /// Compares the current tab with the next one side by side.
+    CompareTabs,
+    /// Browses for a directory to open in the current tab.
+    BrowseDirectory,
+    /// Opens a bookmarked or recent directory in the current tab.
+    QuickOpen,
+    /// Bookmarks the directory of the current tab, or removes its bookmark.
+    ToggleBookmark,
+    /// Searches the sorted column for the keys typed next, even ones bound to
+    /// commands, which typing alone does not start a search with.
+    TypeAhead,
+    /// Moves to the next record missing a value in the current column.
+    NextMissing,
+    /// Moves to the previous record missing a value in the current column.
+    PreviousMissing,
+    /// Lists records with the same or nearly the same artist and title.
+    FindDuplicates,
+    /// Enters a command such as `goto N` or `find-file NAME`.
+    OpenCommandLine,
+}
+
+impl Command {
+    /// Every command, in the order they are listed in the keymap reference.
+    pub const ALL: &'static [Self] = &[
+        Self::EditField,
+        Self::EditExternally,
+        Self::SortDescending,
+        Self::SortAscending,
+        Self::ToggleGrouping,
+        Self::GroupByColumn,
+        Self::ToggleCollapse,
+        Self::ToggleCollapseAll,
+        Self::ExportPreset,
+        Self::ImportPreset,
+        Self::ImportCsv,
+        Self::Save,
+        Self::SaveCurrent,
+        Self::Reload,
+        Self::ReloadConfig,
+        Self::RenameFiles,
+        Self::MoveFiles,
+        Self::CopyFiles,
+        Self::DeleteFiles,
+        Self::TagsFromNames,
+        Self::ExportSnapshot,
+        Self::ExportPlaylist,
+        Self::CompareSnapshot,
+        Self::CopyCell,
+        Self::CopyRow,
+        Self::CopyRowJson,
+        Self::Paste,
+        Self::ChooseRegister,
+        Self::Yank,
+        Self::Put,
+        Self::SetValue,
+        Self::RemoveField,
+        Self::AddField,
+        Self::RenameKey,
+        Self::Undo,
+        Self::Redo,
+        Self::ShowHistory,
+        Self::CopyTags,
+        Self::NumberTracks,
+        Self::RateUp,
+        Self::RateDown,
+        Self::ReplayGain,
+        Self::LookUpAlbum,
+        Self::ShowLyrics,
+        Self::TogglePlayback,
+        Self::RunHook,
+        Self::RunScript,
+        Self::ApplyTagPreset,
+        Self::FillAlbumFields,
+        Self::CleanTags,
+        Self::StripTags,
+        Self::ConvertCase,
+        Self::ConvertToAscii,
+        Self::CycleScope,
+        Self::ToggleSelection,
+        Self::SelectAll,
+        Self::ClearSelection,
+        Self::MoveUp,
+        Self::MoveDown,
+        Self::MoveLeft,
+        Self::MoveRight,
+        Self::PageUp,
+        Self::PageDown,
+        Self::HalfPageUp,
+        Self::HalfPageDown,
+        Self::ToggleRowMode,
+        Self::ToggleDetail,
+        Self::ToggleInvisible,
+        Self::ToggleFitWidth,
+        Self::ToggleHidden,
+        Self::ShowSkipped,
+        Self::NextTab,
+        Self::PreviousTab,
+        Self::OpenTab,
+        Self::CompareTabs,
+        Self::BrowseDirectory,
+        Self::QuickOpen,
+        Self::ToggleBookmark,
+        Self::TypeAhead,
+        Self::NextMissing,
+        Self::PreviousMissing,
+        Self::FindDuplicates,
+        Self::OpenCommandLine,
+    ];
+
+    /// Whether the command changes tags or files, or runs something that
+    /// might, and so is turned off in read-only mode.
+    pub fn is_editing(&self) -> bool {
+        matches!(
+            self,
+            Self::EditField
+            | Self::EditExternally
+            | Self::ImportCsv
+            | Self::Save
+            | Self::SaveCurrent
+            | Self::RenameFiles
+            | Self::MoveFiles
+            | Self::CopyFiles
+            | Self::DeleteFiles
+            | Self::TagsFromNames
+            | Self::CompareSnapshot
+            | Self::Paste
+            | Self::Put
+            | Self::SetValue
+            | Self::RemoveField
+            | Self::AddField
+            | Self::RenameKey
+            | Self::Undo
+            | Self::Redo
+            | Self::ShowHistory
+            | Self::CopyTags
+            | Self::NumberTracks
+            | Self::RateUp
+            | Self::RateDown
+            | Self::ReplayGain
+            | Self::LookUpAlbum
+            | Self::RunHook
+            | Self::RunScript
+            | Self::ApplyTagPreset
+            | Self::FillAlbumFields
+            | Self::CleanTags
+            | Self::StripTags
+            | Self::ConvertCase
+            | Self::ConvertToAscii
+        )
+    }
+
+    /// Whether the command stages edits on the target records, and so is
+    /// refused up front when all of them are read-only.
+    pub fn stages_edits(&self) -> bool {
+        matches!(
+            self,
+            Self::EditField
+            | Self::EditExternally
+            | Self::TagsFromNames
+            | Self::Paste
+            | Self::Put
+            | Self::SetValue
+            | Self::RemoveField
+            | Self::AddField
+            | Self::CopyTags
+            | Self::NumberTracks
+            | Self::RateUp
+            | Self::RateDown
+            | Self::ReplayGain
+            | Self::LookUpAlbum
+            | Self::RunScript
+            | Self::ApplyTagPreset
+            | Self::FillAlbumFields
+            | Self::CleanTags
+            | Self::StripTags
+            | Self::ConvertCase
+            | Self::ConvertToAscii
+        )
+    }
+
+    /// The name and description of this command.
+    fn info(&self) -> (&'static str, &'static str) {
+        match self {
+            Self::EditField => ("edit_field", "Edit the values of the current field"),
+            Self::EditExternally => ("edit_externally", "Edit the values of the current field in $EDITOR, one per line"),
+            Self::SortDescending => ("sort_descending", "Sort by the current column, descending"),
+            Self::SortAscending => ("sort_ascending", "Sort by the current column, ascending"),
+            Self::ToggleGrouping => ("toggle_grouping", "Group records by album, or stop grouping"),
+            Self::GroupByColumn => ("group_by_column", "Group records by the current column, or stop grouping by it"),
+            Self::ToggleCollapse => ("toggle_collapse", "Collapse the current group to its header, or expand it"),
+            Self::ToggleCollapseAll => ("toggle_collapse_all", "Collapse all groups, or expand them if any is collapsed"),
+            Self::ExportPreset => ("export_preset", "Save the column layout and sort to a preset file"),
+            Self::ImportPreset => ("import_preset", "Load the column layout and sort from a preset file"),
+            Self::ImportCsv => ("import_csv", "Stage tag values from a CSV file as edits"),
+            Self::Save => ("save", "Write all pending edits to their files"),
+            Self::SaveCurrent => ("save_current", "Write the pending edits of the current record to its file"),
+            Self::Reload => ("reload", "Re-read all files from disk"),
+            Self::ReloadConfig => ("reload_config", "Re-read the config files and apply them to every tab, keeping pending edits"),
+            Self::RenameFiles => ("rename_files", "Rename the files of the target records from a tag template"),
+            Self::MoveFiles => ("move_files", "Move the files of the target records into a chosen directory"),
+            Self::CopyFiles => ("copy_files", "Copy the files of the target records into a chosen directory, without pending edits"),
+            Self::DeleteFiles => ("delete_files", "Move the files of the target records to the trash"),
+            Self::TagsFromNames => ("tags_from_names", "Fill in missing tags of the target records from their file names"),
+            Self::ExportSnapshot => ("export_snapshot", "Write the current tags to a snapshot file"),
+            Self::ExportPlaylist => ("export_playlist", "Write the records in their current order to an M3U, M3U8, or PLS playlist"),
+            Self::CompareSnapshot => ("compare_snapshot", "Compare against a snapshot file, and restore fields"),
+            Self::CopyCell => ("copy_cell", "Copy the current cell to the clipboard"),
+            Self::CopyRow => ("copy_row", "Copy the current row to the clipboard as tab-separated text"),
+            Self::CopyRowJson => ("copy_row_json", "Copy the current row to the clipboard as JSON"),
+            Self::Paste => ("paste", "Paste the clipboard into the current column of the target records"),
+            Self::ChooseRegister => ("choose_register", "Choose the register for the next yank or put"),
+            Self::Yank => ("yank", "Yank the current cell into a register"),
+            Self::Put => ("put", "Put a register into the current column of the target records"),
+            Self::SetValue => ("set_value", "Set the current column of the target records to an entered value"),
+            Self::RemoveField => ("remove_field", "Remove the current field from the target records, rather than leaving it empty"),
+            Self::AddField => ("add_field", "Add a field with an entered value to the target records, and a column for it if there is none"),
+            Self::RenameKey => ("rename_key", "Move the values of a field to another one, in every loaded record"),
+            Self::Undo => ("undo", "Undo the last edit"),
+            Self::Redo => ("redo", "Redo the last undone edit"),
+            Self::ShowHistory => ("show_history", "List the edits that can be undone or redone, and go back or forward to one of them"),
+            Self::CopyTags => ("copy_tags", "Copy chosen tags from the current record to the target records"),
+            Self::NumberTracks => ("number_tracks", "Number the target records in their current order, per album"),
+            Self::RateUp => ("rate_up", "Raise the rating of the target records by a star"),
+            Self::RateDown => ("rate_down", "Lower the rating of the target records by a star"),
+            Self::ReplayGain => ("replay_gain", "Analyze the loudness of the target records, and tag their track and album ReplayGain"),
+            Self::LookUpAlbum => ("look_up_album", "Look up the current album on MusicBrainz, and apply its tags"),
+            Self::ShowLyrics => ("show_lyrics", "Show the lyrics of the current record, to edit or fetch them"),
+            Self::TogglePlayback => ("toggle_playback", "Play the file of the current record, or stop playing it"),
+            Self::RunHook => ("run_hook", "Run a command from the config on the target records, and show its output"),
+            Self::RunScript => ("run_script", "Transform the tags of the target records with a script from the config"),
+            Self::ApplyTagPreset => ("apply_tag_preset", "Set the tags of a preset from the config on the target records, and show its columns"),
+            Self::FillAlbumFields => ("fill_album_fields", "Fill in fields that most tracks of the albums of the target records share, on the tracks missing them"),
+            Self::CleanTags => ("clean_tags", "Clean up stray whitespace, empty values, and invisible characters in the target records"),
+            Self::StripTags => ("strip_tags", "Remove every tag of the target records, except for the fields in strip_keep"),
+            Self::ConvertCase => ("convert_case", "Change the case of the current cell, the target records, or the whole column"),
+            Self::ConvertToAscii => ("convert_to_ascii", "Preview and convert values or file names in the current column to plain ASCII"),
+            Self::CycleScope => ("cycle_scope", "Cycle which records batch operations apply to"),
+            Self::ToggleSelection => ("toggle_selection", "Select or deselect the current record"),
+            Self::SelectAll => ("select_all", "Select all records"),
+            Self::ClearSelection => ("clear_selection", "Deselect all records"),
+            Self::MoveUp => ("move_up", "Move the cursor up"),
+            Self::MoveDown => ("move_down", "Move the cursor down"),
+            Self::MoveLeft => ("move_left", "Move the cursor left"),
+            Self::MoveRight => ("move_right", "Move the cursor right"),
+            Self::PageUp => ("page_up", "Move the cursor up by a page"),
+            Self::PageDown => ("page_down", "Move the cursor down by a page"),
+            Self::HalfPageUp => ("half_page_up", "Move the cursor up by half a page"),
+            Self::HalfPageDown => ("half_page_down", "Move the cursor down by half a page"),
+            Self::ToggleRowMode => ("toggle_row_mode", "Switch between highlighting a cell and the whole row"),
+            Self::ToggleDetail => ("toggle_detail", "Show or hide every tag of the record under the cursor"),
+            Self::ToggleInvisible => ("toggle_invisible", "Show or hide markers for spaces at the ends, tabs, and other hard to see characters"),
+            Self::ToggleFitWidth => ("toggle_fit_width", "Switch between shrinking columns to fit the view and showing them at full width"),
+            Self::ToggleHidden => ("toggle_hidden", "Load or leave out files whose names start with a dot"),
+            Self::ShowSkipped => ("show_skipped", "List the files that could not be read and were left out, and why"),
+            Self::NextTab => ("next_tab", "Switch to the next directory tab"),
+            Self::PreviousTab => ("previous_tab", "Switch to the previous directory tab"),
+            Self::OpenTab => ("open_tab", "Open another directory in a new tab"),
+            Self::CompareTabs => ("compare_tabs", "Compare the current tab with the next one side by side"),
+            Self::BrowseDirectory => ("browse_directory", "Browse for a directory to open in the current tab"),
+            Self::QuickOpen => ("quick_open", "Open a bookmarked or recent directory in the current tab"),
+            Self::ToggleBookmark => ("toggle_bookmark", "Bookmark the directory of the current tab, or remove its bookmark"),
+            Self::TypeAhead => ("type_ahead", "Search the sorted column for the keys typed next, even ones bound to commands, which typing alone does not start a search with"),
+            Self::NextMissing => ("next_missing", "Move to the next record missing a value in the current column"),
+            Self::PreviousMissing => ("previous_missing", "Move to the previous record missing a value in the current column"),
+            Self::FindDuplicates => ("find_duplicates", "List records with the same or nearly the same artist and title"),
+            Self::OpenCommandLine => ("open_command_line", "Enter a command such as `goto N` or `find-file NAME`"),
+        }
+    }
+
+    /// The name of the command, as used in keymap overrides.
+    pub fn name(&self) -> &'static str {
+        self.info().0
+    }
+
+    /// What the command does, as shown in the keymap reference.
+    pub fn description(&self) -> &'static str {
+        self.info().1
+    }
+}
+
+impl Display for Command {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{}", self.name())
+    }
+}
+
+impl FromStr for Command {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        Self::ALL.iter().copied()
+        .find(|command| command.name() == s)
+        .ok_or_else(|| format!("unknown command: {}", s))
+    }
+}
+
+impl TryFrom<String> for Command {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<Command> for String {
+    fn from(command: Command) -> Self {
+        command.name().to_string()
+    }
+}
+
+const NAMED_KEYS: &[(&str, Key)] = &[
+    ("up", Key::Up),
+    ("down", Key::Down),
+    ("left", Key::Left),
+    ("right", Key::Right),
+    ("pageup", Key::PageUp),
+    ("pagedown", Key::PageDown),
+    ("home", Key::Home),
+    ("end", Key::End),
+    ("enter", Key::Enter),
+    ("tab", Key::Tab),
+    ("esc", Key::Esc),
+    ("backspace", Key::Backspace),
+    ("del", Key::Del),
+    ("ins", Key::Ins),
+    ("f1", Key::F1),
+    ("f2", Key::F2),
+    ("f3", Key::F3),
+    ("f4", Key::F4),
+    ("f5", Key::F5),
+    ("f6", Key::F6),
+    ("f7", Key::F7),
+    ("f8", Key::F8),
+    ("f9", Key::F9),
+    ("f10", Key::F10),
+    ("f11", Key::F11),
+    ("f12", Key::F12),
+];
+
+/// A key press that can trigger a command, written as e.g. `y`, `space`,
+/// `alt-x`, `ctrl-s`, or `f5`.
+#[derive(Debug, Clone, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(try_from = "String", into = "String")]
+pub struct KeyBinding(pub Event);
+
+impl FromStr for KeyBinding {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let single_char = |rest: &str| {
+            let mut chars = rest.chars();
+            match (chars.next(), chars.next()) {
+                (Some(c), None) => Some(c),
+                _ => None,
+            }
+        };
+
+        let event =
+            if s == "space" { Some(Event::Char(' ')) }
+            else if let Some(c) = single_char(s) { Some(Event::Char(c)) }
+            else if let Some(rest) = s.strip_prefix("alt-") { single_char(rest).map(Event::AltChar) }
+            else if let Some(rest) = s.strip_prefix("ctrl-") { single_char(rest).map(Event::CtrlChar) }
+            else { NAMED_KEYS.iter().find(|(name, _)| *name == s).map(|(_, key)| Event::Key(*key)) }
+        ;
+
+        event.map(Self).ok_or_else(|| format!("unknown key: {}", s))
+    }
+}
+
+impl Display for KeyBinding {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match &self.0 {
+            Event::Char(' ') => write!(f, "space"),
+            Event::Char(c) => write!(f, "{}", c),
+            Event::AltChar(c) => write!(f, "alt-{}", c),
+            Event::CtrlChar(c) => write!(f, "ctrl-{}", c),
+            Event::Key(key) => {
+                let name = NAMED_KEYS.iter().find(|(_, k)| k == key).map_or("?", |(name, _)| name);
+                write!(f, "{}", name)
+            },
+            event => write!(f, "{:?}", event),
+        }
+    }
+}
+
+impl TryFrom<String> for KeyBinding {
+    type Error = String;
+
+    fn try_from(s: String) -> Result<Self, Self::Error> {
+        s.parse()
+    }
+}
+
+impl From<KeyBinding> for String {
+    fn from(key_binding: KeyBinding) -> Self {
+        key_binding.to_string()
+    }
+}
+
+/// Overrides to the default keymap, with a command of `None` unbinding a key.
+pub type KeymapOverrides = HashMap<KeyBinding, Option<Command>>;
+
+/// The output formats of a keymap reference.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum ReferenceFormat {
+    /// A Markdown table.
+    Markdown,
+    /// A JSON array of commands.
+    Json,
+}
+
+impl FromStr for ReferenceFormat {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "md" => Ok(Self::Markdown),
+            "json" => Ok(Self::Json),
+            _ => Err(format!("unknown format: {}, expected md or json", s)),
+        }
+    }
+}
+
+/// The mapping of keys to commands.
+#[derive(Debug, Clone)]
+pub struct Keymap {
+    bindings: Vec<(KeyBinding, Command)>,
+}
+
+impl Keymap {
+    /// The default keymap, with keys bound or unbound by overrides.
+    pub fn with_overrides(overrides: &KeymapOverrides) -> Self {
+        let mut keymap = Self::default();
+
+        for (key_binding, command) in overrides {
+            keymap.bindings.retain(|(k, _)| k != key_binding);
+
+            if let Some(command) = command {
+                keymap.bindings.push((key_binding.clone(), *command));
+            }
+        }
+
+        keymap
+    }
+
+    /// The command bound to a key press, if any.
+    pub fn command_for(&self, event: &Event) -> Option<Command> {
+        self.bindings.iter().find(|(k, _)| k.0 == *event).map(|(_, c)| *c)
+    }
+
+    /// The keys bound to a command, in sorted order.
+    pub fn keys_for(&self, command: Command) -> Vec<&KeyBinding> {
+        let mut keys = self.bindings.iter().filter(|(_, c)| *c == command).map(|(k, _)| k).collect::<Vec<_>>();
+        keys.sort_by_key(|k| k.to_string());
+        keys
+    }
+
+    /// Lists every command along with the keys bound to it.
+    pub fn reference(&self, format: ReferenceFormat) -> String {
+        match format {
+            ReferenceFormat::Markdown => {
+                let mut lines = vec![
+                    String::from("| Keys | Command | Description |"),
+                    String::from("| --- | --- | --- |"),
+                ];
+
+                for &command in Command::ALL {
+                    let keys =
+                        self.keys_for(command).into_iter()
+                        .map(|k| format!("`{}`", k))
+                        .collect::<Vec<_>>()
+                        .join(", ")
+                    ;
+
+                    // Pipes in keys would break the table.
+                    let keys = keys.replace('|', "\\|");
+
+                    lines.push(format!("| {} | `{}` | {} |", keys, command, command.description()));
+                }
+
+                lines.join("\n")
+            },
+            ReferenceFormat::Json => {
+                let commands =
+                    Command::ALL.iter()
+                    .map(|&command| {
+                        json!({
+                            "command": command,
+                            "description": command.description(),
+                            "keys": self.keys_for(command),
+                        })
+                    })
+                    .collect::<Vec<_>>()
+                ;
+
+                serde_json::to_string_pretty(&commands).unwrap()
+            },
+        }
+    }
+}
+
+impl Default for Keymap {
+    fn default() -> Self {
+        let bindings = [
+            ("alt-x", Command::EditField),
+            ("alt-E", Command::EditExternally),
+            ("alt-d", Command::SortDescending),
+            ("alt-a", Command::SortAscending),
+            ("alt-g", Command::ToggleGrouping),
+            ("alt-G", Command::GroupByColumn),
+            ("alt-z", Command::ToggleCollapse),
+            ("alt-Z", Command::ToggleCollapseAll),
+            ("alt-p", Command::ExportPreset),
+            ("alt-i", Command::ImportPreset),
+            ("alt-c", Command::ImportCsv),
+            ("ctrl-s", Command::Save),
+            ("ctrl-o", Command::SaveCurrent),
+            ("f5", Command::Reload),
+            ("alt-C", Command::ReloadConfig),
+            ("alt-f", Command::RenameFiles),
+            ("f6", Command::MoveFiles),
+            ("f7", Command::CopyFiles),
+            ("del", Command::DeleteFiles),
+            ("alt-F", Command::TagsFromNames),
+            ("alt-s", Command::ExportSnapshot),
+            ("alt-P", Command::ExportPlaylist),
+            ("alt-r", Command::CompareSnapshot),
+            ("alt-y", Command::CopyCell),
+            ("alt-Y", Command::CopyRow),
+            ("alt-J", Command::CopyRowJson),
+            ("alt-v", Command::Paste),
+            ("\"", Command::ChooseRegister),
+            ("y", Command::Yank),
+            ("p", Command::Put),
+            ("alt-e", Command::SetValue),
+            ("alt-K", Command::RemoveField),
+            ("alt-T", Command::AddField),
+            ("alt-Q", Command::RenameKey),
+            ("ctrl-z", Command::Undo),
+            ("ctrl-r", Command::Redo),
+            ("alt-H", Command::ShowHistory),
+            ("alt-t", Command::CopyTags),
+            ("alt-N", Command::NumberTracks),
+            ("+", Command::RateUp),
+            ("-", Command::RateDown),
+            ("alt-R", Command::ReplayGain),
+            ("alt-L", Command::LookUpAlbum),
+            ("alt-W", Command::ShowLyrics),
+            ("ctrl-p", Command::TogglePlayback),
+            ("alt-h", Command::RunHook),
+            ("alt-j", Command::RunScript),
+            ("f8", Command::ApplyTagPreset),
+            ("ctrl-f", Command::FillAlbumFields),
+            ("alt-l", Command::CleanTags),
+            ("alt-X", Command::StripTags),
+            ("alt-u", Command::ConvertCase),
+            ("alt-A", Command::ConvertToAscii),
+            ("alt-o", Command::CycleScope),
+            ("space", Command::ToggleSelection),
+            ("ctrl-a", Command::SelectAll),
+            ("esc", Command::ClearSelection),
+            ("up", Command::MoveUp),
+            ("down", Command::MoveDown),
+            ("left", Command::MoveLeft),
+            ("right", Command::MoveRight),
+            ("pageup", Command::PageUp),
+            ("pagedown", Command::PageDown),
+            ("ctrl-u", Command::HalfPageUp),
+            ("ctrl-d", Command::HalfPageDown),
+            ("alt-w", Command::ToggleRowMode),
+            ("alt-k", Command::ToggleDetail),
+            ("alt-I", Command::ToggleInvisible),
+            ("alt-V", Command::ToggleFitWidth),
+            ("alt-U", Command::ToggleHidden),
+            ("alt-S", Command::ShowSkipped),
+            ("alt-.", Command::NextTab),
+            ("alt-,", Command::PreviousTab),
+            ("alt-n", Command::OpenTab),
+            ("alt-=", Command::CompareTabs),
+            ("alt-b", Command::BrowseDirectory),
+            ("alt-q", Command::QuickOpen),
+            ("alt-B", Command::ToggleBookmark),
+            ("/", Command::TypeAhead),
+            ("alt-m", Command::NextMissing),
+            ("alt-M", Command::PreviousMissing),
+            ("alt-D", Command::FindDuplicates),
+            (":", Command::OpenCommandLine),
+        ];
+
+        let bindings =
+            bindings.iter()
+            .map(|(key, command)| (key.parse().unwrap(), *command))
+            .collect()
+        ;
+
+        Self { bindings }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn key_bindings() {
+        for key in ["y", "\"", "space", "alt-Y", "ctrl-s", "f5", "pagedown"] {
+            assert_eq!(key.parse::<KeyBinding>().unwrap().to_string(), key);
+        }
+
+        assert!("alt-xy".parse::<KeyBinding>().is_err());
+        assert!("hyper-x".parse::<KeyBinding>().is_err());
+    }
+
+    #[test]
+    fn overrides() {
+        let overrides = serde_json::from_str::<KeymapOverrides>(r#"{
+            "ctrl-w": "save",
+            "ctrl-s": null,
+            "y": "copy_cell"
+        }"#).unwrap();
+
+        let keymap = Keymap::with_overrides(&overrides);
+
+        assert_eq!(keymap.command_for(&Event::CtrlChar('w')), Some(Command::Save));
+        assert_eq!(keymap.command_for(&Event::CtrlChar('s')), None);
+        assert_eq!(keymap.command_for(&Event::Char('y')), Some(Command::CopyCell));
+        assert!(keymap.keys_for(Command::Yank).is_empty());
+
+        let reference = keymap.reference(ReferenceFormat::Markdown);
+        assert!(reference.contains("| `alt-y`, `y` | `copy_cell` |"));
+
+        assert!(serde_json::from_str::<KeymapOverrides>(r#"{"y": "fly"}"#).is_err());
+        assert_eq!(Keymap::default().bindings.len(), Command::ALL.len());
+    }
+}
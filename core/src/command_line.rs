@@ -0,0 +1,53 @@
+//! The commands that can be entered on the `:` command line.
+
+/// A command entered on the `:` command line.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum LineCommand {
+    /// Moves the cursor to a record, counting from 1.
+    Goto(usize),
+    /// Moves the cursor to the next record whose file name contains a string.
+    FindFile(String),
+}
+
+impl LineCommand {
+    /// Reads a command line, with or without the leading `:`.
+    pub fn parse(line: &str) -> Result<Self, String> {
+        let line = line.trim().trim_start_matches(':');
+
+        let (name, arg) = match line.find(char::is_whitespace) {
+            Some(i) => (&line[..i], line[i..].trim()),
+            None => (line, ""),
+        };
+
+        match name {
+            "goto" => {
+                match arg.parse() {
+                    Ok(n) if n > 0 => Ok(Self::Goto(n)),
+                    _ => Err(format!("expected a row number, got: {}", arg)),
+                }
+            },
+            "find-file" if !arg.is_empty() => Ok(Self::FindFile(arg.to_string())),
+            "find-file" => Err(String::from("expected a file name")),
+            _ => Err(format!("unknown command: {}", name)),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(LineCommand::parse("goto 12"), Ok(LineCommand::Goto(12)));
+        assert_eq!(LineCommand::parse(":goto  3 "), Ok(LineCommand::Goto(3)));
+        assert!(LineCommand::parse("goto 0").is_err());
+        assert!(LineCommand::parse("goto x").is_err());
+        assert_eq!(
+            LineCommand::parse("find-file Song 2.flac"),
+            Ok(LineCommand::FindFile(String::from("Song 2.flac"))),
+        );
+        assert!(LineCommand::parse("find-file").is_err());
+        assert!(LineCommand::parse("frobnicate").is_err());
+    }
+}
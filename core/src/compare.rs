@@ -0,0 +1,187 @@
+//! Pairing up the records of two directories, to compare their tags side by
+//! side.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use crate::consts::*;
+use crate::data::InfoKind;
+use crate::data::Record;
+use crate::data::Records;
+
+/// How the records of two directories are paired up for comparing.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum MatchBy {
+    /// The file names, ignoring case.
+    FileName,
+    /// The values of a metadata key, ignoring case.
+    Tag(String),
+}
+
+impl MatchBy {
+    fn key(&self, record: &Record) -> Option<String> {
+        match self {
+            Self::FileName => record.get_info(&InfoKind::FileName).map(|name| name.to_lowercase()),
+            Self::Tag(meta_key) => {
+                record.get_meta(meta_key)
+                .map(|values| values.join(FIELD_SEP_STR).trim().to_lowercase())
+                .filter(|key| !key.is_empty())
+            },
+        }
+    }
+}
+
+impl Display for MatchBy {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::FileName => write!(f, "file name"),
+            Self::Tag(meta_key) => write!(f, "{}", meta_key),
+        }
+    }
+}
+
+/// The indices of matching records on the left and right sides. Either side
+/// is missing if nothing matched.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pair {
+    /// The index of the record on the left side.
+    pub left: Option<usize>,
+    /// The index of the record on the right side.
+    pub right: Option<usize>,
+}
+
+/// Pairs up records in the order of the left side, followed by the right
+/// records that matched nothing. Each record is matched at most once.
+pub fn match_records(left: &Records, right: &Records, match_by: &MatchBy) -> Vec<Pair> {
+    let mut unmatched_right = HashMap::<String, Vec<usize>>::new();
+
+    for (i, record) in right.iter().enumerate().rev() {
+        if let Some(key) = match_by.key(record) {
+            unmatched_right.entry(key).or_default().push(i);
+        }
+    }
+
+    let mut pairs =
+        left.iter()
+        .enumerate()
+        .map(|(i, record)| {
+            let right =
+                match_by.key(record)
+                .and_then(|key| unmatched_right.get_mut(&key)?.pop())
+            ;
+
+            Pair { left: Some(i), right }
+        })
+        .collect::<Vec<_>>()
+    ;
+
+    let mut matched_right = vec![false; right.len()];
+    for j in pairs.iter().filter_map(|p| p.right) {
+        matched_right[j] = true;
+    }
+
+    pairs.extend(
+        matched_right.iter()
+        .enumerate()
+        .filter(|(_, &matched)| !matched)
+        .map(|(j, _)| Pair { left: None, right: Some(j) })
+    );
+
+    pairs
+}
+
+/// The values of one metadata key on both sides of a pair.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldRow {
+    /// The metadata key of the field.
+    pub meta_key: String,
+    /// The values on the left side, or `None` if the field is missing there.
+    pub left: Option<Vec<String>>,
+    /// The values on the right side, or `None` if the field is missing there.
+    pub right: Option<Vec<String>>,
+}
+
+impl FieldRow {
+    /// Whether the two sides have different values.
+    pub fn differs(&self) -> bool {
+        self.left != self.right
+    }
+}
+
+/// Every metadata key found on either side, in sorted order.
+pub fn field_rows(left: Option<&Record>, right: Option<&Record>) -> Vec<FieldRow> {
+    let meta_keys =
+        left.into_iter()
+        .chain(right)
+        .flat_map(Record::meta_keys)
+        .collect::<BTreeSet<_>>()
+    ;
+
+    let get = |record: Option<&Record>, meta_key| record.and_then(|r| r.get_meta(meta_key)).map(<[String]>::to_vec);
+
+    meta_keys.into_iter()
+    .map(|meta_key| FieldRow {
+        meta_key: meta_key.to_string(),
+        left: get(left, meta_key),
+        right: get(right, meta_key),
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    fn record(file_name: &str, title: &str) -> Record {
+        Record::new(hashmap! { str!("TITLE") => vec![title.to_string()] }, PathBuf::from(file_name))
+    }
+
+    #[test]
+    fn matching() {
+        let left = vec![record("01.flac", "One"), record("02.flac", "Two"), record("03.flac", "Three")];
+        let right = vec![record("02.FLAC", "three"), record("04.flac", "One")];
+
+        assert_eq!(
+            match_records(&left, &right, &MatchBy::FileName),
+            vec![
+                Pair { left: Some(0), right: None },
+                Pair { left: Some(1), right: Some(0) },
+                Pair { left: Some(2), right: None },
+                Pair { left: None, right: Some(1) },
+            ],
+        );
+
+        assert_eq!(
+            match_records(&left, &right, &MatchBy::Tag(str!("TITLE"))),
+            vec![
+                Pair { left: Some(0), right: Some(1) },
+                Pair { left: Some(1), right: None },
+                Pair { left: Some(2), right: Some(0) },
+            ],
+        );
+    }
+
+    #[test]
+    fn fields() {
+        let mut left = record("01.flac", "One");
+        left.set_meta("ARTIST", Some(vec![str!("Foo")]));
+        let right = record("01.flac", "One");
+
+        let rows = field_rows(Some(&left), Some(&right));
+
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0], FieldRow { meta_key: str!("ARTIST"), left: Some(vec![str!("Foo")]), right: None });
+        assert!(rows[0].differs());
+        assert!(!rows[1].differs());
+
+        assert_eq!(field_rows(None, Some(&right)).len(), 1);
+    }
+}
@@ -0,0 +1,235 @@
+//! Templates that work out a value from the other fields of a record.
+
+use std::str::FromStr;
+
+use crate::data::Record;
+
+/// What multiple values of a field are joined with in computed values.
+const VALUE_SEP_STR: &str = ", ";
+
+/// A change made to each value of a field before it is filled in.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Filter {
+    /// The year of a date, as in `2001` for `2001-05-03`.
+    Year,
+    Upper,
+    Lower,
+    /// Only the first value of a multi-value field.
+    First,
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "year" => Ok(Self::Year),
+            "upper" => Ok(Self::Upper),
+            "lower" => Ok(Self::Lower),
+            "first" => Ok(Self::First),
+            other => Err(format!("unknown filter '{}'", other)),
+        }
+    }
+}
+
+impl Filter {
+    fn apply(self, values: Vec<String>) -> Vec<String> {
+        match self {
+            Self::Year => {
+                values.into_iter()
+                .map(|v| match v.get(..4) {
+                    Some(year) if year.bytes().all(|b| b.is_ascii_digit()) => year.to_string(),
+                    _ => v,
+                })
+                .collect()
+            },
+            Self::Upper => values.iter().map(|v| v.to_uppercase()).collect(),
+            Self::Lower => values.iter().map(|v| v.to_lowercase()).collect(),
+            Self::First => values.into_iter().take(1).collect(),
+        }
+    }
+}
+
+/// One choice in a placeholder.
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Choice {
+    Field(String),
+    /// Quoted text, used as is.
+    Text(String),
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    /// Choices tried in order until one has a value, and the filters applied
+    /// to that value.
+    Placeholder(Vec<Choice>, Vec<Filter>),
+}
+
+/// A template that works out a value from the fields of a record, such as
+/// `{albumartist|artist} — {album} ({date:year})`. A placeholder takes the
+/// first of its `|`-separated keys that has a value, or quoted text such as
+/// `{genre|"Unknown"}`, followed by `:`-separated filters: `year`, `upper`,
+/// `lower`, and `first`. Keys are not case sensitive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Expression {
+    parts: Vec<Part>,
+}
+
+impl FromStr for Expression {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    let mut inner = String::new();
+                    let mut quote = None;
+
+                    loop {
+                        match (quote, chars.next()) {
+                            (_, None) => return Err(String::from("unclosed '{' in template")),
+                            (None, Some('}')) => break,
+                            (None, Some('{')) => return Err(String::from("unclosed '{' in template")),
+                            (None, Some(c @ ('"' | '\''))) => { quote = Some(c); inner.push(c); },
+                            (Some(q), Some(c)) if c == q => { quote = None; inner.push(c); },
+                            (_, Some(c)) => inner.push(c),
+                        }
+                    }
+
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    parts.push(Self::parse_placeholder(&inner)?);
+                },
+                '}' => return Err(String::from("unmatched '}' in template")),
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        if parts.is_empty() {
+            return Err(String::from("template is empty"));
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+impl Expression {
+    /// Reads the inside of a placeholder. Quoted text may hold `|` and `:`.
+    fn parse_placeholder(inner: &str) -> Result<Part, String> {
+        let mut pieces = vec![String::new()];
+        let mut separators = Vec::new();
+        let mut quote = None;
+
+        for c in inner.chars() {
+            match (quote, c) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), c) => pieces.last_mut().unwrap().push(c),
+                (None, '"' | '\'') => {
+                    quote = Some(c);
+                    // Marks the piece as quoted text.
+                    pieces.last_mut().unwrap().push('\0');
+                },
+                (None, '|' | ':') => {
+                    separators.push(c);
+                    pieces.push(String::new());
+                },
+                (None, c) => pieces.last_mut().unwrap().push(c),
+            }
+        }
+
+        // Filters come after all of the choices.
+        let num_choices = separators.iter().take_while(|&&c| c == '|').count() + 1;
+        if separators[(num_choices - 1)..].contains(&'|') {
+            return Err(String::from("'|' after a filter in template"));
+        }
+
+        let choices =
+            pieces[..num_choices].iter()
+            .map(|piece| match piece.strip_prefix('\0') {
+                Some(text) => Ok(Choice::Text(text.to_string())),
+                None if piece.trim().is_empty() => Err(String::from("empty '{}' in template")),
+                None => Ok(Choice::Field(piece.trim().to_uppercase())),
+            })
+            .collect::<Result<Vec<_>, _>>()?
+        ;
+
+        let filters = pieces[num_choices..].iter().map(|piece| piece.parse()).collect::<Result<Vec<_>, _>>()?;
+
+        Ok(Part::Placeholder(choices, filters))
+    }
+
+    /// Fills in the placeholders with the values of a record. Returns `None`
+    /// if none of the fields the template names has a value.
+    pub fn render(&self, record: &Record) -> Option<String> {
+        let mut rendered = String::new();
+        let mut any_found = false;
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => rendered.push_str(literal),
+                Part::Placeholder(choices, filters) => {
+                    let values = choices.iter().find_map(|choice| match choice {
+                        Choice::Field(meta_key) => {
+                            let values = record.get_meta(meta_key)?;
+                            any_found = true;
+                            Some(values.to_vec())
+                        },
+                        Choice::Text(text) => Some(vec![text.clone()]),
+                    });
+
+                    let values = filters.iter().fold(values.unwrap_or_default(), |values, filter| filter.apply(values));
+                    rendered.push_str(&values.join(VALUE_SEP_STR));
+                },
+            }
+        }
+
+        if any_found { Some(rendered) } else { None }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    #[test]
+    fn rendering() {
+        let record = Record::new(
+            hashmap! {
+                str!("ARTIST") => vec![str!("Artist A"), str!("Guest")],
+                str!("ALBUM") => vec![str!("Album: One | Two")],
+                str!("DATE") => vec![str!("2001-05-03")],
+            },
+            PathBuf::from("01.flac"),
+        );
+
+        let render = |template: &str| template.parse::<Expression>().unwrap().render(&record);
+
+        assert_eq!(render("{albumartist|artist} — {album} ({date:year})"), Some(str!("Artist A, Guest — Album: One | Two (2001)")));
+        assert_eq!(render("{ Artist :first:upper}"), Some(str!("ARTIST A")));
+        assert_eq!(render("{genre|'Unknown: none'} / {album:lower}"), Some(str!("Unknown: none / album: one | two")));
+        assert_eq!(render("{genre} ({comment})"), None);
+
+        assert!("{artist".parse::<Expression>().is_err());
+        assert!("artist}".parse::<Expression>().is_err());
+        assert!("{}".parse::<Expression>().is_err());
+        assert!("{artist:year|album}".parse::<Expression>().is_err());
+        assert!("{artist:reverse}".parse::<Expression>().is_err());
+        assert!("".parse::<Expression>().is_err());
+    }
+}
@@ -0,0 +1,636 @@
+//! The settings read from the config file.
+
+use std::collections::BTreeMap;
+use std::fs::File;
+use std::io::BufReader;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use str_macro::str;
+
+use crate::command::KeymapOverrides;
+use crate::data::Align;
+use crate::data::Column;
+use crate::data::Columns;
+use crate::data::ColumnKey;
+use crate::data::InfoKind;
+use crate::data::Sizing;
+use crate::error::Error;
+use crate::lyrics::LyricsProvider;
+
+/// Limits on how many files an action may touch before asking first. Each
+/// limit is off when not set.
+#[derive(Debug, Clone, Copy, Default, Deserialize)]
+pub struct Confirm {
+    /// Bulk edits touching more files than this ask for confirmation.
+    #[serde(default)]
+    pub bulk_edit_over: Option<usize>,
+
+    /// Saving more files than this lists the files for review first.
+    #[serde(default)]
+    pub save_over: Option<usize>,
+}
+
+impl Confirm {
+    /// Whether a bulk edit of a number of files asks for confirmation first.
+    pub fn needs_bulk_edit_confirm(&self, num_files: usize) -> bool {
+        self.bulk_edit_over.is_some_and(|n| num_files > n)
+    }
+
+    /// Whether saving a number of files lists them for review first.
+    pub fn needs_save_review(&self, num_files: usize) -> bool {
+        self.save_over.is_some_and(|n| num_files > n)
+    }
+}
+
+/// Tags set on the target records in one go, such as a genre shared by a kind
+/// of music, along with the columns worth seeing for it.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct TagPreset {
+    /// Values set by key. An empty list removes the key.
+    #[serde(default)]
+    pub tags: BTreeMap<String, Vec<String>>,
+
+    /// Columns added after the others, unless one with the same key is shown.
+    #[serde(default)]
+    pub columns: Columns,
+}
+
+/// Which parts of tags are converted to Unicode NFC when saving, so that
+/// text typed on systems that decompose accents (NFD) matches everywhere.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NormalizeOnSave {
+    /// Tags are saved as they are.
+    #[default]
+    Off,
+    /// Values are converted, keys are left as they are.
+    Values,
+    /// Both values and keys are converted.
+    ValuesAndKeys,
+}
+
+/// The case tag keys are written in when saving. Keys are meant to be read
+/// regardless of case, but programs differ in which case they look for.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum KeyCase {
+    /// Keys are written as they are.
+    #[default]
+    Preserve,
+    /// Keys are written in upper case.
+    Upper,
+    /// Keys are written in lower case.
+    Lower,
+}
+
+/// Settings for how directories are shown and edited, built up from the
+/// defaults and any number of `ConfigLayer`s.
+#[derive(Debug, Clone)]
+pub struct Config {
+    /// The columns shown, in order.
+    pub columns: Columns,
+
+    /// Whether group header rows should show a cover art indicator.
+    pub show_group_art: bool,
+
+    /// Whether columns sized by their contents are shrunk to fit the view
+    /// when they would not all fit, instead of scrolling sideways.
+    pub fit_width: bool,
+
+    /// Limits on how many files an action may touch before asking first.
+    pub confirm: Confirm,
+
+    /// Changes to the default keymap.
+    pub keys: KeymapOverrides,
+
+    /// Whether opened directories are watched, so that files added, removed,
+    /// or retagged by other programs show up without reloading.
+    pub watch_files: bool,
+
+    /// Whether files whose names start with a dot are loaded.
+    pub show_hidden: bool,
+
+    /// Whether config files are watched, so that changes to them are applied
+    /// to every tab as if `reload_config` was run.
+    pub watch_config: bool,
+
+    /// Words left in lower case when converting to title case, unless they
+    /// start or end a value.
+    pub small_words: Vec<String>,
+
+    /// Whether tags of saved files are converted to NFC first.
+    pub normalize_on_save: NormalizeOnSave,
+
+    /// The case every tag key of a saved file is converted to.
+    pub key_case: KeyCase,
+
+    /// Fields left in place when stripping tags.
+    pub strip_keep: Vec<String>,
+
+    /// Whether saved files keep the modification time they had before, for
+    /// backup and sync tools that go by it.
+    pub preserve_mtime: bool,
+
+    /// The tag that ratings are kept in.
+    pub rating_key: String,
+
+    /// The highest rating, shown as five stars. Commonly 100, or 5.
+    pub rating_max: u32,
+
+    /// Where lyrics are fetched from.
+    pub lyrics_provider: LyricsProvider,
+
+    /// The command files are played with, such as `mpv --no-video {path}`.
+    /// Known players are looked for if not set.
+    pub player: Option<String>,
+
+    /// Commands that can be run on the target records, by name, such as
+    /// `"open_in_player": "mpv {path}"`. See `hook::command_args`.
+    pub hooks: BTreeMap<String, String>,
+
+    /// Rhai scripts that transform the tags of the target records, by name,
+    /// such as `"swap": "let a = tags.ARTIST; tags.ARTIST = tags.TITLE; tags.TITLE = a;"`.
+    /// See `script::Script`.
+    pub scripts: BTreeMap<String, String>,
+
+    /// Tags to set on the target records, by name. See `TagPreset`.
+    pub tag_presets: BTreeMap<String, TagPreset>,
+
+    /// Turns off every command that changes tags or files, for browsing a
+    /// library without touching it.
+    pub read_only: bool,
+
+    /// Makes saving show a diff of what would be written instead of writing.
+    pub dry_run: bool,
+}
+
+/// Any part of a config, as read from one file. Settings left out keep their
+/// value from the layers below.
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct ConfigLayer {
+    /// Overrides `Config::columns`.
+    pub columns: Option<Columns>,
+    /// Overrides `Config::show_group_art`.
+    pub show_group_art: Option<bool>,
+    /// Overrides `Config::fit_width`.
+    pub fit_width: Option<bool>,
+    /// Overrides `Config::confirm`.
+    pub confirm: Option<Confirm>,
+    /// Overrides `Config::keys`.
+    pub keys: Option<KeymapOverrides>,
+    /// Overrides `Config::watch_files`.
+    pub watch_files: Option<bool>,
+    /// Overrides `Config::watch_config`.
+    pub watch_config: Option<bool>,
+    /// Overrides `Config::show_hidden`.
+    pub show_hidden: Option<bool>,
+    /// Overrides `Config::small_words`.
+    pub small_words: Option<Vec<String>>,
+    /// Overrides `Config::normalize_on_save`.
+    pub normalize_on_save: Option<NormalizeOnSave>,
+    /// Overrides `Config::key_case`.
+    pub key_case: Option<KeyCase>,
+    /// Overrides `Config::strip_keep`.
+    pub strip_keep: Option<Vec<String>>,
+    /// Overrides `Config::preserve_mtime`.
+    pub preserve_mtime: Option<bool>,
+    /// Overrides `Config::rating_key`.
+    pub rating_key: Option<String>,
+    /// Overrides `Config::rating_max`.
+    pub rating_max: Option<u32>,
+    /// Overrides `Config::lyrics_provider`.
+    pub lyrics_provider: Option<LyricsProvider>,
+    /// Overrides `Config::player`.
+    pub player: Option<String>,
+    /// Overrides `Config::hooks`.
+    pub hooks: Option<BTreeMap<String, String>>,
+    /// Overrides `Config::scripts`.
+    pub scripts: Option<BTreeMap<String, String>>,
+    /// Overrides `Config::tag_presets`.
+    pub tag_presets: Option<BTreeMap<String, TagPreset>>,
+    /// Overrides `Config::read_only`.
+    pub read_only: Option<bool>,
+    /// Overrides `Config::dry_run`.
+    pub dry_run: Option<bool>,
+}
+
+impl ConfigLayer {
+    /// The extensions config files are looked for with, in order.
+    const EXTENSIONS: &'static [&'static str] = &["toml", "yaml", "yml", "json"];
+
+    /// Reads a config file, as TOML or YAML if its extension says so, and as
+    /// JSON otherwise.
+    pub fn read_from_path(path: &Path) -> Result<Self, Error> {
+        log::debug!("reading config {}", path.display());
+
+        let settings_error = |err: String| Error::Settings(path.to_path_buf(), err);
+        let invalid = |err: &dyn std::fmt::Display| settings_error(format!("invalid config: {}", err));
+
+        let extension = path.extension().and_then(|ext| ext.to_str()).map(str::to_ascii_lowercase);
+
+        match extension.as_deref() {
+            Some("toml") => {
+                let contents = std::fs::read_to_string(path).map_err(|err| settings_error(err.to_string()))?;
+                toml::from_str(&contents).map_err(|err| invalid(&err))
+            },
+            Some("yaml") | Some("yml") => {
+                let reader = BufReader::new(File::open(path).map_err(|err| settings_error(err.to_string()))?);
+                serde_yaml::from_reader(reader).map_err(|err| invalid(&err))
+            },
+            _ => {
+                let reader = BufReader::new(File::open(path).map_err(|err| settings_error(err.to_string()))?);
+                serde_json::from_reader(reader).map_err(|err| invalid(&err))
+            },
+        }
+    }
+
+    /// Whether a path is of a config file with the given name, such as
+    /// `.diargos.yaml`.
+    pub fn is_named(path: &Path, name: &str) -> bool {
+        path.file_stem().is_some_and(|stem| stem == name)
+        && path.extension().and_then(|ext| ext.to_str()).is_some_and(|ext| Self::EXTENSIONS.contains(&ext))
+    }
+
+    /// Reads the first config file in a directory with the given name and a
+    /// known extension, such as `.diargos.toml`. No file makes an empty layer.
+    pub fn find_in(dir: &Path, name: &str) -> Result<Self, Error> {
+        Self::EXTENSIONS.iter()
+        .map(|ext| dir.join(format!("{}.{}", name, ext)))
+        .find(|path| path.is_file())
+        .map_or_else(|| Ok(Self::default()), |path| Self::read_from_path(&path))
+    }
+}
+
+/// The layers a config is built from for each directory, from lowest to
+/// highest precedence: the defaults, the user config, the config in the
+/// directory itself, and the config file and flags given on the command line.
+#[derive(Debug, Clone, Default)]
+pub struct LayeredConfig {
+    /// The user config.
+    pub user: ConfigLayer,
+    /// The config file and flags given on the command line.
+    pub command_line: ConfigLayer,
+}
+
+impl LayeredConfig {
+    /// The name of the config file looked for in each opened directory.
+    pub const DIR_CONFIG_NAME: &'static str = ".diargos";
+
+    /// The name of the config file looked for in the user config directory.
+    pub const USER_CONFIG_NAME: &'static str = "config";
+
+    /// Where the user config is looked for, following the XDG base directory
+    /// spec.
+    pub fn user_config_dir() -> Option<PathBuf> {
+        let config_dir =
+            std::env::var_os("XDG_CONFIG_HOME").map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".config")))?
+        ;
+
+        Some(config_dir.join("diargos"))
+    }
+
+    /// Reads the user config, if there is one, to go under the given command
+    /// line layer.
+    pub fn load(command_line: ConfigLayer) -> Result<Self, Error> {
+        let user = match Self::user_config_dir() {
+            Some(dir) => ConfigLayer::find_in(&dir, Self::USER_CONFIG_NAME)?,
+            None => ConfigLayer::default(),
+        };
+
+        Ok(Self { user, command_line })
+    }
+
+    /// The config for a directory, with its own config file layered in if it
+    /// has one.
+    pub fn resolve(&self, dir: Option<&Path>) -> Result<Config, Error> {
+        let mut config = Config::default();
+        config.apply(&self.user);
+
+        if let Some(dir) = dir {
+            config.apply(&ConfigLayer::find_in(dir, Self::DIR_CONFIG_NAME)?);
+        }
+
+        config.apply(&self.command_line);
+        Ok(config)
+    }
+}
+
+impl Config {
+    /// Takes every setting a layer has. Keys, hooks, scripts, and tag presets
+    /// are merged by name, and confirmation limits one by one, while other
+    /// settings, including the columns, are replaced as a whole.
+    pub fn apply(&mut self, layer: &ConfigLayer) {
+        fn set<T: Clone>(value: &mut T, layer_value: &Option<T>) {
+            if let Some(layer_value) = layer_value {
+                *value = layer_value.clone();
+            }
+        }
+
+        set(&mut self.columns, &layer.columns);
+        set(&mut self.show_group_art, &layer.show_group_art);
+        set(&mut self.fit_width, &layer.fit_width);
+        set(&mut self.watch_files, &layer.watch_files);
+        set(&mut self.watch_config, &layer.watch_config);
+        set(&mut self.show_hidden, &layer.show_hidden);
+        set(&mut self.small_words, &layer.small_words);
+        set(&mut self.normalize_on_save, &layer.normalize_on_save);
+        set(&mut self.key_case, &layer.key_case);
+        set(&mut self.strip_keep, &layer.strip_keep);
+        set(&mut self.preserve_mtime, &layer.preserve_mtime);
+        set(&mut self.rating_key, &layer.rating_key);
+        set(&mut self.rating_max, &layer.rating_max);
+        set(&mut self.lyrics_provider, &layer.lyrics_provider);
+        set(&mut self.read_only, &layer.read_only);
+        set(&mut self.dry_run, &layer.dry_run);
+
+        if let Some(player) = &layer.player {
+            self.player = Some(player.clone());
+        }
+
+        if let Some(confirm) = &layer.confirm {
+            if confirm.bulk_edit_over.is_some() { self.confirm.bulk_edit_over = confirm.bulk_edit_over; }
+            if confirm.save_over.is_some() { self.confirm.save_over = confirm.save_over; }
+        }
+
+        if let Some(keys) = &layer.keys {
+            self.keys.extend(keys.clone());
+        }
+
+        if let Some(hooks) = &layer.hooks {
+            self.hooks.extend(hooks.clone());
+        }
+
+        if let Some(scripts) = &layer.scripts {
+            self.scripts.extend(scripts.clone());
+        }
+
+        if let Some(tag_presets) = &layer.tag_presets {
+            self.tag_presets.extend(tag_presets.clone());
+        }
+    }
+
+    fn default_show_group_art() -> bool {
+        true
+    }
+
+    fn default_fit_width() -> bool {
+        true
+    }
+
+    fn default_watch_files() -> bool {
+        true
+    }
+
+    /// The tag ratings are kept in when none is configured.
+    pub fn default_rating_key() -> String {
+        str!("RATING")
+    }
+
+    /// The highest rating when none is configured.
+    pub fn default_rating_max() -> u32 {
+        100
+    }
+
+    /// The words left in lower case in title case when none are configured.
+    pub fn default_small_words() -> Vec<String> {
+        [
+            "a", "an", "and", "as", "at", "but", "by", "for", "from", "in",
+            "nor", "of", "on", "or", "the", "to", "vs", "with",
+        ]
+        .iter()
+        .map(|w| w.to_string())
+        .collect()
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Self {
+            columns: vec![
+                Column {
+                    key: ColumnKey::Meta(str!("ARTIST")),
+                    title: str!("Artist"),
+                    sizing: Sizing::Auto,
+                    align: Align::Left,
+                    kind: None,
+                    separator: None,
+                },
+                Column {
+                    key: ColumnKey::Meta(str!("TITLE")),
+                    title: str!("Title"),
+                    sizing: Sizing::Auto,
+                    align: Align::Left,
+                    kind: None,
+                    separator: None,
+                },
+                Column {
+                    key: ColumnKey::Meta(str!("ALBUM")),
+                    title: str!("Album"),
+                    sizing: Sizing::Auto,
+                    align: Align::Left,
+                    kind: None,
+                    separator: None,
+                },
+                Column {
+                    key: ColumnKey::Info(InfoKind::FileName),
+                    title: str!("File Name"),
+                    sizing: Sizing::Auto,
+                    align: Align::Left,
+                    kind: None,
+                    separator: None,
+                },
+            ],
+            show_group_art: Self::default_show_group_art(),
+            fit_width: Self::default_fit_width(),
+            confirm: Confirm::default(),
+            keys: KeymapOverrides::new(),
+            watch_files: Self::default_watch_files(),
+            watch_config: false,
+            show_hidden: false,
+            small_words: Self::default_small_words(),
+            normalize_on_save: NormalizeOnSave::default(),
+            key_case: KeyCase::default(),
+            strip_keep: Vec::new(),
+            preserve_mtime: false,
+            rating_key: Self::default_rating_key(),
+            rating_max: Self::default_rating_max(),
+            lyrics_provider: LyricsProvider::default(),
+            player: None,
+            hooks: BTreeMap::new(),
+            scripts: BTreeMap::new(),
+            tag_presets: BTreeMap::new(),
+            read_only: false,
+            dry_run: false,
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn deserialize() {
+        let input = r#"{
+            "columns": [
+                {
+                    "meta": "ARTIST",
+                    "title": "Artist",
+                    "sizing": null,
+                    "separator": "; "
+                },
+                {
+                    "meta": "TITLE",
+                    "title": "Title",
+                    "sizing": null
+                },
+                {
+                    "info": "file_name",
+                    "title": "File Name",
+                    "sizing": null
+                }
+            ],
+            "confirm": {
+                "bulk_edit_over": 10
+            }
+        }"#;
+
+        let mut config = Config::default();
+        config.apply(&serde_json::from_str(input).unwrap());
+        println!("{:?}", config);
+
+        assert_eq!(config.columns[0].separator(), "; ");
+        assert_eq!(config.columns[1].separator(), "|");
+
+        assert!(config.confirm.needs_bulk_edit_confirm(11));
+        assert!(!config.confirm.needs_bulk_edit_confirm(10));
+        assert!(!config.confirm.needs_save_review(1000));
+    }
+
+    #[test]
+    fn deserialize_toml() {
+        let input = r#"
+            read_only = true
+
+            [[columns]]
+            meta = "ARTIST"
+            title = "Artist"
+            separator = "; "
+
+            [[columns]]
+            meta = "TITLE"
+            title = "Title"
+            sizing = { min = 10 }
+
+            [[columns]]
+            info = "file_name"
+            title = "File Name"
+            sizing = [5, 20]
+
+            [keys]
+            "alt-S" = "save"
+
+            [confirm]
+            bulk_edit_over = 10
+
+            [tag_presets.classical]
+            tags = { GENRE = ["Classical"] }
+            columns = [{ meta = "COMPOSER", title = "Composer" }]
+        "#;
+
+        let mut config = Config::default();
+        config.apply(&toml::from_str(input).unwrap());
+
+        assert!(matches!(config.columns[0].sizing, Sizing::Auto));
+        assert_eq!(config.columns[0].separator(), "; ");
+        assert!(matches!(config.columns[1].sizing, Sizing::Lower(10)));
+        assert!(matches!(config.columns[2].sizing, Sizing::Bound(5, 20)));
+        assert!(matches!(config.columns[2].key, ColumnKey::Info(InfoKind::FileName)));
+
+        assert_eq!(config.keys.len(), 1);
+        assert_eq!(config.tag_presets["classical"].tags["GENRE"], vec!["Classical"]);
+        assert_eq!(config.tag_presets["classical"].columns[0].title, "Composer");
+        assert!(config.confirm.needs_bulk_edit_confirm(11));
+        assert!(config.read_only);
+        assert!(config.watch_files);
+    }
+
+    #[test]
+    fn deserialize_yaml() {
+        let input = r#"
+            columns:
+              - meta: ARTIST
+                title: Artist
+                sizing: [~, 30]
+              - meta: TITLE
+                title: Title
+                sizing: { max: 40 }
+                align: right
+              - meta: ALBUM
+                title: Album
+                sizing: { percent: 25 }
+              - meta: GENRE
+                title: Genre
+                sizing: { weight: 2 }
+              - meta: COMMENT
+                title: Comment
+                sizing: visible
+            keys:
+              ctrl-s: ~
+            rating_max: 5
+        "#;
+
+        let mut config = Config::default();
+        config.apply(&serde_yaml::from_str(input).unwrap());
+
+        assert!(matches!(config.columns[0].sizing, Sizing::Upper(30)));
+        assert!(matches!(config.columns[1].sizing, Sizing::Upper(40)));
+        assert_eq!(config.columns[1].align, Align::Right);
+        assert!(matches!(config.columns[2].sizing, Sizing::Percent(25)));
+        assert!(matches!(config.columns[3].sizing, Sizing::Weight(2)));
+        assert!(matches!(config.columns[4].sizing, Sizing::Visible));
+
+        assert_eq!(config.keys.values().collect::<Vec<_>>(), vec![&None]);
+        assert_eq!(config.rating_max, 5);
+    }
+
+    #[test]
+    fn layers() {
+        let dir = std::env::temp_dir().join(format!("diargos-config-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join(".diargos.toml"), "rating_max = 10\n[hooks]\nplay = \"mpv {path}\"\n").unwrap();
+
+        let layered = LayeredConfig {
+            user: serde_json::from_str(r#"{
+                "rating_max": 5,
+                "rating_key": "FMPS_RATING",
+                "hooks": { "open": "xdg-open {path}" },
+                "confirm": { "save_over": 20 }
+            }"#).unwrap(),
+            command_line: ConfigLayer { read_only: Some(true), ..ConfigLayer::default() },
+        };
+
+        let config = layered.resolve(Some(&dir)).unwrap();
+
+        // The directory overrides the user config, field by field.
+        assert_eq!(config.rating_max, 10);
+        assert_eq!(config.rating_key, "FMPS_RATING");
+        assert_eq!(config.hooks.keys().collect::<Vec<_>>(), vec!["open", "play"]);
+        assert_eq!(config.confirm.save_over, Some(20));
+        assert_eq!(config.columns.len(), Config::default().columns.len());
+        assert!(config.read_only);
+
+        // Other directories only get the user config and the command line.
+        let config = layered.resolve(None).unwrap();
+        assert_eq!(config.rating_max, 5);
+        assert_eq!(config.hooks.len(), 1);
+
+        std::fs::write(dir.join(".diargos.toml"), "rating_max = \"ten\"").unwrap();
+        assert!(matches!(layered.resolve(Some(&dir)), Err(Error::Settings(..))));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,68 @@
+//! Text and defaults shared across the table and the UI.
+
+/// Ends text cut short to fit a cell.
+pub const ELLIPSIS_STR: &str = "⋯";
+/// Separates the values of a field with more than one value.
+pub const FIELD_SEP_STR: &str = "|";
+
+/// Fills a cell whose tag is not there.
+pub const MISSING_FILL: &str = "x";
+/// Stands in for a tag that is not there, where there is room for words.
+pub const MISSING_VALUE_STR: &str = "(missing)";
+/// Fills a cell whose tag has nothing but empty values.
+pub const EMPTY_FILL: &str = "-";
+/// Stands in for a tag with nothing but empty values, where there is room
+/// for words.
+pub const EMPTY_VALUE_STR: &str = "(empty)";
+
+/// The key that records are grouped by when no other is chosen.
+pub const GROUP_BY_KEY: &str = "ALBUM";
+
+/// The template files are renamed with when none is configured.
+pub const DEFAULT_RENAME_TEMPLATE: &str = "{tracknumber} - {artist} - {title}";
+/// The pattern tags are read from file names with when none is configured.
+pub const DEFAULT_NAME_PATTERN: &str = "{artist} - {album} - {tracknumber} - {title}";
+
+/// Stands in for a field whose values differ between the records of a group.
+pub const VARIOUS_VALUES_STR: &str = "(various)";
+/// Names the group of records that do not have the grouping key.
+pub const MISSING_GROUP_STR: &str = "(none)";
+/// Marks a group whose records have cover art.
+pub const COVER_ART_STR: &str = "▣";
+/// Marks a group whose records have no cover art.
+pub const NO_COVER_ART_STR: &str = "□";
+/// Marks a group whose records are shown.
+pub const EXPANDED_GROUP_STR: &str = "▾";
+/// A star of a rating that is earned.
+pub const RATING_FULL_STR: &str = "★";
+/// A star of a rating that is not earned.
+pub const RATING_EMPTY_STR: &str = "☆";
+/// Marks a group whose records are hidden.
+pub const COLLAPSED_GROUP_STR: &str = "▸";
+/// Marks a row with changes that have not been written yet.
+pub const EDITED_MARKER: &str = "*";
+/// Marks a row that last failed to be written.
+pub const WRITE_FAILED_MARKER: &str = "!";
+/// Marks a row whose file can't be written.
+pub const READ_ONLY_MARKER: &str = "#";
+
+/// Shows a leading or trailing space.
+pub const SPACE_MARKER: char = '·';
+/// Shows a tab.
+pub const TAB_MARKER: char = '→';
+/// Shows a non-breaking space.
+pub const NBSP_MARKER: char = '⍽';
+/// Shows a line break.
+pub const NEWLINE_MARKER: char = '↵';
+/// Shows a zero-width or control character.
+pub const INVISIBLE_MARKER: char = '¤';
+
+/// Separates the cells of a row.
+pub const COLUMN_SEP: &str = " │ ";
+// pub const COLUMN_HEADER_SEP: &str = "─┼─";
+/// Separates the columns in the bar under the header.
+pub const COLUMN_HEADER_SEP: &str = "═╪═";
+
+// pub const COLUMN_HEADER_BAR: &str = "─";
+/// The bar under the column titles.
+pub const COLUMN_HEADER_BAR: &str = "═";
@@ -1,30 +1,57 @@
+//! Where the cursor is in the table, and how it moves.
 
+/// A direction the cursor moves in.
 #[derive(Debug, Clone, Copy)]
 pub enum CursorDir {
-    U, D, L, R,
+    /// Up, towards the header.
+    U,
+    /// Down.
+    D,
+    /// Left.
+    L,
+    /// Right.
+    R,
 }
 
+/// The cell, column, or row the cursor is on.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum Cursor {
+    /// A field of a record, by column and row.
     Cell(usize, usize),
+    /// The header of a column.
     Column(usize),
+    /// A whole record, for actions on files rather than on single fields.
+    Row(usize),
 }
 
 impl Cursor {
-    pub fn to_xy(&self) -> (usize, Option<usize>) {
+    /// The column and row of the cursor, either of which is `None` when the
+    /// cursor is on a whole row or column.
+    pub fn to_xy(self) -> (Option<usize>, Option<usize>) {
         match self {
-            Self::Cell(x, y) => (*x, Some(*y)),
-            Self::Column(x) => (*x, None),
+            Self::Cell(x, y) => (Some(x), Some(y)),
+            Self::Column(x) => (Some(x), None),
+            Self::Row(y) => (None, Some(y)),
         }
     }
 
+    /// Moves to a record, staying in the same column, or on the whole row.
+    pub fn with_row(self, y: usize) -> Self {
+        match self {
+            Self::Cell(x, _) | Self::Column(x) => Self::Cell(x, y),
+            Self::Row(_) => Self::Row(y),
+        }
+    }
+
+    /// The column whose header the cursor is on, if it is on one.
     pub fn column_index(&self) -> Option<usize> {
         match self {
-            Self::Cell(..) => None,
+            Self::Cell(..) | Self::Row(..) => None,
             Self::Column(x) => Some(*x),
         }
     }
 
+    /// Keeps the cursor within a number of columns and rows.
     pub fn clamp(&mut self, bound_x: usize, bound_y: usize) {
         let max_idx_x = bound_x.saturating_sub(1);
         let max_idx_y = bound_y.saturating_sub(1);
@@ -37,9 +64,15 @@ impl Cursor {
             Self::Column(ref mut x) => {
                 *x = max_idx_x.min(*x);
             },
+            Self::Row(ref mut y) => {
+                *y = max_idx_y.min(*y);
+            },
         };
     }
 
+    /// Moves the cursor `n` steps in a direction, staying within a number of
+    /// columns and rows. Moving up past the first row goes to the header,
+    /// and moving down from the header goes to the `n`th row.
     pub fn shift(&mut self, dir: CursorDir, n: usize, bound_x: usize, bound_y: usize) {
         // Skip work if a delta of 0 is given.
         if n > 0 {
@@ -52,12 +85,13 @@ impl Cursor {
                                 None => { *self = Self::Column(*x); },
                             }
                         },
+                        Self::Row(ref mut y) => { *y = y.saturating_sub(n); },
                         Self::Column(..) => {}
                     }
                 },
                 CursorDir::D => {
                     match self {
-                        Self::Cell(_, ref mut y) => { *y = y.saturating_add(n); },
+                        Self::Cell(_, ref mut y) | Self::Row(ref mut y) => { *y = y.saturating_add(n); },
                         Self::Column(x) => { *self = Self::Cell(*x, n.saturating_sub(1)); }
                     }
                 },
@@ -65,12 +99,14 @@ impl Cursor {
                     match self {
                         Self::Cell(ref mut x, _) => { *x = x.saturating_sub(n); },
                         Self::Column(ref mut x) => { *x = x.saturating_sub(n); }
+                        Self::Row(..) => {}
                     }
                 },
                 CursorDir::R => {
                     match self {
                         Self::Cell(ref mut x, _) => { *x = x.saturating_add(n); },
                         Self::Column(ref mut x) => { *x = x.saturating_add(n); }
+                        Self::Row(..) => {}
                     }
                 },
             };
@@ -80,11 +116,8 @@ impl Cursor {
         self.clamp(bound_x, bound_y);
     }
 
+    /// Whether the cursor is on the header of a column.
     pub fn is_in_column_mode(&self) -> bool {
         matches!(self, Self::Column(..))
     }
-
-    pub fn is_in_cell_mode(&self) -> bool {
-        matches!(self, Self::Cell(..))
-    }
 }
@@ -0,0 +1,550 @@
+//! Records, the columns they are shown under, and the values in each cell.
+
+use std::borrow::Cow;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+use std::path::PathBuf;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::computed::Expression;
+use crate::consts::FIELD_SEP_STR;
+use crate::value::Date;
+use crate::value::Value;
+use crate::value::ValueKind;
+
+/// How the width of a column is worked out.
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+#[serde(from = "SizingRepr", into = "SizingRepr")]
+pub enum Sizing {
+    /// As wide as the widest value in the column.
+    #[default]
+    Auto,
+    /// A fixed width.
+    Fixed(usize),
+    /// As wide as the widest value, but at least this wide.
+    Lower(usize),
+    /// As wide as the widest value, but at most this wide.
+    Upper(usize),
+    /// As wide as the widest value, between a least and a most width.
+    Bound(usize, usize),
+    /// Like `Auto`, but only measuring the rows in view, so that a few very
+    /// long values do not widen the column for the whole table.
+    Visible,
+    /// A share of the width of the view, in percent.
+    Percent(u8),
+    /// A share of the width left over by the other columns, weighed against
+    /// the other weighted columns.
+    Weight(u32),
+}
+
+/// How a `Sizing` is written in config files: `null` for automatic, a number
+/// for a fixed width, `[10, null]`, `[null, 20]` and `[10, 20]` for bounds, and
+/// `{ percent = 30 }` or `{ weight = 2 }` for shares.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(untagged)]
+pub enum SizingRepr {
+    /// `null`
+    Auto,
+    /// A number.
+    Fixed(usize),
+    /// `[min, null]`
+    Lower(usize, ()),
+    /// `[null, max]`
+    Upper((), usize),
+    /// `[min, max]`
+    Bound(usize, usize),
+    /// `{ percent = 30 }`
+    Percent {
+        /// The share of the width of the view, in percent.
+        percent: u8,
+    },
+    /// `{ weight = 2 }`
+    Weight {
+        /// The weight against the other weighted columns.
+        weight: u32,
+    },
+    /// A sizing given by name, such as `"visible"`.
+    Named(NamedSizing),
+    /// Bounds written as a table, such as `{ min = 10 }`, for formats like
+    /// TOML that have no null to write `[10, null]` with.
+    Range {
+        /// The least width, if any.
+        min: Option<usize>,
+        /// The most width, if any.
+        max: Option<usize>,
+    },
+}
+
+impl From<SizingRepr> for Sizing {
+    fn from(repr: SizingRepr) -> Self {
+        match repr {
+            SizingRepr::Auto => Sizing::Auto,
+            SizingRepr::Fixed(width) => Sizing::Fixed(width),
+            SizingRepr::Lower(min_width, ()) => Sizing::Lower(min_width),
+            SizingRepr::Upper((), max_width) => Sizing::Upper(max_width),
+            SizingRepr::Bound(min_width, max_width) => {
+                // Ensure proper order.
+                if min_width > max_width {
+                    log::warn!("column sizing has a minimum width of {} over its maximum of {}, using the minimum", min_width, max_width);
+                    Sizing::Bound(min_width, min_width)
+                } else {
+                    Sizing::Bound(min_width, max_width)
+                }
+            },
+            SizingRepr::Percent { percent } => {
+                if percent > 100 {
+                    log::warn!("column sizing of {} percent is over 100, using 100", percent);
+                }
+
+                Sizing::Percent(percent.min(100))
+            },
+            SizingRepr::Weight { weight } => Sizing::Weight(weight),
+            SizingRepr::Named(NamedSizing::Auto) => Sizing::Auto,
+            SizingRepr::Named(NamedSizing::Visible) => Sizing::Visible,
+            SizingRepr::Range { min: None, max: None } => Sizing::Auto,
+            SizingRepr::Range { min: Some(min_width), max: None } => Sizing::Lower(min_width),
+            SizingRepr::Range { min: None, max: Some(max_width) } => Sizing::Upper(max_width),
+            SizingRepr::Range { min: Some(min_width), max: Some(max_width) } => SizingRepr::Bound(min_width, max_width).into(),
+        }
+    }
+}
+
+impl From<Sizing> for SizingRepr {
+    fn from(sizing: Sizing) -> Self {
+        match sizing {
+            Sizing::Auto => SizingRepr::Auto,
+            Sizing::Fixed(width) => SizingRepr::Fixed(width),
+            Sizing::Lower(min_width) => SizingRepr::Lower(min_width, ()),
+            Sizing::Upper(max_width) => SizingRepr::Upper((), max_width),
+            Sizing::Bound(min_width, max_width) => SizingRepr::Bound(min_width, max_width),
+            Sizing::Percent(percent) => SizingRepr::Percent { percent },
+            Sizing::Weight(weight) => SizingRepr::Weight { weight },
+            Sizing::Visible => SizingRepr::Named(NamedSizing::Visible),
+        }
+    }
+}
+
+/// A sizing given by name.
+#[derive(Clone, Copy, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum NamedSizing {
+    /// `"auto"`
+    Auto,
+    /// `"visible"`
+    Visible,
+}
+
+/// How the values of a column are aligned.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum Align {
+    /// Against the left edge.
+    #[default]
+    Left,
+    /// Against the right edge, as for numbers.
+    Right,
+}
+
+impl Align {
+    /// Calculates the offset needed to align a piece of content of a given
+    /// width within a space of a (possibly larger) target width.
+    pub fn offset(&self, content_width: usize, target_width: usize) -> usize {
+        match self {
+            Self::Left => 0,
+            Self::Right => target_width.saturating_sub(content_width),
+        }
+    }
+}
+
+/// Information about a file, rather than from its tags.
+#[derive(Debug, Clone, Copy, Hash, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum InfoKind {
+    /// The name of the file.
+    FileName,
+    /// The full path of the file.
+    FilePath,
+    /// The size of the file in bytes, shown like `4.2 MiB`.
+    FileSize,
+    /// How long the audio plays for, from the stream info.
+    Duration,
+    /// When the file was last modified, shown in UTC.
+    Modified,
+    /// The directory the file was found in, of those given to load, as it
+    /// was given.
+    SourceRoot,
+}
+
+impl InfoKind {
+    /// Writes out a file size with a binary unit, such as `4.2 MiB`.
+    fn format_size(bytes: u64) -> String {
+        const UNITS: [&str; 4] = ["KiB", "MiB", "GiB", "TiB"];
+
+        if bytes < 1024 {
+            return format!("{} B", bytes);
+        }
+
+        let mut size = bytes as f64 / 1024.0;
+        let mut unit = 0;
+
+        while size >= 1024.0 && unit + 1 < UNITS.len() {
+            size /= 1024.0;
+            unit += 1;
+        }
+
+        format!("{:.1} {}", size, UNITS[unit])
+    }
+
+    /// Writes out seconds since the Unix epoch as a date and time in UTC.
+    fn format_timestamp(secs: u64) -> String {
+        let date = Date::from_days_since_epoch((secs / 86_400) as i64);
+        let secs_of_day = secs % 86_400;
+
+        format!("{} {:02}:{:02}", date, secs_of_day / 3600, (secs_of_day / 60) % 60)
+    }
+}
+
+/// What a column shows.
+#[derive(Debug, Clone, Hash, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ColumnKey {
+    /// The values of a metadata key.
+    Meta(String),
+    /// Information about the file.
+    Info(InfoKind),
+    /// The first line of output of an external command run on each file, see
+    /// `hook::command_args` for its placeholders.
+    Command(String),
+    /// A value worked out from other fields, see `computed::Expression`.
+    Computed(String),
+}
+
+/// A column of the table, and how it is shown.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Column {
+    /// The raw string metadata key for this column.
+    #[serde(flatten)]
+    pub key: ColumnKey,
+
+    /// A friendly human-readable name for the column, used for display.
+    pub title: String,
+
+    /// Sizing for this column.
+    /// This affects the width of the content of the column, it does not include
+    /// any column padding/separators in the width. Automatic if not provided.
+    #[serde(default)]
+    pub sizing: Sizing,
+
+    /// Horizontal alignment of the content within this column.
+    #[serde(default)]
+    pub align: Align,
+
+    /// How the values of this column should be interpreted, for sorting and
+    /// validation. If not provided, this is inferred from the column key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<ValueKind>,
+
+    /// The string shown between the values of a multi-value tag. This only
+    /// affects display, not how the values are stored.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub separator: Option<String>,
+}
+
+impl Column {
+    /// The kind its values are read as, given or inferred from its key.
+    pub fn value_kind(&self) -> ValueKind {
+        match (&self.kind, &self.key) {
+            (Some(kind), _) => *kind,
+            (None, ColumnKey::Meta(meta_key)) => ValueKind::infer(meta_key),
+            (None, ColumnKey::Info(InfoKind::FileSize)) => ValueKind::Integer,
+            (None, ColumnKey::Info(InfoKind::Duration)) => ValueKind::Duration,
+            (None, ColumnKey::Info(..)) => ValueKind::Text,
+            (None, ColumnKey::Command(..) | ColumnKey::Computed(..)) => ValueKind::Text,
+        }
+    }
+
+    /// What the values of a multi-value tag are shown separated by.
+    pub fn separator(&self) -> &str {
+        self.separator.as_deref().unwrap_or(FIELD_SEP_STR)
+    }
+}
+
+/// The column that the records are sorted by, along with the sort direction.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct SortSpec {
+    /// The index of the column.
+    pub column: usize,
+    /// Whether the order is from highest to lowest.
+    #[serde(default)]
+    pub descending: bool,
+}
+
+/// The values of each metadata key.
+pub type Metadata = HashMap<String, Vec<String>>;
+
+/// The tags of one file, as read from disk, plus any edits to them that have
+/// not been written yet.
+#[derive(Clone)]
+pub struct Record {
+    /// The tags as read from disk.
+    pub metadata: Metadata,
+    /// The path of the file.
+    pub file_path: PathBuf,
+
+    /// Changes to the metadata that have not been written to disk yet.
+    /// An entry with a value of `None` marks that key for removal.
+    pub edits: HashMap<String, Option<Vec<String>>>,
+
+    /// Whether the file has at least one embedded picture block.
+    pub has_picture: bool,
+
+    /// The size of the file in bytes, if known.
+    pub file_size: Option<u64>,
+
+    /// How long the audio plays for in seconds, if known.
+    pub duration: Option<u64>,
+
+    /// When the file was last modified as of reading it, if known. Used to
+    /// notice other programs changing the file before writing over it.
+    pub modified: Option<SystemTime>,
+
+    /// Whether the file could not be opened for writing as of reading it,
+    /// such as from its permissions or a read-only filesystem. No edits are
+    /// staged on read-only records.
+    pub read_only: bool,
+
+    /// The directory the file was found in by the source, if any.
+    pub source_root: Option<PathBuf>,
+
+    /// Outputs of column commands run on the file, by command, along with
+    /// when the file was last modified as of running them.
+    pub command_outputs: HashMap<String, (Option<SystemTime>, String)>,
+}
+
+impl Record {
+    /// A record of a file with the given tags, with nothing else known about it.
+    pub fn new(metadata: Metadata, file_path: PathBuf) -> Self {
+        Self { metadata, file_path, edits: HashMap::new(), has_picture: false, file_size: None, duration: None, modified: None, read_only: false, source_root: None, command_outputs: HashMap::new() }
+    }
+
+    /// The values of a metadata key, with pending edits taken into account.
+    pub fn get_meta(&self, meta_key: &str) -> Option<&[String]> {
+        match self.edits.get(meta_key) {
+            Some(edit) => edit.as_deref(),
+            None => self.metadata.get(meta_key).map(AsRef::as_ref),
+        }
+    }
+
+    /// Stages a new value for a metadata key, with `None` removing the key.
+    /// Returns `true` if this changed the value that is seen for the key,
+    /// which is never the case for a read-only record.
+    pub fn set_meta(&mut self, meta_key: &str, values: Option<Vec<String>>) -> bool {
+        if self.read_only {
+            return false;
+        }
+
+        // An empty list of values is treated the same as a missing key.
+        let values = values.filter(|vs| !vs.is_empty());
+
+        if self.get_meta(meta_key) == values.as_deref() {
+            return false;
+        }
+
+        if self.metadata.get(meta_key) == values.as_ref() {
+            // This undoes any pending edit, so there is nothing left to write.
+            self.edits.remove(meta_key);
+        } else {
+            self.edits.insert(meta_key.to_string(), values);
+        }
+
+        true
+    }
+
+    /// Iterates over all metadata keys that currently have a value, taking
+    /// pending edits into account.
+    pub fn meta_keys(&self) -> impl Iterator<Item = &str> {
+        let existing = self.metadata.keys().filter(move |k| !self.edits.contains_key(*k));
+        let edited = self.edits.iter().filter(|(_, v)| v.is_some()).map(|(k, _)| k);
+
+        existing.chain(edited).map(String::as_str)
+    }
+
+    /// Whether the record has edits that have not been written yet.
+    pub fn is_edited(&self) -> bool {
+        !self.edits.is_empty()
+    }
+
+    /// Folds all pending edits into the metadata, to be called once they have
+    /// been written to disk.
+    pub fn commit_edits(&mut self) {
+        for (meta_key, values) in self.edits.drain() {
+            match values {
+                Some(values) => { self.metadata.insert(meta_key, values); },
+                None => { self.metadata.remove(&meta_key); },
+            }
+        }
+    }
+
+    /// Folds edits that were written to disk into the metadata, such as from
+    /// a copy of this record. Pending edits that were made since are kept.
+    pub fn commit_written(&mut self, written: &HashMap<String, Option<Vec<String>>>) {
+        for (meta_key, values) in written {
+            let old_values = match values {
+                Some(values) => self.metadata.insert(meta_key.clone(), values.clone()),
+                None => self.metadata.remove(meta_key),
+            };
+
+            match self.edits.get(meta_key) {
+                Some(pending) if pending == values => { self.edits.remove(meta_key); },
+                Some(_) => {},
+                // The edit was undone since, so the old values need writing back.
+                None => {
+                    if old_values.as_ref() != values.as_ref() {
+                        self.edits.insert(meta_key.clone(), old_values);
+                    }
+                },
+            }
+        }
+    }
+
+    /// Information about the file, as shown in the table.
+    pub fn get_info(&self, info_kind: &InfoKind) -> Option<Cow<'_, str>> {
+        match info_kind {
+            InfoKind::FileName => self.file_path.file_name().and_then(|f| f.to_str()).map(Cow::Borrowed),
+            InfoKind::FilePath => self.file_path.to_str().map(Cow::Borrowed),
+            InfoKind::FileSize => self.file_size.map(|bytes| Cow::Owned(InfoKind::format_size(bytes))),
+            InfoKind::Duration => self.duration.map(|secs| Cow::Owned(Value::Duration(secs).to_string())),
+            InfoKind::Modified => self.modified_secs().map(|secs| Cow::Owned(InfoKind::format_timestamp(secs))),
+            InfoKind::SourceRoot => self.source_root.as_ref().map(|root| root.to_string_lossy()),
+        }
+    }
+
+    /// Information about the file as a typed value, so that sizes, durations
+    /// and times sort by their numbers rather than how they are shown.
+    pub fn get_info_value(&self, info_kind: &InfoKind, kind: ValueKind) -> Option<Value> {
+        match info_kind {
+            InfoKind::FileName | InfoKind::FilePath | InfoKind::SourceRoot => self.get_info(info_kind).map(|val| Value::parse(&val, kind)),
+            InfoKind::FileSize => self.file_size.map(|bytes| Value::Integer(bytes as i64)),
+            InfoKind::Duration => self.duration.map(Value::Duration),
+            InfoKind::Modified => self.modified_secs().map(|secs| Value::Integer(secs as i64)),
+        }
+    }
+
+    fn modified_secs(&self) -> Option<u64> {
+        self.modified?.duration_since(UNIX_EPOCH).ok().map(|d| d.as_secs())
+    }
+
+    /// The output of a column command, unless it has not been run yet or the
+    /// file has changed since.
+    pub fn get_command_output(&self, command: &str) -> Option<&str> {
+        self.command_outputs.get(command)
+        .filter(|(modified, _)| *modified == self.modified)
+        .map(|(_, output)| output.as_str())
+    }
+
+    /// The value of a computed column. A template that can not be read shows
+    /// why in place of the value.
+    pub fn get_computed(&self, template: &str) -> Option<String> {
+        match template.parse::<Expression>() {
+            Ok(expression) => expression.render(self),
+            Err(err) => Some(format!("<{}>", err)),
+        }
+    }
+
+    /// The values of a metadata key read as a kind. Values are kept as the
+    /// strings found in the file, and only typed when asked for.
+    pub fn get_meta_value(&self, meta_key: &str, kind: ValueKind) -> Option<Value> {
+        self.get_meta(meta_key).map(|vals| Value::new(vals, kind))
+    }
+
+    /// The value of a column read as a kind.
+    pub fn get_value(&self, column_key: &ColumnKey, kind: ValueKind) -> Option<Value> {
+        match column_key {
+            ColumnKey::Meta(meta_key) => self.get_meta_value(meta_key, kind),
+            ColumnKey::Info(info_kind) => self.get_info_value(info_kind, kind),
+            ColumnKey::Command(command) => self.get_command_output(command).map(|val| Value::parse(val, kind)),
+            ColumnKey::Computed(template) => self.get_computed(template).map(|val| Value::parse(&val, kind)),
+        }
+    }
+}
+
+/// The columns of a table, in order.
+pub type Columns = Vec<Column>;
+/// Records, in the order they are shown in.
+pub type Records = Vec<Record>;
+
+/// The columns of a table, and the records shown under them.
+pub struct Data {
+    /// The columns, in order.
+    pub columns: Columns,
+    /// The records, in the order they are shown in.
+    pub records: Records,
+}
+
+impl Data {
+    /// No columns and no records.
+    pub fn new() -> Self {
+        Self::with_data(Columns::new(), Records::new())
+    }
+
+    /// The given columns and records.
+    pub fn with_data(columns: Columns, records: Records) -> Self {
+        Self {
+            columns,
+            records,
+        }
+    }
+
+    // pub fn iter_column<'a>(&'a self, column_key: &'a str) -> IterColumn<'a> {
+    //     IterColumn(column_key, self.records.iter())
+    // }
+
+    /// Sorts the records by the values of a column, stably. Missing values sort
+    /// first.
+    pub fn sort_by_column_index(&mut self, column_index: usize, is_descending: bool) {
+        if let Some(column) = self.columns.get(column_index) {
+            let kind = column.value_kind();
+
+            // Parse each value once up front, instead of on every comparison.
+            let mut keyed =
+                self.records.drain(..)
+                .map(|r| (r.get_value(&column.key, kind), r))
+                .collect::<Vec<_>>()
+            ;
+
+            keyed.sort_by(move |(va, _), (vb, _)| {
+                let o = match (va, vb) {
+                    (None, None) => Ordering::Equal,
+                    (None, Some(..)) => Ordering::Less,
+                    (Some(..), None) => Ordering::Greater,
+                    (Some(a), Some(b)) => a.cmp(b),
+                };
+
+                if is_descending { o.reverse() } else { o }
+            });
+
+            self.records.extend(keyed.into_iter().map(|(_, r)| r));
+        }
+    }
+}
+
+impl Default for Data {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+// pub struct IterColumn<'a>(&'a str, SliceIter<'a, Record>);
+
+// impl<'a> Iterator for IterColumn<'a> {
+//     type Item = Option<&'a String>;
+
+//     fn next(&mut self) -> Option<Self::Item> {
+//         let record = self.1.next()?;
+//         Some(record.metadata.get(self.0))
+//     }
+// }
@@ -0,0 +1,206 @@
+//! Finding records that look like the same track.
+
+use std::path::PathBuf;
+
+use crate::data::Record;
+
+/// The metadata keys that records are compared on.
+const COMPARED_KEYS: &[&str] = &["ARTIST", "TITLE"];
+
+/// Words that start a credit for a featured artist.
+const FEATURING_WORDS: &[&str] = &["feat", "feat.", "ft", "ft.", "featuring"];
+
+/// The most edits per character of the longer value for two normalized
+/// values to count as the same.
+const MAX_EDIT_RATIO: f64 = 0.15;
+
+/// Records that look like the same track, by file path, so that they can
+/// still be found after the records are reloaded.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuplicateSet {
+    /// The files of the records in the set.
+    pub file_paths: Vec<PathBuf>,
+    /// Whether the compared tags are exactly equal on every record, as
+    /// opposed to only close once normalized.
+    pub exact: bool,
+}
+
+/// Lowercases a value, and drops featured artist credits, punctuation, and
+/// extra whitespace, so that different spellings of the same name compare
+/// equal.
+pub fn normalize(value: &str) -> String {
+    let mut words = Vec::new();
+
+    // Brackets are opened around credits as often as not, so they just split words.
+    let cleaned =
+        value.to_lowercase()
+        .chars()
+        .map(|c| if "()[]{}".contains(c) { ' ' } else { c })
+        .collect::<String>()
+    ;
+
+    for word in cleaned.split_whitespace() {
+        if FEATURING_WORDS.contains(&word) && !words.is_empty() {
+            break;
+        }
+
+        let word = word.chars().filter(|c| c.is_alphanumeric()).collect::<String>();
+
+        // An ampersand and "and" are the same thing.
+        if word == "and" || word.is_empty() {
+            continue;
+        }
+
+        words.push(word);
+    }
+
+    words.join(" ")
+}
+
+/// The number of single character insertions, deletions, and substitutions
+/// needed to turn one string into another.
+pub fn levenshtein(a: &str, b: &str) -> usize {
+    let b = b.chars().collect::<Vec<_>>();
+    let mut row = (0..=b.len()).collect::<Vec<_>>();
+
+    for (i, ca) in a.chars().enumerate() {
+        let mut diagonal = row[0];
+        row[0] = i + 1;
+
+        for (j, cb) in b.iter().enumerate() {
+            let substitution = diagonal + if ca == *cb { 0 } else { 1 };
+            diagonal = row[j + 1];
+            row[j + 1] = substitution.min(row[j] + 1).min(diagonal + 1);
+        }
+    }
+
+    row[b.len()]
+}
+
+/// The runs of digits in a value, in order.
+fn numbers(value: &str) -> Vec<&str> {
+    value.split(|c: char| !c.is_ascii_digit()).filter(|run| !run.is_empty()).collect()
+}
+
+/// Whether two normalized values are close enough to be the same. Numbers
+/// have to match exactly, since "Part 1" and "Part 2" are different tracks.
+fn is_close(a: &str, b: &str) -> bool {
+    let longest = a.chars().count().max(b.chars().count());
+    a == b || (numbers(a) == numbers(b) && (levenshtein(a, b) as f64) <= longest as f64 * MAX_EDIT_RATIO)
+}
+
+fn compared_values(record: &Record) -> Option<Vec<String>> {
+    COMPARED_KEYS.iter()
+    .map(|key| record.get_meta(key).map(|values| values.join(" ")))
+    .collect()
+}
+
+/// Finds sets of records that have close enough artists and titles to be the
+/// same track, in the order of their first records. Records missing either
+/// tag are never duplicates.
+pub fn find_duplicates(records: &[Record]) -> Vec<DuplicateSet> {
+    let values = records.iter().map(compared_values).collect::<Vec<_>>();
+    let normalized =
+        values.iter()
+        .map(|values| Some(values.as_ref()?.iter().map(|v| normalize(v)).collect::<Vec<_>>()))
+        .collect::<Vec<_>>()
+    ;
+
+    // Each record points at the first record of the set it is in.
+    let mut set_of = (0..records.len()).collect::<Vec<_>>();
+
+    for i in 0..records.len() {
+        let a = match &normalized[i] {
+            Some(a) if set_of[i] == i => a,
+            _ => continue,
+        };
+
+        for j in (i + 1)..records.len() {
+            if set_of[j] != j {
+                continue;
+            }
+
+            if let Some(b) = &normalized[j] {
+                if a.iter().zip(b).all(|(a, b)| is_close(a, b)) {
+                    set_of[j] = i;
+                }
+            }
+        }
+    }
+
+    (0..records.len())
+    .filter_map(|i| {
+        let indices = (i..records.len()).filter(|&j| set_of[j] == i).collect::<Vec<_>>();
+
+        if indices.len() < 2 {
+            return None;
+        }
+
+        let exact = indices.iter().all(|&j| values[j] == values[i]);
+        let file_paths = indices.into_iter().map(|j| records[j].file_path.clone()).collect();
+
+        Some(DuplicateSet { file_paths, exact })
+    })
+    .collect()
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    fn record(artist: &str, title: &str) -> Record {
+        Record::new(
+            hashmap! {
+                str!("ARTIST") => vec![str!(artist)],
+                str!("TITLE") => vec![str!(title)],
+            },
+            PathBuf::from(format!("{} - {}.flac", artist, title)),
+        )
+    }
+
+    #[test]
+    fn normalizing() {
+        assert_eq!(normalize("  Don't  Stop (feat. Someone)"), "dont stop");
+        assert_eq!(normalize("Don't Stop ft. Someone"), "dont stop");
+        assert_eq!(normalize("Simon & Garfunkel"), "simon garfunkel");
+        assert_eq!(normalize("Simon and Garfunkel"), "simon garfunkel");
+        assert_eq!(normalize("Dancing with Myself"), "dancing with myself");
+    }
+
+    #[test]
+    fn distances() {
+        assert_eq!(levenshtein("kitten", "sitting"), 3);
+        assert_eq!(levenshtein("", "abc"), 3);
+        assert_eq!(levenshtein("same", "same"), 0);
+    }
+
+    #[test]
+    fn finding() {
+        let records = vec![
+            record("The Band", "Song Title"),
+            record("Other", "Unrelated"),
+            record("The Band", "Song Title"),
+            record("the band", "Song Title (feat. Guest)"),
+            record("Other", "Unrelatd"),
+            record("Someone", "Different"),
+            record("Other", "Unrelated 2"),
+            record("Other", "Unrelated 3"),
+        ];
+
+        let sets = find_duplicates(&records);
+        let file_paths = |indices: &[usize]| indices.iter().map(|&i| records[i].file_path.clone()).collect::<Vec<_>>();
+
+        assert_eq!(
+            sets,
+            vec![
+                DuplicateSet { file_paths: file_paths(&[0, 2, 3]), exact: false },
+                DuplicateSet { file_paths: file_paths(&[1, 4]), exact: false },
+            ],
+        );
+
+        assert_eq!(find_duplicates(&records[..3]), vec![DuplicateSet { file_paths: file_paths(&[0, 2]), exact: true }]);
+    }
+}
@@ -0,0 +1,33 @@
+//! The errors of loading settings and libraries.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::io::Error as IoError;
+use std::path::PathBuf;
+
+use metaflac::Error as MetaflacError;
+
+/// What can go wrong while loading settings or a library, each with the path
+/// it happened at.
+#[derive(Debug)]
+pub enum Error {
+    /// A directory that could not be listed.
+    Dir(PathBuf, IoError),
+    /// A settings file, such as the config or a preset, that could not be read.
+    Settings(PathBuf, String),
+    /// A file that could not be read as FLAC.
+    Flac(PathBuf, MetaflacError),
+}
+
+impl Display for Error {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Dir(path, err) => write!(f, "unable to read {}: {}", path.display(), err),
+            Self::Settings(path, err) => write!(f, "unable to read {}: {}", path.display(), err),
+            Self::Flac(path, err) => write!(f, "unable to read {}: {}", path.display(), err),
+        }
+    }
+}
+
+impl std::error::Error for Error {}
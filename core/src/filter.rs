@@ -0,0 +1,275 @@
+//! Conditions on tags, for showing or editing only some of the records.
+
+use std::str::FromStr;
+
+use crate::data::Record;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Token {
+    Word(String),
+    /// Quoted text, which is never a keyword.
+    Quoted(String),
+    Open,
+    Close,
+    Equals,
+    NotEquals,
+    Contains,
+}
+
+fn tokenize(s: &str) -> Result<Vec<Token>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = s.chars().peekable();
+
+    while let Some(c) = chars.next() {
+        match c {
+            c if c.is_whitespace() => {},
+            '(' => tokens.push(Token::Open),
+            ')' => tokens.push(Token::Close),
+            '=' => tokens.push(Token::Equals),
+            '~' => tokens.push(Token::Contains),
+            '!' if chars.peek() == Some(&'=') => {
+                chars.next();
+                tokens.push(Token::NotEquals);
+            },
+            '"' | '\'' => {
+                let mut text = String::new();
+
+                loop {
+                    match chars.next() {
+                        Some(q) if q == c => break,
+                        Some(c) => text.push(c),
+                        None => return Err(String::from("unclosed quote in filter")),
+                    }
+                }
+
+                tokens.push(Token::Quoted(text));
+            },
+            c => {
+                let mut word = c.to_string();
+
+                while let Some(&c) = chars.peek() {
+                    if c.is_whitespace() || "()=~!\"'".contains(c) { break; }
+                    word.push(c);
+                    chars.next();
+                }
+
+                tokens.push(Token::Word(word));
+            },
+        }
+    }
+
+    Ok(tokens)
+}
+
+/// A condition on the tags of a record, such as
+/// `missing(ARTIST) or (GENRE~rock and not DATE=2001)`.
+///
+/// `has(KEY)` checks whether a tag has a value that is not empty,
+/// `missing(KEY)` whether the tag is not there at all, and `empty(KEY)`
+/// whether it is there with nothing but empty values. `KEY=value`
+/// and `KEY!=value` compare against each value exactly, and `KEY~text` looks
+/// for text in any value, ignoring case. Conditions combine with `not`,
+/// `and`, and `or`, in that order of precedence, and group with parentheses.
+/// Values may be quoted. Keys are not case sensitive.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Filter {
+    /// `has(KEY)`: the tag has a value that is not empty.
+    Has(String),
+    /// `missing(KEY)`: the tag is not there at all.
+    Missing(String),
+    /// `empty(KEY)`: the tag is there, with nothing but empty values.
+    Empty(String),
+    /// `KEY=value`: a value of the tag is exactly this.
+    Equals(String, String),
+    /// `KEY~text`: a value of the tag contains this text, ignoring case.
+    Contains(String, String),
+    /// `not`: the condition does not hold. `KEY!=value` is read as this.
+    Not(Box<Filter>),
+    /// `and`: both conditions hold.
+    And(Box<Filter>, Box<Filter>),
+    /// `or`: either condition holds.
+    Or(Box<Filter>, Box<Filter>),
+}
+
+struct Parser {
+    tokens: Vec<Token>,
+    position: usize,
+}
+
+impl Parser {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.position)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.position).cloned();
+        self.position += 1;
+        token
+    }
+
+    fn is_keyword(&self, keyword: &str) -> bool {
+        matches!(self.peek(), Some(Token::Word(word)) if word.eq_ignore_ascii_case(keyword))
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<(), String> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            _ => Err(format!("expected {:?} in filter", expected)),
+        }
+    }
+
+    fn or(&mut self) -> Result<Filter, String> {
+        let mut filter = self.and()?;
+
+        while self.is_keyword("or") {
+            self.next();
+            filter = Filter::Or(Box::new(filter), Box::new(self.and()?));
+        }
+
+        Ok(filter)
+    }
+
+    fn and(&mut self) -> Result<Filter, String> {
+        let mut filter = self.not()?;
+
+        while self.is_keyword("and") {
+            self.next();
+            filter = Filter::And(Box::new(filter), Box::new(self.not()?));
+        }
+
+        Ok(filter)
+    }
+
+    fn not(&mut self) -> Result<Filter, String> {
+        if self.is_keyword("not") {
+            self.next();
+            return Ok(Filter::Not(Box::new(self.not()?)));
+        }
+
+        self.condition()
+    }
+
+    fn value(&mut self) -> Result<String, String> {
+        match self.next() {
+            Some(Token::Word(value)) | Some(Token::Quoted(value)) => Ok(value),
+            _ => Err(String::from("expected a value in filter")),
+        }
+    }
+
+    fn condition(&mut self) -> Result<Filter, String> {
+        let word = match self.next() {
+            Some(Token::Open) => {
+                let filter = self.or()?;
+                self.expect(Token::Close)?;
+                return Ok(filter);
+            },
+            Some(Token::Word(word)) => word,
+            Some(Token::Quoted(..)) => return Err(String::from("expected a tag name in filter, not quoted text")),
+            _ => return Err(String::from("expected a condition in filter")),
+        };
+
+        let lowered = word.to_lowercase();
+
+        if matches!(lowered.as_str(), "has" | "missing" | "empty") && self.peek() == Some(&Token::Open) {
+            self.next();
+            let meta_key = self.value()?.to_uppercase();
+            self.expect(Token::Close)?;
+
+            return Ok(match lowered.as_str() {
+                "has" => Filter::Has(meta_key),
+                "missing" => Filter::Missing(meta_key),
+                _ => Filter::Empty(meta_key),
+            });
+        }
+
+        let meta_key = word.to_uppercase();
+
+        match self.next() {
+            Some(Token::Equals) => Ok(Filter::Equals(meta_key, self.value()?)),
+            Some(Token::NotEquals) => Ok(Filter::Not(Box::new(Filter::Equals(meta_key, self.value()?)))),
+            Some(Token::Contains) => Ok(Filter::Contains(meta_key, self.value()?.to_lowercase())),
+            _ => Err(format!("expected =, !=, or ~ after {} in filter", word)),
+        }
+    }
+}
+
+impl FromStr for Filter {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parser = Parser { tokens: tokenize(s)?, position: 0 };
+        let filter = parser.or()?;
+
+        if parser.peek().is_some() {
+            return Err(String::from("unexpected text at the end of filter"));
+        }
+
+        Ok(filter)
+    }
+}
+
+impl Filter {
+    /// Whether the tags of a record meet the condition.
+    pub fn matches(&self, record: &Record) -> bool {
+        let values = |meta_key: &str| record.get_meta(meta_key).unwrap_or_default();
+
+        match self {
+            Self::Has(meta_key) => values(meta_key).iter().any(|v| !v.is_empty()),
+            Self::Missing(meta_key) => record.get_meta(meta_key).is_none(),
+            Self::Empty(meta_key) => record.get_meta(meta_key).is_some_and(|vs| vs.iter().all(|v| v.is_empty())),
+            Self::Equals(meta_key, value) => values(meta_key).iter().any(|v| v == value),
+            Self::Contains(meta_key, text) => values(meta_key).iter().any(|v| v.to_lowercase().contains(text.as_str())),
+            Self::Not(filter) => !filter.matches(record),
+            Self::And(a, b) => a.matches(record) && b.matches(record),
+            Self::Or(a, b) => a.matches(record) || b.matches(record),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    #[test]
+    fn filters() {
+        let record = Record::new(
+            hashmap! {
+                str!("ARTIST") => vec![str!("Artist A"), str!("Guest")],
+                str!("GENRE") => vec![str!("Indie Rock")],
+                str!("DATE") => vec![str!("2001")],
+                str!("COMMENT") => vec![str!("")],
+            },
+            PathBuf::from("01.flac"),
+        );
+
+        let matches = |filter: &str| filter.parse::<Filter>().unwrap().matches(&record);
+
+        assert!(matches("has(artist)"));
+        assert!(matches("missing(ALBUM)"));
+        assert!(matches("artist=Guest"));
+        assert!(matches("ARTIST = 'Artist A'"));
+        assert!(!matches("ARTIST=artist"));
+        assert!(matches("DATE!=1999"));
+        assert!(matches("genre~ROCK"));
+        assert!(matches("missing(ALBUM) and not DATE=1999"));
+        assert!(matches("DATE=1999 or GENRE~indie and has(ARTIST)"));
+        assert!(!matches("(DATE=1999 or GENRE~indie) and missing(ARTIST)"));
+        assert!(matches("TITLE=\"and\" or has(DATE)"));
+
+        // A tag with only an empty value is neither had nor missing.
+        assert!(matches("empty(COMMENT)"));
+        assert!(!matches("has(COMMENT) or missing(COMMENT)"));
+        assert!(!matches("empty(ALBUM) or empty(DATE)"));
+
+        assert!("ARTIST".parse::<Filter>().is_err());
+        assert!("has(ARTIST".parse::<Filter>().is_err());
+        assert!("ARTIST='open".parse::<Filter>().is_err());
+        assert!("has(ARTIST) DATE=1".parse::<Filter>().is_err());
+        assert!("".parse::<Filter>().is_err());
+    }
+}
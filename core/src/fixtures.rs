@@ -0,0 +1,230 @@
+//! Records and sources made up in memory, for tests and demos.
+
+use std::collections::BTreeMap;
+use std::io::Error as IoError;
+use std::io::ErrorKind as IoErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicU64;
+use std::sync::atomic::Ordering;
+use std::time::Duration;
+use std::time::SystemTime;
+
+use metaflac::Error as MetaflacError;
+use metaflac::ErrorKind as MetaflacErrorKind;
+
+use crate::data::Metadata;
+use crate::data::Record;
+use crate::source::RecordSource;
+
+#[derive(Debug, Clone)]
+struct MemoryFile {
+    metadata: Metadata,
+    has_picture: bool,
+    read_only: bool,
+    modified: SystemTime,
+}
+
+/// A record source backed by in-memory data instead of files, for tests and
+/// demos. Clones share the same files, so a clone kept aside can be used to
+/// inspect writes or to simulate changes made by other programs.
+#[derive(Debug, Clone, Default)]
+pub struct MemorySource {
+    files: Arc<Mutex<BTreeMap<PathBuf, MemoryFile>>>,
+    /// Counts up on every change, so modification times always differ.
+    clock: Arc<AtomicU64>,
+    preserve_mtime: bool,
+    show_hidden: bool,
+}
+
+impl MemorySource {
+    fn tick(&self) -> SystemTime {
+        SystemTime::UNIX_EPOCH + Duration::from_secs(self.clock.fetch_add(1, Ordering::SeqCst) + 1)
+    }
+
+    fn to_record(file_path: PathBuf, file: &MemoryFile) -> Record {
+        let mut record = Record::new(file.metadata.clone(), file_path);
+        record.has_picture = file.has_picture;
+        record.read_only = file.read_only;
+        record.modified = Some(file.modified);
+        record
+    }
+}
+
+// Some helpers are only needed by tests, not by `--demo`.
+#[cfg_attr(not(test), allow(dead_code))]
+impl MemorySource {
+    /// A source with no files.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a file, or replaces the metadata of an existing one.
+    pub fn set_file(&self, file_path: impl Into<PathBuf>, metadata: Metadata) {
+        let file = MemoryFile { metadata, has_picture: false, read_only: false, modified: self.tick() };
+        self.files.lock().unwrap().insert(file_path.into(), file);
+    }
+
+    /// Removes a file, as if it was deleted by another program.
+    pub fn remove_file(&self, file_path: impl Into<PathBuf>) {
+        self.files.lock().unwrap().remove(&file_path.into());
+    }
+
+    /// Makes a file refuse writes, as if it had no write permission.
+    pub fn set_read_only(&self, file_path: impl Into<PathBuf>, read_only: bool) {
+        if let Some(file) = self.files.lock().unwrap().get_mut(&file_path.into()) {
+            file.read_only = read_only;
+        }
+    }
+
+    /// The metadata of a file as written, if it is there.
+    pub fn get_file(&self, file_path: impl Into<PathBuf>) -> Option<Metadata> {
+        self.files.lock().unwrap().get(&file_path.into()).map(|f| f.metadata.clone())
+    }
+
+    /// A small, fixed library of two albums, with a few multi-valued and
+    /// missing fields mixed in.
+    pub fn demo() -> Self {
+        let source = Self::new();
+
+        let albums = [
+            ("Album One", "Artist A", "2001", true),
+            ("Second Album", "Artist B", "1999-05-02", false),
+        ];
+
+        let mut n = 0;
+
+        for (album, artist, date, has_picture) in albums {
+            for track in 1..=4 {
+                n += 1;
+
+                let mut metadata = Metadata::new();
+                metadata.insert(String::from("ALBUM"), vec![album.to_string()]);
+                metadata.insert(String::from("ARTIST"), vec![artist.to_string()]);
+                metadata.insert(String::from("TITLE"), vec![format!("Song {} of {}", track, album)]);
+                metadata.insert(String::from("TRACKNUMBER"), vec![track.to_string()]);
+
+                if track == 2 {
+                    metadata.get_mut("ARTIST").unwrap().push(String::from("Guest Person"));
+                }
+
+                if track != 3 {
+                    metadata.insert(String::from("DATE"), vec![date.to_string()]);
+                }
+
+                let file_path = PathBuf::from(format!("{:02} {} - Song {}.flac", n, artist, track));
+                let file = MemoryFile { metadata, has_picture, read_only: false, modified: source.tick() };
+
+                source.files.lock().unwrap().insert(file_path, file);
+            }
+        }
+
+        source
+    }
+}
+
+impl RecordSource for MemorySource {
+    fn list_files(&self) -> Result<Vec<PathBuf>, IoError> {
+        Ok(self.files.lock().unwrap().keys().filter(|p| !self.is_ignored(p)).cloned().collect())
+    }
+
+    fn is_ignored(&self, file_path: &Path) -> bool {
+        !self.show_hidden && crate::ignore::is_hidden(file_path)
+    }
+
+    fn set_show_hidden(&mut self, show_hidden: bool) {
+        self.show_hidden = show_hidden;
+    }
+
+    fn read_record(&self, file_path: PathBuf) -> Result<Record, MetaflacError> {
+        let file = self.files.lock().unwrap().get(&file_path).cloned();
+
+        match file {
+            Some(file) => Ok(Self::to_record(file_path, &file)),
+            None => Err(MetaflacError::new(
+                MetaflacErrorKind::Io(IoError::from(IoErrorKind::NotFound)),
+                "file not found",
+            )),
+        }
+    }
+
+    fn exists(&self, file_path: &Path) -> bool {
+        self.files.lock().unwrap().contains_key(file_path)
+    }
+
+    fn modified(&self, file_path: &Path) -> Option<SystemTime> {
+        self.files.lock().unwrap().get(file_path).map(|file| file.modified)
+    }
+
+    fn write_record(&mut self, record: &Record) -> Result<(), MetaflacError> {
+        let mut disk = self.read_record(record.file_path.clone())?;
+
+        if disk.read_only {
+            return Err(MetaflacError::new(
+                MetaflacErrorKind::Io(IoError::from(IoErrorKind::PermissionDenied)),
+                "file is read-only",
+            ));
+        }
+
+        for (meta_key, values) in record.edits.iter() {
+            disk.set_meta(meta_key, values.clone());
+        }
+
+        disk.commit_edits();
+
+        let modified = self.tick();
+
+        let mut files = self.files.lock().unwrap();
+        let file = files.get_mut(&record.file_path).unwrap();
+        file.metadata = disk.metadata;
+
+        if !self.preserve_mtime {
+            file.modified = modified;
+        }
+
+        Ok(())
+    }
+
+    fn set_preserve_mtime(&mut self, preserve_mtime: bool) {
+        self.preserve_mtime = preserve_mtime;
+    }
+
+    fn rename_file(&mut self, from: &Path, to: &Path) -> Result<(), IoError> {
+        let mut files = self.files.lock().unwrap();
+
+        if files.contains_key(to) {
+            return Err(IoError::from(IoErrorKind::AlreadyExists));
+        }
+
+        let file = files.remove(from).ok_or_else(|| IoError::from(IoErrorKind::NotFound))?;
+        files.insert(to.to_path_buf(), file);
+
+        Ok(())
+    }
+
+    fn copy_file(&mut self, from: &Path, to: &Path) -> Result<(), IoError> {
+        let mut files = self.files.lock().unwrap();
+
+        if files.contains_key(to) {
+            return Err(IoError::from(IoErrorKind::AlreadyExists));
+        }
+
+        let file = files.get(from).cloned().ok_or_else(|| IoError::from(IoErrorKind::NotFound))?;
+        files.insert(to.to_path_buf(), file);
+
+        Ok(())
+    }
+
+    fn delete_file(&mut self, file_path: &Path) -> Result<(), IoError> {
+        match self.files.lock().unwrap().remove(file_path) {
+            Some(_) => Ok(()),
+            None => Err(IoError::from(IoErrorKind::NotFound)),
+        }
+    }
+
+    fn share(&self) -> Box<dyn RecordSource> {
+        Box::new(self.clone())
+    }
+}
@@ -0,0 +1,299 @@
+//! Groups of consecutive records that share an album, shown under a header
+//! row.
+
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::ops::Range;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::consts::*;
+use crate::data::Record;
+use crate::data::Records;
+
+/// The key that splits an album into discs.
+pub const DISC_KEY: &str = "DISCNUMBER";
+
+const COVER_FILE_STEMS: &[&str] = &["cover", "folder", "front", "album"];
+const COVER_FILE_EXTS: &[&str] = &["jpg", "jpeg", "png"];
+
+/// A run of consecutive records that share the same value for a grouping key.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Group {
+    /// The index of the first record.
+    pub start: usize,
+    /// How many records there are.
+    pub len: usize,
+}
+
+impl Group {
+    /// The indices of the records.
+    pub fn range(&self) -> Range<usize> {
+        self.start..(self.start + self.len)
+    }
+
+    /// Splits records into runs that share the same value for a grouping key, in
+    /// the order they are in.
+    pub fn find_all(records: &[Record], meta_key: &str) -> Vec<Self> {
+        let mut groups: Vec<Self> = Vec::new();
+
+        for (i, record) in records.iter().enumerate() {
+            match groups.last_mut() {
+                Some(group) if records[group.start].get_meta(meta_key) == record.get_meta(meta_key) => {
+                    group.len += 1;
+                },
+                _ => groups.push(Self { start: i, len: 1 }),
+            }
+        }
+
+        groups
+    }
+
+    /// Stably sorts records so that records with the same value for a grouping
+    /// key are contiguous, preserving the existing order within each group.
+    /// Within a group, discs of a multi-disc release are kept in disc order.
+    pub fn sort_records(records: &mut Records, meta_key: &str) {
+        records.sort_by(|ra, rb| {
+            ra.get_meta(meta_key).cmp(&rb.get_meta(meta_key))
+            .then_with(|| if meta_key == DISC_KEY { Ordering::Equal } else { disc_number(ra).cmp(&disc_number(rb)) })
+        });
+    }
+}
+
+/// The disc a record is on, read from the leading number of its disc number
+/// tag, so that values like `1/2` are understood.
+pub fn disc_number(record: &Record) -> Option<u32> {
+    let raw = record.get_meta(DISC_KEY)?.first()?;
+    let digits = raw.trim().split('/').next()?.trim();
+
+    digits.parse().ok()
+}
+
+/// Where the cover art of a group of records is found.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum CoverArt {
+    /// A picture embedded in at least one of the files.
+    Embedded,
+    /// An image file next to the files, such as `cover.jpg`.
+    File(PathBuf),
+    /// No cover art was found.
+    Missing,
+}
+
+impl CoverArt {
+    /// Looks for cover art in the files of records, then in the directories they
+    /// are in.
+    pub fn find(records: &[Record]) -> Self {
+        if records.iter().any(|r| r.has_picture) {
+            return Self::Embedded;
+        }
+
+        let mut dirs = records.iter().filter_map(|r| r.file_path.parent()).collect::<Vec<_>>();
+        dirs.dedup();
+
+        dirs.into_iter().find_map(Self::find_in_dir).map_or(Self::Missing, Self::File)
+    }
+
+    fn find_in_dir(dir: &Path) -> Option<PathBuf> {
+        for stem in COVER_FILE_STEMS {
+            for ext in COVER_FILE_EXTS {
+                let path = dir.join(format!("{}.{}", stem, ext));
+                if path.is_file() {
+                    return Some(path);
+                }
+            }
+        }
+
+        None
+    }
+}
+
+/// Aggregate info about the records in a group, used for display in a header row.
+#[derive(Debug, Clone)]
+pub struct GroupSummary {
+    /// The value of the grouping key, or `None` if the records don't have it.
+    pub name: Option<String>,
+    /// The album artist, or else the artist, shared by every record.
+    pub artist: Option<String>,
+    /// The year the records share, read from their dates.
+    pub year: Option<String>,
+    /// How many records there are.
+    pub track_count: usize,
+    /// How many distinct discs the records are on.
+    pub disc_count: usize,
+    /// Where the cover art is, if it was looked for.
+    pub cover_art: Option<CoverArt>,
+}
+
+impl GroupSummary {
+    /// Sums up a group of records, looking for cover art only if asked, as it
+    /// reads from disk.
+    pub fn new(records: &[Record], meta_key: &str, with_cover_art: bool) -> Self {
+        let first_value = |key: &str| {
+            records.first()
+                .and_then(|r| r.get_meta(key))
+                .map(|vals| vals.join(FIELD_SEP_STR))
+        };
+
+        // Only yields a value if it is shared across the entire group.
+        let common_value = |key: &str| {
+            let value = first_value(key);
+            let all_same = records.iter().all(|r| r.get_meta(key).map(|vals| vals.join(FIELD_SEP_STR)) == value);
+
+            if all_same { Some(value) } else { None }
+        };
+
+        let artist_key =
+            if records.iter().any(|r| r.get_meta("ALBUMARTIST").is_some()) { "ALBUMARTIST" }
+            else { "ARTIST" }
+        ;
+
+        let artist = common_value(artist_key).unwrap_or_else(|| Some(String::from(VARIOUS_VALUES_STR)));
+
+        let year = common_value("DATE").flatten().map(|date| date.chars().take(4).collect());
+
+        let mut discs = records.iter().map(disc_number).collect::<Vec<_>>();
+        discs.sort_unstable();
+        discs.dedup();
+
+        let cover_art = if with_cover_art { Some(CoverArt::find(records)) } else { None };
+
+        Self {
+            name: first_value(meta_key),
+            artist,
+            year,
+            track_count: records.len(),
+            disc_count: discs.len(),
+            cover_art,
+        }
+    }
+}
+
+impl Display for GroupSummary {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self.cover_art {
+            Some(CoverArt::Embedded) | Some(CoverArt::File(..)) => write!(f, "{} ", COVER_ART_STR)?,
+            Some(CoverArt::Missing) => write!(f, "{} ", NO_COVER_ART_STR)?,
+            None => {},
+        };
+
+        write!(f, "{}", self.name.as_deref().unwrap_or(MISSING_GROUP_STR))?;
+
+        if let Some(artist) = &self.artist {
+            write!(f, " — {}", artist)?;
+        }
+
+        if let Some(year) = &self.year {
+            write!(f, " ({})", year)?;
+        }
+
+        write!(f, " · {} track(s)", self.track_count)?;
+
+        if self.disc_count > 1 {
+            write!(f, " on {} discs", self.disc_count)?;
+        }
+
+        match &self.cover_art {
+            Some(CoverArt::Embedded) => write!(f, " · embedded art")?,
+            Some(CoverArt::File(path)) => {
+                let file_name = path.file_name().and_then(|f| f.to_str()).unwrap_or_default();
+                write!(f, " · {}", file_name)?;
+            },
+            _ => {},
+        };
+
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    fn record(album: &str, artist: &str) -> Record {
+        Record::new(
+            hashmap! {
+                str!("ALBUM") => vec![str!(album)],
+                str!("ARTIST") => vec![str!(artist)],
+                str!("DATE") => vec![str!("1999-03-01")],
+            },
+            PathBuf::from(format!("{}.flac", artist)),
+        )
+    }
+
+    #[test]
+    fn find_all() {
+        let mut records = vec![
+            record("B", "1"),
+            record("A", "2"),
+            record("B", "3"),
+            record("A", "4"),
+            record("C", "5"),
+        ];
+
+        Group::sort_records(&mut records, "ALBUM");
+
+        let groups = Group::find_all(&records, "ALBUM");
+
+        assert_eq!(
+            groups,
+            vec![
+                Group { start: 0, len: 2 },
+                Group { start: 2, len: 2 },
+                Group { start: 4, len: 1 },
+            ],
+        );
+
+        // Sorting should be stable within each group.
+        assert_eq!(records[2].get_meta("ARTIST"), Some(&[str!("1")][..]));
+        assert_eq!(records[3].get_meta("ARTIST"), Some(&[str!("3")][..]));
+    }
+
+    #[test]
+    fn summary() {
+        let mut records = vec![record("A", "1"), record("A", "2")];
+
+        let summary = GroupSummary::new(&records, "ALBUM", false);
+
+        assert_eq!(
+            summary.to_string(),
+            format!("A — {} (1999) · 2 track(s)", VARIOUS_VALUES_STR),
+        );
+
+        records[1].set_meta("DATE", None);
+
+        let summary = GroupSummary::new(&records[..1], "ALBUM", false);
+        assert_eq!(summary.to_string(), "A — 1 (1999) · 1 track(s)");
+
+        let summary = GroupSummary::new(&records, "ALBUM", false);
+        assert_eq!(summary.to_string(), format!("A — {} · 2 track(s)", VARIOUS_VALUES_STR));
+    }
+
+    #[test]
+    fn discs() {
+        let mut records = vec![record("A", "1"), record("A", "2"), record("A", "3"), record("B", "4")];
+
+        records[0].set_meta(DISC_KEY, Some(vec![str!("2/2")]));
+        records[1].set_meta(DISC_KEY, Some(vec![str!("1/2")]));
+        records[2].set_meta(DISC_KEY, Some(vec![str!("2")]));
+
+        assert_eq!(disc_number(&records[0]), Some(2));
+        assert_eq!(disc_number(&records[3]), None);
+
+        Group::sort_records(&mut records, "ALBUM");
+
+        // Discs of the same album stay in one group, in disc order.
+        assert_eq!(Group::find_all(&records, "ALBUM").len(), 2);
+        assert_eq!(records[0].get_meta("ARTIST"), Some(&[str!("2")][..]));
+        assert_eq!(records[1].get_meta("ARTIST"), Some(&[str!("1")][..]));
+        assert_eq!(records[2].get_meta("ARTIST"), Some(&[str!("3")][..]));
+
+        let summary = GroupSummary::new(&records[..3], "ALBUM", false);
+        assert_eq!(summary.to_string(), format!("A — {} (1999) · 3 track(s) on 2 discs", VARIOUS_VALUES_STR));
+    }
+}
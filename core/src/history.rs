@@ -0,0 +1,280 @@
+//! The undo and redo history of edits.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::data::Records;
+
+/// The visible values of one field of one record, before and after an edit.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldChange {
+    /// The file of the record.
+    pub file_path: PathBuf,
+    /// The metadata key of the field.
+    pub meta_key: String,
+    /// The values before the edit, or `None` if the field was missing.
+    pub before: Option<Vec<String>>,
+    /// The values after the edit, or `None` if the field was removed.
+    pub after: Option<Vec<String>>,
+}
+
+/// A group of edits made by a single command, undone and redone as one.
+#[derive(Debug, Clone)]
+pub struct Operation {
+    /// What the command did, as shown in the history.
+    pub description: String,
+    /// The fields the command changed.
+    pub changes: Vec<FieldChange>,
+    /// When the command was run.
+    pub made_at: SystemTime,
+}
+
+impl Operation {
+    /// Runs a function that stages edits on records, and captures the edits
+    /// it made. The function must not reorder, add, or remove records.
+    pub fn record<F, R>(description: String, records: &mut Records, func: F) -> (R, Self)
+    where
+        F: FnOnce(&mut Records) -> R,
+    {
+        let edits_before = records.iter().map(|r| r.edits.clone()).collect::<Vec<_>>();
+
+        let result = func(records);
+
+        let mut changes = Vec::new();
+
+        for (record, edits_before) in records.iter().zip(edits_before) {
+            let mut meta_keys =
+                record.edits.keys()
+                .chain(edits_before.keys())
+                .filter(|k| record.edits.get(*k) != edits_before.get(*k))
+                .cloned()
+                .collect::<Vec<_>>()
+            ;
+
+            meta_keys.sort();
+            meta_keys.dedup();
+
+            for meta_key in meta_keys {
+                // Loaded metadata does not change while staging edits.
+                let before = match edits_before.get(&meta_key) {
+                    Some(edit) => edit.clone(),
+                    None => record.metadata.get(&meta_key).cloned(),
+                };
+
+                let after = record.get_meta(&meta_key).map(<[String]>::to_vec);
+
+                changes.push(FieldChange { file_path: record.file_path.clone(), meta_key, before, after });
+            }
+        }
+
+        (result, Self { description, changes, made_at: SystemTime::now() })
+    }
+
+    /// The files this operation changed, in order and without repeats.
+    pub fn file_paths(&self) -> Vec<&Path> {
+        let mut file_paths = self.changes.iter().map(|c| c.file_path.as_path()).collect::<Vec<_>>();
+        file_paths.sort();
+        file_paths.dedup();
+        file_paths
+    }
+
+    /// How long ago this operation was made, such as `5m ago`.
+    pub fn age(&self, now: SystemTime) -> String {
+        let secs = now.duration_since(self.made_at).map(|d| d.as_secs()).unwrap_or(0);
+
+        match secs {
+            0..=9 => String::from("just now"),
+            10..=59 => format!("{}s ago", secs),
+            60..=3599 => format!("{}m ago", secs / 60),
+            _ => format!("{}h ago", secs / 3600),
+        }
+    }
+
+    fn apply(&self, records: &mut Records, forward: bool) {
+        let indices =
+            records.iter()
+            .enumerate()
+            .map(|(i, r)| (r.file_path.clone(), i))
+            .collect::<HashMap<_, _>>()
+        ;
+
+        let find = |file_path: &Path| indices.get(file_path).copied();
+
+        let changes: Box<dyn Iterator<Item = &FieldChange>> =
+            if forward { Box::new(self.changes.iter()) }
+            else { Box::new(self.changes.iter().rev()) }
+        ;
+
+        for change in changes {
+            // Records that have gone away since are skipped.
+            if let Some(i) = find(&change.file_path) {
+                let values = if forward { &change.after } else { &change.before };
+                records[i].set_meta(&change.meta_key, values.clone());
+            }
+        }
+    }
+}
+
+/// The stacks of operations that can be undone and redone.
+#[derive(Debug, Default)]
+pub struct History {
+    undo_stack: Vec<Operation>,
+    redo_stack: Vec<Operation>,
+}
+
+impl History {
+    /// Adds a newly made operation, unless it did not change anything.
+    pub fn push(&mut self, operation: Operation) {
+        if !operation.changes.is_empty() {
+            self.undo_stack.push(operation);
+            self.redo_stack.clear();
+        }
+    }
+
+    /// Undoes the most recent operation, returning it.
+    pub fn undo(&mut self, records: &mut Records) -> Option<&Operation> {
+        let operation = self.undo_stack.pop()?;
+        operation.apply(records, false);
+
+        self.redo_stack.push(operation);
+        self.redo_stack.last()
+    }
+
+    /// Points operations at the new paths of renamed files.
+    pub fn rename_paths(&mut self, renamed: &HashMap<PathBuf, PathBuf>) {
+        let changes =
+            self.undo_stack.iter_mut()
+            .chain(self.redo_stack.iter_mut())
+            .flat_map(|operation| operation.changes.iter_mut())
+        ;
+
+        for change in changes {
+            if let Some(to) = renamed.get(&change.file_path) {
+                change.file_path = to.clone();
+            }
+        }
+    }
+
+    /// Redoes the most recently undone operation, returning it.
+    pub fn redo(&mut self, records: &mut Records) -> Option<&Operation> {
+        let operation = self.redo_stack.pop()?;
+        operation.apply(records, true);
+
+        self.undo_stack.push(operation);
+        self.undo_stack.last()
+    }
+
+    /// The operations that can be undone, the most recent last.
+    pub fn done(&self) -> &[Operation] {
+        &self.undo_stack
+    }
+
+    /// The operations that can be redone, the next to redo last.
+    pub fn undone(&self) -> &[Operation] {
+        &self.redo_stack
+    }
+
+    /// Undoes or redoes operations until the given number of them are done,
+    /// returning how many were undone or redone.
+    pub fn go_to(&mut self, records: &mut Records, num_done: usize) -> usize {
+        let mut steps = 0;
+
+        while self.undo_stack.len() > num_done && self.undo(records).is_some() {
+            steps += 1;
+        }
+
+        while self.undo_stack.len() < num_done && self.redo(records).is_some() {
+            steps += 1;
+        }
+
+        steps
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    use crate::data::Record;
+
+    #[test]
+    fn undo_and_redo() {
+        let mut records = vec![
+            Record::new(hashmap! { str!("ARTIST") => vec![str!("Foo")] }, PathBuf::from("a.flac")),
+            Record::new(hashmap! {}, PathBuf::from("b.flac")),
+        ];
+
+        let mut history = History::default();
+
+        let (_, operation) = Operation::record(str!("Set artist"), &mut records, |records| {
+            for record in records.iter_mut() {
+                record.set_meta("ARTIST", Some(vec![str!("Bar")]));
+            }
+        });
+
+        assert_eq!(operation.changes.len(), 2);
+        assert_eq!(operation.changes[1].before, None);
+        history.push(operation);
+
+        let (_, operation) = Operation::record(str!("Clear artist"), &mut records, |records| {
+            records[0].set_meta("ARTIST", None);
+        });
+        history.push(operation);
+
+        // Operations that change nothing are not kept.
+        let (_, operation) = Operation::record(str!("Nothing"), &mut records, |_| {});
+        history.push(operation);
+
+        assert_eq!(history.undo(&mut records).unwrap().description, "Clear artist");
+        assert_eq!(records[0].get_meta("ARTIST"), Some(&[str!("Bar")][..]));
+
+        assert_eq!(history.undo(&mut records).unwrap().description, "Set artist");
+        assert_eq!(records[0].get_meta("ARTIST"), Some(&[str!("Foo")][..]));
+        assert!(!records[0].is_edited());
+        assert!(!records[1].is_edited());
+        assert!(history.undo(&mut records).is_none());
+
+        assert_eq!(history.redo(&mut records).unwrap().description, "Set artist");
+        assert_eq!(records[1].get_meta("ARTIST"), Some(&[str!("Bar")][..]));
+
+        // Making a new edit clears what could be redone.
+        let (_, operation) = Operation::record(str!("Set title"), &mut records, |records| {
+            records[1].set_meta("TITLE", Some(vec![str!("Song")]))
+        });
+        history.push(operation);
+
+        assert!(history.redo(&mut records).is_none());
+    }
+
+    #[test]
+    fn go_to() {
+        let mut records = vec![Record::new(hashmap! {}, PathBuf::from("a.flac"))];
+        let mut history = History::default();
+
+        for title in &["One", "Two", "Three"] {
+            let (_, operation) = Operation::record(format!("Set title to {}", title), &mut records, |records| {
+                records[0].set_meta("TITLE", Some(vec![title.to_string()]));
+            });
+            history.push(operation);
+        }
+
+        assert_eq!(history.done()[0].file_paths(), vec![Path::new("a.flac")]);
+        assert_eq!(history.done()[0].age(history.done()[0].made_at), "just now");
+
+        assert_eq!(history.go_to(&mut records, 1), 2);
+        assert_eq!(records[0].get_meta("TITLE"), Some(&[str!("One")][..]));
+        assert_eq!(history.undone().len(), 2);
+
+        assert_eq!(history.go_to(&mut records, 0), 1);
+        assert!(!records[0].is_edited());
+
+        // Going past either end stops there.
+        assert_eq!(history.go_to(&mut records, 5), 3);
+        assert_eq!(records[0].get_meta("TITLE"), Some(&[str!("Three")][..]));
+    }
+}
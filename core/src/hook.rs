@@ -0,0 +1,262 @@
+//! Running external commands on records, with their tags filled in.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Command as Process;
+use std::process::Output;
+use std::process::Stdio;
+use std::time::SystemTime;
+
+use crate::data::Record;
+
+/// What multiple values of a tag are joined with in a command.
+const VALUE_SEP_STR: &str = ", ";
+
+/// Splits a command into words at whitespace. Text in single or double
+/// quotes stays in one word, whitespace and all.
+pub fn split_words(command: &str) -> Result<Vec<String>, String> {
+    let mut words = Vec::new();
+    let mut word = None::<String>;
+    let mut quote = None;
+
+    for c in command.chars() {
+        match (quote, c) {
+            (Some(q), c) if c == q => quote = None,
+            (Some(_), c) => word.get_or_insert_with(String::new).push(c),
+            (None, '\'' | '"') => {
+                quote = Some(c);
+                word.get_or_insert_with(String::new);
+            },
+            (None, c) if c.is_whitespace() => words.extend(word.take()),
+            (None, c) => word.get_or_insert_with(String::new).push(c),
+        }
+    }
+
+    if quote.is_some() {
+        return Err(String::from("unclosed quote in command"));
+    }
+
+    words.extend(word);
+    Ok(words)
+}
+
+/// Fills in the `{name}` placeholders of a word with what `lookup` gives for
+/// each name.
+pub fn fill<F>(word: &str, lookup: F) -> Result<String, String>
+where
+    F: Fn(&str) -> String,
+{
+    let mut filled = String::new();
+    let mut rest = word;
+
+    while let Some(start) = rest.find('{') {
+        let end = rest[start..].find('}').ok_or_else(|| String::from("unclosed '{' in command"))? + start;
+
+        filled.push_str(&rest[..start]);
+        filled.push_str(&lookup(rest[(start + 1)..end].trim()));
+        rest = &rest[(end + 1)..];
+    }
+
+    filled.push_str(rest);
+    Ok(filled)
+}
+
+/// The value of a placeholder for a record: `{path}`, `{dir}`, and `{name}`
+/// for its file, and the values of the tag for any other name. Missing tags
+/// are empty.
+fn placeholder_value(record: &Record, name: &str) -> String {
+    let path = record.file_path.as_path();
+    let lossy = |p: &Path| p.to_string_lossy().into_owned();
+
+    match name {
+        "path" => lossy(path),
+        "dir" => path.parent().map(lossy).unwrap_or_default(),
+        "name" => path.file_name().map(|n| lossy(Path::new(n))).unwrap_or_default(),
+        meta_key => record.get_meta(&meta_key.to_uppercase()).map(|vs| vs.join(VALUE_SEP_STR)).unwrap_or_default(),
+    }
+}
+
+/// The program and arguments of a command for a record. Placeholders are
+/// filled in after splitting into words, so values with spaces or quotes in
+/// them stay in one argument and are never run by a shell.
+pub fn command_args(command: &str, record: &Record) -> Result<(String, Vec<String>), String> {
+    let mut args =
+        split_words(command)?
+        .iter()
+        .map(|word| fill(word, |name| placeholder_value(record, name)))
+        .collect::<Result<Vec<_>, _>>()?
+    ;
+
+    if args.is_empty() {
+        return Err(String::from("command is empty"));
+    }
+
+    let program = args.remove(0);
+    Ok((program, args))
+}
+
+/// A command with its placeholders filled in for a record, ready to be run
+/// away from the record.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Invocation {
+    /// The file name of the record, to label the output with.
+    pub file_name: String,
+    /// The program and arguments to run, or why the command could not be filled
+    /// in.
+    pub args: Result<(String, Vec<String>), String>,
+}
+
+impl Invocation {
+    /// Fills in a command for a record.
+    pub fn new(command: &str, record: &Record) -> Self {
+        Self { file_name: placeholder_value(record, "name"), args: command_args(command, record) }
+    }
+
+    fn output(&self) -> Result<Output, String> {
+        let (program, args) = self.args.as_ref()?;
+
+        Process::new(program)
+        .args(args)
+        .stdin(Stdio::null())
+        .output()
+        .map_err(|err| format!("unable to run {}: {}", program, err))
+    }
+
+    /// Runs the command and waits for it, returning a report of how it went,
+    /// followed by what it wrote out.
+    pub fn run(self) -> String {
+        let file_name = self.file_name.as_str();
+
+        let output = match self.output() {
+            Ok(output) => output,
+            Err(err) => return format!("{}: {}", file_name, err),
+        };
+
+        let status = if output.status.success() { String::from("done") } else { output.status.to_string() };
+
+        let mut report = format!("{}: {}", file_name, status);
+
+        for stream in [&output.stdout, &output.stderr] {
+            let text = String::from_utf8_lossy(stream);
+            let text = text.trim_end();
+
+            if !text.is_empty() {
+                report.push('\n');
+                report.push_str(text);
+            }
+        }
+
+        report
+    }
+
+    /// Runs the command and waits for it, returning a single line to show in
+    /// a column: the first line it wrote to stdout, or else the last line it
+    /// wrote to stderr, as tools like `flac -t` report there. Progress lines
+    /// ending in carriage returns count as lines of their own.
+    pub fn output_line(&self) -> String {
+        let output = match self.output() {
+            Ok(output) => output,
+            Err(err) => return err,
+        };
+
+        let lines = |stream: &[u8]| {
+            String::from_utf8_lossy(stream)
+            .split(['\n', '\r'])
+            .map(str::trim)
+            .filter(|line| !line.is_empty())
+            .map(String::from)
+            .collect::<Vec<_>>()
+        };
+
+        lines(&output.stdout).into_iter().next()
+        .or_else(|| lines(&output.stderr).pop())
+        .unwrap_or_else(|| if output.status.success() { String::new() } else { output.status.to_string() })
+    }
+}
+
+/// A column command to run on the file of a record, away from the record.
+#[derive(Debug, Clone)]
+pub struct CommandJob {
+    /// The command, before its placeholders were filled in.
+    pub command: String,
+    /// The file of the record.
+    pub file_path: PathBuf,
+    /// When the file was last modified as of filling in the command.
+    pub modified: Option<SystemTime>,
+    invocation: Invocation,
+}
+
+impl CommandJob {
+    /// Fills in a command for a record.
+    pub fn new(command: &str, record: &Record) -> Self {
+        Self {
+            command: command.to_string(),
+            file_path: record.file_path.clone(),
+            modified: record.modified,
+            invocation: Invocation::new(command, record),
+        }
+    }
+
+    /// Runs the command and waits for it, returning the line to show in its
+    /// column.
+    pub fn run(&self) -> String {
+        self.invocation.output_line()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    fn record() -> Record {
+        Record::new(
+            hashmap! {
+                str!("ARTIST") => vec![str!("Artist A"), str!("Guest")],
+                str!("TITLE") => vec![str!("It's \"Quoted\"")],
+            },
+            PathBuf::from("/music/Album/01 Song.flac"),
+        )
+    }
+
+    #[test]
+    fn words() {
+        assert_eq!(split_words("  mpv  --title='My Player' \"a b\" ''").unwrap(), vec![str!("mpv"), str!("--title=My Player"), str!("a b"), str!("")]);
+        assert!(split_words("echo 'open").is_err());
+    }
+
+    #[test]
+    fn placeholders() {
+        let record = record();
+
+        assert_eq!(
+            command_args("echo {name} in {dir}: {artist} - {Title} {missing}", &record).unwrap(),
+            (str!("echo"), vec![str!("01 Song.flac"), str!("in"), str!("/music/Album:"), str!("Artist A, Guest"), str!("-"), str!("It's \"Quoted\""), str!("")]),
+        );
+        assert_eq!(command_args("mpv --title={title} {path}", &record).unwrap().1[1], "/music/Album/01 Song.flac");
+        assert!(command_args("echo {path", &record).is_err());
+        assert!(command_args(" ", &record).is_err());
+    }
+
+    #[test]
+    fn running() {
+        let record = record();
+        let run = |command: &str| Invocation::new(command, &record).run();
+
+        assert_eq!(run("echo {title}"), "01 Song.flac: done\nIt's \"Quoted\"");
+        assert!(run("sh -c 'echo oops >&2; exit 3'").starts_with("01 Song.flac: exit status: 3\noops"));
+        assert!(run("no-such-hook-program").starts_with("01 Song.flac: unable to run no-such-hook-program"));
+        assert_eq!(run("echo {path"), "01 Song.flac: unclosed '{' in command");
+
+        let output_line = |command: &str| Invocation::new(command, &record).output_line();
+
+        assert_eq!(output_line("printf 'first\\nsecond\\n'"), "first");
+        assert_eq!(output_line("sh -c 'printf \"testing, 50%%\\r{name}: ok\\n\" >&2'"), "01 Song.flac: ok");
+        assert_eq!(output_line("false"), "exit status: 1");
+        assert_eq!(output_line("true"), "");
+    }
+}
@@ -0,0 +1,45 @@
+//! Fetching from web services, for lookups such as MusicBrainz and lyrics.
+
+use std::process::Command as Process;
+
+use serde_json::Value as JsonValue;
+
+/// Services like MusicBrainz ask that clients identify themselves.
+const USER_AGENT: &str = concat!("diargos/", env!("CARGO_PKG_VERSION"), " ( https://github.com/linclelinkpart5/diargos )");
+
+/// The most seconds to wait on a single request.
+const TIMEOUT_SECS: &str = "20";
+
+/// Percent-encodes everything but unreserved characters, for use in a query string.
+pub fn percent_encode(value: &str) -> String {
+    value.bytes()
+    .map(|b| match b {
+        b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' => (b as char).to_string(),
+        b => format!("%{:02X}", b),
+    })
+    .collect()
+}
+
+/// Fetches a URL with `curl`, so that no HTTP or TLS library needs to be
+/// built in. Error statuses are errors.
+pub fn get(url: &str) -> Result<Vec<u8>, String> {
+    let output =
+        Process::new("curl")
+        .args(["--silent", "--show-error", "--fail", "--location", "--max-time", TIMEOUT_SECS])
+        .args(["--user-agent", USER_AGENT])
+        .arg(url)
+        .output()
+        .map_err(|err| format!("unable to run curl: {}", err))?
+    ;
+
+    if !output.status.success() {
+        return Err(String::from_utf8_lossy(&output.stderr).trim().to_string());
+    }
+
+    Ok(output.stdout)
+}
+
+/// Fetches a URL as in `get`, and reads the response as JSON.
+pub fn get_json(url: &str) -> Result<JsonValue, String> {
+    serde_json::from_slice(&get(url)?).map_err(|err| format!("unexpected response: {}", err))
+}
@@ -0,0 +1,133 @@
+//! Files left out when scanning a directory: hidden ones, and ones matching
+//! the patterns of a `.diargosignore` file.
+
+use std::io::ErrorKind as IoErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+
+use globset::GlobBuilder;
+use globset::GlobMatcher;
+
+/// The name of the file ignore patterns are read from, in a scanned directory.
+pub const IGNORE_FILE_NAME: &str = ".diargosignore";
+
+/// Whether any part of a path starts with a dot, like `.sync/01.flac`.
+pub fn is_hidden(path: &Path) -> bool {
+    path.iter().any(|part| part.to_string_lossy().starts_with('.'))
+}
+
+struct Rule {
+    matcher: GlobMatcher,
+    /// Whether the pattern started with `!`, so matching paths are kept.
+    negated: bool,
+    /// Whether the pattern ended with `/`, so only directories match.
+    dir_only: bool,
+    /// Whether the pattern has a `/` before its end, so it matches from the
+    /// top of the scanned directory instead of against just names.
+    anchored: bool,
+}
+
+/// Patterns of paths to leave out, written as in a `.gitignore` file: one
+/// glob per line, with `#` starting a comment, `!` keeping paths that an
+/// earlier pattern left out, and a trailing `/` matching only directories.
+#[derive(Default)]
+pub struct IgnoreRules {
+    rules: Vec<Rule>,
+}
+
+impl IgnoreRules {
+    /// Reads the ignore file of a directory, if there is one. Patterns that
+    /// can't be read are skipped with a warning.
+    pub fn read_from_dir(dir: &Path) -> Self {
+        let path = dir.join(IGNORE_FILE_NAME);
+
+        match std::fs::read_to_string(&path) {
+            Ok(text) => Self::parse(&text),
+            Err(err) if err.kind() == IoErrorKind::NotFound => Self::default(),
+            Err(err) => {
+                log::warn!("unable to read {}: {}", path.display(), err);
+                Self::default()
+            },
+        }
+    }
+
+    /// Reads the patterns of an ignore file, one per line. Blank lines and lines
+    /// starting with `#` are skipped.
+    pub fn parse(text: &str) -> Self {
+        let mut rules = Vec::new();
+
+        for line in text.lines().map(str::trim_end) {
+            if line.is_empty() || line.starts_with('#') {
+                continue;
+            }
+
+            let (negated, pattern) = match line.strip_prefix('!') {
+                Some(rest) => (true, rest),
+                None => (false, line),
+            };
+
+            let (dir_only, pattern) = match pattern.strip_suffix('/') {
+                Some(rest) => (true, rest),
+                None => (false, pattern),
+            };
+
+            let anchored = pattern.contains('/');
+            let pattern = pattern.trim_start_matches('/');
+
+            match GlobBuilder::new(pattern).literal_separator(true).build() {
+                Ok(glob) => rules.push(Rule { matcher: glob.compile_matcher(), negated, dir_only, anchored }),
+                Err(err) => log::warn!("skipping ignore pattern {}: {}", line, err),
+            }
+        }
+
+        Self { rules }
+    }
+
+    /// Whether a path, relative to the scanned directory, is left out. Paths
+    /// in a directory that is left out are too.
+    pub fn is_ignored(&self, path: &Path, is_dir: bool) -> bool {
+        let parts = path.iter().collect::<Vec<_>>();
+
+        (1..parts.len()).any(|n| self.matches(&parts[..n].iter().collect::<PathBuf>(), true))
+        || self.matches(path, is_dir)
+    }
+
+    /// Whether the last pattern to match a path leaves it out.
+    fn matches(&self, path: &Path, is_dir: bool) -> bool {
+        let name = path.file_name().map(Path::new).unwrap_or(path);
+
+        self.rules.iter()
+        .filter(|rule| is_dir || !rule.dir_only)
+        .filter(|rule| rule.matcher.is_match(if rule.anchored { path } else { name }))
+        .last()
+        .is_some_and(|rule| !rule.negated)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn is_hidden() {
+        assert!(super::is_hidden(Path::new("._01.flac")));
+        assert!(super::is_hidden(Path::new(".sync/01.flac")));
+        assert!(!super::is_hidden(Path::new("disc 1/01.flac")));
+    }
+
+    #[test]
+    fn is_ignored() {
+        let rules = IgnoreRules::parse("# junk\n@eaDir/\n*.part.flac\n!keep.part.flac\n/disc 1/*.flac\n");
+
+        assert!(rules.is_ignored(Path::new("@eaDir/01.flac"), false));
+        assert!(rules.is_ignored(Path::new("sub/@eaDir/01.flac"), false));
+        assert!(!rules.is_ignored(Path::new("@eaDir"), false));
+
+        assert!(rules.is_ignored(Path::new("01.part.flac"), false));
+        assert!(!rules.is_ignored(Path::new("keep.part.flac"), false));
+
+        assert!(rules.is_ignored(Path::new("disc 1/01.flac"), false));
+        assert!(!rules.is_ignored(Path::new("sub/disc 1/01.flac"), false));
+        assert!(!rules.is_ignored(Path::new("01.flac"), false));
+    }
+}
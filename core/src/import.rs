@@ -0,0 +1,442 @@
+//! Importing tag values from CSV files and JSON edits documents.
+
+use std::collections::BTreeMap;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::File;
+use std::io::Error as IoError;
+use std::io::Read;
+use std::path::Path;
+use std::path::PathBuf;
+
+use csv::Error as CsvError;
+use csv::ReaderBuilder;
+use serde::Deserialize;
+use serde_json::Error as JsonError;
+
+use crate::data::InfoKind;
+use crate::data::Record;
+use crate::data::Records;
+use crate::snapshot::Snapshot;
+use crate::snapshot::SnapshotEntry;
+
+/// What can go wrong reading a file to import.
+#[derive(Debug)]
+pub enum ImportError {
+    /// The file could not be read.
+    Io(IoError),
+    /// The file is not valid CSV.
+    Csv(CsvError),
+    /// The file is not a valid JSON edits document.
+    Json(JsonError),
+    /// The header row has neither a `file_path` nor a `file_name` column.
+    MissingLocator,
+}
+
+impl Display for ImportError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Csv(err) => write!(f, "invalid CSV: {}", err),
+            Self::Json(err) => write!(f, "invalid JSON: {}", err),
+            Self::MissingLocator => write!(f, "no \"file_path\" or \"file_name\" column found"),
+        }
+    }
+}
+
+impl From<IoError> for ImportError {
+    fn from(err: IoError) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<CsvError> for ImportError {
+    fn from(err: CsvError) -> Self {
+        Self::Csv(err)
+    }
+}
+
+impl From<JsonError> for ImportError {
+    fn from(err: JsonError) -> Self {
+        Self::Json(err)
+    }
+}
+
+/// The new values of a field in a JSON edits document.
+#[derive(Debug, Deserialize)]
+#[serde(untagged)]
+enum JsonValues {
+    One(String),
+    Many(Vec<String>),
+}
+
+/// How a row of imported values is matched up with a loaded record.
+#[derive(Debug)]
+enum Locator {
+    Path(PathBuf),
+    Name(String),
+}
+
+impl Locator {
+    fn matches(&self, record: &Record) -> bool {
+        match self {
+            // Relative paths are matched against the trailing components.
+            Self::Path(path) => record.file_path.ends_with(path),
+            Self::Name(name) => record.get_info(&InfoKind::FileName).as_deref() == Some(name.as_str()),
+        }
+    }
+}
+
+impl Display for Locator {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Path(path) => write!(f, "{}", path.display()),
+            Self::Name(name) => write!(f, "{}", name),
+        }
+    }
+}
+
+#[derive(Debug)]
+struct ImportRow {
+    locator: Locator,
+    fields: Vec<(String, Option<Vec<String>>)>,
+}
+
+/// What an import did.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    /// How many rows matched a record.
+    pub matched_rows: usize,
+    /// The rows that matched no record, or more than one, by the path or name
+    /// given for them.
+    pub unmatched_rows: Vec<String>,
+    /// How many records changed.
+    pub edited_records: usize,
+}
+
+impl Display for ImportSummary {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(
+            f,
+            "{} row(s) matched, {} record(s) edited",
+            self.matched_rows,
+            self.edited_records,
+        )?;
+
+        if !self.unmatched_rows.is_empty() {
+            write!(f, "\n\n{} row(s) did not match a unique file:", self.unmatched_rows.len())?;
+
+            for unmatched in self.unmatched_rows.iter() {
+                write!(f, "\n{}", unmatched)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A table of tag values read from a CSV file, with one row per file.
+///
+/// The header row names the metadata key of each column, along with either a
+/// `file_path` or `file_name` column used to find the file for each row. A
+/// key may be repeated over several columns to give it multiple values, and
+/// empty cells are skipped; a key with no values at all is treated as missing.
+///
+/// The same changes can be given as a JSON object from file path to fields,
+/// such as `{"a.flac": {"TITLE": "Song", "GENRE": ["Rock", "Pop"], "COMMENT": null}}`.
+/// Only the fields named for a file are changed, and `null` removes a field.
+#[derive(Debug)]
+pub struct TagImport {
+    meta_keys: Vec<String>,
+    rows: Vec<ImportRow>,
+}
+
+impl TagImport {
+    /// Reads either a JSON edits document or a CSV table, detected by the
+    /// file extension.
+    pub fn read_from_path(path: &Path) -> Result<Self, ImportError> {
+        let file = File::open(path)?;
+
+        if path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("json")) {
+            Self::read_json_from(file)
+        }
+        else {
+            Self::read_from(file)
+        }
+    }
+
+    /// Reads a JSON edits document.
+    pub fn read_json_from<R: Read>(reader: R) -> Result<Self, ImportError> {
+        let document: BTreeMap<String, BTreeMap<String, Option<JsonValues>>> = serde_json::from_reader(reader)?;
+
+        let mut meta_keys: Vec<String> = Vec::new();
+
+        let rows =
+            document.into_iter()
+            .map(|(path, fields)| {
+                let fields =
+                    fields.into_iter()
+                    .map(|(meta_key, values)| {
+                        let meta_key = meta_key.trim().to_ascii_uppercase();
+
+                        if !meta_keys.contains(&meta_key) {
+                            meta_keys.push(meta_key.clone());
+                        }
+
+                        let values = values.map(|values| match values {
+                            JsonValues::One(value) => vec![value],
+                            JsonValues::Many(values) => values,
+                        });
+
+                        (meta_key, values)
+                    })
+                    .collect()
+                ;
+
+                ImportRow { locator: Locator::Path(PathBuf::from(path)), fields }
+            })
+            .collect()
+        ;
+
+        Ok(Self { meta_keys, rows })
+    }
+
+    /// Reads a CSV file.
+    pub fn read_from<R: Read>(reader: R) -> Result<Self, ImportError> {
+        let mut reader = ReaderBuilder::new().has_headers(true).from_reader(reader);
+
+        let mut path_index = None;
+        let mut name_index = None;
+
+        // Group together the column indices for each metadata key, keeping
+        // them in the order they first appear.
+        let mut key_indices: Vec<(String, Vec<usize>)> = Vec::new();
+
+        for (i, header) in reader.headers()?.iter().enumerate() {
+            let header = header.trim();
+
+            match header.to_ascii_lowercase().as_str() {
+                "file_path" => { path_index = Some(i); },
+                "file_name" => { name_index = Some(i); },
+                _ => {
+                    let meta_key = header.to_ascii_uppercase();
+
+                    match key_indices.iter_mut().find(|(k, _)| *k == meta_key) {
+                        Some((_, indices)) => indices.push(i),
+                        None => key_indices.push((meta_key, vec![i])),
+                    }
+                },
+            }
+        }
+
+        if path_index.is_none() && name_index.is_none() {
+            return Err(ImportError::MissingLocator);
+        }
+
+        let mut rows = Vec::new();
+
+        for row in reader.records() {
+            let row = row?;
+
+            let locator =
+                match (path_index.and_then(|i| row.get(i)), name_index.and_then(|i| row.get(i))) {
+                    (Some(path), _) if !path.is_empty() => Locator::Path(PathBuf::from(path)),
+                    (_, Some(name)) if !name.is_empty() => Locator::Name(name.to_string()),
+                    _ => continue,
+                }
+            ;
+
+            let fields =
+                key_indices.iter()
+                .map(|(meta_key, indices)| {
+                    let values =
+                        indices.iter()
+                        .filter_map(|&i| row.get(i))
+                        .filter(|v| !v.is_empty())
+                        .map(String::from)
+                        .collect::<Vec<_>>()
+                    ;
+
+                    (meta_key.clone(), Some(values).filter(|vs| !vs.is_empty()))
+                })
+                .collect()
+            ;
+
+            rows.push(ImportRow { locator, fields });
+        }
+
+        let meta_keys = key_indices.into_iter().map(|(meta_key, _)| meta_key).collect();
+
+        Ok(Self { meta_keys, rows })
+    }
+
+    /// Converts the imported table into a snapshot limited to its columns,
+    /// so that it can be compared against the current state of the records.
+    pub fn into_snapshot(self) -> Snapshot {
+        let entries =
+            self.rows.into_iter()
+            .map(|row| {
+                let file_path = match row.locator {
+                    Locator::Path(path) => path,
+                    Locator::Name(name) => PathBuf::from(name),
+                };
+
+                let metadata =
+                    row.fields.into_iter()
+                    .filter_map(|(meta_key, values)| Some((meta_key, values?)))
+                    .collect()
+                ;
+
+                SnapshotEntry { file_path, metadata }
+            })
+            .collect()
+        ;
+
+        Snapshot {
+            entries,
+            meta_keys: Some(self.meta_keys.into_iter().collect()),
+        }
+    }
+
+    /// The index of the record a row is for, if exactly one record matches.
+    fn matching_index(row: &ImportRow, records: &Records) -> Option<usize> {
+        let mut matching = records.iter().enumerate().filter(|(_, r)| row.locator.matches(r));
+
+        match (matching.next(), matching.next()) {
+            (Some((index, _)), None) => Some(index),
+            _ => None,
+        }
+    }
+
+    /// How many rows match a record, which is the most records applying
+    /// could edit.
+    pub fn matched_rows(&self, records: &Records) -> usize {
+        self.rows.iter().filter(|row| Self::matching_index(row, records).is_some()).count()
+    }
+
+    /// Stages the values of each row as pending edits on its matching record.
+    /// Values that are the same as what the record already has are skipped.
+    pub fn apply(&self, records: &mut Records) -> ImportSummary {
+        let mut summary = ImportSummary::default();
+        let mut edited = vec![false; records.len()];
+
+        for row in self.rows.iter() {
+            let index = match Self::matching_index(row, records) {
+                Some(index) => index,
+                None => {
+                    summary.unmatched_rows.push(row.locator.to_string());
+                    continue;
+                },
+            };
+
+            summary.matched_rows += 1;
+
+            let record = &mut records[index];
+
+            for (meta_key, values) in row.fields.iter() {
+                if record.set_meta(meta_key, values.clone()) {
+                    edited[index] = true;
+                }
+            }
+        }
+
+        summary.edited_records = edited.into_iter().filter(|&e| e).count();
+
+        summary
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    #[test]
+    fn apply() {
+        let input = "\
+file_name,ARTIST,artist,TITLE,GENRE
+a.flac,Foo,Bar,Song A,
+b.flac,Baz,,Song B,Rock
+c.flac,Qux,,Song C,
+";
+
+        let import = TagImport::read_from(input.as_bytes()).unwrap();
+
+        let mut records = vec![
+            Record::new(
+                hashmap! {
+                    str!("ARTIST") => vec![str!("Foo"), str!("Bar")],
+                    str!("TITLE") => vec![str!("Song A")],
+                },
+                PathBuf::from("music/a.flac"),
+            ),
+            Record::new(
+                hashmap! {
+                    str!("ARTIST") => vec![str!("Baz")],
+                    str!("GENRE") => vec![str!("Jazz")],
+                },
+                PathBuf::from("music/b.flac"),
+            ),
+        ];
+
+        assert_eq!(import.matched_rows(&records), 2);
+
+        let summary = import.apply(&mut records);
+
+        assert_eq!(summary.matched_rows, 2);
+        assert_eq!(summary.unmatched_rows, vec![str!("c.flac")]);
+        assert_eq!(summary.edited_records, 1);
+
+        assert!(!records[0].is_edited());
+        assert_eq!(records[1].get_meta("TITLE"), Some(&[str!("Song B")][..]));
+        assert_eq!(records[1].get_meta("GENRE"), Some(&[str!("Rock")][..]));
+        assert_eq!(records[1].edits.len(), 2);
+    }
+
+    #[test]
+    fn apply_json() {
+        let input = r#"{
+            "a.flac": {"title": "New", "GENRE": ["Rock", "Pop"], "ARTIST": null},
+            "other/b.flac": {"TITLE": "Song B"}
+        }"#;
+
+        let import = TagImport::read_json_from(input.as_bytes()).unwrap();
+
+        let mut records = vec![
+            Record::new(
+                hashmap! {
+                    str!("ARTIST") => vec![str!("Foo")],
+                    str!("TITLE") => vec![str!("Song A")],
+                },
+                PathBuf::from("music/a.flac"),
+            ),
+            Record::new(hashmap! {}, PathBuf::from("music/b.flac")),
+        ];
+
+        let summary = import.apply(&mut records);
+
+        assert_eq!(summary.matched_rows, 1);
+        assert_eq!(summary.unmatched_rows, vec![str!("other/b.flac")]);
+        assert_eq!(summary.edited_records, 1);
+
+        assert_eq!(records[0].get_meta("TITLE"), Some(&[str!("New")][..]));
+        assert_eq!(records[0].get_meta("GENRE"), Some(&[str!("Rock"), str!("Pop")][..]));
+        assert_eq!(records[0].get_meta("ARTIST"), None);
+        assert!(!records[1].is_edited());
+
+        assert!(matches!(TagImport::read_json_from(&b"{\"a.flac\": {\"TITLE\": 1}}"[..]), Err(ImportError::Json(_))));
+    }
+
+    #[test]
+    fn missing_locator() {
+        let input = "ARTIST,TITLE\nFoo,Bar\n";
+
+        assert!(matches!(
+            TagImport::read_from(input.as_bytes()),
+            Err(ImportError::MissingLocator),
+        ));
+    }
+}
@@ -0,0 +1,54 @@
+//! The tag table behind diargos, without the terminal UI.
+//!
+//! A `model::Model` holds the records read from a `source::RecordSource`,
+//! such as a directory of FLAC files, along with the columns they are shown
+//! under, the cursor, pending edits, and their undo history. The other
+//! modules read and write the formats that records are imported from and
+//! exported to, and work out the values shown in each cell.
+//!
+//! `table::TableView` is a cursive view that shows any `table::TableSource`
+//! as a scrolling spreadsheet, which is how a model is shown in the UI.
+
+#![warn(missing_docs)]
+
+pub mod album_fill;
+pub mod batch;
+pub mod clipboard;
+pub mod command;
+pub mod command_line;
+pub mod compare;
+pub mod computed;
+pub mod config;
+pub mod consts;
+pub mod cursor;
+pub mod data;
+pub mod duplicate;
+pub mod error;
+pub mod filter;
+#[cfg(any(test, feature = "fixtures"))]
+pub mod fixtures;
+pub mod group;
+pub mod history;
+pub mod hook;
+pub mod http;
+pub mod ignore;
+pub mod import;
+pub mod lyrics;
+pub mod model;
+pub mod musicbrainz;
+pub mod playback;
+pub mod playlist;
+pub mod preset;
+pub mod refresh;
+pub mod register;
+pub mod rename;
+pub mod replaygain;
+pub mod script;
+pub mod snapshot;
+pub mod source;
+pub mod table;
+pub mod transform;
+pub mod trash;
+pub mod util;
+pub mod value;
+pub mod watch;
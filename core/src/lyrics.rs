@@ -0,0 +1,124 @@
+//! Reading lyrics from tags, and fetching them from a lyrics provider.
+
+use serde::Deserialize;
+use serde_json::Value as JsonValue;
+
+use crate::data::Record;
+use crate::http::percent_encode;
+
+/// The tags lyrics are kept in, in order of preference.
+pub const LYRICS_KEYS: &[&str] = &["LYRICS", "UNSYNCEDLYRICS"];
+
+/// Where lyrics are fetched from. The URL may have `{artist}`, `{title}`,
+/// and `{album}` placeholders. Responses are read as plain text, unless a
+/// field to read from a JSON response is given.
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize)]
+pub struct LyricsProvider {
+    /// The URL to fetch lyrics from, with placeholders for the record.
+    pub url: String,
+
+    /// The field holding the lyrics in a JSON response, with dots between
+    /// the keys of nested fields.
+    #[serde(default)]
+    pub field: Option<String>,
+}
+
+impl Default for LyricsProvider {
+    fn default() -> Self {
+        Self {
+            url: String::from("https://lrclib.net/api/get?artist_name={artist}&track_name={title}&album_name={album}"),
+            field: Some(String::from("plainLyrics")),
+        }
+    }
+}
+
+impl LyricsProvider {
+    /// The URL to fetch the lyrics of a record from, or `None` if the record
+    /// lacks a tag the URL needs.
+    pub fn url_for(&self, record: &Record) -> Option<String> {
+        let mut url = self.url.clone();
+
+        for (placeholder, meta_key) in [("{artist}", "ARTIST"), ("{title}", "TITLE"), ("{album}", "ALBUM")] {
+            if url.contains(placeholder) {
+                let value = record.get_meta(meta_key)?.first()?;
+                url = url.replace(placeholder, &percent_encode(value));
+            }
+        }
+
+        Some(url)
+    }
+
+    /// Reads the lyrics out of a response. Missing or blank lyrics are `None`.
+    pub fn parse(&self, body: &[u8]) -> Result<Option<String>, String> {
+        let text = match &self.field {
+            None => String::from_utf8_lossy(body).into_owned(),
+            Some(field) => {
+                let json = serde_json::from_slice::<JsonValue>(body).map_err(|err| format!("unexpected response: {}", err))?;
+
+                match field.split('.').try_fold(&json, |json, key| json.get(key)).and_then(JsonValue::as_str) {
+                    Some(text) => text.to_string(),
+                    None => return Ok(None),
+                }
+            },
+        };
+
+        // Line endings are kept as they would be typed.
+        let text = text.replace("\r\n", "\n").trim().to_string();
+
+        Ok(Some(text).filter(|text| !text.is_empty()))
+    }
+
+    /// Fetches the lyrics of a record.
+    pub fn fetch(&self, url: &str) -> Result<Option<String>, String> {
+        self.parse(&crate::http::get(url)?)
+    }
+}
+
+/// The tag a record keeps its lyrics in: the first one that it has, or the
+/// preferred one if it has none.
+pub fn lyrics_key(record: &Record) -> &'static str {
+    LYRICS_KEYS.iter().find(|k| record.get_meta(k).is_some()).unwrap_or(&LYRICS_KEYS[0])
+}
+
+/// The lyrics of a record, with values split by blank lines.
+pub fn lyrics(record: &Record) -> Option<String> {
+    record.get_meta(lyrics_key(record)).map(|values| values.join("\n\n"))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    #[test]
+    fn providers() {
+        let record = Record::new(
+            hashmap! {
+                str!("ARTIST") => vec![str!("AC/DC")],
+                str!("TITLE") => vec![str!("Back in Black")],
+                str!("UNSYNCEDLYRICS") => vec![str!("Verse"), str!("Chorus")],
+            },
+            PathBuf::from("a.flac"),
+        );
+
+        let provider = LyricsProvider::default();
+        assert_eq!(provider.url_for(&record), None);
+
+        let provider = LyricsProvider { url: str!("https://example.com/{artist}/{title}"), field: None };
+        assert_eq!(provider.url_for(&record).unwrap(), "https://example.com/AC%2FDC/Back%20in%20Black");
+        assert_eq!(provider.parse(b"  Line one\r\nLine two\n").unwrap(), Some(str!("Line one\nLine two")));
+        assert_eq!(provider.parse(b" \n").unwrap(), None);
+
+        let provider = LyricsProvider { url: str!(""), field: Some(str!("result.text")) };
+        assert_eq!(provider.parse(br#"{"result": {"text": "Words"}}"#).unwrap(), Some(str!("Words")));
+        assert_eq!(provider.parse(br#"{"result": null}"#).unwrap(), None);
+        assert!(provider.parse(b"not json").is_err());
+
+        assert_eq!(lyrics_key(&record), "UNSYNCEDLYRICS");
+        assert_eq!(lyrics(&record), Some(str!("Verse\n\nChorus")));
+    }
+}
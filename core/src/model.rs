@@ -0,0 +1,3616 @@
+//! The state of one open library: its records, cursor, edits, and history.
+
+use std::borrow::Cow;
+use std::collections::BTreeMap;
+use std::collections::HashMap;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::io::Error as IoError;
+use std::path::Path;
+use std::path::PathBuf;
+
+use cursive::XY;
+use metaflac::Error as MetaflacError;
+use unicode_width::UnicodeWidthStr;
+
+use crate::album_fill::AlbumFill;
+use crate::command::Keymap;
+use crate::command_line::LineCommand;
+use crate::config::Config;
+use crate::config::Confirm;
+use crate::config::KeyCase;
+use crate::config::NormalizeOnSave;
+use crate::config::TagPreset;
+use crate::consts::*;
+use crate::cursor::Cursor;
+use crate::cursor::CursorDir;
+use crate::data::Align;
+use crate::data::Column;
+use crate::data::ColumnKey;
+use crate::data::Columns;
+use crate::data::Data;
+use crate::data::InfoKind;
+use crate::data::Record;
+use crate::data::Records;
+use crate::data::Sizing;
+use crate::data::SortSpec;
+use crate::duplicate::DuplicateSet;
+use crate::error::Error;
+use crate::group::DISC_KEY;
+use crate::group::Group;
+use crate::group::GroupSummary;
+use crate::group::disc_number;
+use crate::history::History;
+use crate::hook::CommandJob;
+use crate::history::Operation;
+use crate::lyrics::LyricsProvider;
+use crate::playback::Player;
+use crate::refresh::Conflict;
+use crate::replaygain::Loudness;
+use crate::refresh::RefreshSummary;
+use crate::refresh::Resolution;
+use crate::rename::Inferred;
+use crate::rename::Rename;
+use crate::rename::Template;
+use crate::script::Script;
+use crate::source::RecordSource;
+use crate::transform::Case;
+use crate::transform::Cleanup;
+use crate::transform::reveal_invisible;
+use crate::transform::to_ascii;
+use crate::util::Util;
+use crate::value::ValueKind;
+
+/// A line of the table body, which is either a record or a group header.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum DisplayRow {
+    /// The header of a group, by group index.
+    Header(usize),
+    /// A record, by record index.
+    Record(usize),
+}
+
+/// Which records batch operations apply to.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Scope {
+    /// The selected records, or the record under the cursor if none are selected.
+    #[default]
+    Selection,
+    /// The records in the same group as the record under the cursor.
+    Group,
+    /// All records.
+    All,
+}
+
+impl Scope {
+    /// The scope after this one, going around.
+    pub fn next(self) -> Self {
+        match self {
+            Self::Selection => Self::Group,
+            Self::Group => Self::All,
+            Self::All => Self::Selection,
+        }
+    }
+}
+
+impl Display for Scope {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Selection => write!(f, "selection"),
+            Self::Group => write!(f, "album group"),
+            Self::All => write!(f, "all records"),
+        }
+    }
+}
+
+/// The outcome of writing pending edits back to their files.
+#[derive(Debug, Default)]
+pub struct SaveSummary {
+    /// How many files were written.
+    pub saved: usize,
+    /// Files that could not be written.
+    pub failures: Vec<(PathBuf, MetaflacError)>,
+    /// Files that were changed on disk since they were read, and were not written.
+    pub conflicts: Vec<Conflict>,
+}
+
+impl Display for SaveSummary {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "Saved {} file(s)", self.saved)?;
+
+        if !self.conflicts.is_empty() {
+            write!(f, ", {} changed on disk since loading", self.conflicts.len())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// How `Model::number_tracks` writes track numbers.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct Numbering {
+    /// Pads numbers with zeros to the width of the largest, at least two digits.
+    pub zero_pad: bool,
+    /// Also sets TRACKTOTAL to the number of tracks on the disc.
+    pub fill_total: bool,
+    /// Also sets TOTALDISCS to the number of discs in the group.
+    pub fill_disc_total: bool,
+}
+
+/// New values for some records, by record index.
+pub type ValueChanges = Vec<(usize, Vec<String>)>;
+
+/// The viewport height assumed until the view is first laid out.
+const DEFAULT_VIEWPORT_HEIGHT: usize = 10;
+
+/// The viewport width assumed until the view is first laid out.
+const DEFAULT_VIEWPORT_WIDTH: usize = 80;
+
+/// How many cells narrower the rows in view have to be before a column sized
+/// by them narrows, so that it does not jitter while scrolling.
+const VISIBLE_SHRINK_SLACK: usize = 4;
+
+/// A table of records loaded from a source, along with everything needed to
+/// browse and edit it. Edits are staged on the records and only written back
+/// by `save_all`.
+pub struct Model {
+    /// The columns and records.
+    pub data: Data,
+    /// Where the cursor is.
+    pub cursor: Cursor,
+    /// The column the records were last sorted by, if it still applies.
+    pub sort: Option<SortSpec>,
+
+    /// The metadata key used to cluster records under group headers, if any.
+    pub group_by: Option<String>,
+    /// The grouping key values of the groups shown as just their header.
+    pub collapsed_groups: HashSet<Option<Vec<String>>>,
+    /// Whether group headers show whether the group has cover art.
+    pub show_group_art: bool,
+    /// Whether columns sized by their contents are shrunk to fit the view.
+    pub fit_width: bool,
+    /// Whether to show every tag of the record under the cursor below the table.
+    pub show_detail: bool,
+    /// Whether characters that are hard to see are shown with markers.
+    pub show_invisible: bool,
+    /// Whether files whose names start with a dot are loaded.
+    pub show_hidden: bool,
+    /// Limits on how many files an action may touch before asking first.
+    pub confirm: Confirm,
+    /// The commands bound to each key.
+    pub keymap: Keymap,
+    /// Words kept in lower case within values converted to title case.
+    pub small_words: Vec<String>,
+    /// Whether tags are converted to NFC when saving.
+    pub normalize_on_save: NormalizeOnSave,
+    /// The case tag keys are written in when saving.
+    pub key_case: KeyCase,
+    /// Fields left in place when stripping tags.
+    pub strip_keep: Vec<String>,
+    /// The tag ratings are kept in, and the highest rating.
+    pub rating_key: String,
+    /// The highest rating.
+    pub rating_max: u32,
+    /// Where lyrics are fetched from.
+    pub lyrics_provider: LyricsProvider,
+    /// Plays files with an external player.
+    pub player: Player,
+    /// Commands to run on records, by name.
+    pub hooks: BTreeMap<String, String>,
+    /// Scripts that transform the tags of records, by name.
+    pub scripts: BTreeMap<String, String>,
+    /// Named sets of tags and columns applied to the target records, by name.
+    pub tag_presets: BTreeMap<String, TagPreset>,
+    /// Whether commands that change tags or files are turned off.
+    pub read_only: bool,
+    /// Whether saving only shows what would be written, instead of writing.
+    pub dry_run: bool,
+    /// Files that could not be read when loading or last refreshing.
+    pub load_failures: Vec<Error>,
+
+    /// A short message about the last action taken, shown below the table.
+    pub status: Option<String>,
+
+    /// The file paths of the records marked for bulk actions.
+    pub selection: HashSet<PathBuf>,
+    /// Which records batch operations apply to.
+    pub scope: Scope,
+
+    /// The edits that can be undone and redone.
+    pub history: History,
+
+    /// The last template files were renamed with, offered again next time.
+    pub rename_template: String,
+    /// The last pattern tags were read from file names with.
+    pub name_pattern: String,
+
+    /// The width of the contents of each column, as of the last recache.
+    pub cached_content_widths: Vec<usize>,
+    /// The groups of records, as of the last recache.
+    pub cached_groups: Vec<Group>,
+    /// The header text of each group, as of the last recache.
+    pub cached_group_headers: Vec<String>,
+    /// Whether each group is collapsed, as of the last recache.
+    pub cached_group_collapsed: Vec<bool>,
+    /// The lines of the table body, as of the last recache.
+    pub cached_display_rows: Vec<DisplayRow>,
+    dirty: bool,
+
+    /// The files each column command is running or waiting to run on.
+    pending_commands: HashMap<String, HashSet<PathBuf>>,
+
+    /// The files queued for writing by a background save, see `queue_writes`.
+    writing: HashSet<PathBuf>,
+    /// The files that could not be written when last saved.
+    write_failures: HashSet<PathBuf>,
+
+    /// Set when the cursor was moved outside of the view's own event
+    /// handling, so that the view scrolls it into view on the next layout.
+    pub scroll_pending: bool,
+
+    /// The column to go back to when leaving whole-row mode.
+    row_mode_column: usize,
+
+    /// The number of body lines visible in the view, fed in on each layout.
+    pub viewport_height: usize,
+    /// The number of cells across the view, that proportionally sized
+    /// columns are sized against. Set with `set_viewport_width`.
+    viewport_width: usize,
+    /// The first line in view. Set with `set_scroll_top`.
+    scroll_top: usize,
+    /// The last widths of columns sized by the rows in view.
+    visible_widths: HashMap<ColumnKey, usize>,
+    /// Widths that columns were resized to while running, which take the
+    /// place of their configured sizing, even across config reloads.
+    width_overrides: HashMap<ColumnKey, usize>,
+
+    source: Box<dyn RecordSource>,
+}
+
+impl Model {
+    /// Reads every file of a source. Files that can't be read are left out,
+    /// and kept in `load_failures` to be reported.
+    pub fn load(columns: Columns, source: Box<dyn RecordSource>) -> Result<Self, IoError> {
+        let mut records = Records::new();
+        let mut load_failures = Vec::new();
+
+        for file_path in source.list_files()? {
+            match source.read_record(file_path.clone()) {
+                Ok(record) => records.push(record),
+                Err(err) => {
+                    log::warn!("skipping {}: {}", file_path.display(), err);
+                    load_failures.push(Error::Flac(file_path, err));
+                },
+            }
+        }
+
+        log::info!("loaded {} file(s), skipped {}", records.len(), load_failures.len());
+
+        let mut model = Self::with_data(Data::with_data(columns, records), source);
+        model.load_failures = load_failures;
+        Ok(model)
+    }
+
+    /// Takes the settings of a config, such as when it is reloaded. Records
+    /// and their pending edits are left alone, and the sort is kept if its
+    /// column is still there.
+    pub fn apply_config(&mut self, config: &Config) {
+        self.show_group_art = config.show_group_art;
+        self.fit_width = config.fit_width;
+        self.dirty = true;
+        self.confirm = config.confirm;
+        self.small_words = config.small_words.clone();
+        self.normalize_on_save = config.normalize_on_save;
+        self.key_case = config.key_case;
+        self.strip_keep = config.strip_keep.clone();
+        self.source.set_preserve_mtime(config.preserve_mtime);
+        self.show_hidden = config.show_hidden;
+        self.source.set_show_hidden(config.show_hidden);
+        self.rating_key = config.rating_key.clone();
+        self.rating_max = config.rating_max;
+        self.lyrics_provider = config.lyrics_provider.clone();
+        self.player.set_command(config.player.clone());
+        self.hooks = config.hooks.clone();
+        self.scripts = config.scripts.clone();
+        self.tag_presets = config.tag_presets.clone();
+        self.read_only = config.read_only;
+        self.dry_run = config.dry_run;
+        self.keymap = Keymap::with_overrides(&config.keys);
+
+        let sort = self.sort.and_then(|sort| {
+            let sort_key = &self.data.columns.get(sort.column)?.key;
+            let column = config.columns.iter().position(|c| &c.key == sort_key)?;
+            Some(SortSpec { column, ..sort })
+        });
+
+        self.mutate_columns(|columns| { *columns = config.columns.clone(); });
+
+        if let Some(sort) = sort {
+            self.sort_by_column_index(sort.column, sort.descending);
+        }
+    }
+
+    /// A model of columns and records loaded from a source, with the default
+    /// config.
+    pub fn with_data(data: Data, source: Box<dyn RecordSource>) -> Self {
+        let cached_content_widths = Vec::with_capacity(data.columns.len());
+
+        let mut new = Self {
+            data,
+            cursor: Cursor::Cell(0, 0),
+            sort: None,
+
+            group_by: None,
+            collapsed_groups: HashSet::new(),
+            show_group_art: true,
+            fit_width: true,
+            confirm: Confirm::default(),
+            keymap: Keymap::default(),
+            small_words: Config::default_small_words(),
+            normalize_on_save: NormalizeOnSave::default(),
+            key_case: KeyCase::default(),
+            strip_keep: Vec::new(),
+            rating_key: Config::default_rating_key(),
+            rating_max: Config::default_rating_max(),
+            lyrics_provider: LyricsProvider::default(),
+            player: Player::default(),
+            hooks: BTreeMap::new(),
+            scripts: BTreeMap::new(),
+            tag_presets: BTreeMap::new(),
+            read_only: false,
+            dry_run: false,
+            load_failures: Vec::new(),
+
+            status: None,
+
+            selection: HashSet::new(),
+            writing: HashSet::new(),
+            write_failures: HashSet::new(),
+            scope: Scope::default(),
+
+            history: History::default(),
+
+            rename_template: String::from(DEFAULT_RENAME_TEMPLATE),
+            name_pattern: String::from(DEFAULT_NAME_PATTERN),
+
+            cached_content_widths,
+            cached_groups: Vec::new(),
+            cached_group_headers: Vec::new(),
+            cached_group_collapsed: Vec::new(),
+            cached_display_rows: Vec::new(),
+            dirty: true,
+            pending_commands: HashMap::new(),
+
+            show_detail: false,
+            show_invisible: false,
+            show_hidden: false,
+            scroll_pending: false,
+            row_mode_column: 0,
+
+            viewport_height: DEFAULT_VIEWPORT_HEIGHT,
+            viewport_width: DEFAULT_VIEWPORT_WIDTH,
+            scroll_top: 0,
+            visible_widths: HashMap::new(),
+            width_overrides: HashMap::new(),
+
+            source,
+        };
+
+        new.recache();
+
+        new
+    }
+
+    fn move_cursor(&mut self, cursor_dir: CursorDir, n: usize) {
+        let stops = self.cursor_stops();
+
+        if stops.is_empty() {
+            self.cursor.shift(cursor_dir, n, self.data.columns.len(), self.data.records.len());
+            return;
+        }
+
+        // Moves over the rows that can be stopped on, then maps back to records.
+        let (_, y) = self.cursor.to_xy();
+        let mut cursor = match y {
+            Some(y) => self.cursor.with_row(stops.partition_point(|&s| s <= y).saturating_sub(1)),
+            None => self.cursor,
+        };
+
+        cursor.shift(cursor_dir, n, self.data.columns.len(), stops.len());
+
+        self.cursor = match cursor.to_xy() {
+            (_, Some(p)) => cursor.with_row(stops[p]),
+            (_, None) => cursor,
+        };
+    }
+
+    /// The records the cursor can move to, in order. A collapsed group can
+    /// only be stopped on at its first record, which stands for its header.
+    fn cursor_stops(&self) -> Vec<usize> {
+        let meta_key = match &self.group_by {
+            Some(meta_key) if !self.collapsed_groups.is_empty() => meta_key,
+            _ => return (0..self.data.records.len()).collect(),
+        };
+
+        Group::find_all(&self.data.records, meta_key).into_iter()
+        .flat_map(|group| {
+            if self.is_collapsed(group.start) { group.start..(group.start + 1) }
+            else { group.range() }
+        })
+        .collect()
+    }
+
+    /// Whether a record is in a collapsed group.
+    fn is_collapsed(&self, record_index: usize) -> bool {
+        match (&self.group_by, self.data.records.get(record_index)) {
+            (Some(meta_key), Some(record)) => {
+                self.collapsed_groups.contains(&record.get_meta(meta_key).map(<[String]>::to_vec))
+            },
+            _ => false,
+        }
+    }
+
+    /// Expands the group a record is in, if it is collapsed, so that the
+    /// cursor can be put on the record.
+    fn expand_group_of(&mut self, record_index: usize) {
+        if !self.is_collapsed(record_index) {
+            return;
+        }
+
+        if let (Some(meta_key), Some(record)) = (&self.group_by, self.data.records.get(record_index)) {
+            self.collapsed_groups.remove(&record.get_meta(meta_key).map(<[String]>::to_vec));
+            self.dirty = true;
+        }
+    }
+
+    /// Moves the cursor up `n` records, onto the header past the first.
+    pub fn move_cursor_up(&mut self, n: usize) {
+        self.move_cursor(CursorDir::U, n)
+    }
+
+    /// Moves the cursor down `n` records.
+    pub fn move_cursor_down(&mut self, n: usize) {
+        self.move_cursor(CursorDir::D, n)
+    }
+
+    /// Moves the cursor left `n` columns.
+    pub fn move_cursor_left(&mut self, n: usize) {
+        self.move_cursor(CursorDir::L, n)
+    }
+
+    /// Moves the cursor right `n` columns.
+    pub fn move_cursor_right(&mut self, n: usize) {
+        self.move_cursor(CursorDir::R, n)
+    }
+
+    /// The number of records to move by for a page up or down.
+    pub fn page_size(&self) -> usize {
+        self.viewport_height.max(1)
+    }
+
+    /// The number of records to move by for half a page up or down.
+    pub fn half_page_size(&self) -> usize {
+        (self.viewport_height / 2).max(1)
+    }
+
+    /// Whether the cursor is on the header of a column.
+    pub fn is_cursor_at_column(&self, x: usize) -> bool {
+        if let Cursor::Column(cx) = self.cursor {
+            cx == x
+        } else {
+            false
+        }
+    }
+
+    /// Whether the cursor is on a cell, or on the whole row it is in.
+    pub fn is_cursor_at_cell(&self, x: usize, y: usize) -> bool {
+        match self.cursor {
+            Cursor::Cell(cx, cy) => cx == x && cy == y,
+            Cursor::Row(cy) => cy == y,
+            Cursor::Column(..) => false,
+        }
+    }
+
+    /// Switches between highlighting a single cell and the whole row, coming
+    /// back to the column the cursor was in before.
+    pub fn toggle_row_mode(&mut self) {
+        self.cursor = match self.cursor {
+            Cursor::Cell(x, y) => {
+                self.row_mode_column = x;
+                Cursor::Row(y)
+            },
+            Cursor::Column(x) => {
+                self.row_mode_column = x;
+                Cursor::Row(0)
+            },
+            Cursor::Row(y) => Cursor::Cell(self.row_mode_column, y),
+        };
+
+        self.cursor.clamp(self.data.columns.len(), self.data.records.len());
+    }
+
+    /// Runs a command from the command line.
+    pub fn run_line_command(&mut self, line_command: LineCommand) -> Result<(), String> {
+        let (_, y) = self.cursor.to_xy();
+        let num_records = self.data.records.len();
+
+        let target = match line_command {
+            LineCommand::Goto(n) if n <= num_records => n - 1,
+            LineCommand::Goto(n) => return Err(format!("there are only {} rows, not {}", num_records, n)),
+            LineCommand::FindFile(name) => {
+                let name = name.to_lowercase();
+
+                // Searching starts after the current record, so that repeating
+                // a search moves through all matches.
+                let start = y.map_or(0, |y| y + 1);
+
+                let found =
+                    (0..num_records)
+                    .map(|step| (start + step) % num_records)
+                    .find(|&i| {
+                        self.data.records[i].get_info(&InfoKind::FileName)
+                        .is_some_and(|file_name| file_name.to_lowercase().contains(&name))
+                    })
+                ;
+
+                found.ok_or_else(|| format!("no file name contains: {}", name))?
+            },
+        };
+
+        self.expand_group_of(target);
+        self.cursor = self.cursor.with_row(target);
+        self.scroll_pending = true;
+
+        Ok(())
+    }
+
+    /// The column a type-ahead search would look in, if the cursor is on a
+    /// cell in the column the records are sorted by.
+    pub fn type_ahead_column(&self) -> Option<usize> {
+        match self.cursor.to_xy() {
+            (Some(x), Some(_)) if self.sort.map(|sort| sort.column) == Some(x) => Some(x),
+            _ => None,
+        }
+    }
+
+    /// Moves the cursor to the first record whose value in the current column
+    /// starts with a prefix, ignoring case. Returns `None` unless the cursor
+    /// is on a cell in the column the records are sorted by, otherwise
+    /// whether a record was found.
+    pub fn type_ahead(&mut self, prefix: &str) -> Option<bool> {
+        let x = self.type_ahead_column()?;
+
+        let column = self.data.columns.get(x)?;
+        let prefix = prefix.to_lowercase();
+
+        let found = self.data.records.iter().position(|record| {
+            let value = match &column.key {
+                ColumnKey::Meta(meta_key) => record.get_meta(meta_key).map(|values| values.join(FIELD_SEP_STR)),
+                ColumnKey::Info(info_kind) => record.get_info(info_kind).map(Cow::into_owned),
+                ColumnKey::Command(command) => record.get_command_output(command).map(String::from),
+                ColumnKey::Computed(template) => record.get_computed(template),
+            };
+
+            value.is_some_and(|value| value.to_lowercase().starts_with(&prefix))
+        });
+
+        if let Some(i) = found {
+            self.expand_group_of(i);
+            self.cursor = Cursor::Cell(x, i);
+        }
+
+        Some(found.is_some())
+    }
+
+    /// Moves the cursor to the next record in the current column that has no
+    /// value, wrapping around at the ends. Returns `false` if there is none.
+    pub fn jump_to_missing(&mut self, forward: bool) -> bool {
+        let (x, y) = self.cursor.to_xy();
+        let num_records = self.data.records.len();
+
+        let (x, column) = match x.and_then(|x| Some((x, self.data.columns.get(x)?))) {
+            Some((x, column)) if num_records > 0 => (x, column),
+            _ => return false,
+        };
+
+        // From the column header, the search starts from the first or last record.
+        let start = y.unwrap_or(if forward { num_records - 1 } else { 0 });
+
+        let is_missing = |i: &usize| {
+            let record = &self.data.records[*i];
+
+            match &column.key {
+                ColumnKey::Meta(meta_key) => record.get_meta(meta_key).is_none(),
+                ColumnKey::Info(info_kind) => record.get_info(info_kind).is_none(),
+                ColumnKey::Command(command) => record.get_command_output(command).is_none(),
+                ColumnKey::Computed(template) => record.get_computed(template).is_none(),
+            }
+        };
+
+        let found =
+            (1..=num_records)
+            .map(|step| {
+                if forward { (start + step) % num_records }
+                else { (start + num_records - step) % num_records }
+            })
+            .find(is_missing)
+        ;
+
+        match found {
+            Some(i) => {
+                self.expand_group_of(i);
+                self.cursor = Cursor::Cell(x, i);
+                true
+            },
+            None => false,
+        }
+    }
+
+    /// The record under the cursor, if the cursor is on a cell or row.
+    pub fn current_record(&self) -> Option<&Record> {
+        let (_, y) = self.cursor.to_xy();
+        y.and_then(|y| self.data.records.get(y))
+    }
+
+    /// The column under the cursor.
+    pub fn current_column(&self) -> Option<&Column> {
+        let (x, _) = self.cursor.to_xy();
+        self.data.columns.get(x?)
+    }
+
+    /// Whether a record is selected.
+    pub fn is_selected(&self, record_index: usize) -> bool {
+        self.data.records.get(record_index).is_some_and(|r| self.selection.contains(&r.file_path))
+    }
+
+    /// Selects the record under the cursor, or deselects it if already selected.
+    pub fn toggle_selection(&mut self) {
+        if let Some(file_path) = self.current_record().map(|r| r.file_path.clone()) {
+            if !self.selection.remove(&file_path) {
+                self.selection.insert(file_path);
+            }
+        }
+    }
+
+    /// Selects every record.
+    pub fn select_all(&mut self) {
+        self.selection = self.data.records.iter().map(|r| r.file_path.clone()).collect();
+    }
+
+    /// Deselects every record.
+    pub fn clear_selection(&mut self) {
+        self.selection.clear();
+    }
+
+    /// Replaces the selection with the records of the given files, skipping
+    /// files that are no longer loaded. Returns how many were selected.
+    pub fn select_files<'a, I>(&mut self, file_paths: I) -> usize
+    where
+        I: IntoIterator<Item = &'a PathBuf>,
+    {
+        let records = &self.data.records;
+
+        self.selection =
+            file_paths.into_iter()
+            .filter(|&file_path| records.iter().any(|r| r.file_path == *file_path))
+            .cloned()
+            .collect()
+        ;
+
+        self.selection.len()
+    }
+
+    /// Finds the sets of records that look like the same track, see
+    /// `duplicate::find_duplicates`.
+    pub fn find_duplicates(&self) -> Vec<DuplicateSet> {
+        crate::duplicate::find_duplicates(&self.data.records)
+    }
+
+    /// The indices of the records that bulk actions apply to, see `Scope`.
+    pub fn target_record_indices(&self) -> Vec<usize> {
+        match self.scope {
+            Scope::Selection if self.selection.is_empty() => {
+                let (_, y) = self.cursor.to_xy();
+                y.into_iter().filter(|&y| y < self.data.records.len()).collect()
+            },
+            Scope::Selection => (0..self.data.records.len()).filter(|&i| self.is_selected(i)).collect(),
+            Scope::Group => self.current_group_indices(),
+            Scope::All => (0..self.data.records.len()).collect(),
+        }
+    }
+
+    /// The indices of the records that share the grouping key value of the
+    /// record under the cursor. The default grouping key is used when
+    /// grouping is turned off.
+    pub fn current_group_indices(&self) -> Vec<usize> {
+        let meta_key = self.group_by.as_deref().unwrap_or(GROUP_BY_KEY);
+
+        let value = match self.current_record() {
+            Some(record) => record.get_meta(meta_key),
+            None => return Vec::new(),
+        };
+
+        (0..self.data.records.len())
+        .filter(|&i| self.data.records[i].get_meta(meta_key) == value)
+        .collect()
+    }
+
+    /// The indices of the records on the same album as the record under the
+    /// cursor, no matter how records are grouped.
+    pub fn current_album_indices(&self) -> Vec<usize> {
+        let album = match self.current_record().and_then(|record| record.get_meta(GROUP_BY_KEY)) {
+            Some(album) => album,
+            None => return Vec::new(),
+        };
+
+        (0..self.data.records.len())
+        .filter(|&i| self.data.records[i].get_meta(GROUP_BY_KEY) == Some(album))
+        .collect()
+    }
+
+    /// The artist and album of the record under the cursor, to look the album
+    /// up by. The album artist is preferred over the track artist.
+    pub fn current_album_terms(&self) -> Option<(String, String)> {
+        let record = self.current_record()?;
+        let first = |meta_key: &str| record.get_meta(meta_key)?.first().cloned();
+
+        let artist = first("ALBUMARTIST").or_else(|| first("ARTIST"))?;
+        let album = first(GROUP_BY_KEY)?;
+
+        Some((artist, album))
+    }
+
+    /// The values of the cell under the cursor, if the cursor is on a cell.
+    pub fn current_cell_values(&self) -> Option<Option<Vec<String>>> {
+        let column = self.current_column()?;
+        let record = self.current_record()?;
+
+        let values = match &column.key {
+            ColumnKey::Meta(meta_key) => record.get_meta(meta_key).map(<[String]>::to_vec),
+            ColumnKey::Info(info_kind) => record.get_info(info_kind).map(|v| vec![v.into_owned()]),
+            ColumnKey::Command(command) => record.get_command_output(command).map(|v| vec![v.to_string()]),
+            ColumnKey::Computed(template) => record.get_computed(template).map(|v| vec![v]),
+        };
+
+        Some(values)
+    }
+
+    /// The distinct values of the current metadata column across all records,
+    /// in order, for completing values as they are entered.
+    pub fn column_completions(&self) -> Vec<String> {
+        let meta_key = match self.current_column().map(|col| &col.key) {
+            Some(ColumnKey::Meta(meta_key)) => meta_key,
+            _ => return Vec::new(),
+        };
+
+        let mut values =
+            self.data.records.iter()
+            .filter_map(|record| record.get_meta(meta_key))
+            .flatten()
+            .cloned()
+            .collect::<Vec<_>>()
+        ;
+
+        values.sort();
+        values.dedup();
+        values
+    }
+
+    /// Which lines are in view out of how many, such as
+    /// `rows 120–160 of 5,432 (2%)`, where the percentage is how far down the
+    /// view is scrolled.
+    pub fn scroll_position(&self) -> Option<String> {
+        let num_lines = self.cached_display_rows.len();
+
+        if num_lines == 0 {
+            return None;
+        }
+
+        let first = self.scroll_top.min(num_lines - 1);
+        let last = (first + self.viewport_height.max(1)).min(num_lines);
+        let scrollable = num_lines.saturating_sub(self.viewport_height);
+        let percent = (first.min(scrollable) * 100).checked_div(scrollable).unwrap_or(100);
+
+        Some(format!(
+            "rows {}–{} of {} ({}%)",
+            Util::group_digits(first + 1),
+            Util::group_digits(last),
+            Util::group_digits(num_lines),
+            percent,
+        ))
+    }
+
+    /// How the column under the cursor is set up, such as
+    /// `Artist: Meta("ARTIST"), sized Auto, sorted ascending, 12 distinct value(s)`.
+    pub fn current_column_info(&self) -> Option<String> {
+        let (x, _) = self.cursor.to_xy();
+        let x = x?;
+        let column = self.data.columns.get(x)?;
+
+        let sort = match self.sort {
+            Some(sort) if sort.column == x && sort.descending => "sorted descending",
+            Some(sort) if sort.column == x => "sorted ascending",
+            _ => "not sorted",
+        };
+
+        let values = self.data.records.iter().map(|r| crate::clipboard::cell_text(r, column)).collect::<Vec<_>>();
+        let num_missing = values.iter().filter(|v| v.is_none()).count();
+        let num_distinct = values.iter().flatten().collect::<HashSet<_>>().len();
+
+        let mut info = format!("{}: {:?}, sized {:?}, {}, {} distinct value(s)", column.title, column.key, self.column_sizing(column), sort, num_distinct);
+
+        if num_missing > 0 {
+            info.push_str(&format!(", {} missing", num_missing));
+        }
+
+        Some(info)
+    }
+
+    /// The full value of the cell under the cursor on one line, after the
+    /// title of its column, or the file path when a whole row is highlighted.
+    /// In column mode, this describes the column instead.
+    pub fn current_value_line(&self) -> Option<String> {
+        if self.cursor.is_in_column_mode() {
+            return self.current_column_info();
+        }
+
+        let record = self.current_record()?;
+
+        let (title, value) = match (self.current_column(), self.current_cell_values()) {
+            (Some(column), Some(values)) => {
+                let kind = column.value_kind();
+
+                let value = match values {
+                    // Dates are easy to get subtly wrong, so say so.
+                    Some(values) if kind == ValueKind::Date && !values.iter().all(|v| kind.validate(v)) => {
+                        format!("{}  (not a valid YYYY-MM-DD date)", values.join(column.separator()))
+                    },
+                    Some(values) if values.iter().all(|v| v.is_empty()) => String::from(EMPTY_VALUE_STR),
+                    Some(values) => values.join(column.separator()),
+                    None => String::from(MISSING_VALUE_STR),
+                };
+
+                (column.title.as_str(), value)
+            },
+            _ => ("File", record.file_path.display().to_string()),
+        };
+
+        let value = if self.show_invisible { reveal_invisible(&value).into_owned() } else { value };
+
+        // Line breaks would spill onto the lines below.
+        Some(format!("{}: {}", title, value.replace('\n', &NEWLINE_MARKER.to_string())))
+    }
+
+    /// Stages values for the current column on each target record, returning
+    /// how many records changed out of how many were targeted. Returns `None`
+    /// if the current column is not a metadata column.
+    pub fn set_target_values(&mut self, description: &str, values: Option<Vec<String>>) -> Option<(usize, usize)> {
+        let meta_key = match &self.current_column()?.key {
+            ColumnKey::Meta(meta_key) => meta_key.clone(),
+            ColumnKey::Info(..) | ColumnKey::Command(..) | ColumnKey::Computed(..) => return None,
+        };
+
+        let indices = self.target_record_indices();
+
+        let edited = self.edit_records(format!("{} ({})", description, meta_key), |records| {
+            indices.iter()
+            .filter(|&&i| records[i].set_meta(&meta_key, values.clone()))
+            .count()
+        });
+
+        Some((edited, indices.len()))
+    }
+
+    /// Sets a field of the target records as one edit, adding a column for it
+    /// and moving the cursor there if no column shows it yet. Returns how many
+    /// records changed out of how many were targeted, or `None` if the key
+    /// can't be used in a Vorbis comment.
+    pub fn add_field(&mut self, meta_key: &str, values: Vec<String>) -> Option<(usize, usize)> {
+        if !is_valid_meta_key(meta_key) {
+            return None;
+        }
+
+        let title = meta_key;
+        let meta_key = meta_key.to_uppercase();
+        let indices = self.target_record_indices();
+
+        let column_index = self.data.columns.iter().position(|col| col.key == ColumnKey::Meta(meta_key.clone()));
+
+        if column_index.is_none() {
+            // Adding a column at the end leaves the sort column in place.
+            self.data.columns.push(Column {
+                key: ColumnKey::Meta(meta_key.clone()),
+                title: title.to_string(),
+                sizing: Sizing::Auto,
+                align: Align::default(),
+                kind: None,
+                separator: None,
+            });
+
+            if let Cursor::Cell(_, y) = self.cursor {
+                self.cursor = Cursor::Cell(self.data.columns.len() - 1, y);
+            }
+        }
+
+        let edited = self.edit_records(format!("Add field ({})", meta_key), |records| {
+            indices.iter()
+            .filter(|&&i| records[i].set_meta(&meta_key, Some(values.clone())))
+            .count()
+        });
+
+        Some((edited, indices.len()))
+    }
+
+    /// How many loaded records have a field, whatever the case of its key, and
+    /// how many of those already have the field it would be renamed to.
+    pub fn rename_key_counts(&self, from: &str, to: &str) -> (usize, usize) {
+        let to = to.to_uppercase();
+
+        let having =
+            self.data.records.iter()
+            .filter(|record| record.meta_keys().any(|k| k.eq_ignore_ascii_case(from) && k != to))
+            .collect::<Vec<_>>()
+        ;
+
+        (having.len(), having.iter().filter(|record| record.get_meta(&to).is_some()).count())
+    }
+
+    /// Moves the values of a field to another one in every loaded record as
+    /// one edit, after any values already there. The field is matched
+    /// whatever the case of its key. Columns showing the old field are moved
+    /// over to the new one, unless it already has a column, and stay moved
+    /// if the edit is undone. Returns how many records changed, or `None` if
+    /// the new key can't be used in a Vorbis comment.
+    pub fn rename_key(&mut self, from: &str, to: &str) -> Option<usize> {
+        if !is_valid_meta_key(to) {
+            return None;
+        }
+
+        let title = to;
+        let to = to.to_uppercase();
+
+        let edited = self.edit_records(format!("Rename field ({} to {})", from, to), |records| {
+            records.iter_mut()
+            .map(|record| {
+                let meta_keys =
+                    record.meta_keys()
+                    .filter(|k| k.eq_ignore_ascii_case(from))
+                    .map(String::from)
+                    .collect::<Vec<_>>()
+                ;
+
+                meta_keys.into_iter()
+                .filter(|meta_key| crate::transform::stage_rename_key(record, meta_key, &to))
+                .count() > 0
+            })
+            .filter(|&changed| changed)
+            .count()
+        });
+
+        let has_column = self.data.columns.iter().any(|col| col.key == ColumnKey::Meta(to.clone()));
+
+        if edited > 0 && !has_column {
+            for column in self.data.columns.iter_mut() {
+                if matches!(&column.key, ColumnKey::Meta(meta_key) if meta_key.eq_ignore_ascii_case(from)) {
+                    column.key = ColumnKey::Meta(to.clone());
+                    column.title = title.to_string();
+                    self.dirty = true;
+                }
+            }
+        }
+
+        Some(edited)
+    }
+
+    /// Why edits can't be made, if every record they would apply to is
+    /// read-only. Edits that reach only some read-only records leave those
+    /// records alone.
+    pub fn read_only_targets_notice(&self) -> Option<String> {
+        let indices = self.column_target_indices();
+
+        if indices.is_empty() || !indices.iter().all(|&i| self.data.records[i].read_only) {
+            return None;
+        }
+
+        match indices.as_slice() {
+            [i] => Some(format!("{} is read-only", self.data.records[*i].file_path.file_name().unwrap_or_default().to_string_lossy())),
+            _ => Some(format!("All {} target file(s) are read-only", indices.len())),
+        }
+    }
+
+    /// The records that column-wide changes apply to: every record when the
+    /// whole column is highlighted, or else the target records.
+    pub fn column_target_indices(&self) -> Vec<usize> {
+        if self.cursor.is_in_column_mode() { (0..self.data.records.len()).collect() }
+        else { self.target_record_indices() }
+    }
+
+    /// Works out the ASCII spelling of the values in the current column, for
+    /// the records `column_target_indices` gives, leaving out those already
+    /// in ASCII. Returns the metadata key with the new values of each record,
+    /// or `None` if the current column is not a metadata column.
+    pub fn plan_ascii_values(&self) -> Option<(String, ValueChanges)> {
+        let meta_key = match &self.current_column()?.key {
+            ColumnKey::Meta(meta_key) => meta_key.clone(),
+            ColumnKey::Info(..) | ColumnKey::Command(..) | ColumnKey::Computed(..) => return None,
+        };
+
+        let changes =
+            self.column_target_indices().into_iter()
+            .filter_map(|i| {
+                let values = self.data.records[i].get_meta(&meta_key)?;
+                let ascii = values.iter().map(|v| to_ascii(v)).collect::<Vec<_>>();
+
+                if ascii.as_slice() == values { None } else { Some((i, ascii)) }
+            })
+            .collect()
+        ;
+
+        Some((meta_key, changes))
+    }
+
+    /// Works out ASCII names for the files of the records that
+    /// `column_target_indices` gives.
+    pub fn plan_ascii_renames(&self) -> Vec<Rename> {
+        let records = self.column_target_indices().into_iter().map(|i| &self.data.records[i]);
+
+        let name = |record: &Record| {
+            let stem = record.file_path.file_stem().unwrap_or_default().to_string_lossy();
+            vec![crate::rename::sanitize(&to_ascii(&stem))]
+        };
+
+        crate::rename::plan_with(records, name, |path| self.source.exists(path))
+    }
+
+    /// Stages new values of a metadata key on some records as one edit,
+    /// returning how many records changed.
+    pub fn stage_values(&mut self, description: &str, meta_key: &str, changes: &[(usize, Vec<String>)]) -> usize {
+        self.edit_records(format!("{} ({})", description, meta_key), |records| {
+            changes.iter()
+            .filter(|(i, values)| records[*i].set_meta(meta_key, Some(values.clone())))
+            .count()
+        })
+    }
+
+    /// Changes the case of the values in the current column, on the target
+    /// records, or on every record when the whole column is highlighted.
+    /// Returns how many records changed out of how many were targeted, or
+    /// `None` if the current column is not a metadata column.
+    pub fn convert_case(&mut self, case: Case) -> Option<(usize, usize)> {
+        let meta_key = match &self.current_column()?.key {
+            ColumnKey::Meta(meta_key) => meta_key.clone(),
+            ColumnKey::Info(..) | ColumnKey::Command(..) | ColumnKey::Computed(..) => return None,
+        };
+
+        let indices = self.column_target_indices();
+
+        let small_words = &self.small_words;
+        let converted =
+            indices.iter()
+            .map(|&i| {
+                let values = self.data.records[i].get_meta(&meta_key).unwrap_or_default();
+                values.iter().map(|v| case.apply(v, small_words)).collect::<Vec<_>>()
+            })
+            .collect::<Vec<_>>()
+        ;
+
+        let edited = self.edit_records(format!("Convert to {} ({})", case.label(), meta_key), |records| {
+            indices.iter()
+            .zip(converted)
+            .filter(|(&i, values)| records[i].set_meta(&meta_key, Some(values.clone())))
+            .count()
+        });
+
+        Some((edited, indices.len()))
+    }
+
+    /// Raises or lowers the rating of each target record by a star, returning
+    /// how many records changed out of how many were targeted.
+    pub fn bump_rating(&mut self, up: bool) -> (usize, usize) {
+        let indices = self.target_record_indices();
+        let (rating_key, max) = (self.rating_key.clone(), self.rating_max);
+
+        let description = format!("{} ({})", if up { "Rate up" } else { "Rate down" }, rating_key);
+
+        let edited = self.edit_records(description, |records| {
+            indices.iter()
+            .filter(|&&i| {
+                let rating = records[i].get_meta(&rating_key).and_then(|vs| vs.first()).map(String::as_str);
+
+                // Unrated records are left unrated when lowering.
+                if rating.is_none() && !up {
+                    return false;
+                }
+
+                let bumped = crate::value::bump_rating(rating, max, up);
+                records[i].set_meta(&rating_key, Some(vec![bumped]))
+            })
+            .count()
+        });
+
+        (edited, indices.len())
+    }
+
+    /// Stages new lyrics for the record of a file, in the tag it already keeps
+    /// lyrics in. Empty lyrics remove the tag. Returns whether anything
+    /// changed.
+    pub fn set_lyrics(&mut self, file_path: &Path, lyrics: &str) -> bool {
+        let record_index = match self.data.records.iter().position(|r| r.file_path == file_path) {
+            Some(record_index) => record_index,
+            None => return false,
+        };
+
+        let meta_key = crate::lyrics::lyrics_key(&self.data.records[record_index]);
+        let values = Some(vec![lyrics.to_string()]).filter(|_| !lyrics.trim().is_empty());
+
+        self.edit_records(format!("Edit lyrics ({})", meta_key), |records| records[record_index].set_meta(meta_key, values))
+    }
+
+    /// Stages ReplayGain tags for analyzed files. Album gains are worked out
+    /// from the analyzed files that share an album. Returns how many records
+    /// changed.
+    pub fn apply_replay_gain(&mut self, analyzed: &[(PathBuf, Loudness)]) -> usize {
+        let indexed =
+            analyzed.iter()
+            .filter_map(|(file_path, loudness)| Some((self.data.records.iter().position(|r| r.file_path == *file_path)?, loudness)))
+            .collect::<Vec<_>>()
+        ;
+
+        let album_of = |i: usize| self.data.records[i].get_meta(GROUP_BY_KEY).map(<[String]>::to_vec);
+
+        let changes =
+            indexed.iter()
+            .map(|&(i, loudness)| {
+                let album = album_of(i).map(|album| {
+                    Loudness::combine(indexed.iter().filter(|(j, _)| album_of(*j).as_ref() == Some(&album)).map(|(_, l)| *l))
+                });
+
+                (i, crate::replaygain::tags(loudness, album.as_ref()))
+            })
+            .collect::<Vec<_>>()
+        ;
+
+        self.edit_records("ReplayGain", |records| {
+            changes.into_iter()
+            .filter(|(i, tags)| {
+                tags.iter().fold(false, |changed, (meta_key, value)| records[*i].set_meta(meta_key, Some(vec![value.clone()])) | changed)
+            })
+            .count()
+        })
+    }
+
+    /// Runs a script from the config on each target record, staging the tag
+    /// changes it makes as one edit. Nothing is staged if the script fails on
+    /// any record. Returns how many records changed out of how many were
+    /// targeted.
+    pub fn run_script(&mut self, name: &str) -> Result<(usize, usize), String> {
+        let source = self.scripts.get(name).ok_or_else(|| format!("no script named {}", name))?;
+        let script = Script::compile(source)?;
+
+        let indices = self.target_record_indices();
+
+        let changes =
+            indices.iter()
+            .map(|&i| {
+                let record = &self.data.records[i];
+                let changes = script.run(record).map_err(|err| format!("{}: {}", record.file_path.file_name().unwrap_or_default().to_string_lossy(), err))?;
+                Ok((i, changes))
+            })
+            .collect::<Result<Vec<_>, String>>()?
+        ;
+
+        let edited = self.edit_records(format!("Run script ({})", name), |records| {
+            changes.into_iter()
+            .filter(|(i, changes)| {
+                changes.iter().fold(false, |changed, (meta_key, values)| records[*i].set_meta(meta_key, values.clone()) | changed)
+            })
+            .count()
+        });
+
+        Ok((edited, indices.len()))
+    }
+
+    /// Applies a tag preset from the config to the target records, staging its
+    /// tags as one edit. Columns of the preset that aren't shown yet are then
+    /// added, if a record changed or the preset has no tags; unlike the tags,
+    /// added columns are not taken back by undoing. Returns how many records
+    /// changed out of how many were targeted.
+    pub fn apply_tag_preset(&mut self, name: &str) -> Result<(usize, usize), String> {
+        let TagPreset { tags, columns } = self.tag_presets.get(name).cloned().ok_or_else(|| format!("no tag preset named {}", name))?;
+
+        if let Some(meta_key) = tags.keys().find(|k| !is_valid_meta_key(k)) {
+            return Err(format!("{} is not a valid field name", meta_key));
+        }
+
+        let indices = self.target_record_indices();
+
+        let edited = self.edit_records(format!("Apply tag preset ({})", name), |records| {
+            indices.iter()
+            .filter(|&&i| {
+                tags.iter().fold(false, |changed, (meta_key, values)| {
+                    records[i].set_meta(&meta_key.to_uppercase(), Some(values.clone())) | changed
+                })
+            })
+            .count()
+        });
+
+        // Adding columns at the end leaves the sort column in place.
+        if edited > 0 || tags.is_empty() {
+            for column in columns {
+                if !self.data.columns.iter().any(|col| col.key == column.key) {
+                    self.data.columns.push(column);
+                    self.dirty = true;
+                }
+            }
+        }
+
+        Ok((edited, indices.len()))
+    }
+
+    /// Works out which fields to fill in on the albums of the target records.
+    /// See `album_fill::plan`.
+    pub fn plan_album_fills(&self) -> Vec<AlbumFill> {
+        crate::album_fill::plan(&self.data.records, &self.target_record_indices())
+    }
+
+    /// Fills in the values of album fills as one edit, returning how many
+    /// records changed.
+    pub fn fill_album_fields(&mut self, fills: &[AlbumFill]) -> usize {
+        self.edit_records("Fill album fields", |records| {
+            records.iter_mut()
+            .map(|record| {
+                let file_path = record.file_path.clone();
+
+                fills.iter()
+                .filter(|fill| fill.file_paths.contains(&file_path))
+                .fold(false, |changed, fill| record.set_meta(&fill.meta_key, Some(fill.values.clone())) | changed)
+            })
+            .filter(|&changed| changed)
+            .count()
+        })
+    }
+
+    /// Copies the values of some metadata keys from the record of a file onto
+    /// each of the other target records, returning how many records changed
+    /// out of how many were targeted. Returns `None` if the file is no longer
+    /// loaded.
+    pub fn copy_tags(&mut self, source_path: &Path, meta_keys: &[String]) -> Option<(usize, usize)> {
+        let source_index = self.data.records.iter().position(|r| r.file_path == source_path)?;
+
+        let indices =
+            self.target_record_indices().into_iter()
+            .filter(|&i| i != source_index)
+            .collect::<Vec<_>>()
+        ;
+
+        let values =
+            meta_keys.iter()
+            .map(|meta_key| {
+                let values = self.data.records[source_index].get_meta(meta_key).map(<[String]>::to_vec);
+                (meta_key, values)
+            })
+            .collect::<Vec<_>>()
+        ;
+
+        let description = format!("Copy {}", meta_keys.join(", "));
+
+        let edited = self.edit_records(description, |records| {
+            indices.iter()
+            .filter(|&&i| {
+                // Avoid short-circuiting, so that every key gets copied.
+                values.iter().fold(false, |changed, (meta_key, values)| {
+                    records[i].set_meta(meta_key, values.clone()) | changed
+                })
+            })
+            .count()
+        });
+
+        Some((edited, indices.len()))
+    }
+
+    /// Sets the message shown below the table.
+    pub fn set_status(&mut self, message: impl Into<String>) {
+        self.status = Some(message.into());
+    }
+
+    /// Feeds in the width of the view, resizing proportionally sized columns
+    /// on the next recache if it changed.
+    pub fn set_viewport_width(&mut self, width: usize) {
+        if self.viewport_width != width {
+            self.viewport_width = width;
+            self.dirty = true;
+        }
+    }
+
+    /// Feeds in the first line in view, resizing columns sized by the rows
+    /// in view on the next recache if it changed.
+    pub fn set_scroll_top(&mut self, line: usize) {
+        if self.scroll_top != line {
+            self.scroll_top = line;
+
+            if self.data.columns.iter().any(|c| matches!(c.sizing, Sizing::Visible)) {
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// Sizes a column to a fixed width for as long as this runs.
+    pub fn resize_column(&mut self, x: usize, width: usize) {
+        if let Some(column) = self.data.columns.get(x) {
+            if self.width_overrides.insert(column.key.clone(), width) != Some(width) {
+                self.dirty = true;
+            }
+        }
+    }
+
+    /// How a column is sized, after any width it was resized to.
+    pub fn column_sizing(&self, column: &Column) -> Sizing {
+        self.width_overrides.get(&column.key).map_or(column.sizing, |&width| Sizing::Fixed(width))
+    }
+
+    /// Works out the column widths, groups, and lines of the table again, if
+    /// anything they depend on changed since the last time.
+    pub fn recache(&mut self) {
+        // Proceed and clear the flag if it was set.
+        // Otherwise, bail out.
+        if self.dirty { self.dirty = false; }
+        else { return; }
+
+        self.cached_groups.clear();
+        self.cached_group_headers.clear();
+        self.cached_group_collapsed.clear();
+        self.cached_display_rows.clear();
+
+        match &self.group_by {
+            None => {
+                self.cached_display_rows.extend((0..self.data.records.len()).map(DisplayRow::Record));
+            },
+            Some(group_by) => {
+                for (i, group) in Group::find_all(&self.data.records, group_by).into_iter().enumerate() {
+                    let summary = GroupSummary::new(
+                        &self.data.records[group.range()],
+                        group_by,
+                        self.show_group_art,
+                    );
+
+                    let collapsed = self.is_collapsed(group.start);
+
+                    self.cached_display_rows.push(DisplayRow::Header(i));
+                    if !collapsed {
+                        self.cached_display_rows.extend(group.range().map(DisplayRow::Record));
+                    }
+
+                    self.cached_groups.push(group);
+                    self.cached_group_headers.push(summary.to_string());
+                    self.cached_group_collapsed.push(collapsed);
+                }
+            },
+        };
+
+        self.cached_content_widths.clear();
+        self.cached_content_widths.reserve(self.data.columns.len());
+
+        let records = &self.data.records;
+        let visible_records =
+            self.cached_display_rows.iter()
+            .skip(self.scroll_top)
+            .take(self.viewport_height)
+            .filter_map(|row| match row {
+                DisplayRow::Record(i) => Some(&records[*i]),
+                DisplayRow::Header(_) => None,
+            })
+            .collect::<Vec<_>>()
+        ;
+
+        let mut visible_widths = HashMap::new();
+
+        for column in self.data.columns.iter() {
+            let column_sizing = self.column_sizing(column);
+
+            let mccw = || {
+                Util::max_column_content_width(
+                    column,
+                    &self.data.records,
+                    self.show_invisible,
+                )
+            };
+
+            let content_width = match column_sizing {
+                Sizing::Auto => mccw(),
+                Sizing::Fixed(width) => width,
+                Sizing::Lower(min_width) => mccw().max(min_width),
+                Sizing::Upper(max_width) => mccw().min(max_width),
+                Sizing::Bound(min_width, max_width) => mccw().max(min_width).min(max_width),
+                Sizing::Visible => {
+                    let width = Util::max_column_content_width(column, visible_records.iter().copied(), self.show_invisible);
+
+                    let width = match self.visible_widths.get(&column.key) {
+                        Some(&last_width) if width < last_width && last_width - width <= VISIBLE_SHRINK_SLACK => last_width,
+                        _ => width,
+                    };
+
+                    visible_widths.insert(column.key.clone(), width);
+                    width
+                },
+                // Filled in below, once the other widths are known.
+                Sizing::Percent(_) | Sizing::Weight(_) => 0,
+            };
+
+            self.cached_content_widths.push(content_width);
+        }
+
+        self.visible_widths = visible_widths;
+
+        let sizings = self.data.columns.iter().map(|c| self.column_sizing(c)).collect::<Vec<_>>();
+        let widths = &mut self.cached_content_widths;
+
+        fill_percent_widths(&sizings, widths, self.viewport_width);
+        if self.fit_width { fit_widths(&sizings, widths, self.viewport_width); }
+        fill_weighted_widths(&sizings, widths, self.viewport_width);
+
+        assert_eq!(self.cached_content_widths.len(), self.data.columns.len());
+    }
+
+    /// The column commands that have yet to be run on the files of the
+    /// records, marking them as running until their outputs are stored.
+    /// Commands are run again once their file changes.
+    pub fn take_command_jobs(&mut self) -> Vec<CommandJob> {
+        let mut jobs = Vec::new();
+
+        for column in self.data.columns.iter() {
+            let command = match &column.key {
+                ColumnKey::Command(command) => command,
+                _ => continue,
+            };
+
+            let pending = self.pending_commands.entry(command.clone()).or_default();
+
+            for record in self.data.records.iter() {
+                if record.get_command_output(command).is_none() && !pending.contains(&record.file_path) {
+                    pending.insert(record.file_path.clone());
+                    jobs.push(CommandJob::new(command, record));
+                }
+            }
+        }
+
+        jobs
+    }
+
+    /// Stores the outputs of column commands with the records they were run on.
+    pub fn set_command_outputs(&mut self, outputs: Vec<(CommandJob, String)>) {
+        let indices =
+            self.data.records.iter().enumerate()
+            .map(|(i, record)| (record.file_path.clone(), i))
+            .collect::<HashMap<_, _>>()
+        ;
+
+        for (job, output) in outputs {
+            if let Some(pending) = self.pending_commands.get_mut(&job.command) {
+                pending.remove(&job.file_path);
+            }
+
+            // The record may have gone away in the meantime.
+            if let Some(&i) = indices.get(&job.file_path) {
+                self.data.records[i].command_outputs.insert(job.command, (job.modified, output));
+            }
+        }
+
+        self.dirty = true;
+    }
+
+    /// Every tag of the record under the cursor as sorted key and value pairs,
+    /// with multiple values joined, and whether each has a pending edit.
+    pub fn detail_lines(&self) -> Vec<(String, String, bool)> {
+        let record = match self.current_record() {
+            Some(record) => record,
+            None => return Vec::new(),
+        };
+
+        let mut lines =
+            record.meta_keys()
+            .map(|meta_key| {
+                let values = record.get_meta(meta_key).unwrap_or_default().join(FIELD_SEP_STR);
+                let values = if self.show_invisible { reveal_invisible(&values).into_owned() } else { values };
+                (meta_key.to_string(), values, record.edits.contains_key(meta_key))
+            })
+            .collect::<Vec<_>>()
+        ;
+
+        lines.sort();
+        lines
+    }
+
+    /// Returns the line of the table body that a record is displayed on. The
+    /// records of a collapsed group are all on the line of its header.
+    pub fn record_display_row(&self, record_index: usize) -> usize {
+        // Each group that starts at or before this record adds a header row.
+        let num_groups = self.cached_groups.partition_point(|g| g.start <= record_index);
+
+        let hidden =
+            self.cached_groups[..num_groups].iter()
+            .zip(&self.cached_group_collapsed)
+            .filter(|(_, &collapsed)| collapsed)
+            .map(|(g, _)| g.len)
+            .sum::<usize>()
+        ;
+
+        match num_groups.checked_sub(1) {
+            Some(g) if self.cached_group_collapsed[g] => {
+                let group = self.cached_groups[g];
+                group.start + g - (hidden - group.len)
+            },
+            _ => record_index + num_groups - hidden,
+        }
+    }
+
+    /// Switches between shrinking columns to fit the view and showing them
+    /// at their full width.
+    pub fn toggle_fit_width(&mut self) {
+        self.fit_width = !self.fit_width;
+        self.dirty = true;
+    }
+
+    /// Switches whether hidden files are loaded, which takes effect on the
+    /// next refresh.
+    pub fn toggle_show_hidden(&mut self) {
+        self.show_hidden = !self.show_hidden;
+        self.source.set_show_hidden(self.show_hidden);
+    }
+
+    /// Shows or hides markers for characters that are hard to see.
+    pub fn toggle_show_invisible(&mut self) {
+        self.show_invisible = !self.show_invisible;
+        self.dirty = true;
+    }
+
+    /// Turns grouping by a metadata key on, or off if it is already on.
+    pub fn toggle_grouping(&mut self, meta_key: &str) {
+        if self.group_by.take().is_none() {
+            Group::sort_records(&mut self.data.records, meta_key);
+            self.group_by = Some(meta_key.to_string());
+        }
+
+        self.collapsed_groups.clear();
+        self.dirty = true;
+    }
+
+    /// Groups records by the metadata column under the cursor, or stops
+    /// grouping if already grouped by it. Returns the key grouped by, or
+    /// `None` if grouping was turned off or the column is not a metadata one.
+    pub fn group_by_column(&mut self) -> Option<String> {
+        let meta_key = match &self.current_column()?.key {
+            ColumnKey::Meta(meta_key) => meta_key.clone(),
+            ColumnKey::Info(..) | ColumnKey::Command(..) | ColumnKey::Computed(..) => return None,
+        };
+
+        if self.group_by.as_deref() == Some(meta_key.as_str()) {
+            self.toggle_grouping(&meta_key);
+            return None;
+        }
+
+        self.group_by = None;
+        self.toggle_grouping(&meta_key);
+        Some(meta_key)
+    }
+
+    /// Collapses the group under the cursor down to its header, or expands
+    /// it if already collapsed, leaving the cursor on its first record.
+    /// Returns `false` if records are not grouped.
+    pub fn toggle_collapse(&mut self) -> bool {
+        let meta_key = match &self.group_by {
+            Some(meta_key) => meta_key.clone(),
+            None => return false,
+        };
+
+        let (_, y) = self.cursor.to_xy();
+        let value = match y.and_then(|y| self.data.records.get(y)) {
+            Some(record) => record.get_meta(&meta_key).map(<[String]>::to_vec),
+            None => return true,
+        };
+
+        if !self.collapsed_groups.remove(&value) {
+            self.collapsed_groups.insert(value.clone());
+        }
+
+        if let Some(start) = self.data.records.iter().position(|r| r.get_meta(&meta_key).map(<[String]>::to_vec) == value) {
+            self.cursor = self.cursor.with_row(start);
+        }
+
+        self.dirty = true;
+        self.scroll_pending = true;
+        true
+    }
+
+    /// Collapses every group, or expands them all if any is collapsed.
+    /// Returns `false` if records are not grouped.
+    pub fn toggle_collapse_all(&mut self) -> bool {
+        let meta_key = match &self.group_by {
+            Some(meta_key) => meta_key.clone(),
+            None => return false,
+        };
+
+        if self.collapsed_groups.is_empty() {
+            self.collapsed_groups = self.data.records.iter().map(|r| r.get_meta(&meta_key).map(<[String]>::to_vec)).collect();
+
+            // Lands on the first record of the group the cursor was in.
+            self.move_cursor(CursorDir::D, 0);
+        }
+        else {
+            self.collapsed_groups.clear();
+        }
+
+        self.dirty = true;
+        self.scroll_pending = true;
+        true
+    }
+
+    /// How wide all the columns are together, with separators of a given width
+    /// between them.
+    pub fn total_display_width(&self, column_sep_width: usize) -> usize {
+        let total_sep_width = self.cached_content_widths.len().saturating_sub(1) * column_sep_width;
+        self.cached_content_widths.iter().sum::<usize>() + total_sep_width
+    }
+
+    /// Where a column starts, with separators of a given width between columns.
+    pub fn column_offset(&self, column_index: usize, column_sep_width: usize) -> Option<usize> {
+        if column_index >= self.cached_content_widths.len() {
+            None
+        } else {
+            let offset =
+                self.cached_content_widths.iter().cloned().take(column_index).sum::<usize>()
+                + column_sep_width * column_index
+            ;
+            Some(offset)
+        }
+    }
+
+    /// How much room the table needs, with separators of a given width between
+    /// columns.
+    pub fn required_size(&self, column_sep_width: usize) -> XY<usize> {
+        XY::new(self.total_display_width(column_sep_width), self.cached_display_rows.len())
+    }
+
+    /// Changes the columns. This clears the sort, as the sort column may be gone.
+    pub fn mutate_columns<F, R>(&mut self, func: F) -> R
+    where
+        F: FnOnce(&mut Columns) -> R,
+    {
+        let result = func(&mut self.data.columns);
+
+        // The old sort column may no longer exist, and the cursor may now be
+        // pointing past the last column.
+        self.sort = None;
+        self.cursor.clamp(self.data.columns.len(), self.data.records.len());
+
+        self.dirty = true;
+        result
+    }
+
+    /// Changes the records, outside of the undo history.
+    pub fn mutate_records<F, R>(&mut self, func: F) -> R
+    where
+        F: FnOnce(&mut Records) -> R,
+    {
+        let result = func(&mut self.data.records);
+        self.dirty = true;
+        result
+    }
+
+    /// Stages edits on records as a single operation that can be undone.
+    /// The function must not reorder, add, or remove records.
+    pub fn edit_records<F, R>(&mut self, description: impl Into<String>, func: F) -> R
+    where
+        F: FnOnce(&mut Records) -> R,
+    {
+        let (result, operation) = Operation::record(description.into(), &mut self.data.records, func);
+
+        self.history.push(operation);
+        self.dirty = true;
+        result
+    }
+
+    /// Undoes the last edit operation, returning its description.
+    pub fn undo(&mut self) -> Option<String> {
+        let description = self.history.undo(&mut self.data.records)?.description.clone();
+        self.dirty = true;
+        Some(description)
+    }
+
+    /// Redoes the last undone edit operation, returning its description.
+    pub fn redo(&mut self) -> Option<String> {
+        let description = self.history.redo(&mut self.data.records)?.description.clone();
+        self.dirty = true;
+        Some(description)
+    }
+
+    /// Undoes or redoes edit operations until the given number of them are
+    /// done, returning how many were undone or redone.
+    pub fn go_to_history(&mut self, num_done: usize) -> usize {
+        let steps = self.history.go_to(&mut self.data.records, num_done);
+        self.dirty = true;
+        steps
+    }
+
+    /// Sorts the records by a column, within their groups if grouped.
+    pub fn sort_by_column_index(&mut self, column_index: usize, is_descending: bool) {
+        // No recaching should be needed with sorting.
+        self.data.sort_by_column_index(column_index, is_descending);
+
+        // Sorting happens within each group when grouping is enabled.
+        if let Some(group_by) = &self.group_by {
+            Group::sort_records(&mut self.data.records, group_by);
+        }
+
+        self.sort = Some(SortSpec { column: column_index, descending: is_descending });
+        self.dirty = true;
+    }
+
+    /// What saving would write, as the old and new values of each pending
+    /// edit, grouped by file. Lines start with `-` for old values and `+` for
+    /// new ones, as in `+ TITLE=Song 1`.
+    pub fn pending_diff(&self) -> String {
+        let mut diff = String::new();
+
+        for record in self.data.records.iter().filter(|r| r.is_edited()) {
+            if !diff.is_empty() {
+                diff.push('\n');
+            }
+
+            diff.push_str(&format!("{}\n", record.file_path.display()));
+
+            let mut meta_keys = record.edits.keys().collect::<Vec<_>>();
+            meta_keys.sort();
+
+            for meta_key in meta_keys {
+                let old = record.metadata.get(meta_key).map(Vec::as_slice).unwrap_or_default();
+                let new = record.edits[meta_key].as_deref().unwrap_or_default();
+
+                for (sigil, values) in [("-", old), ("+", new)] {
+                    for value in values {
+                        diff.push_str(&format!("{} {}={}\n", sigil, meta_key, value));
+                    }
+                }
+            }
+        }
+
+        diff
+    }
+
+    /// Writes all records with pending edits back to their files. Files that
+    /// were changed on disk since they were read are left alone, and are
+    /// reported as conflicts to settle before saving again. When normalizing
+    /// on save, every tag of a written file is converted to NFC. Nothing is
+    /// written in read-only mode.
+    pub fn save_all(&mut self) -> SaveSummary {
+        let (records, mut summary) = self.queue_writes();
+
+        for record in records {
+            let result = self.source.write_record(&record);
+
+            match self.finish_write(&record, result) {
+                Ok(()) => { summary.saved += 1; },
+                Err(err) => { summary.failures.push((record.file_path, err)); },
+            }
+        }
+
+        log::info!("{}", summary);
+        summary
+    }
+
+    /// Gets the edited records ready to be written, like `save_all`, and
+    /// returns copies of them for a writer to work through. Their files are
+    /// marked as being written until `finish_write` is called for each, and
+    /// are not queued again until then. The summary holds the records that
+    /// could not be queued.
+    pub fn queue_writes(&mut self) -> (Vec<Record>, SaveSummary) {
+        self.queue_writes_where(|_| true)
+    }
+
+    /// Like `queue_writes`, but only for the record of one file.
+    pub fn queue_file_write(&mut self, file_path: &Path) -> (Vec<Record>, SaveSummary) {
+        self.queue_writes_where(|r| r.file_path == file_path)
+    }
+
+    fn queue_writes_where(&mut self, pred: impl Fn(&Record) -> bool) -> (Vec<Record>, SaveSummary) {
+        let mut summary = SaveSummary::default();
+        let mut queued = Vec::new();
+
+        if self.read_only {
+            return (queued, summary);
+        }
+
+        let writing = &self.writing;
+        let write_failures = &mut self.write_failures;
+
+        for record in self.data.records.iter_mut().filter(|r| r.is_edited() && !writing.contains(&r.file_path) && pred(r)) {
+            if record.modified.is_some() && self.source.modified(&record.file_path) != record.modified {
+                let disk = match self.source.read_record(record.file_path.clone()) {
+                    Ok(disk) => disk,
+                    Err(err) => {
+                        write_failures.insert(record.file_path.clone());
+                        summary.failures.push((record.file_path.clone(), err));
+                        continue;
+                    },
+                };
+
+                // Only the time changed, such as from being touched.
+                if disk.metadata == record.metadata {
+                    record.modified = disk.modified;
+                }
+                else {
+                    summary.conflicts.push(Conflict::from_disk(disk));
+                    continue;
+                }
+            }
+
+            match self.normalize_on_save {
+                NormalizeOnSave::Off => {},
+                NormalizeOnSave::Values => { crate::transform::stage_nfc(record, false); },
+                NormalizeOnSave::ValuesAndKeys => { crate::transform::stage_nfc(record, true); },
+            };
+
+            crate::transform::stage_key_case(record, self.key_case);
+
+            queued.push(record.clone());
+        }
+
+        self.writing.extend(queued.iter().map(|r| r.file_path.clone()));
+
+        self.dirty = true;
+        (queued, summary)
+    }
+
+    /// Records the result of writing a record returned by `queue_writes`.
+    /// Only the edits that were written are committed, so edits made to the
+    /// record in the meantime stay pending.
+    pub fn finish_write(&mut self, written: &Record, result: Result<(), MetaflacError>) -> Result<(), MetaflacError> {
+        self.writing.remove(&written.file_path);
+        self.dirty = true;
+
+        if let Err(err) = result {
+            log::warn!("unable to save {}: {}", written.file_path.display(), err);
+            self.write_failures.insert(written.file_path.clone());
+            return Err(err);
+        }
+
+        self.write_failures.remove(&written.file_path);
+
+        log::debug!("saved {}", written.file_path.display());
+
+        // The record may have been renamed or dropped while being written.
+        let modified = self.source.modified(&written.file_path);
+
+        if let Some(record) = self.data.records.iter_mut().find(|r| r.file_path == written.file_path) {
+            record.commit_written(&written.edits);
+            record.modified = modified;
+        }
+
+        Ok(())
+    }
+
+    /// Whether any files are being written by a background save.
+    pub fn is_writing(&self) -> bool {
+        !self.writing.is_empty()
+    }
+
+    /// Whether the file of a record is being written by a background save.
+    pub fn is_record_writing(&self, record_index: usize) -> bool {
+        self.data.records.get(record_index).is_some_and(|r| self.writing.contains(&r.file_path))
+    }
+
+    /// Whether writing the file of a record failed when last saved.
+    pub fn failed_to_write(&self, record_index: usize) -> bool {
+        self.data.records.get(record_index).is_some_and(|r| self.write_failures.contains(&r.file_path))
+    }
+
+    /// Another handle to the files of the records, for writing them from a
+    /// background thread.
+    pub fn writer(&self) -> Box<dyn RecordSource> {
+        self.source.share()
+    }
+
+    /// Re-reads all records from the source, see `refresh::refresh`.
+    pub fn refresh(&mut self) -> Result<RefreshSummary, IoError> {
+        let mut file_paths = self.source.list_files()?;
+
+        // Files that were moved into subdirectories by renaming are not
+        // listed, but stay loaded for as long as they are there and are not
+        // ignored.
+        let listed = file_paths.iter().cloned().collect::<HashSet<_>>();
+
+        file_paths.extend(
+            self.data.records.iter()
+            .map(|r| &r.file_path)
+            .filter(|p| !listed.contains(*p) && self.source.exists(p) && !self.source.is_ignored(p))
+            .cloned()
+        );
+
+        let source = &self.source;
+        let summary = crate::refresh::refresh(&mut self.data.records, &file_paths, &self.writing, |file_path| source.read_record(file_path));
+        log::info!("refreshed: {}", summary);
+
+        // Added files are put in their place among the others.
+        if let Some(sort) = self.sort {
+            self.sort_by_column_index(sort.column, sort.descending);
+        }
+
+        let data = &self.data;
+        self.selection.retain(|file_path| data.records.iter().any(|r| &r.file_path == file_path));
+        self.cursor.clamp(self.data.columns.len(), self.data.records.len());
+
+        self.dirty = true;
+        Ok(summary)
+    }
+
+    /// Numbers the target records from one in their current order, starting
+    /// over for each album, or each value of the grouping key when grouping,
+    /// and for each disc of a multi-disc album. Returns how many records
+    /// changed out of how many were targeted.
+    pub fn number_tracks(&mut self, numbering: Numbering) -> (usize, usize) {
+        let meta_key = self.group_by.clone().unwrap_or_else(|| String::from(GROUP_BY_KEY));
+        let indices = self.target_record_indices();
+
+        // Each disc of a group is numbered on its own.
+        let mut groups = Vec::<(Option<&[String]>, Vec<(Option<u32>, Vec<usize>)>)>::new();
+
+        for &i in &indices {
+            let value = self.data.records[i].get_meta(&meta_key);
+            let disc = if meta_key == DISC_KEY { None } else { disc_number(&self.data.records[i]) };
+
+            let discs = match groups.iter().position(|(v, _)| *v == value) {
+                Some(g) => &mut groups[g].1,
+                None => {
+                    groups.push((value, Vec::new()));
+                    &mut groups.last_mut().unwrap().1
+                },
+            };
+
+            match discs.iter_mut().find(|(d, _)| *d == disc) {
+                Some((_, disc_group)) => disc_group.push(i),
+                None => discs.push((disc, vec![i])),
+            }
+        }
+
+        let pad = |n: usize, width: usize| format!("{:0width$}", n, width = width);
+
+        let numbers =
+            groups.into_iter()
+            .flat_map(|(_, discs)| {
+                let disc_total = discs.len().to_string();
+
+                discs.into_iter()
+                .flat_map(move |(_, disc_group)| {
+                    let total = disc_group.len();
+                    let width = if numbering.zero_pad { total.to_string().len().max(2) } else { 1 };
+                    let disc_total = disc_total.clone();
+
+                    disc_group.into_iter()
+                    .enumerate()
+                    .map(move |(n, i)| (i, pad(n + 1, width), pad(total, width), disc_total.clone()))
+                })
+            })
+            .collect::<Vec<_>>()
+        ;
+
+        let edited = self.edit_records("Number tracks", |records| {
+            numbers.into_iter()
+            .filter(|(i, number, total, disc_total)| {
+                let changed = records[*i].set_meta("TRACKNUMBER", Some(vec![number.clone()]));
+                let total_changed = numbering.fill_total && records[*i].set_meta("TRACKTOTAL", Some(vec![total.clone()]));
+                let disc_total_changed = numbering.fill_disc_total && records[*i].set_meta("TOTALDISCS", Some(vec![disc_total.clone()]));
+                changed | total_changed | disc_total_changed
+            })
+            .count()
+        });
+
+        (edited, indices.len())
+    }
+
+    /// Applies cleanups to every tag of the target records as one edit.
+    /// Returns how many records changed out of how many were targeted.
+    pub fn clean_tags(&mut self, cleanups: &[Cleanup]) -> (usize, usize) {
+        let indices = self.target_record_indices();
+
+        let edited = self.edit_records("Clean tags", |records| {
+            indices.iter()
+            .filter(|&&i| {
+                let record = &mut records[i];
+                let meta_keys = record.meta_keys().map(String::from).collect::<Vec<_>>();
+
+                meta_keys.into_iter()
+                .filter(|meta_key| {
+                    let cleaned = crate::transform::clean(record.get_meta(meta_key).unwrap_or_default(), cleanups);
+                    record.set_meta(meta_key, Some(cleaned))
+                })
+                .count() > 0
+            })
+            .count()
+        });
+
+        (edited, indices.len())
+    }
+
+    /// Removes every tag of the target records as one edit, except for the
+    /// fields in `strip_keep`, whatever their case. Returns how many records
+    /// changed out of how many were targeted.
+    pub fn strip_tags(&mut self) -> (usize, usize) {
+        let indices = self.target_record_indices();
+        let strip_keep = self.strip_keep.clone();
+
+        let edited = self.edit_records("Strip tags", |records| {
+            indices.iter()
+            .filter(|&&i| {
+                let record = &mut records[i];
+                let meta_keys = record.meta_keys().map(String::from).collect::<Vec<_>>();
+
+                meta_keys.into_iter()
+                .filter(|meta_key| !strip_keep.iter().any(|k| k.eq_ignore_ascii_case(meta_key)))
+                .filter(|meta_key| record.set_meta(meta_key, None))
+                .count() > 0
+            })
+            .count()
+        });
+
+        (edited, indices.len())
+    }
+
+    /// Works out how the files of the target records would be renamed.
+    pub fn plan_renames(&self, template: &Template) -> Vec<Rename> {
+        let records = self.target_record_indices().into_iter().map(|i| &self.data.records[i]);
+
+        crate::rename::plan(records, template, |path| self.source.exists(path))
+    }
+
+    /// Renames files, and points their records, the selection, and the undo
+    /// history at the new paths. Files whose new name is the old name of
+    /// another file are moved out of the way first. Returns the files that
+    /// could not be renamed.
+    pub fn rename_files(&mut self, renames: &[Rename]) -> Vec<(PathBuf, IoError)> {
+        let sources = renames.iter().map(|r| &r.from).collect::<HashSet<_>>();
+        let temp_path = |path: &Path| {
+            let file_name = path.file_name().unwrap_or_default().to_string_lossy();
+            path.with_file_name(format!(".{}.renaming", file_name))
+        };
+
+        let mut failures = Vec::new();
+        let mut staged = Vec::new();
+
+        for rename in renames {
+            if !sources.contains(&rename.to) {
+                staged.push((rename, rename.from.clone()));
+                continue;
+            }
+
+            let temp = temp_path(&rename.from);
+
+            match self.source.rename_file(&rename.from, &temp) {
+                Ok(()) => staged.push((rename, temp)),
+                Err(err) => failures.push((rename.from.clone(), err)),
+            }
+        }
+
+        let mut renamed = HashMap::new();
+
+        for (rename, current) in staged {
+            match self.source.rename_file(&current, &rename.to) {
+                Ok(()) => { renamed.insert(rename.from.clone(), rename.to.clone()); },
+                Err(err) => {
+                    if current != rename.from {
+                        self.source.rename_file(&current, &rename.from).ok();
+                    }
+
+                    failures.push((rename.from.clone(), err));
+                },
+            }
+        }
+
+        for record in self.data.records.iter_mut() {
+            if let Some(to) = renamed.get(&record.file_path) {
+                record.file_path = to.clone();
+            }
+        }
+
+        self.selection = self.selection.drain().map(|p| renamed.get(&p).cloned().unwrap_or(p)).collect();
+        self.history.rename_paths(&renamed);
+
+        self.dirty = true;
+        failures
+    }
+
+    /// Moves files into a directory, keeping their names, and points their
+    /// records at the new paths as `rename_files` does. Files being written
+    /// are left alone. Returns the files that could not be moved.
+    pub fn move_files(&mut self, file_paths: &[PathBuf], dest_dir: &Path) -> Vec<(PathBuf, IoError)> {
+        let mut failures = Vec::new();
+        let mut renames = Vec::new();
+
+        for file_path in file_paths {
+            if self.writing.contains(file_path) {
+                failures.push((file_path.clone(), IoError::other("file is being written")));
+                continue;
+            }
+
+            let to = dest_dir.join(file_path.file_name().unwrap_or_default());
+
+            if &to != file_path {
+                renames.push(Rename { from: file_path.clone(), to, collided: false });
+            }
+        }
+
+        failures.extend(self.rename_files(&renames));
+        failures
+    }
+
+    /// Copies files into a directory, keeping their names. Pending edits are
+    /// not part of the copies. Returns the files that could not be copied.
+    pub fn copy_files(&mut self, file_paths: &[PathBuf], dest_dir: &Path) -> Vec<(PathBuf, IoError)> {
+        file_paths.iter()
+        .filter_map(|file_path| {
+            let to = dest_dir.join(file_path.file_name().unwrap_or_default());
+            self.source.copy_file(file_path, &to).err().map(|err| (file_path.clone(), err))
+        })
+        .collect()
+    }
+
+    /// Moves files to the trash and drops their records, along with any
+    /// pending edits. Files being written are left alone. Returns the files
+    /// that could not be deleted.
+    pub fn delete_files(&mut self, file_paths: &[PathBuf]) -> Vec<(PathBuf, IoError)> {
+        let mut failures = Vec::new();
+        let mut deleted = HashSet::new();
+
+        for file_path in file_paths {
+            if self.writing.contains(file_path) {
+                failures.push((file_path.clone(), IoError::other("file is being written")));
+                continue;
+            }
+
+            match self.source.delete_file(file_path) {
+                Ok(()) => { deleted.insert(file_path); },
+                Err(err) => {
+                    log::warn!("unable to delete {}: {}", file_path.display(), err);
+                    failures.push((file_path.clone(), err));
+                },
+            }
+        }
+
+        self.data.records.retain(|r| !deleted.contains(&r.file_path));
+        self.selection.retain(|file_path| !deleted.contains(file_path));
+        self.write_failures.retain(|file_path| !deleted.contains(file_path));
+        self.cursor.clamp(self.data.columns.len(), self.data.records.len());
+
+        self.dirty = true;
+        failures
+    }
+
+    /// The tags each target record would get from its file name, by index.
+    /// Records whose names do not match are given as `None`.
+    pub fn plan_tags_from_names(&self, template: &Template) -> Vec<(usize, Option<Inferred>)> {
+        self.target_record_indices().into_iter()
+        .map(|i| (i, crate::rename::infer_tags(&self.data.records[i], template)))
+        .collect()
+    }
+
+    /// Settles a refresh conflict on the record it came from, if still loaded.
+    pub fn resolve_conflict(&mut self, conflict: Conflict, resolution: &Resolution) {
+        self.mutate_records(|records| {
+            if let Some(record) = records.iter_mut().find(|r| r.file_path == conflict.file_path) {
+                conflict.resolve(record, resolution);
+            }
+        })
+    }
+
+    /// The width of the contents of each column, as of the last recache.
+    pub fn iter_cached_widths<'a>(&'a self) -> impl Iterator<Item = usize> + 'a {
+        self.cached_content_widths.iter().copied()
+    }
+}
+
+/// The narrowest a column sized by its contents is shrunk to when fitting
+/// the view, unless it has a minimum width of its own.
+const MIN_FIT_WIDTH: usize = 4;
+
+/// Whether a key can be used in a Vorbis comment: printable ASCII, without
+/// an `=` or a `~`.
+fn is_valid_meta_key(meta_key: &str) -> bool {
+    !meta_key.is_empty() && meta_key.chars().all(|c| (' '..='}').contains(&c) && c != '=')
+}
+
+/// How many cells the columns have to share in a view, after separators.
+fn available_width(num_columns: usize, viewport_width: usize) -> usize {
+    viewport_width.saturating_sub(COLUMN_SEP.width() * num_columns.saturating_sub(1))
+}
+
+/// Cuts a total into shares by weight. Shares are cut off cumulatively, so
+/// that rounding never loses a cell.
+fn share_out(total: usize, weights: &[usize]) -> Vec<usize> {
+    let total_weight = weights.iter().sum::<usize>().max(1);
+    let mut weight_so_far = 0;
+
+    weights.iter().map(|weight| {
+        let start = total * weight_so_far / total_weight;
+        weight_so_far += weight;
+        total * weight_so_far / total_weight - start
+    })
+    .collect()
+}
+
+/// Sizes columns with a percentage of the view width. Every such column gets
+/// at least one cell, so that it does not vanish in a narrow view.
+fn fill_percent_widths(sizings: &[Sizing], widths: &mut [usize], viewport_width: usize) {
+    let available = available_width(sizings.len(), viewport_width);
+
+    for (sizing, width) in sizings.iter().zip(widths.iter_mut()) {
+        if let Sizing::Percent(percent) = sizing {
+            *width = (available * *percent as usize / 100).max(1);
+        }
+    }
+}
+
+/// Shrinks the columns sized by their contents when the columns would not
+/// all fit in the view, each in proportion to how far it can shrink. Fixed
+/// widths and minimum widths are kept, and each weighted column is left one
+/// cell.
+fn fit_widths(sizings: &[Sizing], widths: &mut [usize], viewport_width: usize) {
+    let available = available_width(sizings.len(), viewport_width);
+
+    let num_weighted = sizings.iter().filter(|s| matches!(s, Sizing::Weight(_))).count();
+    let used = widths.iter().sum::<usize>() + num_weighted;
+
+    let excess = match used.checked_sub(available) {
+        Some(excess) if excess > 0 => excess,
+        _ => return,
+    };
+
+    let slacks =
+        sizings.iter().zip(widths.iter())
+        .map(|(sizing, &width)| {
+            let min_width = match sizing {
+                Sizing::Auto | Sizing::Upper(_) | Sizing::Visible => width.min(MIN_FIT_WIDTH),
+                Sizing::Lower(min_width) | Sizing::Bound(min_width, _) => *min_width,
+                Sizing::Fixed(_) | Sizing::Percent(_) | Sizing::Weight(_) => width,
+            };
+
+            width.saturating_sub(min_width)
+        })
+        .collect::<Vec<_>>()
+    ;
+
+    let total_slack = slacks.iter().sum::<usize>();
+
+    for (width, cut) in widths.iter_mut().zip(share_out(excess.min(total_slack), &slacks)) {
+        *width -= cut;
+    }
+}
+
+/// Shares what the other columns leave of the view among the columns with a
+/// weight. Every such column gets at least one cell.
+fn fill_weighted_widths(sizings: &[Sizing], widths: &mut [usize], viewport_width: usize) {
+    let available = available_width(sizings.len(), viewport_width);
+
+    let weights = sizings.iter().map(|s| match s { Sizing::Weight(w) => *w as usize, _ => 0 }).collect::<Vec<_>>();
+
+    if weights.iter().all(|&w| w == 0) {
+        return;
+    }
+
+    let used = sizings.iter().zip(widths.iter()).filter(|(s, _)| !matches!(s, Sizing::Weight(_))).map(|(_, w)| w).sum::<usize>();
+
+    for ((sizing, width), share) in sizings.iter().zip(widths.iter_mut()).zip(share_out(available.saturating_sub(used), &weights)) {
+        if let Sizing::Weight(_) = sizing {
+            *width = share.max(1);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    use crate::config::Config;
+    use crate::data::Metadata;
+    use crate::fixtures::MemorySource;
+
+    fn demo_model() -> (Model, MemorySource) {
+        let source = MemorySource::demo();
+        let model = Model::load(Config::default().columns, Box::new(source.clone())).unwrap();
+        (model, source)
+    }
+
+    #[test]
+    fn proportional_widths() {
+        // A view of 46 cells has 40 left after the two separators.
+        let sizings = [Sizing::Percent(25), Sizing::Weight(1), Sizing::Weight(2)];
+        let mut widths = vec![0; 3];
+        fill_percent_widths(&sizings, &mut widths, 46);
+        fill_weighted_widths(&sizings, &mut widths, 46);
+        assert_eq!(widths, vec![10, 10, 20]);
+
+        // Weights share what the other columns leave, without losing a cell.
+        let sizings = [Sizing::Fixed(9), Sizing::Weight(1), Sizing::Weight(1)];
+        let mut widths = vec![9, 0, 0];
+        fill_weighted_widths(&sizings, &mut widths, 20);
+        assert_eq!(widths, vec![9, 2, 3]);
+
+        // Nothing vanishes in a view too narrow for everything.
+        let mut widths = vec![9, 0, 0];
+        fill_weighted_widths(&sizings, &mut widths, 5);
+        assert_eq!(widths, vec![9, 1, 1]);
+    }
+
+    #[test]
+    fn visible_sizing() {
+        let (mut model, _) = demo_model();
+        model.viewport_height = 2;
+        model.fit_width = false;
+        model.mutate_columns(|columns| { columns[1].sizing = Sizing::Visible; });
+
+        let last = model.data.records.len() - 1;
+        model.mutate_records(|records| {
+            records[0].set_meta("TITLE", Some(vec![str!("Short")]));
+            records[1].set_meta("TITLE", Some(vec![str!("Shorter")]));
+            records[last].set_meta("TITLE", Some(vec![str!("A much, much longer title")]));
+        });
+
+        model.recache();
+        assert_eq!(model.cached_content_widths[1], 7);
+
+        model.set_scroll_top(last - 1);
+        model.recache();
+        assert_eq!(model.cached_content_widths[1], 25);
+
+        // Narrowing by a lot narrows right away, but not by a little.
+        model.set_scroll_top(0);
+        model.recache();
+        assert_eq!(model.cached_content_widths[1], 7);
+
+        model.mutate_records(|records| { records[1].set_meta("TITLE", Some(vec![str!("Tiny")])); });
+        model.recache();
+        assert_eq!(model.cached_content_widths[1], 7);
+    }
+
+    #[test]
+    fn scroll_position() {
+        let (mut model, _) = demo_model();
+        let num_records = model.data.records.len();
+        model.viewport_height = 3;
+        model.recache();
+
+        assert_eq!(model.scroll_position().unwrap(), format!("rows 1–3 of {} (0%)", num_records));
+
+        model.set_scroll_top(num_records - 3);
+        assert_eq!(model.scroll_position().unwrap(), format!("rows {}–{} of {} (100%)", num_records - 2, num_records, num_records));
+    }
+
+    #[test]
+    fn sort_info_numerically() {
+        let (mut model, _) = demo_model();
+
+        model.mutate_columns(|columns| {
+            columns[0].key = ColumnKey::Info(InfoKind::FileSize);
+            columns[0].title = str!("Size");
+        });
+
+        // As text, "9.8 KiB" would sort before "900 B".
+        for (record, size) in model.data.records.iter_mut().zip([10_000, 900, 5_000_000].iter().cycle()) {
+            record.file_size = Some(*size);
+        }
+
+        model.sort_by_column_index(0, false);
+
+        let shown = model.data.records.iter().map(|r| r.get_info(&InfoKind::FileSize).unwrap().into_owned()).collect::<Vec<_>>();
+        assert_eq!(shown.first().unwrap(), "900 B");
+        assert_eq!(shown.last().unwrap(), "4.8 MiB");
+        assert!(shown.contains(&str!("9.8 KiB")));
+
+        model.data.records[0].modified = Some(std::time::UNIX_EPOCH + std::time::Duration::from_secs(86_400 + 3_660));
+        assert_eq!(model.data.records[0].get_info(&InfoKind::Modified).unwrap(), "1970-01-02 01:01");
+    }
+
+    #[test]
+    fn column_info() {
+        let (mut model, _) = demo_model();
+        model.sort_by_column_index(0, true);
+        model.data.records[0].set_meta("ARTIST", None);
+
+        // Moving up from the first row highlights the whole column.
+        model.move_cursor_up(1);
+
+        let artists = model.data.records.iter().filter_map(|r| r.get_meta("ARTIST")).collect::<HashSet<_>>();
+        assert_eq!(
+            model.current_value_line().unwrap(),
+            format!("Artist: Meta(\"ARTIST\"), sized Auto, sorted descending, {} distinct value(s), 1 missing", artists.len()),
+        );
+    }
+
+    #[test]
+    fn resize_column() {
+        let (mut model, _) = demo_model();
+        model.fit_width = false;
+        model.recache();
+
+        model.resize_column(1, 3);
+        model.recache();
+        assert_eq!(model.cached_content_widths[1], 3);
+
+        // Resized widths outlast reloading the config.
+        model.apply_config(&Config::default());
+        model.recache();
+        assert_eq!(model.cached_content_widths[1], 3);
+        assert!(matches!(model.column_sizing(&model.data.columns[1]), Sizing::Fixed(3)));
+    }
+
+    #[test]
+    fn fit_to_width() {
+        // A view of 36 cells has 30 left after the two separators, 20 short.
+        let sizings = [Sizing::Auto, Sizing::Lower(12), Sizing::Fixed(10)];
+        let mut widths = vec![24, 16, 10];
+        fit_widths(&sizings, &mut widths, 36);
+        assert_eq!(widths, vec![8, 12, 10]);
+
+        // What can shrink shrinks in proportion.
+        let mut widths = vec![24, 16, 10];
+        fit_widths(&sizings, &mut widths, 51);
+        assert_eq!(widths, vec![20, 15, 10]);
+
+        // Minimum widths are kept even if that overflows.
+        let mut widths = vec![24, 16, 10];
+        fit_widths(&sizings, &mut widths, 20);
+        assert_eq!(widths, vec![4, 12, 10]);
+
+        // Columns that already fit are left alone.
+        let mut widths = vec![10, 12, 10];
+        fit_widths(&sizings, &mut widths, 80);
+        assert_eq!(widths, vec![10, 12, 10]);
+    }
+
+    #[test]
+    fn sort_and_group() {
+        let (mut model, _) = demo_model();
+
+        // Sort by title, descending.
+        model.sort_by_column_index(1, true);
+        model.recache();
+
+        assert_eq!(model.data.records[0].get_meta("TITLE"), Some(&[str!("Song 4 of Second Album")][..]));
+
+        model.toggle_grouping("ALBUM");
+        model.recache();
+
+        assert_eq!(model.cached_groups.len(), 2);
+        assert_eq!(model.cached_display_rows.len(), 10);
+        assert_eq!(model.cached_display_rows[1], DisplayRow::Record(0));
+        assert_eq!(model.data.records[0].get_meta("TITLE"), Some(&[str!("Song 4 of Album One")][..]));
+        assert_eq!(model.record_display_row(4), 6);
+    }
+
+    #[test]
+    fn collapse_groups() {
+        let (mut model, _) = demo_model();
+
+        assert!(!model.toggle_collapse());
+
+        model.toggle_grouping("ALBUM");
+        model.move_cursor_down(2);
+
+        // The cursor goes to the first record of the collapsed group.
+        assert!(model.toggle_collapse());
+        model.recache();
+
+        assert_eq!(model.cursor, Cursor::Cell(0, 0));
+        assert_eq!(model.cached_display_rows.len(), 6);
+        assert_eq!(model.cached_display_rows[1], DisplayRow::Header(1));
+        assert_eq!(model.record_display_row(3), 0);
+        assert_eq!(model.record_display_row(5), 3);
+
+        // Moving down skips over the hidden records.
+        model.move_cursor_down(1);
+        assert_eq!(model.cursor, Cursor::Cell(0, 4));
+        model.move_cursor_up(1);
+        assert_eq!(model.cursor, Cursor::Cell(0, 0));
+
+        // With any group collapsed, all of them are expanded first.
+        model.toggle_collapse_all();
+        model.recache();
+        assert_eq!(model.cached_display_rows.len(), 10);
+
+        model.move_cursor_down(5);
+        model.toggle_collapse_all();
+        model.recache();
+
+        assert_eq!(model.cursor, Cursor::Cell(0, 4));
+        assert_eq!(model.cached_display_rows, vec![DisplayRow::Header(0), DisplayRow::Header(1)]);
+        assert_eq!(model.record_display_row(6), 1);
+
+        // Grouping by another column starts over with every group expanded.
+        model.toggle_collapse();
+        assert_eq!(model.group_by_column().as_deref(), Some("ARTIST"));
+        assert!(model.collapsed_groups.is_empty());
+        assert_eq!(model.group_by_column(), None);
+        assert_eq!(model.group_by, None);
+    }
+
+    #[test]
+    fn background_writes() {
+        let (mut model, source) = demo_model();
+        let file_path = model.data.records[0].file_path.clone();
+
+        model.mutate_records(|records| {
+            records[0].set_meta("GENRE", Some(vec![str!("Rock")]));
+            records[0].set_meta("MOOD", Some(vec![str!("Calm")]));
+        });
+
+        let (records, summary) = model.queue_writes();
+        assert_eq!(records.len(), 1);
+        assert!(summary.failures.is_empty() && summary.conflicts.is_empty());
+        assert!(model.is_record_writing(0));
+
+        // Files being written are not queued twice, nor re-read.
+        assert!(model.queue_writes().0.is_empty());
+        assert!(model.refresh().unwrap().conflicts.is_empty());
+
+        // Edits made while writing stay pending, including undoing one.
+        model.data.records[0].set_meta("TITLE", Some(vec![str!("Later")]));
+        model.data.records[0].set_meta("MOOD", None);
+
+        let mut writer = model.writer();
+        let result = writer.write_record(&records[0]);
+        model.finish_write(&records[0], result).unwrap();
+
+        assert!(!model.is_writing());
+        assert_eq!(source.get_file(&file_path).unwrap()["GENRE"], vec![str!("Rock")]);
+
+        let record = &model.data.records[0];
+        assert_eq!(record.get_meta("GENRE"), Some(&[str!("Rock")][..]));
+        assert_eq!(record.get_meta("TITLE"), Some(&[str!("Later")][..]));
+        assert_eq!(record.get_meta("MOOD"), None);
+        assert!(!record.edits.contains_key("GENRE"));
+        assert_eq!(record.edits["MOOD"], None);
+    }
+
+    #[test]
+    fn queue_file_write() {
+        let (mut model, _) = demo_model();
+        let file_path = model.data.records[1].file_path.clone();
+
+        model.mutate_records(|records| {
+            records[0].set_meta("GENRE", Some(vec![str!("Rock")]));
+            records[1].set_meta("GENRE", Some(vec![str!("Pop")]));
+        });
+
+        let (records, _) = model.queue_file_write(&file_path);
+        assert_eq!(records.len(), 1);
+        assert_eq!(records[0].file_path, file_path);
+        assert!(!model.is_record_writing(0));
+        assert!(model.is_record_writing(1));
+
+        // Unedited files are not queued.
+        let file_path = model.data.records[2].file_path.clone();
+        assert!(model.queue_file_write(&file_path).0.is_empty());
+    }
+
+    #[test]
+    fn remove_field() {
+        let (mut model, _) = demo_model();
+
+        model.cursor = Cursor::Cell(1, 0);
+        model.scope = Scope::All;
+        assert_eq!(model.set_target_values("Set", Some(vec![str!("")])), Some((8, 8)));
+        assert_eq!(model.current_cell_values(), Some(Some(vec![str!("")])));
+
+        // Removing the field is not the same as leaving it empty.
+        assert_eq!(model.set_target_values("Remove field", None), Some((8, 8)));
+        assert_eq!(model.current_cell_values(), Some(None));
+        assert!(model.data.records.iter().all(|r| r.get_meta("TITLE").is_none()));
+    }
+
+    #[test]
+    fn add_field() {
+        let (mut model, _) = demo_model();
+        let num_columns = model.data.columns.len();
+
+        model.scope = Scope::All;
+        assert_eq!(model.add_field("MOOD=calm", vec![str!("Calm")]), None);
+        assert_eq!(model.add_field("Mood", vec![str!("Calm")]), Some((8, 8)));
+
+        assert_eq!(model.data.columns.len(), num_columns + 1);
+        assert_eq!(model.data.columns[num_columns].title, "Mood");
+        assert_eq!(model.current_column().unwrap().key, ColumnKey::Meta(str!("MOOD")));
+        assert_eq!(model.current_cell_values(), Some(Some(vec![str!("Calm")])));
+
+        // A field that already has a column doesn't get another one.
+        assert_eq!(model.add_field("mood", vec![str!("Calm")]), Some((0, 8)));
+        assert_eq!(model.data.columns.len(), num_columns + 1);
+    }
+
+    #[test]
+    fn rename_key() {
+        let (mut model, _) = demo_model();
+        let num_columns = model.data.columns.len();
+
+        model.data.columns.push(Column {
+            key: ColumnKey::Meta(str!("YEAR")),
+            title: str!("Year"),
+            sizing: Sizing::Auto,
+            align: Default::default(),
+            kind: None,
+            separator: None,
+        });
+
+        model.data.records[0].set_meta("Year", Some(vec![str!("1999")]));
+        model.data.records[1].set_meta("YEAR", Some(vec![str!("2001")]));
+        model.data.records[1].set_meta("ORIGINALYEAR", Some(vec![str!("1998"), str!("2001")]));
+        model.data.records[2].set_meta("ORIGINALYEAR", Some(vec![str!("2002")]));
+
+        assert_eq!(model.rename_key_counts("YEAR", "originalyear"), (2, 1));
+        assert_eq!(model.rename_key("YEAR", "ORIGINAL=YEAR"), None);
+        assert_eq!(model.rename_key("YEAR", "originalyear"), Some(2));
+
+        let values = |i: usize| model.data.records[i].get_meta("ORIGINALYEAR").unwrap().to_vec();
+        assert_eq!(values(0), vec![str!("1999")]);
+        assert_eq!(values(1), vec![str!("1998"), str!("2001")]);
+        assert_eq!(values(2), vec![str!("2002")]);
+        assert_eq!(model.data.records[0].get_meta("Year"), None);
+        assert_eq!(model.rename_key_counts("YEAR", "ORIGINALYEAR"), (0, 0));
+
+        // The column of the old field now shows the new one.
+        assert_eq!(model.data.columns.len(), num_columns + 1);
+        assert_eq!(model.data.columns[num_columns].key, ColumnKey::Meta(str!("ORIGINALYEAR")));
+        assert_eq!(model.data.columns[num_columns].title, "originalyear");
+
+        // A field that already has a column leaves the other columns alone.
+        assert_eq!(model.rename_key("TITLE", "ORIGINALYEAR"), Some(8));
+        assert!(model.data.columns.iter().any(|col| col.key == ColumnKey::Meta(str!("TITLE"))));
+    }
+
+    #[test]
+    fn apply_tag_preset() {
+        use maplit::btreemap;
+
+        let (mut model, _) = demo_model();
+        let num_columns = model.data.columns.len();
+        let composer = Column {
+            key: ColumnKey::Meta(str!("COMPOSER")),
+            title: str!("Composer"),
+            sizing: Sizing::Auto,
+            align: Default::default(),
+            kind: None,
+            separator: None,
+        };
+
+        model.tag_presets.insert(str!("classical"), TagPreset {
+            tags: btreemap! { str!("GENRE") => vec![str!("Classical")], str!("Artist") => vec![] },
+            columns: vec![composer, model.data.columns[0].clone()],
+        });
+        model.scope = Scope::All;
+
+        assert!(model.apply_tag_preset("jazz").is_err());
+        assert_eq!(model.apply_tag_preset("classical"), Ok((8, 8)));
+        assert_eq!(model.data.columns.len(), num_columns + 1);
+        assert!(model.data.records.iter().all(|r| r.get_meta("GENRE") == Some(&[str!("Classical")][..])));
+        assert!(model.data.records.iter().all(|r| r.get_meta("ARTIST").is_none()));
+
+        assert_eq!(model.apply_tag_preset("classical"), Ok((0, 8)));
+        assert_eq!(model.data.columns.len(), num_columns + 1);
+
+        // Columns are only added by a preset that changed something.
+        let mut genre = model.data.columns[num_columns].clone();
+        genre.key = ColumnKey::Meta(str!("GENRE"));
+        model.tag_presets.insert(str!("same"), TagPreset {
+            tags: btreemap! { str!("GENRE") => vec![str!("Classical")] },
+            columns: vec![genre],
+        });
+
+        assert_eq!(model.apply_tag_preset("same"), Ok((0, 8)));
+        assert_eq!(model.data.columns.len(), num_columns + 1);
+
+        // Undoing takes back the tags, but not the columns.
+        model.undo();
+        model.undo();
+        assert!(model.data.records.iter().all(|r| r.get_meta("GENRE").is_none()));
+        assert_eq!(model.data.columns.len(), num_columns + 1);
+    }
+
+    #[test]
+    fn fill_album_fields() {
+        let (mut model, _) = demo_model();
+
+        // The third track of each demo album has no date.
+        model.cursor = Cursor::Cell(0, 0);
+        let album = model.current_album_indices();
+        let undated = album.iter().copied()
+            .find(|&i| model.data.records[i].get_meta("DATE").is_none())
+            .unwrap();
+        let dated = album.iter().copied().find(|&i| i != undated).unwrap();
+
+        // A key spelled in another case still counts as the same field.
+        let date = model.data.records[dated].get_meta("DATE").unwrap().to_vec();
+        model.data.records[dated].set_meta("DATE", None);
+        model.data.records[dated].set_meta("Date", Some(date.clone()));
+
+        let fills = model.plan_album_fills();
+        assert_eq!(fills.len(), 1);
+        assert_eq!(fills[0].meta_key, "DATE");
+        assert_eq!(fills[0].file_paths, vec![model.data.records[undated].file_path.clone()]);
+
+        assert_eq!(model.fill_album_fields(&fills), 1);
+        assert_eq!(model.data.records[undated].get_meta("DATE"), Some(&date[..]));
+        assert_eq!(model.data.records[dated].get_meta("DATE"), None);
+        assert!(model.plan_album_fills().is_empty());
+    }
+
+    #[test]
+    fn read_only_files() {
+        let source = MemorySource::demo();
+        let file_path = source.list_files().unwrap()[0].clone();
+        source.set_read_only(&file_path, true);
+
+        let mut model = Model::load(Config::default().columns, Box::new(source.clone())).unwrap();
+        let i = model.data.records.iter().position(|r| r.file_path == file_path).unwrap();
+        assert!(model.data.records[i].read_only);
+
+        model.cursor = Cursor::Cell(1, i);
+        assert_eq!(model.read_only_targets_notice().unwrap(), format!("{} is read-only", file_path.display()));
+
+        // Edits that reach other records too skip the read-only one.
+        model.scope = Scope::All;
+        assert_eq!(model.read_only_targets_notice(), None);
+        assert_eq!(model.set_target_values("Set", Some(vec![str!("New")])), Some((7, 8)));
+        assert!(!model.data.records[i].is_edited());
+
+        // Files made writable are picked up on refresh.
+        source.set_read_only(&file_path, false);
+        model.refresh().unwrap();
+        assert!(!model.data.records[i].read_only);
+    }
+
+    #[test]
+    fn write_failures() {
+        let (mut model, source) = demo_model();
+        let file_path = model.data.records[0].file_path.clone();
+        let metadata = source.get_file(&file_path).unwrap();
+
+        model.data.records[0].set_meta("GENRE", Some(vec![str!("Rock")]));
+        source.remove_file(&file_path);
+
+        let summary = model.save_all();
+        assert_eq!(summary.failures.len(), 1);
+        assert!(model.failed_to_write(0));
+
+        // Saving again once the file is back clears the failure.
+        source.set_file(&file_path, metadata);
+        model.data.records[0].modified = source.modified(&file_path);
+
+        assert_eq!(model.save_all().saved, 1);
+        assert!(!model.failed_to_write(0));
+    }
+
+    #[test]
+    fn edit_save_and_refresh() {
+        let (mut model, source) = demo_model();
+
+        model.move_cursor_down(1);
+        model.toggle_selection();
+        model.move_cursor_down(1);
+        model.toggle_selection();
+
+        let indices = model.target_record_indices();
+        assert_eq!(indices, vec![1, 2]);
+
+        model.mutate_records(|records| {
+            for i in indices {
+                records[i].set_meta("GENRE", Some(vec![str!("Rock")]));
+            }
+        });
+
+        let summary = model.save_all();
+        assert_eq!(summary.saved, 2);
+        assert!(summary.failures.is_empty() && summary.conflicts.is_empty());
+        assert!(!model.data.records[1].is_edited());
+
+        let file_path = model.data.records[1].file_path.clone();
+        assert_eq!(source.get_file(&file_path).unwrap()["GENRE"], vec![str!("Rock")]);
+
+        // Simulate another program retagging a file that has pending edits.
+        model.data.records[1].set_meta("TITLE", Some(vec![str!("Mine")]));
+
+        let mut metadata = source.get_file(&file_path).unwrap();
+        metadata.insert(str!("TITLE"), vec![str!("Theirs")]);
+        source.set_file(&file_path, metadata);
+
+        let summary = model.refresh().unwrap();
+
+        assert_eq!(summary.updated, 0);
+        assert_eq!(summary.conflicts.len(), 1);
+
+        let conflict = summary.conflicts.into_iter().next().unwrap();
+        model.resolve_conflict(conflict, &Resolution::TakeDisk);
+
+        assert_eq!(model.data.records[1].get_meta("TITLE"), Some(&[str!("Theirs")][..]));
+        assert!(!model.data.records[1].is_edited());
+
+        // Files added and removed by another program show up on a re-scan.
+        let num_records = model.data.records.len();
+        let removed = model.data.records[0].file_path.clone();
+
+        source.remove_file(&removed);
+        source.set_file("new.flac", hashmap! { str!("TITLE") => vec![str!("New")] });
+
+        let summary = model.refresh().unwrap();
+
+        assert_eq!((summary.added, summary.removed), (1, 1));
+        assert_eq!(model.data.records.len(), num_records);
+        assert!(model.data.records.iter().all(|r| r.file_path != removed));
+        assert!(model.data.records.iter().any(|r| r.file_path == std::path::Path::new("new.flac")));
+    }
+
+    #[test]
+    fn normalize_on_save() {
+        let (mut model, source) = demo_model();
+
+        let file_path = model.data.records[0].file_path.clone();
+        model.data.records[0].set_meta("TITLE", Some(vec![str!("Cafe\u{301}")]));
+        model.data.records[0].set_meta("ARTIST", Some(vec![str!("Bjo\u{308}rk")]));
+        model.data.records[1].set_meta("TITLE", Some(vec![str!("Cafe\u{301}")]));
+
+        model.normalize_on_save = NormalizeOnSave::Values;
+        assert_eq!(model.save_all().saved, 2);
+
+        let metadata = source.get_file(&file_path).unwrap();
+        assert_eq!(metadata["TITLE"], vec![str!("Caf\u{e9}")]);
+        assert_eq!(metadata["ARTIST"], vec![str!("Bj\u{f6}rk")]);
+        assert!(!model.data.records[0].is_edited());
+    }
+
+    #[test]
+    fn preserve_mtime() {
+        let (mut model, source) = demo_model();
+        let file_path = model.data.records[0].file_path.clone();
+        let modified = source.modified(&file_path);
+
+        model.apply_config(&Config { preserve_mtime: true, ..Config::default() });
+        model.data.records[0].set_meta("TITLE", Some(vec![str!("New")]));
+        assert_eq!(model.save_all().saved, 1);
+
+        assert_eq!(source.get_file(&file_path).unwrap()["TITLE"], vec![str!("New")]);
+        assert_eq!(source.modified(&file_path), modified);
+        assert_eq!(model.data.records[0].modified, modified);
+    }
+
+    #[test]
+    fn pending_diff() {
+        let (mut model, _) = demo_model();
+        assert_eq!(model.pending_diff(), "");
+
+        let file_path = model.data.records[1].file_path.clone();
+        let old_title = model.data.records[1].get_meta("TITLE").unwrap()[0].clone();
+        let num_artists = model.data.records[1].get_meta("ARTIST").unwrap().len();
+        model.data.records[1].set_meta("TITLE", Some(vec![str!("New")]));
+        model.data.records[1].set_meta("GENRE", Some(vec![str!("Rock"), str!("Pop")]));
+        model.data.records[1].set_meta("ARTIST", None);
+
+        let diff = model.pending_diff();
+        let lines = diff.lines().collect::<Vec<_>>();
+
+        assert_eq!(lines[0], file_path.display().to_string());
+        assert!(lines[1..=num_artists].iter().all(|line| line.starts_with("- ARTIST=")));
+        assert_eq!(&lines[(num_artists + 1)..], [
+            "+ GENRE=Rock",
+            "+ GENRE=Pop",
+            &format!("- TITLE={}", old_title),
+            "+ TITLE=New",
+        ]);
+    }
+
+    #[test]
+    fn load_failures() {
+        let dir = std::env::temp_dir().join(format!("diargos-load-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+        std::fs::write(dir.join("01.flac"), "not really a FLAC").unwrap();
+
+        let model = Model::load(Config::default().columns, Box::new(crate::source::FlacDir::new(dir.clone()))).unwrap();
+
+        assert!(model.data.records.is_empty());
+        assert!(matches!(&model.load_failures[..], [Error::Flac(file_path, _)] if *file_path == dir.join("01.flac")));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+        assert!(Model::load(Config::default().columns, Box::new(crate::source::FlacDir::new(dir))).is_err());
+    }
+
+    #[test]
+    fn read_only() {
+        let (mut model, source) = demo_model();
+
+        let file_path = model.data.records[0].file_path.clone();
+        model.data.records[0].set_meta("TITLE", Some(vec![str!("Changed")]));
+
+        model.read_only = true;
+        assert_eq!(model.save_all().saved, 0);
+        assert_ne!(source.get_file(&file_path).unwrap()["TITLE"], vec![str!("Changed")]);
+        assert!(model.data.records[0].is_edited());
+    }
+
+    #[test]
+    fn apply_config() {
+        let (mut model, _) = demo_model();
+
+        // Sort by title, descending, and stage an edit.
+        model.sort_by_column_index(1, true);
+        model.data.records[0].set_meta("TITLE", Some(vec![str!("Changed")]));
+
+        let mut config = Config::default();
+        config.columns.remove(0);
+        config.rating_max = 5;
+        config.read_only = true;
+        model.apply_config(&config);
+
+        assert_eq!(model.data.columns.len(), 3);
+        assert_eq!(model.sort, Some(SortSpec { column: 0, descending: true }));
+        assert_eq!(model.rating_max, 5);
+        assert!(model.read_only);
+        assert!(model.data.records.iter().any(|r| r.get_meta("TITLE") == Some(&[str!("Changed")][..])));
+
+        // The sort goes when its column does.
+        config.columns.remove(0);
+        model.apply_config(&config);
+        assert_eq!(model.sort, None);
+    }
+
+    #[test]
+    fn pipes_in_values() {
+        let (mut model, source) = demo_model();
+
+        // Values are kept apart, so one can contain the display separator.
+        let values = vec![str!("AC|DC"), str!("Guest")];
+        let file_path = model.data.records[0].file_path.clone();
+        model.data.records[0].set_meta("ARTIST", Some(values.clone()));
+
+        assert_eq!(model.save_all().saved, 1);
+        assert_eq!(source.get_file(&file_path).unwrap()["ARTIST"], values);
+
+        model.refresh().unwrap();
+        let record = model.data.records.iter().find(|r| r.file_path == file_path).unwrap();
+        assert_eq!(record.get_meta("ARTIST"), Some(&values[..]));
+    }
+
+    #[test]
+    fn write_conflicts() {
+        let (mut model, source) = demo_model();
+
+        let file_path = model.data.records[0].file_path.clone();
+        model.data.records[0].set_meta("TITLE", Some(vec![str!("Mine")]));
+
+        // Another program retags the file after it was loaded.
+        let mut metadata = source.get_file(&file_path).unwrap();
+        metadata.insert(str!("ARTIST"), vec![str!("Theirs")]);
+        source.set_file(&file_path, metadata.clone());
+
+        let summary = model.save_all();
+
+        assert_eq!(summary.saved, 0);
+        assert_eq!(summary.conflicts.len(), 1);
+        assert_eq!(source.get_file(&file_path).unwrap(), metadata);
+
+        let conflict = summary.conflicts.into_iter().next().unwrap();
+        model.resolve_conflict(conflict, &Resolution::Merge(HashSet::new()));
+
+        assert_eq!(model.save_all().saved, 1);
+
+        let metadata = source.get_file(&file_path).unwrap();
+        assert_eq!(metadata["TITLE"], vec![str!("Mine")]);
+        assert_eq!(metadata["ARTIST"], vec![str!("Theirs")]);
+
+        // A file that was only touched is written as usual.
+        model.data.records[0].set_meta("TITLE", Some(vec![str!("Mine Again")]));
+        source.set_file(&file_path, metadata);
+
+        let summary = model.save_all();
+        assert_eq!((summary.saved, summary.conflicts.len()), (1, 0));
+    }
+
+    #[test]
+    fn move_and_copy_files() {
+        let (mut model, source) = demo_model();
+        let file_path = model.data.records[0].file_path.clone();
+        let dest_dir = PathBuf::from("elsewhere");
+        let moved = dest_dir.join(&file_path);
+
+        assert!(model.copy_files(std::slice::from_ref(&file_path), &dest_dir).is_empty());
+        assert!(source.exists(&file_path) && source.exists(&moved));
+
+        // The copy is in the way of moving the file there.
+        assert_eq!(model.move_files(std::slice::from_ref(&file_path), &dest_dir).len(), 1);
+        source.remove_file(&moved);
+
+        assert!(model.move_files(std::slice::from_ref(&file_path), &dest_dir).is_empty());
+        assert!(!source.exists(&file_path));
+        assert_eq!(model.data.records[0].file_path, moved);
+    }
+
+    #[test]
+    fn hidden_files() {
+        let source = MemorySource::demo();
+        source.set_file("._01.flac", Metadata::new());
+
+        let mut model = Model::load(Config::default().columns, Box::new(source.clone())).unwrap();
+        assert_eq!(model.data.records.len(), 8);
+
+        model.toggle_show_hidden();
+        assert_eq!(model.refresh().unwrap().added, 1);
+
+        // Hidden files are dropped again, even though they are still there.
+        model.toggle_show_hidden();
+        assert_eq!(model.refresh().unwrap().removed, 1);
+        assert_eq!(model.data.records.len(), 8);
+    }
+
+    #[test]
+    fn delete_files() {
+        let (mut model, source) = demo_model();
+        let file_paths = [model.data.records[0].file_path.clone(), model.data.records[1].file_path.clone()];
+
+        model.toggle_selection();
+        model.data.records[1].set_meta("TITLE", Some(vec![str!("New")]));
+        model.queue_writes();
+
+        // The file being written is kept.
+        let failures = model.delete_files(&file_paths);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(failures[0].0, file_paths[1]);
+
+        assert_eq!(model.data.records.len(), 7);
+        assert!(model.selection.is_empty());
+        assert!(!source.exists(&file_paths[0]));
+        assert!(source.exists(&file_paths[1]));
+    }
+
+    #[test]
+    fn rename_files() {
+        let (mut model, source) = demo_model();
+
+        let a = model.data.records[0].file_path.clone();
+        let b = model.data.records[1].file_path.clone();
+        let title = source.get_file(&a).unwrap()["TITLE"].clone();
+
+        model.edit_records("Set genre", |records| records[0].set_meta("GENRE", Some(vec![str!("Rock")])));
+        model.toggle_selection();
+
+        // Swapping two names needs one of the files moved out of the way first.
+        let renames = [
+            Rename { from: a.clone(), to: b.clone(), collided: false },
+            Rename { from: b.clone(), to: a.clone(), collided: false },
+        ];
+        assert!(model.rename_files(&renames).is_empty());
+
+        assert_eq!(model.data.records[0].file_path, b);
+        assert_eq!(model.data.records[1].file_path, a);
+        assert_eq!(source.get_file(&b).unwrap()["TITLE"], title);
+        assert!(model.is_selected(0));
+
+        // Undoing follows the file to its new name.
+        model.undo();
+        assert!(!model.data.records[0].is_edited());
+
+        let failures = model.rename_files(&[Rename { from: a, to: b.clone(), collided: false }]);
+        assert_eq!(failures.len(), 1);
+        assert_eq!(model.data.records[0].file_path, b);
+    }
+
+    #[test]
+    fn number_tracks() {
+        let (mut model, _) = demo_model();
+
+        model.sort_by_column_index(1, true);
+        model.scope = Scope::All;
+
+        let (edited, total) = model.number_tracks(Numbering { zero_pad: true, fill_total: true, fill_disc_total: false });
+
+        // Titles sort in reverse, so the fourth song of each album is first.
+        assert_eq!((edited, total), (8, 8));
+        assert_eq!(model.data.records[0].get_meta("TITLE"), Some(&[str!("Song 4 of Second Album")][..]));
+        assert_eq!(model.data.records[0].get_meta("TRACKNUMBER"), Some(&[str!("01")][..]));
+        assert_eq!(model.data.records[0].get_meta("TRACKTOTAL"), Some(&[str!("04")][..]));
+
+        let album_one = model.data.records.iter().find(|r| r.get_meta("TITLE") == Some(&[str!("Song 1 of Album One")][..])).unwrap();
+        assert_eq!(album_one.get_meta("TRACKNUMBER"), Some(&[str!("04")][..]));
+
+        // Numbering the same way again changes nothing.
+        assert_eq!(model.number_tracks(Numbering { zero_pad: true, fill_total: true, fill_disc_total: false }), (0, 8));
+
+        model.undo();
+        assert_eq!(model.number_tracks(Numbering::default()).0, 8);
+        assert_eq!(model.data.records[0].get_meta("TRACKNUMBER"), Some(&[str!("1")][..]));
+        assert_eq!(model.data.records[0].get_meta("TRACKTOTAL"), None);
+
+        // Songs one and two of each album are on the first disc, the rest on the
+        // second, and each disc is numbered separately.
+        model.undo();
+        model.sort_by_column_index(1, false);
+
+        for (i, record) in model.data.records.iter_mut().enumerate() {
+            record.set_meta("DISCNUMBER", Some(vec![format!("{}/2", 1 + i / 4)]));
+        }
+
+        let numbering = Numbering { zero_pad: false, fill_total: true, fill_disc_total: true };
+        assert_eq!(model.number_tracks(numbering), (8, 8));
+
+        let tags = |i: usize, key: &str| model.data.records[i].get_meta(key).unwrap()[0].clone();
+        assert_eq!((tags(0, "TRACKNUMBER"), tags(1, "TRACKNUMBER"), tags(2, "TRACKNUMBER")), (str!("1"), str!("1"), str!("2")));
+        assert_eq!((tags(0, "TRACKTOTAL"), tags(0, "TOTALDISCS")), (str!("2"), str!("2")));
+    }
+
+    #[test]
+    fn clean_tags() {
+        let (mut model, _) = demo_model();
+
+        model.data.records[0].set_meta("TITLE", Some(vec![str!(" Song\u{200B}  One ")]));
+        model.data.records[0].set_meta("GENRE", Some(vec![str!(" ")]));
+        model.data.records[1].set_meta("TITLE", Some(vec![str!(" Two")]));
+        model.scope = Scope::All;
+
+        assert_eq!(model.clean_tags(&[Cleanup::Trim]), (2, 8));
+        assert_eq!(model.data.records[0].get_meta("TITLE"), Some(&[str!("Song\u{200B}  One")][..]));
+        assert_eq!(model.data.records[0].get_meta("GENRE"), Some(&[str!("")][..]));
+
+        assert_eq!(model.clean_tags(&Cleanup::ALL), (1, 8));
+        assert_eq!(model.data.records[0].get_meta("TITLE"), Some(&[str!("Song One")][..]));
+        assert_eq!(model.data.records[0].get_meta("GENRE"), None);
+    }
+
+    #[test]
+    fn strip_tags() {
+        let (mut model, _) = demo_model();
+
+        model.strip_keep = vec![str!("title"), str!("REPLAYGAIN_TRACK_GAIN")];
+        model.scope = Scope::All;
+
+        assert_eq!(model.strip_tags(), (8, 8));
+        assert_eq!(model.data.records[0].meta_keys().collect::<Vec<_>>(), vec!["TITLE"]);
+        assert_eq!(model.strip_tags(), (0, 8));
+
+        model.undo();
+        assert!(model.data.records[0].get_meta("ARTIST").is_some());
+    }
+
+    #[test]
+    fn convert_case() {
+        let (mut model, _) = demo_model();
+
+        model.move_cursor_right(1);
+        assert_eq!(model.convert_case(Case::Upper), Some((1, 1)));
+        assert_eq!(model.data.records[0].get_meta("TITLE"), Some(&[str!("SONG 1 OF ALBUM ONE")][..]));
+
+        // The whole column is converted when the column is highlighted.
+        model.cursor = Cursor::Column(1);
+        assert_eq!(model.convert_case(Case::Title), Some((1, 8)));
+        assert_eq!(model.data.records[0].get_meta("TITLE"), Some(&[str!("Song 1 of Album One")][..]));
+
+        model.cursor = Cursor::Cell(3, 0);
+        assert_eq!(model.convert_case(Case::Lower), None);
+    }
+
+    #[test]
+    fn convert_to_ascii() {
+        let (mut model, source) = demo_model();
+
+        model.data.records[1].set_meta("ARTIST", Some(vec![str!("Björk"), str!("Guðmundsdóttir")]));
+
+        let (meta_key, changes) = model.plan_ascii_values().unwrap();
+        assert_eq!(changes, vec![]);
+
+        model.cursor = Cursor::Column(0);
+        let (meta_key_all, changes) = model.plan_ascii_values().unwrap();
+        assert_eq!((meta_key.as_str(), meta_key_all.as_str()), ("ARTIST", "ARTIST"));
+        assert_eq!(changes, vec![(1, vec![str!("Bjork"), str!("Gudmundsdottir")])]);
+
+        assert_eq!(model.stage_values("Convert to ASCII", &meta_key, &changes), 1);
+        assert_eq!(model.data.records[1].get_meta("ARTIST"), Some(&[str!("Bjork"), str!("Gudmundsdottir")][..]));
+
+        // File names are planned as renames, numbered if they are taken.
+        source.set_file("Café.flac", Metadata::new());
+        source.set_file("Cafe.flac", Metadata::new());
+        model.refresh().unwrap();
+
+        let renames = model.plan_ascii_renames();
+        assert_eq!(renames.len(), 1);
+        assert_eq!(renames[0].from, std::path::Path::new("Café.flac"));
+        assert_eq!(renames[0].to, std::path::Path::new("Cafe (2).flac"));
+    }
+
+    #[test]
+    fn scope() {
+        let (mut model, _) = demo_model();
+
+        model.move_cursor_down(5);
+        assert_eq!(model.target_record_indices(), vec![5]);
+
+        model.scope = Scope::Group;
+        assert_eq!(model.target_record_indices(), vec![4, 5, 6, 7]);
+
+        model.scope = Scope::All;
+        assert_eq!(model.target_record_indices().len(), 8);
+
+        assert_eq!(model.scope.next(), Scope::Selection);
+    }
+
+    #[test]
+    fn jump_to_missing() {
+        let (mut model, _) = demo_model();
+
+        assert!(!model.jump_to_missing(true));
+
+        model.data.records[1].set_meta("ARTIST", None);
+        model.data.records[5].set_meta("ARTIST", None);
+
+        assert!(model.jump_to_missing(true));
+        assert_eq!(model.cursor, Cursor::Cell(0, 1));
+        assert!(model.jump_to_missing(true));
+        assert_eq!(model.cursor, Cursor::Cell(0, 5));
+        assert!(model.jump_to_missing(true));
+        assert_eq!(model.cursor, Cursor::Cell(0, 1));
+        assert!(model.jump_to_missing(false));
+        assert_eq!(model.cursor, Cursor::Cell(0, 5));
+
+        model.cursor = Cursor::Column(0);
+        assert!(model.jump_to_missing(false));
+        assert_eq!(model.cursor, Cursor::Cell(0, 5));
+    }
+
+    #[test]
+    fn paging() {
+        let (mut model, _) = demo_model();
+        assert_eq!(model.page_size(), 10);
+
+        model.viewport_height = 3;
+        model.cursor = Cursor::Cell(0, 0);
+        model.move_cursor_down(model.page_size());
+        assert_eq!(model.cursor, Cursor::Cell(0, 3));
+        model.move_cursor_down(model.half_page_size());
+        assert_eq!(model.cursor, Cursor::Cell(0, 4));
+
+        // A viewport with no room still moves the cursor.
+        model.viewport_height = 0;
+        assert_eq!(model.page_size(), 1);
+        assert_eq!(model.half_page_size(), 1);
+    }
+
+    #[test]
+    fn row_mode() {
+        let (mut model, _) = demo_model();
+
+        model.cursor = Cursor::Cell(2, 1);
+        model.toggle_row_mode();
+        assert_eq!(model.cursor, Cursor::Row(1));
+        assert!(model.current_column().is_none());
+        assert!(model.is_cursor_at_cell(0, 1));
+        assert!(model.is_cursor_at_cell(3, 1));
+
+        // Moving sideways stays on the row, and moving up stops at the first record.
+        model.move_cursor_right(1);
+        model.move_cursor_up(5);
+        assert_eq!(model.cursor, Cursor::Row(0));
+
+        assert!(model.run_line_command(LineCommand::Goto(3)).is_ok());
+        assert_eq!(model.cursor, Cursor::Row(2));
+
+        model.toggle_row_mode();
+        assert_eq!(model.cursor, Cursor::Cell(2, 2));
+    }
+
+    #[test]
+    fn detail_lines() {
+        let (mut model, _) = demo_model();
+
+        model.cursor = Cursor::Cell(0, 1);
+        model.data.records[1].set_meta("COMMENT", Some(vec![str!("Nice")]));
+
+        let lines = model.detail_lines();
+        let keys = lines.iter().map(|(k, _, _)| k.as_str()).collect::<Vec<_>>();
+
+        assert_eq!(keys, ["ALBUM", "ARTIST", "COMMENT", "DATE", "TITLE", "TRACKNUMBER"]);
+        assert_eq!(lines[1], (str!("ARTIST"), str!("Artist A|Guest Person"), false));
+        assert_eq!(lines[2], (str!("COMMENT"), str!("Nice"), true));
+
+        model.cursor = Cursor::Column(0);
+        assert!(model.detail_lines().is_empty());
+    }
+
+    #[test]
+    fn value_line() {
+        let (mut model, _) = demo_model();
+
+        model.data.records[1].set_meta("TITLE", Some(vec![str!("First line\nsecond line ")]));
+
+        model.cursor = Cursor::Cell(0, 1);
+        assert_eq!(model.current_value_line(), Some(str!("Artist: Artist A|Guest Person")));
+
+        model.cursor = Cursor::Cell(1, 1);
+        assert_eq!(model.current_value_line(), Some(str!("Title: First line↵second line ")));
+
+        model.toggle_show_invisible();
+        assert_eq!(model.current_value_line(), Some(str!("Title: First line↵second line·")));
+
+        // A highlighted column is described instead.
+        model.cursor = Cursor::Column(0);
+        assert!(model.current_value_line().unwrap().starts_with("Artist: Meta(\"ARTIST\")"));
+
+        // Invalid dates are called out.
+        let mut column = model.data.columns[0].clone();
+        column.key = ColumnKey::Meta(str!("DATE"));
+        column.title = str!("Date");
+        model.data.columns[0] = column;
+
+        model.data.records[1].set_meta("DATE", Some(vec![str!("2001-02-30")]));
+        model.cursor = Cursor::Cell(0, 1);
+        assert_eq!(model.current_value_line(), Some(str!("Date: 2001-02-30  (not a valid YYYY-MM-DD date)")));
+    }
+
+    #[test]
+    fn bump_rating() {
+        let (mut model, _) = demo_model();
+
+        model.cursor = Cursor::Cell(0, 2);
+        model.data.records[2].set_meta("RATING", Some(vec![str!("60")]));
+
+        assert_eq!(model.bump_rating(true), (1, 1));
+        assert_eq!(model.data.records[2].get_meta("RATING"), Some(&[str!("80")][..]));
+
+        model.rating_max = 5;
+        model.cursor = Cursor::Cell(0, 3);
+        assert_eq!(model.bump_rating(false), (0, 1));
+        assert_eq!(model.data.records[3].get_meta("RATING"), None);
+
+        model.data.records[3].set_meta("RATING", Some(vec![str!("1")]));
+        assert_eq!(model.bump_rating(false), (1, 1));
+        assert_eq!(model.data.records[3].get_meta("RATING"), Some(&[str!("0")][..]));
+    }
+
+    #[test]
+    fn column_completions() {
+        let (mut model, _) = demo_model();
+
+        model.cursor = Cursor::Cell(0, 0);
+        assert_eq!(model.column_completions(), vec![str!("Artist A"), str!("Artist B"), str!("Guest Person")]);
+
+        model.cursor = Cursor::Cell(3, 0);
+        assert!(model.column_completions().is_empty());
+    }
+
+    #[test]
+    fn current_album() {
+        let (mut model, _) = demo_model();
+
+        model.cursor = Cursor::Cell(0, 5);
+        assert_eq!(model.current_album_indices(), vec![4, 5, 6, 7]);
+        assert_eq!(model.current_album_terms(), Some((str!("Artist B"), str!("Second Album"))));
+
+        model.mutate_records(|records| records[5].set_meta("ALBUMARTIST", Some(vec![str!("Various")])));
+        model.cursor = Cursor::Cell(0, 5);
+        assert_eq!(model.current_album_terms(), Some((str!("Various"), str!("Second Album"))));
+
+        model.mutate_records(|records| records[5].set_meta("ALBUM", None));
+        model.cursor = Cursor::Cell(0, 5);
+        assert!(model.current_album_indices().is_empty());
+        assert_eq!(model.current_album_terms(), None);
+    }
+
+    #[test]
+    fn apply_replay_gain() {
+        let (mut model, _) = demo_model();
+        let loudness = |mean_square: f64, peak: f64| Loudness { blocks: vec![mean_square; 10], peak };
+
+        let analyzed = vec![
+            (model.data.records[4].file_path.clone(), loudness(0.01, 0.5)),
+            (model.data.records[5].file_path.clone(), loudness(0.04, 0.8)),
+            (model.data.records[0].file_path.clone(), loudness(0.0, 0.0)),
+        ];
+
+        assert_eq!(model.apply_replay_gain(&analyzed), 3);
+
+        let tag = |i: usize, meta_key: &str| model.data.records[i].get_meta(meta_key).map(|vs| vs[0].clone());
+
+        assert_eq!(tag(4, "REPLAYGAIN_TRACK_GAIN"), Some(str!("+2.69 dB")));
+        assert_eq!(tag(4, "REPLAYGAIN_ALBUM_PEAK"), Some(str!("0.800000")));
+        assert_eq!(tag(4, "REPLAYGAIN_ALBUM_GAIN"), tag(5, "REPLAYGAIN_ALBUM_GAIN"));
+        assert_eq!(tag(0, "REPLAYGAIN_TRACK_GAIN"), None);
+        assert_eq!(tag(0, "REPLAYGAIN_ALBUM_PEAK"), Some(str!("0.000000")));
+
+        assert_eq!(model.apply_replay_gain(&analyzed), 0);
+    }
+
+    #[test]
+    fn command_columns() {
+        let (mut model, _) = demo_model();
+        let command = str!("echo {title}");
+
+        assert!(model.take_command_jobs().is_empty());
+
+        model.data.columns.push(Column {
+            key: ColumnKey::Command(command.clone()),
+            title: str!("Echo"),
+            sizing: Sizing::Auto,
+            align: Default::default(),
+            kind: None,
+            separator: None,
+        });
+
+        let jobs = model.take_command_jobs();
+        assert_eq!(jobs.len(), model.data.records.len());
+
+        // Running jobs are not handed out again.
+        assert!(model.take_command_jobs().is_empty());
+
+        let outputs = jobs.into_iter().take(2).map(|job| { let output = job.run(); (job, output) }).collect();
+        model.set_command_outputs(outputs);
+
+        assert_eq!(model.data.records[0].get_command_output(&command), Some("Song 1 of Album One"));
+        assert_eq!(model.data.records[2].get_command_output(&command), None);
+
+        // Outputs go stale once the file changes.
+        model.data.records[0].modified = Some(std::time::SystemTime::UNIX_EPOCH);
+        assert_eq!(model.data.records[0].get_command_output(&command), None);
+        assert_eq!(model.take_command_jobs().len(), 1);
+    }
+
+    #[test]
+    fn run_script() {
+        let (mut model, _) = demo_model();
+        model.scripts.insert(str!("shout"), str!("tags.TITLE = tags.TITLE.to_upper();"));
+        model.scripts.insert(str!("broken"), str!("tags.TITLE = tags.MISSING.to_upper();"));
+
+        model.selection.insert(model.data.records[0].file_path.clone());
+        model.selection.insert(model.data.records[1].file_path.clone());
+
+        assert_eq!(model.run_script("shout"), Ok((2, 2)));
+        assert_eq!(model.data.records[1].get_meta("TITLE"), Some(&[str!("SONG 2 OF ALBUM ONE")][..]));
+        assert_eq!(model.run_script("shout"), Ok((0, 2)));
+
+        assert!(model.run_script("broken").is_err());
+        assert!(model.run_script("missing").is_err());
+        assert_eq!(model.undo(), Some(str!("Run script (shout)")));
+    }
+
+    #[test]
+    fn set_lyrics() {
+        let (mut model, _) = demo_model();
+        let file_path = model.data.records[0].file_path.clone();
+
+        assert!(model.set_lyrics(&file_path, "Line one\nLine two"));
+        assert_eq!(model.data.records[0].get_meta("LYRICS"), Some(&[str!("Line one\nLine two")][..]));
+        assert!(!model.set_lyrics(&file_path, "Line one\nLine two"));
+
+        assert!(model.set_lyrics(&file_path, "  "));
+        assert_eq!(model.data.records[0].get_meta("LYRICS"), None);
+        assert!(!model.set_lyrics(Path::new("missing.flac"), "Words"));
+    }
+
+    #[test]
+    fn type_ahead() {
+        let (mut model, _) = demo_model();
+
+        // Only the sorted column can be searched.
+        model.cursor = Cursor::Cell(1, 0);
+        assert_eq!(model.type_ahead("s"), None);
+
+        model.sort_by_column_index(1, false);
+        assert_eq!(model.type_ahead_column(), Some(1));
+        assert_eq!(model.type_ahead("song 3 of s"), Some(true));
+        assert_eq!(model.cursor, Cursor::Cell(1, 5));
+        assert_eq!(model.type_ahead("song 9"), Some(false));
+        assert_eq!(model.cursor, Cursor::Cell(1, 5));
+
+        model.cursor = Cursor::Column(1);
+        assert_eq!(model.type_ahead_column(), None);
+        assert_eq!(model.type_ahead("s"), None);
+    }
+
+    #[test]
+    fn searches_expand_collapsed_groups() {
+        let (mut model, _) = demo_model();
+
+        model.toggle_grouping("ALBUM");
+        model.sort_by_column_index(1, false);
+        model.toggle_collapse_all();
+        model.cursor = Cursor::Cell(1, 0);
+
+        // The record found is shown, rather than hidden under its header.
+        assert_eq!(model.type_ahead("song 3 of s"), Some(true));
+        let (_, y) = model.cursor.to_xy();
+        assert_eq!(model.data.records[y.unwrap()].get_meta("TITLE").unwrap()[0], "Song 3 of Second Album");
+        assert!(!model.is_collapsed(y.unwrap()));
+        assert!(model.is_collapsed(0));
+
+        model.data.records[1].set_meta("TITLE", None);
+        assert!(model.jump_to_missing(true));
+        assert_eq!(model.cursor, Cursor::Cell(1, 1));
+        assert!(!model.is_collapsed(1));
+        assert!(model.collapsed_groups.is_empty());
+
+        model.toggle_collapse_all();
+        assert!(model.run_line_command(LineCommand::Goto(2)).is_ok());
+        assert_eq!(model.cursor, Cursor::Cell(1, 1));
+        assert!(!model.is_collapsed(1));
+        assert!(model.is_collapsed(4));
+
+        assert!(model.run_line_command(LineCommand::FindFile(str!("artist b - song 4"))).is_ok());
+        let (_, y) = model.cursor.to_xy();
+        assert!(!model.is_collapsed(y.unwrap()));
+        assert!(model.collapsed_groups.is_empty());
+    }
+
+    #[test]
+    fn line_commands() {
+        let (mut model, _) = demo_model();
+
+        assert!(model.run_line_command(LineCommand::Goto(4)).is_ok());
+        assert_eq!(model.cursor, Cursor::Cell(0, 3));
+        assert!(model.run_line_command(LineCommand::Goto(9)).is_err());
+
+        let find = || LineCommand::FindFile(str!("song 2"));
+
+        assert!(model.run_line_command(find()).is_ok());
+        assert_eq!(model.cursor, Cursor::Cell(0, 5));
+        assert!(model.run_line_command(find()).is_ok());
+        assert_eq!(model.cursor, Cursor::Cell(0, 1));
+        assert!(model.run_line_command(LineCommand::FindFile(str!("nope"))).is_err());
+    }
+
+    #[test]
+    fn copy_tags() {
+        let (mut model, _) = demo_model();
+
+        model.select_all();
+
+        // The first record of the second album.
+        let source_path = model.data.records[4].file_path.clone();
+        let (edited, total) = model.copy_tags(&source_path, &[str!("ALBUM"), str!("DATE")]).unwrap();
+
+        // Only the first album and the track missing its date need changes.
+        assert_eq!((edited, total), (5, 7));
+        assert!(model.data.records.iter().all(|r| r.get_meta("ALBUM") == Some(&[str!("Second Album")][..])));
+        assert!(model.data.records.iter().all(|r| r.get_meta("DATE") == Some(&[str!("1999-05-02")][..])));
+
+        // Records that only needed one of the keys changed still count.
+        model.data.records[0].set_meta("DATE", None);
+        assert_eq!(model.copy_tags(&source_path, &[str!("ALBUM"), str!("DATE")]), Some((1, 7)));
+    }
+
+    #[test]
+    fn copy_tags_after_refresh() {
+        let (mut model, source) = demo_model();
+        let source_path = model.data.records[4].file_path.clone();
+
+        // A record before the source goes away while the tags are chosen.
+        source.remove_file(&model.data.records[0].file_path);
+        model.refresh().unwrap();
+        model.select_all();
+
+        assert_eq!(model.copy_tags(&source_path, &[str!("ALBUM")]), Some((3, 6)));
+        assert!(model.data.records.iter().all(|r| r.get_meta("ALBUM") == Some(&[str!("Second Album")][..])));
+
+        // Tags are not copied from a record that is gone.
+        source.remove_file(&source_path);
+        model.refresh().unwrap();
+        assert_eq!(model.copy_tags(&source_path, &[str!("ALBUM")]), None);
+    }
+
+    #[test]
+    fn select_files() {
+        let (mut model, source) = demo_model();
+        let file_paths = vec![model.data.records[1].file_path.clone(), model.data.records[5].file_path.clone()];
+
+        // Records move and go away between finding files and selecting them.
+        source.remove_file(&model.data.records[0].file_path);
+        source.remove_file(&file_paths[1]);
+        model.refresh().unwrap();
+
+        assert_eq!(model.select_files(&file_paths), 1);
+        assert!(model.is_selected(0));
+        assert_eq!(model.target_record_indices(), vec![0]);
+    }
+}
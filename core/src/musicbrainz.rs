@@ -0,0 +1,347 @@
+//! Looking up albums on MusicBrainz, to fill in their tags.
+
+use std::collections::BTreeMap;
+use std::convert::TryFrom;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+
+use serde_json::Value as JsonValue;
+
+use crate::data::Record;
+use crate::group::disc_number;
+use crate::http::get_json;
+use crate::http::percent_encode;
+use crate::snapshot::Snapshot;
+use crate::snapshot::SnapshotEntry;
+use crate::value::Value;
+use crate::value::ValueKind;
+
+const API_ROOT: &str = "https://musicbrainz.org/ws/2";
+
+const SEARCH_LIMIT: usize = 15;
+
+/// A release found by a search, enough to pick the right one from a list.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ReleaseSummary {
+    /// The MusicBrainz ID of the release.
+    pub id: String,
+    /// The title of the release.
+    pub title: String,
+    /// The credited artists.
+    pub artist: String,
+    /// When the release came out, as in `2001-05-14`.
+    pub date: Option<String>,
+    /// The country the release came out in, as a two letter code.
+    pub country: Option<String>,
+    /// How many tracks the release has, over all its discs.
+    pub track_count: usize,
+}
+
+impl Display for ReleaseSummary {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} - {}", self.artist, self.title)?;
+
+        let details =
+            self.date.iter().chain(self.country.iter()).cloned()
+            .chain(std::iter::once(format!("{} tracks", self.track_count)))
+            .collect::<Vec<_>>()
+        ;
+
+        write!(f, " ({})", details.join(", "))
+    }
+}
+
+/// A track of a release.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Track {
+    /// The MusicBrainz ID of the track.
+    pub id: String,
+    /// The MusicBrainz ID of the recording on the track.
+    pub recording_id: String,
+    /// The title of the track.
+    pub title: String,
+    /// The credited artists, those of the release if the track has none of its
+    /// own.
+    pub artist: String,
+    /// The MusicBrainz IDs of the credited artists.
+    pub artist_ids: Vec<String>,
+    /// The disc the track is on, counting from 1.
+    pub disc: u32,
+    /// The number of the track on its disc, counting from 1.
+    pub number: u32,
+}
+
+/// A release with its tracks, as looked up by its ID.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Release {
+    /// The MusicBrainz ID of the release.
+    pub id: String,
+    /// The title of the release.
+    pub title: String,
+    /// The credited artists.
+    pub artist: String,
+    /// The MusicBrainz IDs of the credited artists.
+    pub artist_ids: Vec<String>,
+    /// When the release came out, as in `2001-05-14`.
+    pub date: Option<String>,
+    /// How many discs the release has.
+    pub disc_count: u32,
+    /// The tracks of every disc, in order.
+    pub tracks: Vec<Track>,
+}
+
+/// Quotes a value as a search phrase, escaping the characters that have
+/// meaning in a search query.
+fn phrase(value: &str) -> String {
+    let escaped = value.chars().fold(String::new(), |mut escaped, c| {
+        if "\\\"".contains(c) { escaped.push('\\'); }
+        escaped.push(c);
+        escaped
+    });
+
+    format!("\"{}\"", escaped)
+}
+
+/// The URL to search for the releases of an album by an artist.
+pub fn search_url(artist: &str, album: &str) -> String {
+    let query = format!("release:{} AND artist:{}", phrase(album), phrase(artist));
+    format!("{}/release?query={}&limit={}&fmt=json", API_ROOT, percent_encode(&query), SEARCH_LIMIT)
+}
+
+/// The URL to look up a release with its tracks.
+pub fn release_url(id: &str) -> String {
+    format!("{}/release/{}?inc=recordings+artist-credits&fmt=json", API_ROOT, percent_encode(id))
+}
+
+/// Searches MusicBrainz for the releases of an album by an artist.
+pub fn search(artist: &str, album: &str) -> Result<Vec<ReleaseSummary>, String> {
+    get_json(&search_url(artist, album)).map(|json| parse_search(&json))
+}
+
+/// Looks up a release with its tracks on MusicBrainz.
+pub fn lookup(id: &str) -> Result<Release, String> {
+    let json = get_json(&release_url(id))?;
+    parse_release(&json).ok_or_else(|| String::from("unexpected response: missing release fields"))
+}
+
+fn string(json: &JsonValue, key: &str) -> Option<String> {
+    json.get(key)?.as_str().filter(|s| !s.is_empty()).map(String::from)
+}
+
+/// The names of the credited artists with the phrases joining them, and
+/// their IDs.
+fn artist_credit(json: &JsonValue) -> (String, Vec<String>) {
+    let credits = json.get("artist-credit").and_then(JsonValue::as_array).map(Vec::as_slice).unwrap_or_default();
+
+    let name = credits.iter().fold(String::new(), |mut name, credit| {
+        name.push_str(credit.get("name").and_then(JsonValue::as_str).unwrap_or_default());
+        name.push_str(credit.get("joinphrase").and_then(JsonValue::as_str).unwrap_or_default());
+        name
+    });
+
+    let ids = credits.iter().filter_map(|credit| string(credit.get("artist")?, "id")).collect();
+
+    (name, ids)
+}
+
+/// Reads the releases from the response to a search.
+pub fn parse_search(json: &JsonValue) -> Vec<ReleaseSummary> {
+    let releases = json.get("releases").and_then(JsonValue::as_array).map(Vec::as_slice).unwrap_or_default();
+
+    releases.iter()
+    .filter_map(|release| {
+        Some(ReleaseSummary {
+            id: string(release, "id")?,
+            title: string(release, "title")?,
+            artist: artist_credit(release).0,
+            date: string(release, "date"),
+            country: string(release, "country"),
+            track_count: release.get("track-count").and_then(JsonValue::as_u64).unwrap_or(0) as usize,
+        })
+    })
+    .collect()
+}
+
+/// Reads a release from the response to a lookup, or `None` if fields are
+/// missing.
+pub fn parse_release(json: &JsonValue) -> Option<Release> {
+    let (artist, artist_ids) = artist_credit(json);
+    let media = json.get("media")?.as_array()?;
+
+    let mut tracks = Vec::new();
+
+    for (i, medium) in media.iter().enumerate() {
+        let disc = medium.get("position").and_then(JsonValue::as_u64).unwrap_or(i as u64 + 1) as u32;
+
+        for (j, track) in medium.get("tracks").and_then(JsonValue::as_array).into_iter().flatten().enumerate() {
+            let (track_artist, track_artist_ids) = artist_credit(track);
+
+            tracks.push(Track {
+                id: string(track, "id")?,
+                recording_id: track.get("recording").and_then(|r| string(r, "id")).unwrap_or_default(),
+                title: string(track, "title")?,
+                artist: if track_artist.is_empty() { artist.clone() } else { track_artist },
+                artist_ids: if track_artist_ids.is_empty() { artist_ids.clone() } else { track_artist_ids },
+                disc,
+                number: track.get("position").and_then(JsonValue::as_u64).unwrap_or(j as u64 + 1) as u32,
+            });
+        }
+    }
+
+    Some(Release {
+        id: string(json, "id")?,
+        title: string(json, "title")?,
+        artist,
+        artist_ids,
+        date: string(json, "date"),
+        disc_count: media.len() as u32,
+        tracks,
+    })
+}
+
+fn track_number(record: &Record) -> Option<u32> {
+    let raw = record.get_meta("TRACKNUMBER")?.first()?;
+
+    match Value::parse(raw, ValueKind::Integer) {
+        Value::Integer(n) => u32::try_from(n).ok(),
+        _ => None,
+    }
+}
+
+/// Works out the tags each record of an album would get from a release, as
+/// a snapshot of only those tags to compare against and restore from.
+/// Records are matched to tracks by disc and track number where they have a
+/// track number, and by their order otherwise. Records with no matching track
+/// are left out.
+pub fn proposed_tags(release: &Release, records: &[&Record]) -> Snapshot {
+    let entries =
+        records.iter()
+        .enumerate()
+        .filter_map(|(i, record)| {
+            let track = match track_number(record) {
+                Some(number) => {
+                    let disc = disc_number(record).unwrap_or(1);
+                    release.tracks.iter().find(|t| t.number == number && t.disc == disc)?
+                },
+                None => release.tracks.get(i)?,
+            };
+
+            let mut metadata = BTreeMap::new();
+            let mut propose = |key: &str, values: Vec<String>| {
+                if !values.is_empty() { metadata.insert(key.to_string(), values); }
+            };
+
+            propose("ALBUM", vec![release.title.clone()]);
+            propose("ALBUMARTIST", vec![release.artist.clone()]);
+            propose("DATE", release.date.iter().cloned().collect());
+            propose("TITLE", vec![track.title.clone()]);
+            propose("ARTIST", vec![track.artist.clone()]);
+            propose("TRACKNUMBER", vec![track.number.to_string()]);
+            propose("MUSICBRAINZ_ALBUMID", vec![release.id.clone()]);
+            propose("MUSICBRAINZ_ALBUMARTISTID", release.artist_ids.clone());
+            propose("MUSICBRAINZ_ARTISTID", track.artist_ids.clone());
+            propose("MUSICBRAINZ_RELEASETRACKID", vec![track.id.clone()]);
+            propose("MUSICBRAINZ_TRACKID", Some(track.recording_id.clone()).filter(|id| !id.is_empty()).into_iter().collect());
+
+            if release.disc_count > 1 {
+                propose("DISCNUMBER", vec![track.disc.to_string()]);
+            }
+
+            Some(SnapshotEntry { file_path: record.file_path.clone(), metadata })
+        })
+        .collect::<Vec<_>>()
+    ;
+
+    // Only compare the proposed tags, so that other tags are left alone.
+    let meta_keys = entries.iter().flat_map(|e| e.metadata.keys().cloned()).collect();
+
+    Snapshot { entries, meta_keys: Some(meta_keys) }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use maplit::hashmap;
+    use serde_json::json;
+    use str_macro::str;
+
+    fn release_json() -> JsonValue {
+        json!({
+            "id": "rel-1",
+            "title": "Album One",
+            "date": "1999-05-02",
+            "artist-credit": [
+                { "name": "Artist A", "joinphrase": " & ", "artist": { "id": "art-a" } },
+                { "name": "Artist B", "joinphrase": "", "artist": { "id": "art-b" } },
+            ],
+            "media": [
+                {
+                    "position": 1,
+                    "tracks": [
+                        { "id": "t-1", "position": 1, "title": "First", "recording": { "id": "rec-1" } },
+                        {
+                            "id": "t-2", "position": 2, "title": "Second", "recording": { "id": "rec-2" },
+                            "artist-credit": [{ "name": "Guest", "joinphrase": "", "artist": { "id": "art-g" } }],
+                        },
+                    ],
+                },
+            ],
+        })
+    }
+
+    #[test]
+    fn urls() {
+        assert_eq!(
+            search_url("AC/DC", "Back in \"Black\""),
+            "https://musicbrainz.org/ws/2/release?query=release%3A%22Back%20in%20%5C%22Black%5C%22%22%20AND%20artist%3A%22AC%2FDC%22&limit=15&fmt=json",
+        );
+        assert_eq!(release_url("rel-1"), "https://musicbrainz.org/ws/2/release/rel-1?inc=recordings+artist-credits&fmt=json");
+    }
+
+    #[test]
+    fn parsing() {
+        let search = json!({
+            "releases": [
+                { "id": "rel-1", "title": "Album One", "date": "1999", "track-count": 2, "artist-credit": [{ "name": "Artist A" }] },
+                { "title": "No ID" },
+            ],
+        });
+
+        let found = parse_search(&search);
+        assert_eq!(found.len(), 1);
+        assert_eq!(found[0].to_string(), "Artist A - Album One (1999, 2 tracks)");
+
+        let release = parse_release(&release_json()).unwrap();
+        assert_eq!(release.artist, "Artist A & Artist B");
+        assert_eq!(release.artist_ids, vec![str!("art-a"), str!("art-b")]);
+        assert_eq!(release.tracks[0].artist, "Artist A & Artist B");
+        assert_eq!(release.tracks[1].artist, "Guest");
+        assert_eq!(release.tracks[1].recording_id, "rec-2");
+    }
+
+    #[test]
+    fn proposing() {
+        let release = parse_release(&release_json()).unwrap();
+
+        let numbered = Record::new(hashmap! { str!("TRACKNUMBER") => vec![str!("2/2")] }, PathBuf::from("b.flac"));
+        let unnumbered = Record::new(hashmap! { str!("COMMENT") => vec![str!("Kept")] }, PathBuf::from("a.flac"));
+
+        let snapshot = proposed_tags(&release, &[&unnumbered, &numbered]);
+        let entry = |i: usize, key: &str| snapshot.entries[i].metadata[key].clone();
+
+        assert_eq!(entry(0, "TITLE"), vec![str!("First")]);
+        assert_eq!(entry(0, "DATE"), vec![str!("1999-05-02")]);
+        assert_eq!(entry(1, "TITLE"), vec![str!("Second")]);
+        assert_eq!(entry(1, "TRACKNUMBER"), vec![str!("2")]);
+        assert_eq!(entry(1, "MUSICBRAINZ_ARTISTID"), vec![str!("art-g")]);
+        assert!(!snapshot.entries[0].metadata.contains_key("DISCNUMBER"));
+
+        let comparison = snapshot.compare(&[unnumbered, numbered]);
+        assert!(!comparison.changed.iter().any(|diff| diff.meta_key == "COMMENT"));
+        assert!(comparison.changed.iter().any(|diff| diff.meta_key == "TITLE" && diff.current.is_none()));
+    }
+}
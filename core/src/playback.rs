@@ -0,0 +1,170 @@
+//! Playing files with an external player.
+
+use std::path::Path;
+use std::path::PathBuf;
+use std::process::Child;
+use std::process::Command as Process;
+use std::process::Stdio;
+
+use crate::hook::fill;
+use crate::hook::split_words;
+
+/// The placeholder for the file to play in a player command.
+const PATH_PLACEHOLDER: &str = "{path}";
+
+/// Players tried in order when none is configured. Each plays a file and
+/// exits, without a window or anything written to the terminal.
+const DEFAULT_PLAYERS: &[&str] = &[
+    "mpv --no-video --really-quiet {path}",
+    "ffplay -nodisp -autoexit -loglevel quiet {path}",
+    "cvlc --play-and-exit --quiet {path}",
+];
+
+/// The program and arguments to run a player command with, with the file
+/// put in place of `{path}`, or after the other arguments if there is none.
+/// Words are split as for hooks, see `hook::split_words`.
+pub fn player_args(command: &str, path: &Path) -> Option<(String, Vec<String>)> {
+    let path_str = path.to_string_lossy().into_owned();
+    let has_placeholder = command.contains(PATH_PLACEHOLDER);
+
+    let mut words =
+        split_words(command).ok()?
+        .iter()
+        .map(|word| fill(word, |name| if name == "path" { path_str.clone() } else { String::new() }))
+        .collect::<Result<Vec<_>, _>>()
+        .ok()?
+    ;
+
+    if words.is_empty() {
+        return None;
+    }
+
+    if !has_placeholder {
+        words.push(path_str);
+    }
+
+    let program = words.remove(0);
+    Some((program, words))
+}
+
+/// Plays one file at a time with an external player, in the background.
+/// Playback is stopped when dropped.
+#[derive(Default)]
+pub struct Player {
+    /// The player command to use, or `None` to look for a known one.
+    command: Option<String>,
+    playing: Option<(Child, PathBuf)>,
+}
+
+impl Player {
+    /// A player using a player command, or a known player if `None`.
+    pub fn new(command: Option<String>) -> Self {
+        Self { command, playing: None }
+    }
+
+    /// Changes the player command, from the next file played on.
+    pub fn set_command(&mut self, command: Option<String>) {
+        self.command = command;
+    }
+
+    /// The file being played, if it has not finished.
+    pub fn playing(&mut self) -> Option<&Path> {
+        let is_finished = match &mut self.playing {
+            Some((child, _)) => !matches!(child.try_wait(), Ok(None)),
+            None => return None,
+        };
+
+        if is_finished {
+            self.playing = None;
+        }
+
+        self.playing.as_ref().map(|(_, path)| path.as_path())
+    }
+
+    /// Stops playback. Returns whether anything was playing.
+    pub fn stop(&mut self) -> bool {
+        let was_playing = self.playing().is_some();
+
+        if let Some((mut child, _)) = self.playing.take() {
+            child.kill().ok();
+            child.wait().ok();
+        }
+
+        was_playing
+    }
+
+    fn spawn(command: &str, path: &Path) -> std::io::Result<Child> {
+        let (program, args) = player_args(command, path).ok_or_else(|| std::io::Error::other("the player command is empty"))?;
+
+        // The player must leave the terminal to the UI.
+        Process::new(program).args(args).stdin(Stdio::null()).stdout(Stdio::null()).stderr(Stdio::null()).spawn()
+    }
+
+    /// Plays a file, stopping whatever was playing first.
+    pub fn play(&mut self, path: &Path) -> Result<(), String> {
+        self.stop();
+
+        let child = match &self.command {
+            Some(command) => Self::spawn(command, path).map_err(|err| format!("unable to run player: {}", err))?,
+            None => {
+                DEFAULT_PLAYERS.iter()
+                .find_map(|command| Self::spawn(command, path).ok())
+                .ok_or_else(|| String::from("no player found, install mpv or set `player` in the config"))?
+            },
+        };
+
+        self.playing = Some((child, path.to_path_buf()));
+        Ok(())
+    }
+
+    /// Stops playback if the file is the one playing, otherwise plays it.
+    /// Returns whether the file is now playing.
+    pub fn toggle(&mut self, path: &Path) -> Result<bool, String> {
+        if self.playing() == Some(path) {
+            self.stop();
+            return Ok(false);
+        }
+
+        self.play(path).map(|_| true)
+    }
+}
+
+impl Drop for Player {
+    fn drop(&mut self) {
+        self.stop();
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn commands() {
+        let path = Path::new("/music/01 Song.flac");
+
+        assert_eq!(
+            player_args("mpv --no-video {path} --volume=50", path),
+            Some((String::from("mpv"), vec![String::from("--no-video"), String::from("/music/01 Song.flac"), String::from("--volume=50")])),
+        );
+        assert_eq!(player_args("paplay", path), Some((String::from("paplay"), vec![String::from("/music/01 Song.flac")])));
+        assert_eq!(player_args("'my player' --flag", path).unwrap().0, "my player");
+        assert_eq!(player_args("  ", path), None);
+    }
+
+    #[test]
+    fn playing() {
+        // Seconds to sleep for stand in for the file.
+        let mut player = Player::new(Some(String::from("sleep")));
+        let path = Path::new("5");
+
+        assert_eq!(player.toggle(path), Ok(true));
+        assert_eq!(player.playing(), Some(path));
+        assert_eq!(player.toggle(path), Ok(false));
+        assert_eq!(player.playing(), None);
+        assert!(!player.stop());
+
+        let mut player = Player::new(Some(String::from("no-such-player-program")));
+        assert!(player.play(path).is_err());
+    }
+}
@@ -0,0 +1,197 @@
+//! Writing playlists of records, in M3U and PLS formats.
+
+use std::path::Component;
+use std::path::Path;
+use std::path::PathBuf;
+
+use crate::data::Record;
+
+/// A kind of playlist file, told apart by its extension.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PlaylistFormat {
+    /// Extended M3U.
+    M3u,
+    /// M3U that is explicitly UTF-8.
+    M3u8,
+    /// PLS.
+    Pls,
+}
+
+impl PlaylistFormat {
+    /// The format an extension names, ignoring case.
+    pub fn from_path(path: &Path) -> Option<Self> {
+        let ext = path.extension()?.to_str()?.to_lowercase();
+
+        match ext.as_str() {
+            "m3u" => Some(Self::M3u),
+            "m3u8" => Some(Self::M3u8),
+            "pls" => Some(Self::Pls),
+            _ => None,
+        }
+    }
+}
+
+/// How files are referred to from a playlist.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PathStyle {
+    /// Relative to the directory of the playlist, so that the two can be
+    /// moved together.
+    Relative,
+    /// As absolute paths.
+    Absolute,
+}
+
+/// A file in a playlist.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct PlaylistEntry {
+    /// The absolute path of the file.
+    pub path: PathBuf,
+    /// The title shown for the file, if any.
+    pub title: Option<String>,
+}
+
+impl PlaylistEntry {
+    /// An entry for a record, titled "Artist - Title" if it has a title.
+    pub fn from_record(record: &Record) -> Self {
+        let joined = |meta_key: &str| record.get_meta(meta_key).filter(|vs| !vs.is_empty()).map(|vs| vs.join(", "));
+
+        let title = match (joined("ARTIST"), joined("TITLE")) {
+            (Some(artist), Some(title)) => Some(format!("{} - {}", artist, title)),
+            (None, title) => title,
+            (Some(_), None) => None,
+        };
+
+        Self { path: record.file_path.clone(), title }
+    }
+}
+
+/// The path that leads from a directory to a file, where both are absolute.
+/// Goes up out of the directory with `..` as needed.
+pub fn relative_path(path: &Path, base_dir: &Path) -> PathBuf {
+    let path_components = path.components().collect::<Vec<_>>();
+    let base_components = base_dir.components().collect::<Vec<_>>();
+
+    let common = path_components.iter().zip(&base_components).take_while(|(a, b)| a == b).count();
+
+    // Paths on different roots, such as other drives, have nothing to be relative to.
+    if common == 0 {
+        return path.to_path_buf();
+    }
+
+    std::iter::repeat_n(Component::ParentDir, base_components.len() - common)
+    .chain(path_components[common..].iter().copied())
+    .collect()
+}
+
+/// Writes out a playlist of entries. Paths of entries must be absolute, and
+/// are made relative to the directory the playlist is in if asked.
+pub fn render(entries: &[PlaylistEntry], format: PlaylistFormat, style: PathStyle, base_dir: &Path) -> String {
+    let path_str = |entry: &PlaylistEntry| match style {
+        PathStyle::Relative => relative_path(&entry.path, base_dir).to_string_lossy().into_owned(),
+        PathStyle::Absolute => entry.path.to_string_lossy().into_owned(),
+    };
+
+    let mut lines = Vec::new();
+
+    match format {
+        PlaylistFormat::M3u | PlaylistFormat::M3u8 => {
+            lines.push(String::from("#EXTM3U"));
+
+            for entry in entries {
+                // The length of tracks is not known, which is allowed.
+                lines.push(format!("#EXTINF:-1,{}", entry.title.as_deref().unwrap_or_default()));
+                lines.push(path_str(entry));
+            }
+        },
+        PlaylistFormat::Pls => {
+            lines.push(String::from("[playlist]"));
+
+            for (i, entry) in entries.iter().enumerate() {
+                let n = i + 1;
+                lines.push(format!("File{}={}", n, path_str(entry)));
+
+                if let Some(title) = &entry.title {
+                    lines.push(format!("Title{}={}", n, title));
+                }
+
+                lines.push(format!("Length{}=-1", n));
+            }
+
+            lines.push(format!("NumberOfEntries={}", entries.len()));
+            lines.push(String::from("Version=2"));
+        },
+    }
+
+    let mut text = lines.join("\n");
+    text.push('\n');
+    text
+}
+
+/// Writes a playlist of records to a file, in the format its extension names.
+/// Returns how many entries were written.
+pub fn write_to_path(records: &[&Record], path: &Path, style: PathStyle) -> Result<usize, String> {
+    let format = PlaylistFormat::from_path(path).ok_or_else(|| String::from("playlists must end in .m3u, .m3u8, or .pls"))?;
+
+    let path = std::path::absolute(path).map_err(|err| err.to_string())?;
+    let base_dir = path.parent().unwrap_or(&path);
+
+    let entries =
+        records.iter()
+        .map(|record| {
+            let mut entry = PlaylistEntry::from_record(record);
+            entry.path = std::path::absolute(&entry.path).map_err(|err| err.to_string())?;
+            Ok(entry)
+        })
+        .collect::<Result<Vec<_>, String>>()?
+    ;
+
+    std::fs::write(&path, render(&entries, format, style, base_dir)).map_err(|err| err.to_string())?;
+
+    Ok(entries.len())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    #[test]
+    fn relative_paths() {
+        let base_dir = Path::new("/music/playlists");
+
+        assert_eq!(relative_path(Path::new("/music/playlists/a.flac"), base_dir), PathBuf::from("a.flac"));
+        assert_eq!(relative_path(Path::new("/music/Album/a.flac"), base_dir), PathBuf::from("../Album/a.flac"));
+        assert_eq!(relative_path(Path::new("/other/a.flac"), base_dir), PathBuf::from("../../other/a.flac"));
+    }
+
+    #[test]
+    fn rendering() {
+        let records = [
+            Record::new(
+                hashmap! {
+                    str!("ARTIST") => vec![str!("Artist A"), str!("Guest")],
+                    str!("TITLE") => vec![str!("Song")],
+                },
+                PathBuf::from("/music/Album/01.flac"),
+            ),
+            Record::new(hashmap! {}, PathBuf::from("/music/Album/02.flac")),
+        ];
+        let entries = records.iter().map(PlaylistEntry::from_record).collect::<Vec<_>>();
+        let base_dir = Path::new("/music");
+
+        assert_eq!(
+            render(&entries, PlaylistFormat::M3u8, PathStyle::Relative, base_dir),
+            "#EXTM3U\n#EXTINF:-1,Artist A, Guest - Song\nAlbum/01.flac\n#EXTINF:-1,\nAlbum/02.flac\n",
+        );
+
+        assert_eq!(
+            render(&entries, PlaylistFormat::Pls, PathStyle::Absolute, base_dir),
+            "[playlist]\nFile1=/music/Album/01.flac\nTitle1=Artist A, Guest - Song\nLength1=-1\nFile2=/music/Album/02.flac\nLength2=-1\nNumberOfEntries=2\nVersion=2\n",
+        );
+
+        assert_eq!(PlaylistFormat::from_path(Path::new("mix.M3U")), Some(PlaylistFormat::M3u));
+        assert_eq!(PlaylistFormat::from_path(Path::new("mix.txt")), None);
+    }
+}
@@ -0,0 +1,136 @@
+//! Column layouts saved to files of their own, to share them.
+
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Error as IoError;
+use std::path::Path;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Error as JsonError;
+
+use crate::data::Columns;
+use crate::data::SortSpec;
+use crate::model::Model;
+
+/// What can go wrong reading or writing a preset file.
+#[derive(Debug)]
+pub enum PresetError {
+    /// The file could not be read or written.
+    Io(IoError),
+    /// The file is not a valid preset.
+    Json(JsonError),
+}
+
+impl Display for PresetError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Json(err) => write!(f, "invalid preset: {}", err),
+        }
+    }
+}
+
+impl From<IoError> for PresetError {
+    fn from(err: IoError) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<JsonError> for PresetError {
+    fn from(err: JsonError) -> Self {
+        Self::Json(err)
+    }
+}
+
+/// A standalone, shareable snapshot of a column layout, kept separate from the
+/// main config so that it can be passed around between users.
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Preset {
+    /// The columns, in order.
+    pub columns: Columns,
+
+    /// The sort applied to the records, indexing into the columns of this preset.
+    #[serde(default)]
+    pub sort: Option<SortSpec>,
+}
+
+impl Preset {
+    /// The column layout and sort of a model.
+    pub fn from_model(model: &Model) -> Self {
+        Self {
+            columns: model.data.columns.clone(),
+            sort: model.sort,
+        }
+    }
+
+    /// Reads a preset from a JSON file.
+    pub fn read_from_path(path: &Path) -> Result<Self, PresetError> {
+        let reader = BufReader::new(File::open(path)?);
+        let preset = serde_json::from_reader(reader)?;
+        Ok(preset)
+    }
+
+    /// Writes the preset to a JSON file, replacing the file if it is there.
+    pub fn write_to_path(&self, path: &Path) -> Result<(), PresetError> {
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self)?;
+        Ok(())
+    }
+
+    /// Replaces the columns of a model with the ones from this preset, and
+    /// re-sorts the records if the preset specifies a valid sort column.
+    pub fn apply(self, model: &mut Model) {
+        let Self { columns, sort } = self;
+
+        model.mutate_columns(move |model_columns| { *model_columns = columns; });
+
+        if let Some(sort) = sort {
+            if sort.column < model.data.columns.len() {
+                model.sort_by_column_index(sort.column, sort.descending);
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::data::Align;
+    use crate::data::Sizing;
+
+    #[test]
+    fn round_trip() {
+        let input = r#"{
+            "columns": [
+                {
+                    "meta": "ARTIST",
+                    "title": "Artist",
+                    "sizing": [4, null],
+                    "align": "right"
+                },
+                {
+                    "info": "file_name",
+                    "title": "File Name",
+                    "sizing": null
+                }
+            ],
+            "sort": { "column": 1, "descending": true }
+        }"#;
+
+        let preset = serde_json::from_str::<Preset>(input).unwrap();
+        let output = serde_json::to_string(&preset).unwrap();
+        let reparsed = serde_json::from_str::<Preset>(&output).unwrap();
+
+        assert_eq!(reparsed.columns.len(), 2);
+        assert_eq!(reparsed.sort, Some(SortSpec { column: 1, descending: true }));
+        assert!(matches!(reparsed.columns[0].sizing, Sizing::Lower(4)));
+        assert_eq!(reparsed.columns[0].align, Align::Right);
+        assert_eq!(reparsed.columns[1].align, Align::Left);
+    }
+}
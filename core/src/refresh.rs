@@ -0,0 +1,363 @@
+//! Reloading records from disk, and settling files that were changed both
+//! here and elsewhere.
+
+use std::collections::BTreeSet;
+use std::collections::HashSet;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use metaflac::Error as MetaflacError;
+
+use crate::data::Metadata;
+use crate::data::Record;
+use crate::data::Records;
+
+/// How to settle a file that has pending edits and was also changed on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Resolution {
+    /// Keeps the pending edits, writing them over the changes on disk.
+    KeepMine,
+    /// Drops the pending edits, taking the file as it is on disk.
+    TakeDisk,
+    /// Takes whichever side changed each field. For fields changed on both
+    /// sides, the pending edit wins only if its key is in this set.
+    Merge(HashSet<String>),
+}
+
+/// A field that was changed differently by a pending edit and on disk.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldConflict {
+    /// The metadata key of the field.
+    pub meta_key: String,
+    /// The values of the pending edit, or `None` if it removes the field.
+    pub mine: Option<Vec<String>>,
+    /// The values on disk, or `None` if the field is gone there.
+    pub theirs: Option<Vec<String>>,
+}
+
+/// A file with pending edits whose metadata on disk has changed since it was loaded.
+#[derive(Debug, Clone)]
+pub struct Conflict {
+    /// The file of the record.
+    pub file_path: PathBuf,
+    disk_metadata: Metadata,
+    disk_has_picture: bool,
+    disk_modified: Option<SystemTime>,
+}
+
+impl Conflict {
+    /// A conflict with the record as it was just read from disk.
+    pub fn from_disk(disk: Record) -> Self {
+        Self {
+            file_path: disk.file_path,
+            disk_metadata: disk.metadata,
+            disk_has_picture: disk.has_picture,
+            disk_modified: disk.modified,
+        }
+    }
+
+    fn all_keys<'a>(&'a self, record: &'a Record) -> BTreeSet<&'a str> {
+        record.metadata.keys()
+        .chain(record.edits.keys())
+        .chain(self.disk_metadata.keys())
+        .map(String::as_str)
+        .collect()
+    }
+
+    /// The fields that were changed on both sides to different values.
+    pub fn field_conflicts(&self, record: &Record) -> Vec<FieldConflict> {
+        self.all_keys(record).into_iter()
+        .filter_map(|meta_key| {
+            let base = record.metadata.get(meta_key).map(Vec::as_slice);
+            let mine = record.get_meta(meta_key);
+            let theirs = self.disk_metadata.get(meta_key).map(Vec::as_slice);
+
+            if mine == base || theirs == base || mine == theirs { return None; }
+
+            Some(FieldConflict {
+                meta_key: meta_key.to_string(),
+                mine: mine.map(<[String]>::to_vec),
+                theirs: theirs.map(<[String]>::to_vec),
+            })
+        })
+        .collect()
+    }
+
+    /// Replaces the loaded metadata of a record with what is on disk, and
+    /// stages whatever values the resolution picks as pending edits on top.
+    pub fn resolve(self, record: &mut Record, resolution: &Resolution) {
+        let resolved =
+            self.all_keys(record).into_iter()
+            .map(|meta_key| {
+                let base = record.metadata.get(meta_key).map(Vec::as_slice);
+                let mine = record.get_meta(meta_key);
+                let theirs = self.disk_metadata.get(meta_key).map(Vec::as_slice);
+
+                let value = match resolution {
+                    Resolution::KeepMine => mine,
+                    Resolution::TakeDisk => theirs,
+                    Resolution::Merge(..) if mine == base => theirs,
+                    Resolution::Merge(..) if theirs == base => mine,
+                    Resolution::Merge(keep_mine) if keep_mine.contains(meta_key) => mine,
+                    Resolution::Merge(..) => theirs,
+                };
+
+                (meta_key.to_string(), value.map(<[String]>::to_vec))
+            })
+            .collect::<Vec<_>>()
+        ;
+
+        record.metadata = self.disk_metadata;
+        record.has_picture = self.disk_has_picture;
+        record.modified = self.disk_modified;
+        record.edits.clear();
+
+        for (meta_key, values) in resolved {
+            record.set_meta(&meta_key, values);
+        }
+    }
+}
+
+/// What a refresh found changed on disk.
+#[derive(Debug, Default)]
+pub struct RefreshSummary {
+    /// How many records were updated in place.
+    pub updated: usize,
+    /// How many files were added.
+    pub added: usize,
+    /// How many records of files that are gone were dropped.
+    pub removed: usize,
+    /// Files that are gone from disk but have pending edits, which are kept
+    /// loaded so the edits are not silently lost.
+    pub removed_with_edits: Vec<PathBuf>,
+    /// Files that could not be read.
+    pub failures: Vec<(PathBuf, MetaflacError)>,
+    /// Files with pending edits that were also changed on disk, to be settled.
+    pub conflicts: Vec<Conflict>,
+}
+
+impl RefreshSummary {
+    /// Whether nothing was found to have changed on disk.
+    pub fn is_empty(&self) -> bool {
+        self.updated == 0
+        && self.added == 0
+        && self.removed == 0
+        && self.removed_with_edits.is_empty()
+        && self.failures.is_empty()
+        && self.conflicts.is_empty()
+    }
+}
+
+impl Display for RefreshSummary {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{} file(s) changed on disk", self.updated + self.conflicts.len())?;
+
+        if !self.conflicts.is_empty() {
+            write!(f, ", {} with pending edits", self.conflicts.len())?;
+        }
+
+        if self.added > 0 {
+            write!(f, ", {} added", self.added)?;
+        }
+
+        if self.removed > 0 {
+            write!(f, ", {} removed", self.removed)?;
+        }
+
+        if !self.removed_with_edits.is_empty() {
+            write!(f, ", {} removed despite pending edits", self.removed_with_edits.len())?;
+        }
+
+        if !self.failures.is_empty() {
+            write!(f, ", {} could not be read", self.failures.len())?;
+        }
+
+        Ok(())
+    }
+}
+
+/// Re-reads each record from disk, given the files that are there now.
+/// Records without pending edits are updated in place, while records with
+/// pending edits that also changed on disk are left untouched and reported as
+/// conflicts. New files are added at the end, and records of files that are
+/// gone are dropped unless they have pending edits. Records of files that are
+/// busy being written are left alone.
+pub fn refresh<F>(records: &mut Records, file_paths: &[PathBuf], busy: &HashSet<PathBuf>, read_record: F) -> RefreshSummary
+where
+    F: Fn(PathBuf) -> Result<Record, MetaflacError>,
+{
+    let mut summary = RefreshSummary::default();
+
+    let on_disk = file_paths.iter().collect::<HashSet<_>>();
+
+    records.retain(|record| {
+        if on_disk.contains(&record.file_path) { return true; }
+
+        if record.is_edited() {
+            summary.removed_with_edits.push(record.file_path.clone());
+            true
+        }
+        else {
+            summary.removed += 1;
+            false
+        }
+    });
+
+    let loaded = records.iter().map(|r| r.file_path.clone()).collect::<HashSet<_>>();
+
+    for record in records.iter_mut().filter(|r| on_disk.contains(&r.file_path) && !busy.contains(&r.file_path)) {
+        let disk = match read_record(record.file_path.clone()) {
+            Ok(disk) => disk,
+            Err(err) => {
+                summary.failures.push((record.file_path.clone(), err));
+                continue;
+            },
+        };
+
+        record.read_only = disk.read_only;
+
+        if disk.metadata == record.metadata {
+            record.has_picture = disk.has_picture;
+            record.modified = disk.modified;
+        }
+        else if record.is_edited() {
+            summary.conflicts.push(Conflict::from_disk(disk));
+        }
+        else {
+            record.metadata = disk.metadata;
+            record.has_picture = disk.has_picture;
+            record.modified = disk.modified;
+            summary.updated += 1;
+        }
+    }
+
+    for file_path in file_paths.iter().filter(|p| !loaded.contains(*p)) {
+        match read_record(file_path.clone()) {
+            Ok(record) => {
+                records.push(record);
+                summary.added += 1;
+            },
+            Err(err) => summary.failures.push((file_path.clone(), err)),
+        }
+    }
+
+    summary
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use maplit::hashset;
+    use str_macro::str;
+
+    fn loaded() -> Record {
+        Record::new(
+            hashmap! {
+                str!("ARTIST") => vec![str!("Foo")],
+                str!("TITLE") => vec![str!("Song")],
+                str!("GENRE") => vec![str!("Rock")],
+            },
+            PathBuf::from("a.flac"),
+        )
+    }
+
+    fn on_disk(_: PathBuf) -> Result<Record, MetaflacError> {
+        Ok(Record::new(
+            hashmap! {
+                str!("ARTIST") => vec![str!("Foo")],
+                str!("TITLE") => vec![str!("Song (Live)")],
+                str!("GENRE") => vec![str!("Jazz")],
+            },
+            PathBuf::from("a.flac"),
+        ))
+    }
+
+    #[test]
+    fn refresh_unedited() {
+        let mut records = vec![loaded()];
+
+        let summary = refresh(&mut records, &[PathBuf::from("a.flac")], &HashSet::new(), on_disk);
+
+        assert_eq!(summary.updated, 1);
+        assert!(summary.conflicts.is_empty());
+        assert_eq!(records[0].get_meta("GENRE"), Some(&[str!("Jazz")][..]));
+        assert!(!records[0].is_edited());
+    }
+
+    #[test]
+    fn added_and_removed() {
+        let mut records = vec![loaded(), Record::new(hashmap! {}, PathBuf::from("b.flac"))];
+
+        let mut edited = Record::new(hashmap! {}, PathBuf::from("c.flac"));
+        edited.set_meta("ARTIST", Some(vec![str!("Foo")]));
+        records.push(edited);
+
+        let read = |file_path: PathBuf| Ok(Record::new(hashmap! {}, file_path));
+        let summary = refresh(&mut records, &[PathBuf::from("a.flac"), PathBuf::from("d.flac")], &HashSet::new(), read);
+
+        assert_eq!(summary.added, 1);
+        assert_eq!(summary.removed, 1);
+        assert_eq!(summary.removed_with_edits, vec![PathBuf::from("c.flac")]);
+
+        let file_paths = records.iter().map(|r| r.file_path.to_str().unwrap()).collect::<Vec<_>>();
+        assert_eq!(file_paths, ["a.flac", "c.flac", "d.flac"]);
+    }
+
+    #[test]
+    fn resolve() {
+        let edited = || {
+            let mut record = loaded();
+            record.set_meta("ARTIST", Some(vec![str!("Bar")]));
+            record.set_meta("GENRE", Some(vec![str!("Pop")]));
+            record
+        };
+
+        let mut records = vec![edited()];
+        let mut summary = refresh(&mut records, &[PathBuf::from("a.flac")], &HashSet::new(), on_disk);
+
+        assert_eq!(summary.updated, 0);
+        assert_eq!(summary.conflicts.len(), 1);
+
+        // Conflicting records are left alone until resolved.
+        assert_eq!(records[0].get_meta("TITLE"), Some(&[str!("Song")][..]));
+
+        let conflict = summary.conflicts.remove(0);
+
+        assert_eq!(
+            conflict.field_conflicts(&records[0]),
+            vec![FieldConflict {
+                meta_key: str!("GENRE"),
+                mine: Some(vec![str!("Pop")]),
+                theirs: Some(vec![str!("Jazz")]),
+            }],
+        );
+
+        let mut record = edited();
+        conflict.clone().resolve(&mut record, &Resolution::KeepMine);
+        assert_eq!(record.get_meta("TITLE"), Some(&[str!("Song")][..]));
+        assert_eq!(record.get_meta("GENRE"), Some(&[str!("Pop")][..]));
+        assert_eq!(record.edits.len(), 3);
+
+        let mut record = edited();
+        conflict.clone().resolve(&mut record, &Resolution::TakeDisk);
+        assert_eq!(record.get_meta("ARTIST"), Some(&[str!("Foo")][..]));
+        assert!(!record.is_edited());
+
+        let mut record = edited();
+        conflict.clone().resolve(&mut record, &Resolution::Merge(HashSet::new()));
+        assert_eq!(record.get_meta("ARTIST"), Some(&[str!("Bar")][..]));
+        assert_eq!(record.get_meta("TITLE"), Some(&[str!("Song (Live)")][..]));
+        assert_eq!(record.get_meta("GENRE"), Some(&[str!("Jazz")][..]));
+        assert_eq!(record.edits.len(), 1);
+
+        let mut record = edited();
+        conflict.resolve(&mut record, &Resolution::Merge(hashset! { str!("GENRE") }));
+        assert_eq!(record.get_meta("GENRE"), Some(&[str!("Pop")][..]));
+        assert_eq!(record.edits.len(), 2);
+    }
+}
@@ -0,0 +1,93 @@
+//! Registers that yanked values are kept in, as in Vim.
+
+use std::collections::HashMap;
+
+/// The register used when no other register has been chosen.
+pub const UNNAMED_REGISTER: char = '"';
+
+/// Vim-like registers holding yanked cell values. A register can hold a
+/// missing value, which removes the field when put.
+#[derive(Debug, Default)]
+pub struct Registers {
+    contents: HashMap<char, Option<Vec<String>>>,
+    chosen: Option<char>,
+}
+
+impl Registers {
+    /// Empty registers, with none chosen.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Whether there is a register with a name: the unnamed register, or a
+    /// lowercase letter.
+    pub fn is_valid_name(name: char) -> bool {
+        name == UNNAMED_REGISTER || name.is_ascii_lowercase()
+    }
+
+    /// Chooses the register for the next yank or put, returning `false` if
+    /// there is no register with that name.
+    pub fn choose(&mut self, name: char) -> bool {
+        if Self::is_valid_name(name) {
+            self.chosen = Some(name);
+            true
+        }
+        else {
+            false
+        }
+    }
+
+    fn take_chosen(&mut self) -> char {
+        self.chosen.take().unwrap_or(UNNAMED_REGISTER)
+    }
+
+    /// Stores values in the chosen register, returning its name. As in Vim,
+    /// the unnamed register always receives the last yank as well.
+    pub fn yank(&mut self, values: Option<Vec<String>>) -> char {
+        let name = self.take_chosen();
+
+        if name != UNNAMED_REGISTER {
+            self.contents.insert(name, values.clone());
+        }
+
+        self.contents.insert(UNNAMED_REGISTER, values);
+
+        name
+    }
+
+    /// Reads the values in the chosen register, along with its name. Returns
+    /// `None` if nothing has been yanked into it.
+    pub fn put(&mut self) -> (char, Option<&Option<Vec<String>>>) {
+        let name = self.take_chosen();
+        (name, self.contents.get(&name))
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use str_macro::str;
+
+    #[test]
+    fn yank_and_put() {
+        let mut registers = Registers::new();
+
+        assert_eq!(registers.put(), ('"', None));
+
+        assert_eq!(registers.yank(Some(vec![str!("Foo")])), '"');
+
+        assert!(registers.choose('a'));
+        assert_eq!(registers.yank(Some(vec![str!("Bar"), str!("Baz")])), 'a');
+
+        assert!(!registers.choose('!'));
+        assert!(registers.choose('b'));
+        assert_eq!(registers.yank(None), 'b');
+
+        let bar_baz = Some(vec![str!("Bar"), str!("Baz")]);
+
+        assert!(registers.choose('a'));
+        assert_eq!(registers.put(), ('a', Some(&bar_baz)));
+        assert_eq!(registers.put(), ('"', Some(&None)));
+    }
+}
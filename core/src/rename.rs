@@ -0,0 +1,402 @@
+//! Renaming files after their tags, and reading tags from file names.
+
+use std::collections::HashSet;
+use std::path::Path;
+use std::path::PathBuf;
+use std::str::FromStr;
+
+use crate::data::Record;
+
+/// Characters that are not allowed in file names on at least one common file
+/// system, and are replaced when renaming.
+const INVALID_CHARS: &[char] = &['/', '\\', ':', '*', '?', '"', '<', '>', '|'];
+
+/// What multiple values of a field are joined with in file names.
+const VALUE_SEP_STR: &str = ", ";
+
+/// Metadata keys and the values read for them from a file name.
+pub type Fields = Vec<(String, String)>;
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+enum Part {
+    Literal(String),
+    Field(String),
+    /// Starts a new path component.
+    Separator,
+}
+
+/// A file name pattern with `{key}` placeholders for metadata values, such as
+/// `{tracknumber} - {artist} - {title}`. Keys are not case sensitive. A `/`
+/// puts files into subdirectories, as in `{albumartist}/{album}/{title}`.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Template {
+    parts: Vec<Part>,
+}
+
+impl FromStr for Template {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let mut parts = Vec::new();
+        let mut literal = String::new();
+        let mut chars = s.chars();
+
+        while let Some(c) = chars.next() {
+            match c {
+                '{' => {
+                    let mut meta_key = String::new();
+
+                    loop {
+                        match chars.next() {
+                            Some('}') => break,
+                            Some('{') | None => return Err(String::from("unclosed '{' in template")),
+                            Some(c) => meta_key.push(c),
+                        }
+                    }
+
+                    let meta_key = meta_key.trim().to_uppercase();
+
+                    if meta_key.is_empty() {
+                        return Err(String::from("empty '{}' in template"));
+                    }
+
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    parts.push(Part::Field(meta_key));
+                },
+                '}' => return Err(String::from("unmatched '}' in template")),
+                '/' => {
+                    if !literal.is_empty() {
+                        parts.push(Part::Literal(std::mem::take(&mut literal)));
+                    }
+
+                    parts.push(Part::Separator);
+                },
+                c => literal.push(c),
+            }
+        }
+
+        if !literal.is_empty() {
+            parts.push(Part::Literal(literal));
+        }
+
+        if parts.is_empty() {
+            return Err(String::from("template is empty"));
+        }
+
+        Ok(Self { parts })
+    }
+}
+
+impl Template {
+    /// Fills in the placeholders with the values of a record, leaving missing
+    /// fields empty. Returns the sanitized path components, the last of which
+    /// is the file name without its extension.
+    pub fn render(&self, record: &Record) -> Vec<String> {
+        let mut components = Vec::new();
+        let mut component = String::new();
+
+        for part in &self.parts {
+            match part {
+                Part::Literal(literal) => component.push_str(literal),
+                Part::Field(meta_key) => {
+                    let values = record.get_meta(meta_key).map(|vs| vs.join(VALUE_SEP_STR)).unwrap_or_default();
+                    component.push_str(&values);
+                },
+                Part::Separator => components.push(sanitize(&std::mem::take(&mut component))),
+            }
+        }
+
+        components.push(sanitize(&component));
+        components
+    }
+
+    /// Reads field values back out of the path of a file, by matching the
+    /// template against as many trailing path components as it has, without
+    /// the extension. Each field takes as little as it can while still
+    /// letting the rest match.
+    pub fn parse_path(&self, path: &Path) -> Option<Fields> {
+        let num_components = 1 + self.parts.iter().filter(|p| **p == Part::Separator).count();
+
+        let mut components =
+            path.with_extension("")
+            .iter()
+            .rev()
+            .take(num_components)
+            .map(|c| c.to_string_lossy().into_owned())
+            .collect::<Vec<_>>()
+        ;
+
+        if components.len() < num_components {
+            return None;
+        }
+
+        components.reverse();
+
+        let mut fields = Vec::new();
+
+        if match_parts(&self.parts, &components.join("/"), &mut fields) { Some(fields) } else { None }
+    }
+}
+
+fn match_parts(parts: &[Part], text: &str, fields: &mut Fields) -> bool {
+    let (part, rest) = match parts.split_first() {
+        Some(split) => split,
+        None => return text.is_empty(),
+    };
+
+    match part {
+        Part::Literal(literal) => text.strip_prefix(literal.as_str()).is_some_and(|text| match_parts(rest, text, fields)),
+        Part::Separator => text.strip_prefix('/').is_some_and(|text| match_parts(rest, text, fields)),
+        Part::Field(meta_key) => {
+            let ends = text.char_indices().map(|(i, _)| i).skip(1).chain(std::iter::once(text.len()));
+
+            for end in ends.filter(|&end| end > 0) {
+                let value = &text[..end];
+
+                if value.contains('/') {
+                    break;
+                }
+
+                fields.push((meta_key.clone(), value.trim().to_string()));
+
+                if match_parts(rest, &text[end..], fields) {
+                    return true;
+                }
+
+                fields.pop();
+            }
+
+            false
+        },
+    }
+}
+
+/// What the file name of a record says about its tags.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Inferred {
+    /// Values for the tags the record has no value for yet.
+    pub missing: Fields,
+    /// Keys of tags whose values differ from the file name, which are kept.
+    pub differing: Vec<String>,
+}
+
+/// Reads tags from the file name of a record. `None` if the name does not
+/// match the template.
+pub fn infer_tags(record: &Record, template: &Template) -> Option<Inferred> {
+    let mut inferred = Inferred { missing: Vec::new(), differing: Vec::new() };
+    let mut seen = HashSet::new();
+
+    for (meta_key, value) in template.parse_path(&record.file_path)? {
+        if value.is_empty() || !seen.insert(meta_key.clone()) {
+            continue;
+        }
+
+        match record.get_meta(&meta_key) {
+            None => inferred.missing.push((meta_key, value)),
+            Some(values) if values != [value] => inferred.differing.push(meta_key),
+            Some(_) => {},
+        }
+    }
+
+    Some(inferred)
+}
+
+/// Makes a string safe to use as a file name, by replacing characters that
+/// are not allowed and trimming ones that would hide or mangle the file.
+pub fn sanitize(name: &str) -> String {
+    let name =
+        name.chars()
+        .map(|c| if INVALID_CHARS.contains(&c) || c.is_control() { '_' } else { c })
+        .collect::<String>()
+    ;
+
+    let name = name.trim().trim_start_matches('.').trim_end_matches('.').trim();
+
+    if name.is_empty() { String::from("_") } else { name.to_string() }
+}
+
+/// Moving a file from one path to another.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Rename {
+    /// The current path of the file.
+    pub from: PathBuf,
+    /// The path to move the file to.
+    pub to: PathBuf,
+    /// Whether the path the template gave was taken, so a number was added.
+    pub collided: bool,
+}
+
+fn numbered(dir: &Path, stem: &str, extension: &str, n: usize) -> PathBuf {
+    let name = if n > 1 { format!("{} ({})", stem, n) } else { stem.to_string() };
+
+    if extension.is_empty() { dir.join(name) }
+    else { dir.join(format!("{}.{}", name, extension)) }
+}
+
+/// Works out the new path of the file of each record from a template, see
+/// `plan_with`.
+pub fn plan<'a, I, F>(records: I, template: &Template, exists: F) -> Vec<Rename>
+where
+    I: IntoIterator<Item = &'a Record>,
+    F: Fn(&Path) -> bool,
+{
+    plan_with(records, |record| template.render(record), exists)
+}
+
+/// Works out the new path of the file of each record from the path
+/// components that `name` gives for it, relative to the directory it is in
+/// now and with the same extension. Paths of other files that `exists`
+/// reports, or already picked for earlier records, get a number added. Files
+/// that keep their path are left out.
+pub fn plan_with<'a, I, N, F>(records: I, name: N, exists: F) -> Vec<Rename>
+where
+    I: IntoIterator<Item = &'a Record>,
+    N: Fn(&Record) -> Vec<String>,
+    F: Fn(&Path) -> bool,
+{
+    let records = records.into_iter().collect::<Vec<_>>();
+    let moving = records.iter().map(|r| &r.file_path).collect::<HashSet<_>>();
+
+    let mut picked = HashSet::new();
+    let is_taken = |path: &PathBuf, picked: &HashSet<PathBuf>| {
+        picked.contains(path) || (!moving.contains(path) && exists(path))
+    };
+
+    let mut renames = Vec::new();
+
+    for record in records {
+        let from = &record.file_path;
+        let extension = from.extension().map(|e| e.to_string_lossy().into_owned()).unwrap_or_default();
+
+        let mut components = name(record);
+        let stem = components.pop().unwrap_or_default();
+
+        let mut dir = from.parent().map(Path::to_path_buf).unwrap_or_default();
+        dir.extend(components);
+
+        let (n, to) =
+            (1..)
+            .map(|n| (n, numbered(&dir, &stem, &extension, n)))
+            .find(|(_, to)| !is_taken(to, &picked))
+            .unwrap()
+        ;
+
+        picked.insert(to.clone());
+
+        if &to != from {
+            renames.push(Rename { from: from.clone(), to, collided: n > 1 });
+        }
+    }
+
+    renames
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    fn record(file_path: &str, track: &str, title: &str) -> Record {
+        let metadata = hashmap! {
+            str!("TRACKNUMBER") => vec![track.to_string()],
+            str!("TITLE") => vec![title.to_string()],
+            str!("ARTIST") => vec![str!("Foo"), str!("Bar")],
+        };
+
+        Record::new(metadata, PathBuf::from(file_path))
+    }
+
+    #[test]
+    fn parse_and_render() {
+        let template = "{tracknumber} - {Artist} - {title}".parse::<Template>().unwrap();
+        assert_eq!(template.render(&record("a.flac", "01", "Song")), vec!["01 - Foo, Bar - Song"]);
+
+        let template = "{GENRE}!".parse::<Template>().unwrap();
+        assert_eq!(template.render(&record("a.flac", "01", "Song")), vec!["!"]);
+
+        // Values cannot add path components of their own.
+        let template = "{artist}/{title}".parse::<Template>().unwrap();
+        assert_eq!(template.render(&record("a.flac", "01", "AC/DC")), vec!["Foo, Bar", "AC_DC"]);
+
+        assert!("{title".parse::<Template>().is_err());
+        assert!("title}".parse::<Template>().is_err());
+        assert!("{ }".parse::<Template>().is_err());
+        assert!("".parse::<Template>().is_err());
+    }
+
+    #[test]
+    fn parsing_paths() {
+        let template = "{artist} - {album} - {tracknumber} - {title}".parse::<Template>().unwrap();
+
+        assert_eq!(
+            template.parse_path(Path::new("music/Foo - Bar - Baz - 01 - Song - Live.flac")).unwrap(),
+            vec![
+                (str!("ARTIST"), str!("Foo")),
+                (str!("ALBUM"), str!("Bar")),
+                (str!("TRACKNUMBER"), str!("Baz")),
+                (str!("TITLE"), str!("01 - Song - Live")),
+            ],
+        );
+
+        assert_eq!(template.parse_path(Path::new("music/01 Song.flac")), None);
+
+        let template = "{album}/{tracknumber} {title}".parse::<Template>().unwrap();
+
+        assert_eq!(
+            template.parse_path(Path::new("music/Bar/01 Song Name.flac")).unwrap(),
+            vec![(str!("ALBUM"), str!("Bar")), (str!("TRACKNUMBER"), str!("01")), (str!("TITLE"), str!("Song Name"))],
+        );
+
+        // Only missing tags are filled in.
+        let template = "{tracknumber} {title}".parse::<Template>().unwrap();
+        let mut record = record("music/07 Other Song.flac", "01", "Song");
+        record.set_meta("TRACKNUMBER", None);
+
+        assert_eq!(
+            infer_tags(&record, &template),
+            Some(Inferred { missing: vec![(str!("TRACKNUMBER"), str!("07"))], differing: vec![str!("TITLE")] }),
+        );
+    }
+
+    #[test]
+    fn sanitizing() {
+        assert_eq!(sanitize("AC/DC: Back in Black?"), "AC_DC_ Back in Black_");
+        assert_eq!(sanitize(" ..hidden. "), "hidden");
+        assert_eq!(sanitize("..."), "_");
+    }
+
+    #[test]
+    fn planning() {
+        let template = "{tracknumber} {title}".parse::<Template>().unwrap();
+
+        let records = vec![
+            record("music/a.flac", "01", "Song"),
+            record("music/b.flac", "02", "Song"),
+            record("music/c.flac", "02", "Song"),
+            record("music/01 Intro.flac", "01", "Intro"),
+        ];
+
+        let exists = |path: &Path| path == Path::new("music/02 Song.flac");
+
+        assert_eq!(
+            plan(&records, &template, exists),
+            vec![
+                Rename { from: PathBuf::from("music/a.flac"), to: PathBuf::from("music/01 Song.flac"), collided: false },
+                Rename { from: PathBuf::from("music/b.flac"), to: PathBuf::from("music/02 Song (2).flac"), collided: true },
+                Rename { from: PathBuf::from("music/c.flac"), to: PathBuf::from("music/02 Song (3).flac"), collided: true },
+            ],
+        );
+
+        let template = "{artist}/{tracknumber} {title}".parse::<Template>().unwrap();
+
+        assert_eq!(
+            plan(&records[..1], &template, exists),
+            vec![Rename { from: PathBuf::from("music/a.flac"), to: PathBuf::from("music/Foo, Bar/01 Song.flac"), collided: false }],
+        );
+    }
+}
@@ -0,0 +1,483 @@
+//! Working out ReplayGain tags from the loudness of tracks and albums.
+
+use std::collections::VecDeque;
+use std::f64::consts::PI;
+use std::io::BufReader;
+use std::io::Read;
+use std::path::Path;
+use std::process::Command as Process;
+use std::process::Stdio;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+/// The gain that brings a track to the reference loudness.
+pub const TRACK_GAIN_KEY: &str = "REPLAYGAIN_TRACK_GAIN";
+/// The loudest sample of a track, where 1 is full scale.
+pub const TRACK_PEAK_KEY: &str = "REPLAYGAIN_TRACK_PEAK";
+/// The gain that brings an album to the reference loudness.
+pub const ALBUM_GAIN_KEY: &str = "REPLAYGAIN_ALBUM_GAIN";
+/// The loudest sample of an album, where 1 is full scale.
+pub const ALBUM_PEAK_KEY: &str = "REPLAYGAIN_ALBUM_PEAK";
+
+/// The loudness that ReplayGain 2.0 brings tracks to, in LUFS.
+const REFERENCE_LUFS: f64 = -18.0;
+
+/// Blocks quieter than this are silence, and left out of the loudness.
+const ABSOLUTE_GATE_LUFS: f64 = -70.0;
+
+/// Blocks this much quieter than the loudness of the louder blocks are left out.
+const RELATIVE_GATE_LU: f64 = -10.0;
+
+/// Loudness is measured over 400 ms blocks, which start every 100 ms.
+const STEPS_PER_SECOND: f64 = 10.0;
+const STEPS_PER_BLOCK: usize = 4;
+
+/// The most frames decoded at once.
+const FRAMES_PER_READ: usize = 4096;
+
+const CANCELLED_STR: &str = "cancelled";
+
+/// A second order IIR filter, with coefficients normalized so that `a[0]` is 1.
+#[derive(Debug, Clone, Copy)]
+struct Biquad {
+    b: [f64; 3],
+    a: [f64; 3],
+}
+
+impl Biquad {
+    /// Filters one sample, keeping the filter state of a channel in `state`.
+    fn process(&self, state: &mut [f64; 2], x: f64) -> f64 {
+        let y = self.b[0] * x + state[0];
+        state[0] = self.b[1] * x - self.a[1] * y + state[1];
+        state[1] = self.b[2] * x - self.a[2] * y;
+        y
+    }
+}
+
+/// The two stages of the K-weighting filter from ITU-R BS.1770, a high shelf
+/// modelling the head, then a high pass, worked out for a sample rate.
+fn k_weighting(sample_rate: f64) -> [Biquad; 2] {
+    let (f0, gain_db, q) = (1681.974450955533, 3.999843853973347, 0.7071752369554196);
+    let k = (PI * f0 / sample_rate).tan();
+    let vh = 10f64.powf(gain_db / 20.0);
+    let vb = vh.powf(0.4996667741545416);
+    let a0 = 1.0 + k / q + k * k;
+
+    let shelf = Biquad {
+        b: [(vh + vb * k / q + k * k) / a0, 2.0 * (k * k - vh) / a0, (vh - vb * k / q + k * k) / a0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    };
+
+    let (f0, q) = (38.13547087602444, 0.5003270373238773);
+    let k = (PI * f0 / sample_rate).tan();
+    let a0 = 1.0 + k / q + k * k;
+
+    let high_pass = Biquad {
+        b: [1.0, -2.0, 1.0],
+        a: [1.0, 2.0 * (k * k - 1.0) / a0, (1.0 - k / q + k * k) / a0],
+    };
+
+    [shelf, high_pass]
+}
+
+/// How much each channel counts towards loudness. Surround channels count
+/// for more, and the LFE channel of 5.1 audio not at all.
+fn channel_weights(channels: usize) -> Vec<f64> {
+    match channels {
+        5 => vec![1.0, 1.0, 1.0, 1.41, 1.41],
+        6 => vec![1.0, 1.0, 1.0, 0.0, 1.41, 1.41],
+        n => vec![1.0; n],
+    }
+}
+
+fn to_lufs(mean_square: f64) -> f64 {
+    -0.691 + 10.0 * mean_square.log10()
+}
+
+fn mean(values: &[f64]) -> f64 {
+    values.iter().sum::<f64>() / values.len() as f64
+}
+
+/// The measured loudness of a track: the weighted mean square of each of its
+/// blocks, and its sample peak.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Loudness {
+    /// The weighted mean square of each block.
+    pub blocks: Vec<f64>,
+    /// The loudest sample, where 1 is full scale.
+    pub peak: f64,
+}
+
+impl Loudness {
+    /// The loudness of several tracks played one after another, as for an
+    /// album.
+    pub fn combine<'a>(tracks: impl IntoIterator<Item = &'a Self>) -> Self {
+        tracks.into_iter().fold(Self::default(), |mut combined, track| {
+            combined.blocks.extend_from_slice(&track.blocks);
+            combined.peak = combined.peak.max(track.peak);
+            combined
+        })
+    }
+
+    /// The gated loudness in LUFS, or `None` if there is nothing louder than
+    /// silence.
+    pub fn integrated(&self) -> Option<f64> {
+        let loud = self.blocks.iter().copied().filter(|&z| to_lufs(z) > ABSOLUTE_GATE_LUFS).collect::<Vec<_>>();
+
+        if loud.is_empty() {
+            return None;
+        }
+
+        let relative_gate = to_lufs(mean(&loud)) + RELATIVE_GATE_LU;
+        let gated = loud.into_iter().filter(|&z| to_lufs(z) > relative_gate).collect::<Vec<_>>();
+
+        Some(to_lufs(mean(&gated)))
+    }
+
+    /// The gain in dB that brings this to the reference loudness.
+    pub fn gain(&self) -> Option<f64> {
+        self.integrated().map(|lufs| REFERENCE_LUFS - lufs)
+    }
+}
+
+/// Measures loudness from frames of samples, one sample per channel, between
+/// -1.0 and 1.0.
+pub struct Analyzer {
+    filters: [Biquad; 2],
+    /// The filter state of each stage, for each channel.
+    states: Vec<[[f64; 2]; 2]>,
+    weights: Vec<f64>,
+
+    step_len: usize,
+    step_frames: usize,
+    step_sums: Vec<f64>,
+    /// The weighted mean squares of the latest steps, enough for a block.
+    steps: VecDeque<f64>,
+
+    loudness: Loudness,
+}
+
+impl Analyzer {
+    /// An analyzer for audio with a sample rate and number of channels.
+    pub fn new(sample_rate: u32, channels: usize) -> Self {
+        Self {
+            filters: k_weighting(sample_rate as f64),
+            states: vec![[[0.0; 2]; 2]; channels],
+            weights: channel_weights(channels),
+            step_len: ((sample_rate as f64 / STEPS_PER_SECOND).round() as usize).max(1),
+            step_frames: 0,
+            step_sums: vec![0.0; channels],
+            steps: VecDeque::with_capacity(STEPS_PER_BLOCK),
+            loudness: Loudness::default(),
+        }
+    }
+
+    /// Adds one sample for each channel.
+    pub fn add_frame(&mut self, frame: &[f64]) {
+        for (c, &sample) in frame.iter().enumerate().take(self.states.len()) {
+            self.loudness.peak = self.loudness.peak.max(sample.abs());
+
+            let [shelf_state, high_pass_state] = &mut self.states[c];
+            let filtered = self.filters[1].process(high_pass_state, self.filters[0].process(shelf_state, sample));
+
+            self.step_sums[c] += filtered * filtered;
+        }
+
+        self.step_frames += 1;
+
+        if self.step_frames == self.step_len {
+            let step_len = self.step_len as f64;
+            let step = self.step_sums.iter().zip(&self.weights).map(|(sum, w)| w * sum / step_len).sum();
+
+            if self.steps.len() == STEPS_PER_BLOCK {
+                self.steps.pop_front();
+            }
+            self.steps.push_back(step);
+
+            if self.steps.len() == STEPS_PER_BLOCK {
+                self.loudness.blocks.push(self.steps.iter().sum::<f64>() / STEPS_PER_BLOCK as f64);
+            }
+
+            self.step_frames = 0;
+            self.step_sums.iter_mut().for_each(|sum| *sum = 0.0);
+        }
+    }
+
+    /// The loudness of the frames so far. A last partial step is left out.
+    pub fn finish(self) -> Loudness {
+        self.loudness
+    }
+}
+
+fn read_u16(bytes: &[u8], at: usize) -> u16 {
+    u16::from_le_bytes([bytes[at], bytes[at + 1]])
+}
+
+fn read_u32(bytes: &[u8], at: usize) -> u32 {
+    u32::from_le_bytes([bytes[at], bytes[at + 1], bytes[at + 2], bytes[at + 3]])
+}
+
+/// Reads until the buffer is full or there is nothing left, returning how
+/// much was read.
+fn read_full<R: Read>(reader: &mut R, buf: &mut [u8]) -> Result<usize, String> {
+    let mut filled = 0;
+
+    while filled < buf.len() {
+        match reader.read(&mut buf[filled..]).map_err(|err| err.to_string())? {
+            0 => break,
+            n => filled += n,
+        }
+    }
+
+    Ok(filled)
+}
+
+/// Turns a little-endian integer PCM sample into one between -1.0 and 1.0.
+/// 8-bit samples are unsigned, wider ones signed.
+fn to_sample(bytes: &[u8]) -> f64 {
+    if bytes.len() == 1 {
+        return (bytes[0] as f64 - 128.0) / 128.0;
+    }
+
+    // Shifting into the top of an `i32` sign extends, whatever the width.
+    let shift = 32 - 8 * bytes.len();
+    let value = bytes.iter().enumerate().fold(0i32, |value, (i, &b)| value | ((b as i32) << (8 * i + shift)));
+
+    value as f64 / 2f64.powi(31)
+}
+
+/// Measures the loudness of integer PCM audio in a WAV stream. Stops early
+/// with an error if cancelled.
+pub fn analyze_wav<R: Read>(mut reader: R, cancelled: &AtomicBool) -> Result<Loudness, String> {
+    let mut header = [0u8; 12];
+    if read_full(&mut reader, &mut header)? < header.len() || &header[0..4] != b"RIFF" || &header[8..12] != b"WAVE" {
+        return Err(String::from("not a WAV stream"));
+    }
+
+    let mut format = None;
+
+    let data_len = loop {
+        let mut chunk = [0u8; 8];
+        if read_full(&mut reader, &mut chunk)? < chunk.len() {
+            return Err(String::from("no audio data"));
+        }
+
+        let len = read_u32(&chunk, 4);
+
+        if &chunk[0..4] == b"data" {
+            break len;
+        }
+
+        // Chunks are padded to an even length.
+        let mut body = vec![0u8; len as usize + (len as usize & 1)];
+        read_full(&mut reader, &mut body)?;
+
+        if &chunk[0..4] == b"fmt " && body.len() >= 16 {
+            let is_extensible = read_u16(&body, 0) == 0xFFFE;
+            let tag = if is_extensible && body.len() >= 26 { read_u16(&body, 24) } else { read_u16(&body, 0) };
+
+            if tag != 1 {
+                return Err(String::from("not integer PCM audio"));
+            }
+
+            format = Some((read_u16(&body, 2) as usize, read_u32(&body, 4), (read_u16(&body, 14) as usize).div_ceil(8)));
+        }
+    };
+
+    let (channels, sample_rate, sample_bytes) = match format {
+        Some(format) if format.0 > 0 && format.1 > 0 && (1..=4).contains(&format.2) => format,
+        _ => return Err(String::from("unsupported audio format")),
+    };
+
+    // Streams of unknown length say they have no data, or as much as can be said.
+    let mut reader: Box<dyn Read> = match data_len {
+        0 | u32::MAX => Box::new(reader),
+        len => Box::new(reader.take(len as u64)),
+    };
+
+    let frame_bytes = channels * sample_bytes;
+    let mut buf = vec![0u8; frame_bytes * FRAMES_PER_READ];
+    let mut frame = vec![0.0; channels];
+    let mut analyzer = Analyzer::new(sample_rate, channels);
+
+    loop {
+        if cancelled.load(Ordering::Relaxed) {
+            return Err(String::from(CANCELLED_STR));
+        }
+
+        let filled = read_full(&mut reader, &mut buf)?;
+
+        for frame_buf in buf[..filled].chunks_exact(frame_bytes) {
+            for (sample, bytes) in frame.iter_mut().zip(frame_buf.chunks_exact(sample_bytes)) {
+                *sample = to_sample(bytes);
+            }
+
+            analyzer.add_frame(&frame);
+        }
+
+        if filled < buf.len() {
+            break;
+        }
+    }
+
+    Ok(analyzer.finish())
+}
+
+/// Measures the loudness of a FLAC file, decoding it with the `flac` command.
+pub fn analyze_file(path: &Path, cancelled: &AtomicBool) -> Result<Loudness, String> {
+    let mut child =
+        Process::new("flac")
+        .args(["--decode", "--stdout", "--silent"])
+        .arg(path)
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .map_err(|err| format!("unable to run flac: {}", err))?
+    ;
+
+    let stdout = child.stdout.take().ok_or_else(|| String::from("unable to read from flac"))?;
+    let result = analyze_wav(BufReader::new(stdout), cancelled);
+
+    if result.is_err() {
+        child.kill().ok();
+    }
+
+    let output = child.wait_with_output().map_err(|err| err.to_string())?;
+    let stderr = String::from_utf8_lossy(&output.stderr).trim().to_string();
+
+    match result {
+        Err(err) if err == CANCELLED_STR || stderr.is_empty() => Err(err),
+        Ok(loudness) if output.status.success() => Ok(loudness),
+        _ => Err(stderr),
+    }
+}
+
+/// Writes out a gain as in `-6.50 dB`.
+pub fn format_gain(gain: f64) -> String {
+    format!("{:+.2} dB", gain)
+}
+
+/// Writes out a peak with six decimal places.
+pub fn format_peak(peak: f64) -> String {
+    format!("{:.6}", peak)
+}
+
+/// The ReplayGain tags for a track, and for its album if it is on one. Gains
+/// are left out for silence.
+pub fn tags(track: &Loudness, album: Option<&Loudness>) -> Vec<(&'static str, String)> {
+    let mut tags = Vec::new();
+
+    if let Some(gain) = track.gain() {
+        tags.push((TRACK_GAIN_KEY, format_gain(gain)));
+    }
+    tags.push((TRACK_PEAK_KEY, format_peak(track.peak)));
+
+    if let Some(album) = album {
+        if let Some(gain) = album.gain() {
+            tags.push((ALBUM_GAIN_KEY, format_gain(gain)));
+        }
+        tags.push((ALBUM_PEAK_KEY, format_peak(album.peak)));
+    }
+
+    tags
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    const RATE: u32 = 48000;
+
+    /// Seconds of a 1 kHz sine wave, in the given channels of stereo audio.
+    fn sine(amplitude: f64, seconds: f64, channels: [bool; 2]) -> Loudness {
+        let mut analyzer = Analyzer::new(RATE, 2);
+
+        for i in 0..(seconds * RATE as f64) as usize {
+            let sample = amplitude * (2.0 * PI * 1000.0 * i as f64 / RATE as f64).sin();
+            let frame = channels.map(|on| if on { sample } else { 0.0 });
+
+            analyzer.add_frame(&frame);
+        }
+
+        analyzer.finish()
+    }
+
+    fn assert_close(a: f64, b: f64) {
+        assert!((a - b).abs() < 0.05, "{} is not close to {}", a, b);
+    }
+
+    #[test]
+    fn filters() {
+        // The coefficients given for 48 kHz in BS.1770.
+        let [shelf, high_pass] = k_weighting(48000.0);
+
+        for (a, b) in shelf.b.iter().zip(&[1.53512485958697, -2.69169618940638, 1.19839281085285]) { assert_close(*a, *b); }
+        for (a, b) in shelf.a.iter().zip(&[1.0, -1.69065929318241, 0.73248077421585]) { assert_close(*a, *b); }
+        for (a, b) in high_pass.a.iter().zip(&[1.0, -1.99004745483398, 0.99007225036621]) { assert_close(*a, *b); }
+    }
+
+    #[test]
+    fn loudness() {
+        // A full scale 1 kHz sine in one channel is -3.01 LUFS, by definition.
+        let left = sine(1.0, 2.0, [true, false]);
+        assert_close(left.integrated().unwrap(), -3.01);
+        assert_close(left.peak, 1.0);
+        assert_eq!(left.blocks.len(), 17);
+
+        let half = sine(0.5, 2.0, [true, true]);
+        assert_close(half.integrated().unwrap(), -6.03);
+        assert_close(half.gain().unwrap(), -11.97);
+
+        assert_eq!(sine(1.0, 0.3, [true, true]).integrated(), None);
+        assert_eq!(sine(0.0, 1.0, [true, true]).integrated(), None);
+
+        // Quiet passages do not drag the album loudness down.
+        let silence = sine(0.0, 5.0, [true, true]);
+        let album = Loudness::combine(vec![&half, &silence]);
+        assert_close(album.integrated().unwrap(), -6.03);
+        assert_close(album.peak, 0.5);
+
+        assert_eq!(
+            tags(&half, Some(&album)).into_iter().map(|(k, _)| k).collect::<Vec<_>>(),
+            vec![TRACK_GAIN_KEY, TRACK_PEAK_KEY, ALBUM_GAIN_KEY, ALBUM_PEAK_KEY],
+        );
+        assert_eq!(tags(&silence, None), vec![(TRACK_PEAK_KEY, String::from("0.000000"))]);
+        assert_eq!(format_gain(2.345), "+2.35 dB");
+        assert_eq!(format_gain(-11.971), "-11.97 dB");
+    }
+
+    #[test]
+    fn wav() {
+        let frames = RATE as usize;
+        let mut wav = Vec::new();
+
+        wav.extend_from_slice(b"RIFF\0\0\0\0WAVE");
+        wav.extend_from_slice(b"fmt \x10\0\0\0");
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&1u16.to_le_bytes());
+        wav.extend_from_slice(&RATE.to_le_bytes());
+        wav.extend_from_slice(&(RATE * 2).to_le_bytes());
+        wav.extend_from_slice(&2u16.to_le_bytes());
+        wav.extend_from_slice(&16u16.to_le_bytes());
+        wav.extend_from_slice(b"LIST\x03\0\0\0abc\0");
+        wav.extend_from_slice(b"data");
+        wav.extend_from_slice(&(frames as u32 * 2).to_le_bytes());
+
+        for i in 0..frames {
+            let sample = (16384.0 * (2.0 * PI * 1000.0 * i as f64 / RATE as f64).sin()) as i16;
+            wav.extend_from_slice(&sample.to_le_bytes());
+        }
+
+        let loudness = analyze_wav(&wav[..], &AtomicBool::new(false)).unwrap();
+        assert_close(loudness.integrated().unwrap(), -9.03);
+        assert_close(loudness.peak, 0.5);
+
+        assert_eq!(analyze_wav(&wav[..], &AtomicBool::new(true)), Err(String::from(CANCELLED_STR)));
+        assert!(analyze_wav(&b"RIFX"[..], &AtomicBool::new(false)).is_err());
+
+        assert_eq!(to_sample(&[0x00, 0x80]), -1.0);
+        assert_eq!(to_sample(&[0x00, 0x00, 0x40]), 0.5);
+        assert_eq!(to_sample(&[0x80]), 0.0);
+    }
+}
@@ -0,0 +1,150 @@
+//! Rhai scripts that transform the tags of records.
+
+use std::collections::BTreeSet;
+use std::collections::HashMap;
+
+use rhai::Array;
+use rhai::Dynamic;
+use rhai::Engine;
+use rhai::Map;
+use rhai::Scope;
+use rhai::AST;
+
+use crate::data::Record;
+
+/// The most steps a script may take on one record, so that a script stuck
+/// in a loop fails instead of hanging the UI.
+const MAX_OPERATIONS: u64 = 1_000_000;
+
+/// The changes a script made to the tags of a record, with `None` removing
+/// a tag.
+pub type TagChanges = Vec<(String, Option<Vec<String>>)>;
+
+/// A Rhai script that transforms the tags of records, such as
+/// `let a = tags.ARTIST; tags.ARTIST = tags.TITLE; tags.TITLE = a;`.
+///
+/// Scripts see the tags of a record as the map `tags`, keyed by upper case
+/// tag name, along with the `path` and `file_name` of its file. Tags with one
+/// value are strings and tags with more are arrays of strings. Setting a tag
+/// to `()` or removing it from the map removes the tag.
+pub struct Script {
+    engine: Engine,
+    ast: AST,
+}
+
+impl Script {
+    /// Compiles a script, returning the error if it does not parse.
+    pub fn compile(source: &str) -> Result<Self, String> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+
+        let ast = engine.compile(source).map_err(|err| err.to_string())?;
+
+        Ok(Self { engine, ast })
+    }
+
+    fn to_dynamic(values: &[String]) -> Dynamic {
+        match values {
+            [value] => Dynamic::from(value.clone()),
+            values => Dynamic::from_array(values.iter().cloned().map(Dynamic::from).collect()),
+        }
+    }
+
+    fn from_dynamic(value: Dynamic) -> Option<Vec<String>> {
+        if value.is_unit() {
+            return None;
+        }
+
+        if value.is_array() {
+            Some(value.cast::<Array>().into_iter().map(|v| v.to_string()).collect())
+        }
+        else {
+            Some(vec![value.to_string()])
+        }
+    }
+
+    /// Runs the script on the tags of a record, returning the tags it changed.
+    pub fn run(&self, record: &Record) -> Result<TagChanges, String> {
+        let tags =
+            record.meta_keys()
+            .map(|meta_key| (meta_key.into(), Self::to_dynamic(record.get_meta(meta_key).unwrap_or_default())))
+            .collect::<Map>()
+        ;
+
+        let mut scope = Scope::new();
+        scope.push("tags", tags);
+        scope.push_constant("path", record.file_path.to_string_lossy().into_owned());
+        scope.push_constant("file_name", record.file_path.file_name().unwrap_or_default().to_string_lossy().into_owned());
+
+        self.engine.run_ast_with_scope(&mut scope, &self.ast).map_err(|err| err.to_string())?;
+
+        let mut tags =
+            scope.get_value::<Map>("tags").ok_or_else(|| String::from("`tags` is no longer a map"))?
+            .into_iter()
+            .map(|(meta_key, value)| (meta_key.to_uppercase(), value))
+            .collect::<HashMap<_, _>>()
+        ;
+
+        let meta_keys = record.meta_keys().map(String::from).chain(tags.keys().cloned()).collect::<BTreeSet<_>>();
+
+        let changes =
+            meta_keys.into_iter()
+            .filter_map(|meta_key| {
+                let values = tags.remove(&meta_key).and_then(Self::from_dynamic).filter(|vs| !vs.is_empty());
+
+                if record.get_meta(&meta_key) == values.as_deref() { None } else { Some((meta_key, values)) }
+            })
+            .collect()
+        ;
+
+        Ok(changes)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::path::PathBuf;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    #[test]
+    fn running() {
+        let record = Record::new(
+            hashmap! {
+                str!("ARTIST") => vec![str!("Song Title")],
+                str!("TITLE") => vec![str!("The Artist")],
+                str!("ALBUM") => vec![str!("Album [CAT-001]")],
+                str!("GENRE") => vec![str!("Rock"), str!("Pop")],
+            },
+            PathBuf::from("/music/01.flac"),
+        );
+
+        let run = |source: &str| Script::compile(source).unwrap().run(&record);
+
+        assert_eq!(
+            run("let a = tags.ARTIST; tags.ARTIST = tags.TITLE; tags.TITLE = a;"),
+            Ok(vec![(str!("ARTIST"), Some(vec![str!("The Artist")])), (str!("TITLE"), Some(vec![str!("Song Title")]))]),
+        );
+        assert_eq!(
+            run(r#"let i = tags.ALBUM.index_of(" ["); if i >= 0 { tags.ALBUM.truncate(i); }"#),
+            Ok(vec![(str!("ALBUM"), Some(vec![str!("Album")]))]),
+        );
+        assert_eq!(
+            run("tags.GENRE.push(\"Jazz\"); tags.remove(\"ARTIST\"); tags.comment = file_name; tags.TITLE = ();"),
+            Ok(vec![
+                (str!("ARTIST"), None),
+                (str!("COMMENT"), Some(vec![str!("01.flac")])),
+                (str!("GENRE"), Some(vec![str!("Rock"), str!("Pop"), str!("Jazz")])),
+                (str!("TITLE"), None),
+            ]),
+        );
+        assert_eq!(run("tags.GENRE = tags.GENRE;"), Ok(vec![]));
+
+        assert!(run("tags = 5;").is_err());
+        assert!(run("loop {}").is_err());
+        assert!(Script::compile("let = ;").is_err());
+    }
+}
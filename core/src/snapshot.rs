@@ -0,0 +1,392 @@
+//! Snapshots of the tags of a library, to compare against or restore later.
+
+use std::collections::BTreeMap;
+use std::collections::BTreeSet;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::fs::File;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Error as IoError;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+use serde_json::Error as JsonError;
+
+use crate::data::Record;
+use crate::data::Records;
+use crate::import::ImportError;
+use crate::import::TagImport;
+
+/// What can go wrong reading or writing a snapshot file.
+#[derive(Debug)]
+pub enum SnapshotError {
+    /// The file could not be read or written.
+    Io(IoError),
+    /// The file is not a valid JSON snapshot.
+    Json(JsonError),
+    /// The file is not a valid CSV export.
+    Csv(ImportError),
+}
+
+impl Display for SnapshotError {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Io(err) => write!(f, "{}", err),
+            Self::Json(err) => write!(f, "invalid snapshot: {}", err),
+            Self::Csv(err) => write!(f, "{}", err),
+        }
+    }
+}
+
+impl From<IoError> for SnapshotError {
+    fn from(err: IoError) -> Self {
+        Self::Io(err)
+    }
+}
+
+impl From<JsonError> for SnapshotError {
+    fn from(err: JsonError) -> Self {
+        Self::Json(err)
+    }
+}
+
+impl From<ImportError> for SnapshotError {
+    fn from(err: ImportError) -> Self {
+        Self::Csv(err)
+    }
+}
+
+impl From<csv::Error> for SnapshotError {
+    fn from(err: csv::Error) -> Self {
+        Self::Csv(ImportError::Csv(err))
+    }
+}
+
+/// The metadata of a single file, as captured in a snapshot.
+/// Keys are kept sorted so that snapshot files are stable and diffable.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct SnapshotEntry {
+    /// The path of the file.
+    pub file_path: PathBuf,
+    /// The values of each metadata key.
+    pub metadata: BTreeMap<String, Vec<String>>,
+}
+
+impl SnapshotEntry {
+    /// Checks if this entry is for the file of a record. Relative paths, such
+    /// as bare file names from a CSV export, match against trailing components.
+    pub fn matches(&self, record: &Record) -> bool {
+        record.file_path.ends_with(&self.file_path)
+    }
+}
+
+/// A difference in the values of a single metadata key for a file, between a
+/// snapshot and the current state of that file.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FieldDiff {
+    /// The path of the file.
+    pub file_path: PathBuf,
+    /// The metadata key of the field.
+    pub meta_key: String,
+    /// The values in the snapshot, or `None` if the field was missing.
+    pub snapshot: Option<Vec<String>>,
+    /// The values now, or `None` if the field is missing.
+    pub current: Option<Vec<String>>,
+}
+
+/// The result of comparing a snapshot against the current set of records.
+#[derive(Debug, Default)]
+pub struct Comparison {
+    /// Files that are loaded now, but are not in the snapshot.
+    pub added: Vec<PathBuf>,
+
+    /// Files that are in the snapshot, but are not loaded now.
+    pub removed: Vec<PathBuf>,
+
+    /// Fields whose values are not the same now as in the snapshot.
+    pub changed: Vec<FieldDiff>,
+}
+
+impl Comparison {
+    /// Whether nothing changed since the snapshot.
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.changed.is_empty()
+    }
+}
+
+/// The full tag state of a set of files at a point in time, including any
+/// edits that were pending at the time it was taken.
+#[derive(Debug, Deserialize, Serialize)]
+pub struct Snapshot {
+    /// The files in the snapshot.
+    pub entries: Vec<SnapshotEntry>,
+
+    /// If provided, only these metadata keys were captured, such as when
+    /// reading an export that only contains some columns.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub meta_keys: Option<BTreeSet<String>>,
+}
+
+fn is_csv_path(path: &Path) -> bool {
+    path.extension().is_some_and(|ext| ext.eq_ignore_ascii_case("csv"))
+}
+
+impl Snapshot {
+    /// Captures the tags of records, pending edits included.
+    pub fn from_records(records: &[Record]) -> Self {
+        let entries =
+            records.iter()
+            .map(|record| {
+                let metadata =
+                    record.meta_keys()
+                    .filter_map(|k| Some((k.to_string(), record.get_meta(k)?.to_vec())))
+                    .collect()
+                ;
+
+                SnapshotEntry { file_path: record.file_path.clone(), metadata }
+            })
+            .collect()
+        ;
+
+        Self { entries, meta_keys: None }
+    }
+
+    /// Reads a snapshot from either a JSON snapshot file or a CSV export,
+    /// detected by the file extension.
+    pub fn read_from_path(path: &Path) -> Result<Self, SnapshotError> {
+        if is_csv_path(path) {
+            return Ok(TagImport::read_from_path(path)?.into_snapshot());
+        }
+
+        let reader = BufReader::new(File::open(path)?);
+        let snapshot = serde_json::from_reader(reader)?;
+        Ok(snapshot)
+    }
+
+    /// Writes a snapshot as either JSON or CSV, detected by the file extension.
+    pub fn write_to_path(&self, path: &Path) -> Result<(), SnapshotError> {
+        let writer = BufWriter::new(File::create(path)?);
+
+        if is_csv_path(path) {
+            self.write_csv(writer)
+        } else {
+            serde_json::to_writer_pretty(writer, self)?;
+            Ok(())
+        }
+    }
+
+    /// Writes the snapshot in the same CSV format that is read by `TagImport`,
+    /// repeating the column of a key once for each of its values.
+    fn write_csv<W: Write>(&self, writer: W) -> Result<(), SnapshotError> {
+        let mut max_counts: BTreeMap<&str, usize> = BTreeMap::new();
+
+        for entry in self.entries.iter() {
+            for (meta_key, values) in entry.metadata.iter() {
+                let max_count = max_counts.entry(meta_key).or_default();
+                *max_count = values.len().max(*max_count);
+            }
+        }
+
+        let mut writer = csv::Writer::from_writer(writer);
+
+        let mut header = vec!["file_path"];
+        for (meta_key, max_count) in max_counts.iter() {
+            header.extend(std::iter::repeat_n(*meta_key, *max_count));
+        }
+        writer.write_record(&header)?;
+
+        for entry in self.entries.iter() {
+            let mut row = vec![entry.file_path.to_string_lossy().into_owned()];
+
+            for (meta_key, max_count) in max_counts.iter() {
+                let values = entry.metadata.get(*meta_key).map(Vec::as_slice).unwrap_or_default();
+                row.extend((0..*max_count).map(|i| values.get(i).cloned().unwrap_or_default()));
+            }
+
+            writer.write_record(&row)?;
+        }
+
+        writer.flush()?;
+        Ok(())
+    }
+
+    /// Compares the snapshot against the current state of the records.
+    /// Changed fields are ordered by file and then key.
+    pub fn compare(&self, records: &[Record]) -> Comparison {
+        let mut comparison = Comparison::default();
+
+        for entry in self.entries.iter() {
+            let record = match records.iter().find(|r| entry.matches(r)) {
+                Some(record) => record,
+                None => {
+                    comparison.removed.push(entry.file_path.clone());
+                    continue;
+                },
+            };
+
+            let mut meta_keys = entry.metadata.keys().map(String::as_str).collect::<Vec<_>>();
+            meta_keys.extend(record.meta_keys().filter(|k| !entry.metadata.contains_key(*k)));
+            meta_keys.retain(|k| self.meta_keys.as_ref().is_none_or(|mks| mks.contains(*k)));
+            meta_keys.sort_unstable();
+
+            for meta_key in meta_keys {
+                let snapshot = entry.metadata.get(meta_key);
+                let current = record.get_meta(meta_key);
+
+                if snapshot.map(Vec::as_slice) != current {
+                    comparison.changed.push(FieldDiff {
+                        file_path: record.file_path.clone(),
+                        meta_key: meta_key.to_string(),
+                        snapshot: snapshot.cloned(),
+                        current: current.map(<[String]>::to_vec),
+                    });
+                }
+            }
+        }
+
+        comparison.added.extend(
+            records.iter()
+            .filter(|r| !self.entries.iter().any(|e| e.matches(r)))
+            .map(|r| r.file_path.clone())
+        );
+
+        comparison
+    }
+
+    /// Stages the snapshot values of the given diffs as pending edits,
+    /// returning the number of fields that were restored.
+    pub fn restore(diffs: Vec<FieldDiff>, records: &mut Records) -> usize {
+        let mut restored = 0;
+
+        for diff in diffs {
+            if let Some(record) = records.iter_mut().find(|r| r.file_path == diff.file_path) {
+                if record.set_meta(&diff.meta_key, diff.snapshot) {
+                    restored += 1;
+                }
+            }
+        }
+
+        restored
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use maplit::hashmap;
+    use str_macro::str;
+
+    #[test]
+    fn diff_and_restore() {
+        let mut records = vec![
+            Record::new(
+                hashmap! {
+                    str!("ARTIST") => vec![str!("Foo")],
+                    str!("TITLE") => vec![str!("Song")],
+                },
+                PathBuf::from("a.flac"),
+            ),
+        ];
+
+        let snapshot = Snapshot::from_records(&records);
+
+        assert!(snapshot.compare(&records).is_empty());
+
+        records[0].set_meta("ARTIST", Some(vec![str!("Bar")]));
+        records[0].set_meta("TITLE", None);
+        records[0].set_meta("GENRE", Some(vec![str!("Rock")]));
+
+        let diffs = snapshot.compare(&records).changed;
+
+        assert_eq!(
+            diffs,
+            vec![
+                FieldDiff {
+                    file_path: PathBuf::from("a.flac"),
+                    meta_key: str!("ARTIST"),
+                    snapshot: Some(vec![str!("Foo")]),
+                    current: Some(vec![str!("Bar")]),
+                },
+                FieldDiff {
+                    file_path: PathBuf::from("a.flac"),
+                    meta_key: str!("GENRE"),
+                    snapshot: None,
+                    current: Some(vec![str!("Rock")]),
+                },
+                FieldDiff {
+                    file_path: PathBuf::from("a.flac"),
+                    meta_key: str!("TITLE"),
+                    snapshot: Some(vec![str!("Song")]),
+                    current: None,
+                },
+            ],
+        );
+
+        // Only restore the title and genre.
+        let restored = Snapshot::restore(diffs.into_iter().skip(1).collect(), &mut records);
+
+        assert_eq!(restored, 2);
+        assert_eq!(records[0].get_meta("ARTIST"), Some(&[str!("Bar")][..]));
+        assert_eq!(records[0].get_meta("GENRE"), None);
+        assert_eq!(records[0].get_meta("TITLE"), Some(&[str!("Song")][..]));
+        assert_eq!(records[0].edits.len(), 1);
+    }
+
+    #[test]
+    fn compare_csv() {
+        let records = vec![
+            Record::new(
+                hashmap! {
+                    str!("ARTIST") => vec![str!("Foo"), str!("Bar")],
+                    str!("TITLE") => vec![str!("Song")],
+                },
+                PathBuf::from("music/a.flac"),
+            ),
+            Record::new(
+                hashmap! {
+                    str!("TITLE") => vec![str!("New")],
+                },
+                PathBuf::from("music/c.flac"),
+            ),
+        ];
+
+        let input = "\
+file_name,ARTIST,ARTIST
+a.flac,Foo,Baz
+b.flac,Qux,
+";
+
+        let snapshot = TagImport::read_from(input.as_bytes()).unwrap().into_snapshot();
+        let comparison = snapshot.compare(&records);
+
+        assert_eq!(comparison.added, vec![PathBuf::from("music/c.flac")]);
+        assert_eq!(comparison.removed, vec![PathBuf::from("b.flac")]);
+
+        // The title is not part of the export, so it is not compared.
+        assert_eq!(
+            comparison.changed,
+            vec![
+                FieldDiff {
+                    file_path: PathBuf::from("music/a.flac"),
+                    meta_key: str!("ARTIST"),
+                    snapshot: Some(vec![str!("Foo"), str!("Baz")]),
+                    current: Some(vec![str!("Foo"), str!("Bar")]),
+                },
+            ],
+        );
+
+        let mut output = Vec::new();
+        Snapshot::from_records(&records).write_csv(&mut output).unwrap();
+
+        assert_eq!(
+            String::from_utf8(output).unwrap(),
+            "file_path,ARTIST,ARTIST,TITLE\nmusic/a.flac,Foo,Bar,Song\nmusic/c.flac,,,New\n",
+        );
+    }
+}
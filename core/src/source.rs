@@ -0,0 +1,271 @@
+//! Reading records from, and writing them back to, where they are kept.
+
+use std::io::Error as IoError;
+use std::io::ErrorKind as IoErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use metaflac::Error as MetaflacError;
+
+use crate::data::Record;
+use crate::ignore::IgnoreRules;
+use crate::util::Util;
+
+/// Where records are loaded from and written back to.
+pub trait RecordSource: Send {
+    /// Lists the files to read records from, leaving out ignored ones.
+    fn list_files(&self) -> Result<Vec<PathBuf>, IoError>;
+
+    /// Whether a file is left out of listing, such as for being hidden.
+    fn is_ignored(&self, file_path: &Path) -> bool;
+
+    /// Sets whether hidden files are listed.
+    fn set_show_hidden(&mut self, show_hidden: bool);
+
+    /// Reads the current state of a single record, with no pending edits.
+    fn read_record(&self, file_path: PathBuf) -> Result<Record, MetaflacError>;
+
+    /// Whether a file is there.
+    fn exists(&self, file_path: &Path) -> bool;
+
+    /// When a file was last modified, if known.
+    fn modified(&self, file_path: &Path) -> Option<SystemTime>;
+
+    /// Writes the pending edits of a record.
+    fn write_record(&mut self, record: &Record) -> Result<(), MetaflacError>;
+
+    /// Sets whether written files keep their modification time.
+    fn set_preserve_mtime(&mut self, preserve_mtime: bool);
+
+    /// Moves a file, creating the directories it goes into. Fails instead of
+    /// replacing a file that is already there.
+    fn rename_file(&mut self, from: &Path, to: &Path) -> Result<(), IoError>;
+
+    /// Copies a file, in the same way as `rename_file` moves one.
+    fn copy_file(&mut self, from: &Path, to: &Path) -> Result<(), IoError>;
+
+    /// Moves a file to the trash.
+    fn delete_file(&mut self, file_path: &Path) -> Result<(), IoError>;
+
+    /// Another handle to the same files, such as for writing from a
+    /// background thread.
+    fn share(&self) -> Box<dyn RecordSource>;
+}
+
+/// The FLAC files in a directory. Hidden files are left out unless shown, as
+/// are files matching the patterns of its `.diargosignore` file.
+pub struct FlacDir {
+    /// The directory the files are in.
+    pub working_dir: PathBuf,
+    preserve_mtime: bool,
+    show_hidden: bool,
+}
+
+impl FlacDir {
+    /// The FLAC files in a directory, with hidden files left out.
+    pub fn new(working_dir: PathBuf) -> Self {
+        Self { working_dir, preserve_mtime: false, show_hidden: false }
+    }
+
+    fn is_ignored_by(&self, rules: &IgnoreRules, file_path: &Path) -> bool {
+        let relative = file_path.strip_prefix(&self.working_dir).unwrap_or(file_path);
+
+        (!self.show_hidden && crate::ignore::is_hidden(relative)) || rules.is_ignored(relative, false)
+    }
+}
+
+impl RecordSource for FlacDir {
+    fn list_files(&self) -> Result<Vec<PathBuf>, IoError> {
+        let rules = IgnoreRules::read_from_dir(&self.working_dir);
+        let mut file_paths = Util::list_flac_files(&self.working_dir)?;
+
+        file_paths.retain(|file_path| !self.is_ignored_by(&rules, file_path));
+        Ok(file_paths)
+    }
+
+    fn is_ignored(&self, file_path: &Path) -> bool {
+        self.is_ignored_by(&IgnoreRules::read_from_dir(&self.working_dir), file_path)
+    }
+
+    fn set_show_hidden(&mut self, show_hidden: bool) {
+        self.show_hidden = show_hidden;
+    }
+
+    fn read_record(&self, file_path: PathBuf) -> Result<Record, MetaflacError> {
+        let mut record = Util::read_record(file_path)?;
+        record.source_root = Some(self.working_dir.clone());
+        Ok(record)
+    }
+
+    fn exists(&self, file_path: &Path) -> bool {
+        file_path.exists()
+    }
+
+    fn modified(&self, file_path: &Path) -> Option<SystemTime> {
+        Util::modified_time(file_path)
+    }
+
+    fn write_record(&mut self, record: &Record) -> Result<(), MetaflacError> {
+        Util::write_record(record, self.preserve_mtime)
+    }
+
+    fn set_preserve_mtime(&mut self, preserve_mtime: bool) {
+        self.preserve_mtime = preserve_mtime;
+    }
+
+    fn rename_file(&mut self, from: &Path, to: &Path) -> Result<(), IoError> {
+        // Renaming replaces an existing file without asking on most platforms.
+        if to.exists() {
+            return Err(IoError::new(IoErrorKind::AlreadyExists, format!("{} already exists", to.display())));
+        }
+
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        match std::fs::rename(from, to) {
+            // Files can't be renamed onto another filesystem, only copied.
+            Err(err) if err.kind() == IoErrorKind::CrossesDevices => {
+                std::fs::copy(from, to)?;
+                std::fs::remove_file(from)
+            },
+            result => result,
+        }
+    }
+
+    fn copy_file(&mut self, from: &Path, to: &Path) -> Result<(), IoError> {
+        if to.exists() {
+            return Err(IoError::new(IoErrorKind::AlreadyExists, format!("{} already exists", to.display())));
+        }
+
+        if let Some(parent) = to.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        std::fs::copy(from, to).map(|_| ())
+    }
+
+    fn delete_file(&mut self, file_path: &Path) -> Result<(), IoError> {
+        crate::trash::move_to_trash(file_path)
+    }
+
+    fn share(&self) -> Box<dyn RecordSource> {
+        Box::new(Self { working_dir: self.working_dir.clone(), ..*self })
+    }
+}
+
+/// The FLAC files in several directories, loaded together as one table. Each
+/// file is read through the directory it is in.
+pub struct FlacDirs {
+    dirs: Vec<FlacDir>,
+}
+
+impl FlacDirs {
+    /// Takes at least one directory.
+    pub fn new(working_dirs: Vec<PathBuf>) -> Self {
+        assert!(!working_dirs.is_empty(), "no directories to load");
+        Self { dirs: working_dirs.into_iter().map(FlacDir::new).collect() }
+    }
+
+    /// The directory a file is in, going by the deepest one that holds it,
+    /// or else the first one.
+    fn dir_index(&self, file_path: &Path) -> usize {
+        (0..self.dirs.len())
+        .filter(|&i| file_path.starts_with(&self.dirs[i].working_dir))
+        .max_by_key(|&i| self.dirs[i].working_dir.components().count())
+        .unwrap_or(0)
+    }
+
+    fn dir_of(&self, file_path: &Path) -> &FlacDir {
+        &self.dirs[self.dir_index(file_path)]
+    }
+
+    fn dir_of_mut(&mut self, file_path: &Path) -> &mut FlacDir {
+        let i = self.dir_index(file_path);
+        &mut self.dirs[i]
+    }
+}
+
+impl RecordSource for FlacDirs {
+    fn list_files(&self) -> Result<Vec<PathBuf>, IoError> {
+        let mut file_paths = Vec::new();
+
+        // A directory given twice is only listed once.
+        for (i, dir) in self.dirs.iter().enumerate() {
+            file_paths.extend(dir.list_files()?.into_iter().filter(|p| self.dir_index(p) == i));
+        }
+
+        Ok(file_paths)
+    }
+
+    fn is_ignored(&self, file_path: &Path) -> bool {
+        self.dir_of(file_path).is_ignored(file_path)
+    }
+
+    fn set_show_hidden(&mut self, show_hidden: bool) {
+        self.dirs.iter_mut().for_each(|dir| dir.set_show_hidden(show_hidden));
+    }
+
+    fn read_record(&self, file_path: PathBuf) -> Result<Record, MetaflacError> {
+        self.dir_of(&file_path).read_record(file_path)
+    }
+
+    fn exists(&self, file_path: &Path) -> bool {
+        self.dir_of(file_path).exists(file_path)
+    }
+
+    fn modified(&self, file_path: &Path) -> Option<SystemTime> {
+        self.dir_of(file_path).modified(file_path)
+    }
+
+    fn write_record(&mut self, record: &Record) -> Result<(), MetaflacError> {
+        self.dir_of_mut(&record.file_path).write_record(record)
+    }
+
+    fn set_preserve_mtime(&mut self, preserve_mtime: bool) {
+        self.dirs.iter_mut().for_each(|dir| dir.set_preserve_mtime(preserve_mtime));
+    }
+
+    fn rename_file(&mut self, from: &Path, to: &Path) -> Result<(), IoError> {
+        self.dir_of_mut(from).rename_file(from, to)
+    }
+
+    fn copy_file(&mut self, from: &Path, to: &Path) -> Result<(), IoError> {
+        self.dir_of_mut(from).copy_file(from, to)
+    }
+
+    fn delete_file(&mut self, file_path: &Path) -> Result<(), IoError> {
+        self.dir_of_mut(file_path).delete_file(file_path)
+    }
+
+    fn share(&self) -> Box<dyn RecordSource> {
+        let dirs = self.dirs.iter().map(|dir| FlacDir { working_dir: dir.working_dir.clone(), ..*dir }).collect();
+        Box::new(Self { dirs })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn flac_dirs() {
+        let dir = std::env::temp_dir().join(format!("diargos-dirs-{}", std::process::id()));
+        let sub_dir = dir.join("disc 2");
+        std::fs::create_dir_all(&sub_dir).unwrap();
+        std::fs::write(dir.join("01.flac"), "").unwrap();
+        std::fs::write(sub_dir.join("01.flac"), "").unwrap();
+
+        // A directory given twice is listed once.
+        let source = FlacDirs::new(vec![dir.clone(), sub_dir.clone(), dir.clone()]);
+        let mut file_paths = source.list_files().unwrap();
+        file_paths.sort();
+
+        assert_eq!(file_paths, vec![dir.join("01.flac"), sub_dir.join("01.flac")]);
+        assert_eq!(source.dir_of(&sub_dir.join("01.flac")).working_dir, sub_dir);
+        assert_eq!(source.dir_of(Path::new("elsewhere/01.flac")).working_dir, dir);
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,714 @@
+//! A cursive view that shows rows and columns of cells as a scrolling table.
+
+use std::borrow::Cow;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Printer;
+use cursive::Rect;
+use cursive::XY;
+use cursive::direction::Direction;
+use cursive::event::Event;
+use cursive::event::EventResult;
+use cursive::event::MouseButton;
+use cursive::event::MouseEvent;
+use cursive::theme::BaseColor;
+use cursive::theme::Color;
+use cursive::theme::ColorStyle;
+use cursive::view::View;
+use cursive::view::scroll::Scroller;
+use cursive::views::Canvas;
+use cursive::views::ScrollView;
+use unicode_width::UnicodeWidthStr;
+
+use crate::consts::*;
+use crate::data::Align;
+use crate::data::ColumnKey;
+use crate::model::DisplayRow;
+use crate::model::Model;
+use crate::transform::reveal_invisible;
+use crate::util::Util;
+use crate::util::MultiFigments;
+use crate::value::ValueKind;
+use crate::value::draw_rating;
+use crate::value::rating_stars;
+
+/// The column titles and the bar under them.
+pub const HEADER_HEIGHT: usize = 2;
+
+/// A marker and a space before the rows of a model.
+const MARKER_GUTTER_WIDTH: usize = 2;
+
+/// What a cell of a table shows.
+pub enum Cell<'a> {
+    /// Plain text.
+    Text(Cow<'a, str>),
+    /// Values, the separator shown between them, and whether they are valid.
+    /// Invalid values are shown in red.
+    Values(Cow<'a, [String]>, &'a str, bool),
+    /// A tag that is not there.
+    Missing,
+    /// A tag that is there, but with nothing but empty values.
+    Empty,
+}
+
+/// What the gutter left of a row marks it with.
+pub enum RowMarker {
+    /// The row has changes that have not been written yet.
+    Edited,
+    /// Writing the row last failed.
+    WriteFailed,
+    /// The row can't be written, so can't be edited.
+    ReadOnly,
+}
+
+/// One line of a table.
+pub enum Line {
+    /// The cells of a row.
+    Row(usize),
+    /// Text across the whole line, such as the name of a group of rows, and
+    /// whether the cursor is on it.
+    Heading(String, bool),
+}
+
+/// The rows and columns of cells shown by a `TableView`.
+pub trait TableSource: Send + 'static {
+    /// Brings anything the other methods depend on up to date, before each
+    /// layout.
+    fn recache(&mut self) {}
+
+    /// How wide the contents of each column are.
+    fn column_widths(&self) -> &[usize];
+
+    /// The title of a column.
+    fn column_title(&self, x: usize) -> &str;
+
+    /// How the cells of a column are aligned.
+    fn column_align(&self, _x: usize) -> Align {
+        Align::Left
+    }
+
+    /// How many lines there are below the header.
+    fn num_lines(&self) -> usize;
+
+    /// What a line below the header shows.
+    fn line(&self, index: usize) -> Line;
+
+    /// The cell of a row in a column.
+    fn cell(&self, x: usize, y: usize) -> Cell<'_>;
+
+    /// The column and row of the cursor. A cursor without a column covers the
+    /// whole row, and one without a row the whole column.
+    fn cursor(&self) -> (Option<usize>, Option<usize>);
+
+    /// Whether a row is selected, and is shown highlighted.
+    fn is_selected(&self, _y: usize) -> bool {
+        false
+    }
+
+    /// Whether a row is being worked on in the background, and is shown dimmed.
+    fn is_busy(&self, _y: usize) -> bool {
+        false
+    }
+
+    /// The line a row is shown on.
+    fn line_of_row(&self, y: usize) -> usize {
+        y
+    }
+
+    /// Feeds in the first line in view, before each layout.
+    fn set_scroll_top(&mut self, _line: usize) {}
+
+    /// Feeds in how many cells across the lines have room for, before each
+    /// layout.
+    fn set_viewport_width(&mut self, _width: usize) {}
+
+    /// Sets the width of a column, as when its separator is dragged.
+    fn resize_column(&mut self, _x: usize, _width: usize) {}
+
+    /// How wide the gutter left of the rows is, if there is one. The gutter
+    /// stays put when scrolling sideways.
+    fn gutter_width(&self) -> usize {
+        0
+    }
+
+    /// What the gutter marks a row with, if anything.
+    fn row_marker(&self, _y: usize) -> Option<RowMarker> {
+        None
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq)]
+enum Highlight {
+    Off,
+    Busy,
+    Selected,
+    Cursor,
+}
+
+impl Highlight {
+    fn color_or(self, default: ColorStyle) -> ColorStyle {
+        match self {
+            Self::Off => default,
+            Self::Busy => ColorStyle::secondary(),
+            Self::Selected => ColorStyle::highlight_inactive(),
+            Self::Cursor => ColorStyle::highlight(),
+        }
+    }
+}
+
+/// A scrolling, spreadsheet-like table of cells, under a header of column
+/// titles that scrolls sideways along with it.
+pub struct TableView<S: TableSource> {
+    shared_source: Arc<Mutex<S>>,
+    scroll_view: ScrollView<Canvas<Arc<Mutex<S>>>>,
+    drag: Option<ColumnDrag>,
+}
+
+/// A column separator being dragged in the header.
+struct ColumnDrag {
+    column: usize,
+    start_x: usize,
+    start_width: usize,
+}
+
+impl<S: TableSource> TableView<S> {
+    /// A table of the rows and columns of a shared source.
+    pub fn new(shared_source: Arc<Mutex<S>>) -> Self {
+        let canvas =
+            Canvas::new(shared_source.clone())
+            .with_draw(|shared_source, printer| {
+                let source = shared_source.lock().unwrap();
+                Self::draw_lines(&*source, printer);
+            })
+            .with_required_size(|shared_source, _constraints| {
+                let mut source = shared_source.lock().unwrap();
+                source.recache();
+
+                Self::content_size(&*source)
+            })
+            .with_important_area(|shared_source, _final_size| {
+                let source = shared_source.lock().unwrap();
+
+                // Figure out the logical X and Y coordinates of the highlighted cell, if any.
+                let (lx, ly) = match source.cursor() {
+                    // Return a view showing the entire visible canvas.
+                    (lx, None) => (lx, 0),
+                    (lx, Some(ly)) => (lx, ly),
+                };
+
+                let ty = source.line_of_row(ly);
+
+                // A highlighted row spans every column.
+                let widths = source.column_widths();
+                let (tx, dx) = match lx {
+                    Some(lx) => (
+                        widths.iter().take(lx).map(|w| w + COLUMN_SEP.width()).sum(),
+                        widths.get(lx).copied().unwrap_or(0),
+                    ),
+                    None => (0, Self::content_size(&*source).x),
+                };
+                let dy = 1;
+
+                Rect::from_size((tx, ty), (dx, dy))
+            })
+        ;
+
+        let mut scroll_view = ScrollView::new(canvas).scroll_x(true).scroll_y(true);
+
+        // Set the scrollbar padding to be 0 on both axes.
+        let scroller = scroll_view.get_scroller_mut();
+        scroller.set_scrollbar_padding((0, 0));
+
+        Self { shared_source, scroll_view, drag: None }
+    }
+
+    /// Scrolls so that the cursor is in view.
+    pub fn scroll_to_cursor(&mut self) {
+        self.scroll_view.scroll_to_important_area();
+    }
+
+    /// Feeds the room offered to the lines to the source, leaving room for
+    /// the scrollbar if the lines will not all fit.
+    fn offer_size(source: &mut S, size: XY<usize>) {
+        let body_height = size.y.saturating_sub(HEADER_HEIGHT);
+        let scrollbar_width = if source.num_lines() > body_height { 1 } else { 0 };
+
+        source.set_viewport_width(size.x.saturating_sub(scrollbar_width + source.gutter_width()));
+    }
+
+    /// The column whose separator is at a position across the header, in
+    /// content coordinates. The last column has no separator.
+    fn separator_at(source: &S, content_x: usize) -> Option<usize> {
+        let widths = source.column_widths();
+        let mut offset_x = 0;
+
+        for (x, &width) in widths.iter().enumerate().take(widths.len().saturating_sub(1)) {
+            offset_x += width;
+
+            if (offset_x..offset_x + COLUMN_SEP.width()).contains(&content_x) {
+                return Some(x);
+            }
+
+            offset_x += COLUMN_SEP.width();
+        }
+
+        None
+    }
+
+    /// Resizes columns by dragging their separators in the header.
+    fn on_mouse(&mut self, position: XY<usize>, event: MouseEvent) -> EventResult {
+        let gutter_width = self.shared_source.lock().unwrap().gutter_width();
+        let content_x = position.x.saturating_sub(gutter_width) + self.scroll_view.content_viewport().left();
+
+        match (event, &self.drag) {
+            (MouseEvent::Press(MouseButton::Left), _) if position.y < HEADER_HEIGHT => {
+                let source = self.shared_source.lock().unwrap();
+
+                self.drag = Self::separator_at(&*source, content_x).map(|column| ColumnDrag {
+                    column,
+                    start_x: content_x,
+                    start_width: source.column_widths()[column],
+                });
+
+                if self.drag.is_some() { EventResult::Consumed(None) } else { EventResult::Ignored }
+            },
+            (MouseEvent::Hold(MouseButton::Left), Some(drag)) => {
+                let width = (drag.start_width + content_x).saturating_sub(drag.start_x).max(1);
+                self.shared_source.lock().unwrap().resize_column(drag.column, width);
+
+                EventResult::Consumed(None)
+            },
+            (MouseEvent::Release(MouseButton::Left), Some(_)) => {
+                self.drag = None;
+                EventResult::Consumed(None)
+            },
+            _ => EventResult::Ignored,
+        }
+    }
+
+    fn content_size(source: &S) -> XY<usize> {
+        let widths = source.column_widths();
+        let width = widths.iter().sum::<usize>() + COLUMN_SEP.width() * widths.len().saturating_sub(1);
+
+        XY::new(width, source.num_lines())
+    }
+
+    /// Marks the rows in view in the gutter.
+    fn draw_gutter(&self, printer: &Printer) {
+        let source = self.shared_source.lock().unwrap();
+        let viewport = self.scroll_view.content_viewport();
+        let lines = viewport.top()..(viewport.top() + viewport.height()).min(source.num_lines());
+
+        for (offset_y, line) in lines.enumerate() {
+            let marker = match source.line(line) {
+                Line::Row(y) => source.row_marker(y),
+                Line::Heading(..) => None,
+            };
+
+            let (marker, style) = match marker {
+                Some(RowMarker::Edited) => (EDITED_MARKER, ColorStyle::title_primary()),
+                Some(RowMarker::WriteFailed) => (WRITE_FAILED_MARKER, ColorStyle::from(Color::Dark(BaseColor::Red))),
+                Some(RowMarker::ReadOnly) => (READ_ONLY_MARKER, ColorStyle::secondary()),
+                None => continue,
+            };
+
+            printer.with_color(style, |pr| { pr.print((0, offset_y), marker); });
+        }
+    }
+
+    fn draw_lines(source: &S, printer: &Printer) {
+        let (cursor_x, cursor_y) = source.cursor();
+        let widths = source.column_widths();
+
+        for offset_y in 0..source.num_lines() {
+            let y = match source.line(offset_y) {
+                Line::Heading(text, on_cursor) => {
+                    let style = if on_cursor { ColorStyle::highlight() } else { ColorStyle::title_secondary() };
+
+                    printer.with_color(
+                        style,
+                        |pr| { pr.print((0, offset_y), &text); },
+                    );
+
+                    continue;
+                },
+                Line::Row(y) => y,
+            };
+
+            let is_selected = source.is_selected(y);
+            let is_busy = source.is_busy(y);
+
+            let cells =
+                widths.iter()
+                .enumerate()
+                .map(|(x, &width)| {
+                    let highlighted =
+                        if cursor_y == Some(y) && cursor_x.is_none_or(|cx| cx == x) { Highlight::Cursor }
+                        else if is_selected { Highlight::Selected }
+                        else if is_busy { Highlight::Busy }
+                        else { Highlight::Off }
+                    ;
+
+                    (source.cell(x, y), highlighted, width, source.column_align(x))
+                })
+            ;
+
+            Self::draw_delimited_row(printer, offset_y, cells);
+        }
+    }
+
+    fn draw_delimited_row<'a>(
+        printer: &Printer,
+        offset_y: usize,
+        cells: impl Iterator<Item = (Cell<'a>, Highlight, usize, Align)>,
+    )
+    {
+        let mut offset_x = 0;
+        let mut is_first_col = true;
+
+        for (cell, highlighted, content_width, align) in cells {
+            if is_first_col { is_first_col = false; }
+            else {
+                printer.print((offset_x, offset_y), COLUMN_SEP);
+                offset_x += COLUMN_SEP.width();
+            }
+
+            match cell {
+                Cell::Missing | Cell::Empty => {
+                    // Print out a highlighted sentinel, to indicate a missing
+                    // or empty value, which mean different things.
+                    let (fill, color) = match cell {
+                        Cell::Missing => (MISSING_FILL, highlighted.color_or(ColorStyle::secondary())),
+                        _ => (EMPTY_FILL, highlighted.color_or(ColorStyle::tertiary())),
+                    };
+
+                    printer.with_color(
+                        color,
+                        |pr| {
+                            pr.print_hline(
+                                (offset_x, offset_y),
+                                content_width,
+                                fill,
+                            );
+                        },
+                    );
+                },
+                Cell::Text(value) => {
+                    let color = highlighted.color_or(ColorStyle::primary());
+
+                    let trim_output = Util::trim_display_str_elided(
+                        &value,
+                        content_width,
+                        ELLIPSIS_STR.width(),
+                    );
+
+                    let display_str = trim_output.display_str;
+                    let emit_ellipsis = trim_output.trim_status.emit_ellipsis();
+
+                    // Trimmed values fill up the entire width, so they are never shifted.
+                    let offset_x = offset_x + align.offset(trim_output.full_real_width, content_width);
+
+                    printer.with_color(
+                        color,
+                        move |pr| {
+                            pr.print((offset_x, offset_y), display_str);
+
+                            if emit_ellipsis {
+                                let ellipsis_offset = trim_output.ellipsis_offset();
+
+                                pr.print((offset_x + ellipsis_offset, offset_y), ELLIPSIS_STR);
+                            }
+                        },
+                    );
+                },
+                Cell::Values(values, separator, valid) => {
+                    let color =
+                        if !valid { highlighted.color_or(ColorStyle::from(Color::Dark(BaseColor::Red))) }
+                        else { highlighted.color_or(ColorStyle::primary()) }
+                    ;
+
+                    let multi_figments = MultiFigments::new(&values, content_width, separator, ELLIPSIS_STR);
+
+                    let full_width = Util::multi_display_width(&values, separator);
+                    let offset_x = offset_x + align.offset(full_width, content_width);
+
+                    for (offset, figment, figment_kind) in multi_figments {
+                        let used_color =
+                            if figment_kind.is_sep() { ColorStyle::title_primary() }
+                            else { color }
+                        ;
+
+                        printer.with_color(
+                            used_color,
+                            move |pr| {
+                                pr.print((offset_x + offset, offset_y), figment);
+                            },
+                        );
+                    }
+                },
+            };
+
+            offset_x += content_width;
+        }
+    }
+
+    /// Draws the column titles and the bar under them, scrolled sideways
+    /// along with the content.
+    fn draw_header(&self, printer: &Printer) {
+        let source = self.shared_source.lock().unwrap();
+        let (cursor_x, cursor_y) = source.cursor();
+
+        let titles =
+            source.column_widths().iter()
+            .enumerate()
+            .map(|(x, &width)| {
+                let highlighted =
+                    if cursor_y.is_none() && cursor_x == Some(x) { Highlight::Cursor }
+                    else { Highlight::Off }
+                ;
+
+                (Cell::Text(Cow::Borrowed(source.column_title(x))), highlighted, width, source.column_align(x))
+            })
+        ;
+
+        Self::draw_delimited_row(printer, 0, titles);
+
+        let mut offset_x = 0;
+
+        for (x, &width) in source.column_widths().iter().enumerate() {
+            if x > 0 {
+                printer.print((offset_x, 1), COLUMN_HEADER_SEP);
+                offset_x += COLUMN_HEADER_SEP.width();
+            }
+
+            printer.print_hline((offset_x, 1), width, COLUMN_HEADER_BAR);
+            offset_x += width;
+        }
+    }
+}
+
+impl<S: TableSource> View for TableView<S> {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        let gutter_width = self.shared_source.lock().unwrap().gutter_width();
+        let table_printer = printer.offset((gutter_width, 0));
+
+        // Draw the header all the way to the left, so it scrolls with the content.
+        let content_viewport = self.scroll_view.content_viewport();
+        self.draw_header(&table_printer.content_offset((content_viewport.left(), 0)));
+
+        self.scroll_view.draw(&table_printer.offset((0, HEADER_HEIGHT)));
+
+        if gutter_width > 0 {
+            self.draw_gutter(&printer.offset((0, HEADER_HEIGHT)));
+        }
+    }
+
+    fn layout(&mut self, final_size: XY<usize>) {
+        {
+            let mut source = self.shared_source.lock().unwrap();
+            source.set_scroll_top(self.scroll_view.content_viewport().top());
+            source.recache();
+
+            // Whether there is a scrollbar depends on the lines just recached.
+            Self::offer_size(&mut *source, final_size);
+            source.recache();
+        }
+
+        let gutter_width = self.shared_source.lock().unwrap().gutter_width();
+        self.scroll_view.layout(final_size.saturating_sub((gutter_width, HEADER_HEIGHT)));
+
+        log::trace!("table laid out at {:?}, showing {:?}", final_size, self.scroll_view.content_viewport());
+    }
+
+    fn required_size(&mut self, hinted_size: XY<usize>) -> XY<usize> {
+        let gutter_width = {
+            let mut source = self.shared_source.lock().unwrap();
+
+            // Proportionally sized columns take up as much room as is offered.
+            Self::offer_size(&mut *source, hinted_size);
+            source.gutter_width()
+        };
+
+        let margin_size = XY::new(gutter_width, HEADER_HEIGHT);
+        self.scroll_view.required_size(hinted_size.saturating_sub(margin_size)) + margin_size
+    }
+
+    fn take_focus(&mut self, source: Direction) -> bool {
+        self.scroll_view.take_focus(source)
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::Mouse { offset, position, event } => {
+                match position.checked_sub(offset) {
+                    Some(position) => self.on_mouse(position, event),
+                    None => EventResult::Ignored,
+                }
+            },
+            _ => EventResult::Ignored,
+        }
+    }
+}
+
+impl TableSource for Model {
+    fn recache(&mut self) {
+        Model::recache(self);
+    }
+
+    fn column_widths(&self) -> &[usize] {
+        &self.cached_content_widths
+    }
+
+    fn column_title(&self, x: usize) -> &str {
+        &self.data.columns[x].title
+    }
+
+    fn column_align(&self, x: usize) -> Align {
+        self.data.columns[x].align
+    }
+
+    fn num_lines(&self) -> usize {
+        self.cached_display_rows.len()
+    }
+
+    fn line(&self, index: usize) -> Line {
+        match self.cached_display_rows[index] {
+            DisplayRow::Header(group_index) => {
+                let group = self.cached_groups[group_index];
+                let collapsed = self.cached_group_collapsed[group_index];
+
+                let marker = if collapsed { COLLAPSED_GROUP_STR } else { EXPANDED_GROUP_STR };
+
+                // The cursor shows on the header of a collapsed group it is in.
+                let on_cursor = collapsed && self.cursor.to_xy().1.is_some_and(|y| group.range().contains(&y));
+
+                Line::Heading(format!("{} {}", marker, self.cached_group_headers[group_index]), on_cursor)
+            },
+            DisplayRow::Record(y) => Line::Row(y),
+        }
+    }
+
+    fn cell(&self, x: usize, y: usize) -> Cell<'_> {
+        let record = &self.data.records[y];
+        let col = &self.data.columns[x];
+
+        match &col.key {
+            ColumnKey::Meta(meta_key) => {
+                let vals = match record.get_meta(meta_key) {
+                    None => return Cell::Missing,
+                    Some(vals) => vals,
+                };
+
+                if vals.iter().all(|v| v.is_empty()) {
+                    return Cell::Empty;
+                }
+
+                let kind = col.value_kind();
+                let valid = vals.iter().all(|v| kind.validate(v));
+
+                // Values as they are shown, where that differs from how they are stored.
+                let shown =
+                    if kind == ValueKind::Rating {
+                        vals.iter().map(|v| rating_stars(v, self.rating_max).map(draw_rating)).collect::<Option<Vec<_>>>()
+                    }
+                    else if self.show_invisible {
+                        Some(vals.iter().map(|v| reveal_invisible(v).into_owned()).collect())
+                    }
+                    else { None }
+                ;
+
+                let shown = match shown {
+                    Some(shown) => Cow::Owned(shown),
+                    None => Cow::Borrowed(vals),
+                };
+
+                Cell::Values(shown, col.separator(), valid)
+            },
+            ColumnKey::Info(info_key) => {
+                match record.get_info(info_key) {
+                    None => Cell::Missing,
+                    Some(val) => Cell::Text(val),
+                }
+            },
+            ColumnKey::Command(command) => {
+                match record.get_command_output(command) {
+                    None => Cell::Missing,
+                    Some(val) => Cell::Text(Cow::Borrowed(val)),
+                }
+            },
+            ColumnKey::Computed(template) => {
+                match record.get_computed(template) {
+                    None => Cell::Missing,
+                    Some(val) => Cell::Text(Cow::Owned(val)),
+                }
+            },
+        }
+    }
+
+    fn cursor(&self) -> (Option<usize>, Option<usize>) {
+        self.cursor.to_xy()
+    }
+
+    fn is_selected(&self, y: usize) -> bool {
+        Model::is_selected(self, y)
+    }
+
+    fn is_busy(&self, y: usize) -> bool {
+        self.is_record_writing(y)
+    }
+
+    fn line_of_row(&self, y: usize) -> usize {
+        self.record_display_row(y)
+    }
+
+    fn set_scroll_top(&mut self, line: usize) {
+        Model::set_scroll_top(self, line)
+    }
+
+    fn set_viewport_width(&mut self, width: usize) {
+        Model::set_viewport_width(self, width)
+    }
+
+    fn resize_column(&mut self, x: usize, width: usize) {
+        Model::resize_column(self, x, width)
+    }
+
+    fn gutter_width(&self) -> usize {
+        MARKER_GUTTER_WIDTH
+    }
+
+    fn row_marker(&self, y: usize) -> Option<RowMarker> {
+        let record = self.data.records.get(y)?;
+
+        if self.failed_to_write(y) { Some(RowMarker::WriteFailed) }
+        else if record.is_edited() { Some(RowMarker::Edited) }
+        else if record.read_only { Some(RowMarker::ReadOnly) }
+        else { None }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use crate::config::Config;
+    use crate::fixtures::MemorySource;
+
+    #[test]
+    fn model_as_table() {
+        let mut model = Model::load(Config::default().columns, Box::new(MemorySource::demo())).unwrap();
+        model.data.records[0].set_meta("ARTIST", None);
+        model.toggle_grouping("ALBUM");
+        TableSource::recache(&mut model);
+
+        assert_eq!(model.column_widths().len(), model.data.columns.len());
+        assert_eq!(model.column_title(1), "Title");
+        assert!(matches!(model.line(0), Line::Heading(..)));
+        assert!(matches!(model.line(1), Line::Row(0)));
+
+        assert!(matches!(model.cell(0, 0), Cell::Missing));
+        assert!(matches!(model.cell(1, 0), Cell::Values(values, _, true) if values[0] == model.data.records[0].get_meta("TITLE").unwrap()[0]));
+        assert!(matches!(model.cell(3, 0), Cell::Text(name) if name.ends_with(".flac")));
+    }
+}
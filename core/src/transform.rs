@@ -0,0 +1,381 @@
+//! Cleanups for common ways tag values end up messy.
+
+use std::borrow::Cow;
+
+use unicode_normalization::UnicodeNormalization;
+
+use crate::config::KeyCase;
+use crate::consts::*;
+use crate::data::Record;
+
+/// A fix for a common way tag values end up messy.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Cleanup {
+    /// Removes zero-width and control characters, other than line breaks.
+    StripInvisible,
+    /// Turns runs of whitespace within a line into a single space.
+    CollapseWhitespace,
+    /// Removes whitespace from the start and end of values.
+    Trim,
+    /// Removes values that are empty or only whitespace.
+    DropEmpty,
+}
+
+impl Cleanup {
+    /// Every cleanup, in the order they are applied.
+    pub const ALL: [Self; 4] = [Self::StripInvisible, Self::CollapseWhitespace, Self::Trim, Self::DropEmpty];
+
+    /// What the cleanup is called in menus.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::StripInvisible => "Remove zero-width and control characters",
+            Self::CollapseWhitespace => "Collapse repeated whitespace",
+            Self::Trim => "Trim leading and trailing whitespace",
+            Self::DropEmpty => "Drop empty values",
+        }
+    }
+
+    fn apply(&self, values: Vec<String>) -> Vec<String> {
+        match self {
+            Self::StripInvisible => values.into_iter().map(|v| v.chars().filter(|&c| !is_invisible(c)).collect()).collect(),
+            Self::CollapseWhitespace => values.into_iter().map(|v| collapse_whitespace(&v)).collect(),
+            Self::Trim => values.into_iter().map(|v| v.trim().to_string()).collect(),
+            Self::DropEmpty => values.into_iter().filter(|v| !v.trim().is_empty()).collect(),
+        }
+    }
+}
+
+/// Whether a character takes up no room when shown, so that it is hard to
+/// notice in a tag. Line breaks are kept, as multi-line values are common in
+/// comments and lyrics.
+pub fn is_invisible(c: char) -> bool {
+    match c {
+        '\n' => false,
+        '\u{200B}'..='\u{200F}' | '\u{2060}'..='\u{2064}' | '\u{FEFF}' | '\u{00AD}' => true,
+        _ => c.is_control(),
+    }
+}
+
+/// Whether a character is a space that does not allow a line break there.
+fn is_non_breaking_space(c: char) -> bool {
+    matches!(c, '\u{00A0}' | '\u{2007}' | '\u{202F}')
+}
+
+/// Shows characters that are easy to miss in a value with visible markers:
+/// leading and trailing spaces, tabs, line breaks, non-breaking spaces, and
+/// zero-width and control characters.
+pub fn reveal_invisible(value: &str) -> Cow<'_, str> {
+    let is_hidden = |c: char| c == '\t' || c == '\n' || is_non_breaking_space(c) || is_invisible(c);
+    let trimmed = value.trim_matches(' ');
+
+    if !value.chars().any(is_hidden) && trimmed.len() == value.len() {
+        return Cow::Borrowed(value);
+    }
+
+    // Spaces are only hard to spot at the ends.
+    let leading = value.len() - value.trim_start_matches(' ').len();
+    let trailing_start = leading + value[leading..].trim_end_matches(' ').len();
+
+    let revealed =
+        value.char_indices()
+        .map(|(i, c)| match c {
+            ' ' if i < leading || i >= trailing_start => SPACE_MARKER,
+            '\t' => TAB_MARKER,
+            '\n' => NEWLINE_MARKER,
+            c if is_non_breaking_space(c) => NBSP_MARKER,
+            c if is_invisible(c) => INVISIBLE_MARKER,
+            c => c,
+        })
+        .collect()
+    ;
+
+    Cow::Owned(revealed)
+}
+
+fn collapse_whitespace(value: &str) -> String {
+    let mut collapsed = String::with_capacity(value.len());
+    let mut in_run = false;
+
+    for c in value.chars() {
+        if c.is_whitespace() && c != '\n' {
+            if !in_run {
+                collapsed.push(' ');
+            }
+            in_run = true;
+        }
+        else {
+            collapsed.push(c);
+            in_run = false;
+        }
+    }
+
+    collapsed
+}
+
+/// Applies the chosen cleanups to the values of a tag, in the order of
+/// `Cleanup::ALL` no matter the order they are given in.
+pub fn clean(values: &[String], cleanups: &[Cleanup]) -> Vec<String> {
+    Cleanup::ALL.iter()
+    .filter(|cleanup| cleanups.contains(cleanup))
+    .fold(values.to_vec(), |values, cleanup| cleanup.apply(values))
+}
+
+/// A way to change the capitalization of a value.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Case {
+    /// UPPER CASE.
+    Upper,
+    /// lower case.
+    Lower,
+    /// Only the first letter of the value is capitalized.
+    Sentence,
+    /// Every word is capitalized, except for small words that are not first
+    /// or last.
+    Title,
+}
+
+impl Case {
+    /// Every case, in the order they are offered in.
+    pub const ALL: [Self; 4] = [Self::Upper, Self::Lower, Self::Sentence, Self::Title];
+
+    /// What the case is called in menus, written in that case.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Upper => "UPPER CASE",
+            Self::Lower => "lower case",
+            Self::Sentence => "Sentence case",
+            Self::Title => "Title Case",
+        }
+    }
+
+    /// Changes the case of a value. Small words are compared ignoring case.
+    pub fn apply(&self, value: &str, small_words: &[String]) -> String {
+        match self {
+            Self::Upper => value.to_uppercase(),
+            Self::Lower => value.to_lowercase(),
+            Self::Sentence => capitalize(&value.to_lowercase()),
+            Self::Title => {
+                let words = value.split(' ').collect::<Vec<_>>();
+                let last = words.len().saturating_sub(1);
+
+                words.iter()
+                .enumerate()
+                .map(|(i, word)| {
+                    let lower = word.to_lowercase();
+                    let bare = lower.trim_matches(|c: char| !c.is_alphanumeric());
+                    let is_small = small_words.iter().any(|small| small.to_lowercase() == bare);
+
+                    // Words after a colon start a subtitle, so are never small.
+                    let after_colon = i > 0 && words[i - 1].ends_with(':');
+
+                    if is_small && i != 0 && i != last && !after_colon { lower }
+                    else { capitalize(&lower) }
+                })
+                .collect::<Vec<_>>()
+                .join(" ")
+            },
+        }
+    }
+}
+
+/// Uppercases the first letter of a value, skipping over any leading
+/// punctuation like an opening bracket.
+fn capitalize(value: &str) -> String {
+    match value.char_indices().find(|(_, c)| c.is_alphanumeric()) {
+        Some((i, c)) => format!("{}{}{}", &value[..i], c.to_uppercase(), &value[(i + c.len_utf8())..]),
+        None => value.to_string(),
+    }
+}
+
+/// Folds accented and other non-ASCII letters to their closest ASCII
+/// spelling, such as é to e and ß to ss. Characters with no such spelling
+/// become underscores.
+pub fn to_ascii(value: &str) -> String {
+    deunicode::deunicode_with_tofu(value, "_")
+}
+
+/// Whether a value has characters that `to_ascii` has no spelling for.
+pub fn lacks_ascii_spelling(value: &str) -> bool {
+    value.chars().any(|c| deunicode::deunicode_char(c).is_none())
+}
+
+/// Stages the NFC form of every tag value of a record that is not already
+/// in NFC, and of every key too if asked. Values of keys that become the same
+/// once normalized are merged. Returns whether anything changed.
+pub fn stage_nfc(record: &mut Record, keys: bool) -> bool {
+    let meta_keys = record.meta_keys().map(String::from).collect::<Vec<_>>();
+    let mut changed = false;
+
+    for meta_key in meta_keys {
+        let values = record.get_meta(&meta_key).unwrap_or_default().iter().map(|v| v.nfc().collect()).collect::<Vec<String>>();
+
+        let normalized_key = if keys { meta_key.nfc().collect::<String>() } else { meta_key.clone() };
+
+        if normalized_key == meta_key {
+            changed |= record.set_meta(&meta_key, Some(values));
+        }
+        else {
+            let mut merged = record.get_meta(&normalized_key).unwrap_or_default().to_vec();
+            merged.extend(values);
+
+            record.set_meta(&meta_key, None);
+            record.set_meta(&normalized_key, Some(merged));
+            changed = true;
+        }
+    }
+
+    changed
+}
+
+/// Stages every tag key of a record in the given case. Values of keys that
+/// become the same are merged, leaving out repeats. Returns whether anything
+/// changed.
+pub fn stage_key_case(record: &mut Record, key_case: KeyCase) -> bool {
+    let convert = match key_case {
+        KeyCase::Preserve => return false,
+        KeyCase::Upper => str::to_ascii_uppercase,
+        KeyCase::Lower => str::to_ascii_lowercase,
+    };
+
+    let meta_keys = record.meta_keys().map(String::from).collect::<Vec<_>>();
+    let mut changed = false;
+
+    for meta_key in meta_keys {
+        changed |= stage_rename_key(record, &meta_key, &convert(&meta_key));
+    }
+
+    changed
+}
+
+/// Stages the values of one tag key under another, after any values the
+/// record already has for it, leaving out repeats. Returns whether anything
+/// changed.
+pub fn stage_rename_key(record: &mut Record, from: &str, to: &str) -> bool {
+    if from == to {
+        return false;
+    }
+
+    let mut merged = record.get_meta(to).unwrap_or_default().to_vec();
+
+    for value in record.get_meta(from).unwrap_or_default() {
+        if !merged.contains(value) {
+            merged.push(value.clone());
+        }
+    }
+
+    let removed = record.set_meta(from, None);
+    let added = record.set_meta(to, Some(merged));
+
+    removed | added
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use str_macro::str;
+
+    #[test]
+    fn cleaning() {
+        let values = vec![str!("  The\u{200B}  Band\t "), str!(" "), str!("Line one\nLine\u{0007} two")];
+
+        assert_eq!(
+            clean(&values, &Cleanup::ALL),
+            vec![str!("The Band"), str!("Line one\nLine two")],
+        );
+
+        assert_eq!(
+            clean(&values, &[Cleanup::Trim]),
+            vec![str!("The\u{200B}  Band"), str!(""), str!("Line one\nLine\u{0007} two")],
+        );
+
+        assert_eq!(
+            clean(&values, &[Cleanup::DropEmpty, Cleanup::CollapseWhitespace]),
+            vec![str!(" The\u{200B} Band "), str!("Line one\nLine\u{0007} two")],
+        );
+    }
+
+    #[test]
+    fn revealing() {
+        assert!(matches!(reveal_invisible("A normal value"), Cow::Borrowed(..)));
+        assert_eq!(reveal_invisible(" Two  spaces "), "·Two  spaces·");
+        assert_eq!(reveal_invisible("Tab\there\u{00A0}and\u{200B}\nnext"), "Tab→here⍽and¤↵next");
+        assert_eq!(reveal_invisible("   "), "···");
+    }
+
+    #[test]
+    fn ascii() {
+        assert_eq!(to_ascii("Sigur Rós — Ágætis byrjun"), "Sigur Ros -- Agaetis byrjun");
+        assert_eq!(to_ascii("Straße"), "Strasse");
+        assert_eq!(to_ascii("plain"), "plain");
+        assert!(lacks_ascii_spelling("\u{E000}"));
+        assert!(!lacks_ascii_spelling("Straße"));
+    }
+
+    #[test]
+    fn nfc() {
+        use std::path::PathBuf;
+
+        use maplit::hashmap;
+
+        let mut record = Record::new(
+            hashmap! {
+                str!("TITLE") => vec![str!("Cafe\u{301}")],
+                str!("ARTIST") => vec![str!("Plain")],
+                str!("NOTE\u{301}") => vec![str!("Key")],
+            },
+            PathBuf::from("a.flac"),
+        );
+
+        assert!(stage_nfc(&mut record, false));
+        assert_eq!(record.get_meta("TITLE"), Some(&[str!("Caf\u{e9}")][..]));
+        assert!(!record.edits.contains_key("ARTIST"));
+        assert!(record.get_meta("NOTE\u{301}").is_some());
+
+        assert!(stage_nfc(&mut record, true));
+        assert_eq!(record.get_meta("NOTE\u{301}"), None);
+        assert_eq!(record.get_meta("NOT\u{c9}"), Some(&[str!("Key")][..]));
+        assert!(!stage_nfc(&mut record, true));
+    }
+
+    #[test]
+    fn key_case() {
+        use std::path::PathBuf;
+
+        use maplit::hashmap;
+
+        let mut record = Record::new(
+            hashmap! {
+                str!("Artist") => vec![str!("A"), str!("B")],
+                str!("ARTIST") => vec![str!("A")],
+                str!("title") => vec![str!("T")],
+            },
+            PathBuf::from("a.flac"),
+        );
+
+        assert!(!stage_key_case(&mut record, KeyCase::Preserve));
+
+        assert!(stage_key_case(&mut record, KeyCase::Upper));
+        assert_eq!(record.get_meta("Artist"), None);
+        assert_eq!(record.get_meta("ARTIST"), Some(&[str!("A"), str!("B")][..]));
+        assert_eq!(record.get_meta("TITLE"), Some(&[str!("T")][..]));
+        assert!(!stage_key_case(&mut record, KeyCase::Upper));
+
+        assert!(stage_key_case(&mut record, KeyCase::Lower));
+        assert_eq!(record.get_meta("ARTIST"), None);
+        assert_eq!(record.get_meta("artist"), Some(&[str!("A"), str!("B")][..]));
+    }
+
+    #[test]
+    fn cases() {
+        let small_words = vec![str!("a"), str!("of"), str!("the"), str!("IN")];
+        let value = "the END of the world (live in berlin)";
+
+        assert_eq!(Case::Upper.apply(value, &small_words), "THE END OF THE WORLD (LIVE IN BERLIN)");
+        assert_eq!(Case::Lower.apply(value, &small_words), "the end of the world (live in berlin)");
+        assert_eq!(Case::Sentence.apply(value, &small_words), "The end of the world (live in berlin)");
+        assert_eq!(Case::Title.apply(value, &small_words), "The End of the World (Live in Berlin)");
+        assert_eq!(Case::Title.apply("return of the king: a tale", &small_words), "Return of the King: A Tale");
+        assert_eq!(Case::Title.apply("what is it of", &small_words), "What Is It Of");
+    }
+}
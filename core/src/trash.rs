@@ -0,0 +1,289 @@
+//! Moving files to the trash, following the FreeDesktop.org trash spec, so
+//! that they can be restored with any file manager.
+
+use std::fs::DirBuilder;
+use std::fs::OpenOptions;
+use std::io::Error as IoError;
+use std::io::ErrorKind as IoErrorKind;
+use std::io::Write;
+use std::os::unix::ffi::OsStrExt;
+use std::os::unix::fs::DirBuilderExt;
+use std::os::unix::fs::MetadataExt;
+use std::os::unix::fs::PermissionsExt;
+use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
+
+use crate::value::Date;
+
+/// The trash in the user's home, following the XDG base directory spec.
+pub fn home_trash_dir() -> Option<PathBuf> {
+    let data_dir =
+        std::env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?
+    ;
+
+    Some(data_dir.join("Trash"))
+}
+
+/// Moves a file to the trash in the user's home, or to the trash at the top
+/// of its own filesystem if it is on another one, such as an external drive.
+pub fn move_to_trash(path: &Path) -> Result<(), IoError> {
+    let trash_dir = home_trash_dir().ok_or_else(|| IoError::new(IoErrorKind::NotFound, "no home directory to keep the trash in"))?;
+    move_to_trash_in(&trash_dir, path)
+}
+
+fn move_to_trash_in(home_trash_dir: &Path, path: &Path) -> Result<(), IoError> {
+    match move_into(home_trash_dir, path, None) {
+        Err(err) if err.kind() == IoErrorKind::CrossesDevices => {
+            let path = path.canonicalize()?;
+            let top_dir = mount_point(&path)?;
+            move_into(&top_dir_trash_dir(&top_dir)?, &path, Some(&top_dir))
+        },
+        result => result,
+    }
+}
+
+/// The directory at the top of the filesystem a path is on.
+fn mount_point(path: &Path) -> Result<PathBuf, IoError> {
+    let dev = std::fs::metadata(path)?.dev();
+    let mut top_dir = path;
+
+    while let Some(parent) = top_dir.parent() {
+        if std::fs::metadata(parent)?.dev() != dev {
+            break;
+        }
+
+        top_dir = parent;
+    }
+
+    Ok(top_dir.to_path_buf())
+}
+
+/// The trash of the user at the top of a filesystem, as the trash spec
+/// asks: `.Trash/$uid` if the administrator set up a shared `.Trash` there,
+/// otherwise `.Trash-$uid`, which is created if need be.
+fn top_dir_trash_dir(top_dir: &Path) -> Result<PathBuf, IoError> {
+    let uid = unsafe { libc::getuid() };
+    let shared_dir = top_dir.join(".Trash");
+
+    // The shared trash must be a real directory with the sticky bit set, so
+    // that users can't remove each other's trash.
+    let is_usable = std::fs::symlink_metadata(&shared_dir).is_ok_and(|meta| meta.is_dir() && meta.permissions().mode() & 0o1000 != 0);
+
+    if is_usable {
+        let trash_dir = shared_dir.join(uid.to_string());
+
+        if DirBuilder::new().mode(0o700).create(&trash_dir).is_ok() || trash_dir.is_dir() {
+            return Ok(trash_dir);
+        }
+    }
+
+    let trash_dir = top_dir.join(format!(".Trash-{}", uid));
+
+    match DirBuilder::new().mode(0o700).create(&trash_dir) {
+        Err(err) if err.kind() != IoErrorKind::AlreadyExists => Err(err),
+        _ => Ok(trash_dir),
+    }
+}
+
+/// Moves a file into a trash directory, next to a `.trashinfo` file noting
+/// where it came from and when. Files are not copied across filesystems, so
+/// this fails for files on another filesystem than the trash. The path kept
+/// in a trash at the top of a filesystem is relative to the top directory.
+pub fn move_into(trash_dir: &Path, path: &Path, top_dir: Option<&Path>) -> Result<(), IoError> {
+    let path = path.canonicalize()?;
+    let files_dir = trash_dir.join("files");
+    let info_dir = trash_dir.join("info");
+
+    std::fs::create_dir_all(&files_dir)?;
+    std::fs::create_dir_all(&info_dir)?;
+
+    let file_name = path.file_name().ok_or_else(|| IoError::new(IoErrorKind::InvalidInput, "not a file"))?;
+
+    // The info file is created first, which claims the name.
+    for n in 1.. {
+        let mut trash_name = file_name.to_os_string();
+        if n > 1 { trash_name.push(format!(" {}", n)); }
+
+        let mut info_name = trash_name.clone();
+        info_name.push(".trashinfo");
+
+        let info_path = info_dir.join(info_name);
+        let trashed_path = files_dir.join(&trash_name);
+
+        if trashed_path.exists() {
+            continue;
+        }
+
+        let mut info_file = match OpenOptions::new().write(true).create_new(true).open(&info_path) {
+            Ok(info_file) => info_file,
+            Err(err) if err.kind() == IoErrorKind::AlreadyExists => continue,
+            Err(err) => return Err(err),
+        };
+
+        let info_path_value = top_dir.and_then(|top_dir| path.strip_prefix(top_dir).ok()).unwrap_or(&path);
+        let info = format!("[Trash Info]\nPath={}\nDeletionDate={}\n", encode_path(info_path_value), deletion_date(SystemTime::now()));
+
+        let result = info_file.write_all(info.as_bytes()).and_then(|()| std::fs::rename(&path, &trashed_path));
+
+        if let Err(err) = result {
+            std::fs::remove_file(&info_path).ok();
+            return Err(err);
+        }
+
+        return Ok(());
+    }
+
+    unreachable!()
+}
+
+/// Percent-encodes a path as the trash spec asks, leaving slashes as they are.
+fn encode_path(path: &Path) -> String {
+    let mut encoded = String::new();
+
+    for &byte in path.as_os_str().as_bytes() {
+        match byte {
+            b'A'..=b'Z' | b'a'..=b'z' | b'0'..=b'9' | b'-' | b'_' | b'.' | b'~' | b'/' => encoded.push(byte as char),
+            _ => encoded.push_str(&format!("%{:02X}", byte)),
+        }
+    }
+
+    encoded
+}
+
+/// Writes out a time in local time, as the trash spec asks.
+fn deletion_date(time: SystemTime) -> String {
+    let secs = time.duration_since(SystemTime::UNIX_EPOCH).map_or(0, |d| d.as_secs()) as i64;
+    date_time(secs + utc_offset(secs))
+}
+
+/// How many seconds local time is ahead of UTC at a time, from the C library,
+/// which knows the time zone. Falls back to UTC if it cannot tell.
+fn utc_offset(secs: i64) -> i64 {
+    let time = secs as libc::time_t;
+    let mut tm = unsafe { std::mem::zeroed::<libc::tm>() };
+
+    if unsafe { libc::localtime_r(&time, &mut tm) }.is_null() { 0 }
+    else { tm.tm_gmtoff as i64 }
+}
+
+/// Writes out seconds since the epoch as in `2024-05-02T13:04:05`.
+fn date_time(secs: i64) -> String {
+    let date = Date::from_days_since_epoch(secs.div_euclid(86_400));
+    let secs_of_day = secs.rem_euclid(86_400);
+
+    format!("{}T{:02}:{:02}:{:02}", date, secs_of_day / 3600, (secs_of_day / 60) % 60, secs_of_day % 60)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use std::ffi::OsStr;
+
+    #[test]
+    fn encode_path() {
+        assert_eq!(super::encode_path(Path::new("/music/01 Café.flac")), "/music/01%20Caf%C3%A9.flac");
+
+        // Names that are not UTF-8 keep their bytes.
+        let path = Path::new(OsStr::from_bytes(b"/music/01 Caf\xE9.flac"));
+        assert_eq!(super::encode_path(path), "/music/01%20Caf%E9.flac");
+    }
+
+    #[test]
+    fn date_time() {
+        assert_eq!(super::date_time(86_400 + 3_661), "1970-01-02T01:01:01");
+        assert_eq!(super::date_time(-1), "1969-12-31T23:59:59");
+    }
+
+    #[test]
+    fn move_into() {
+        let dir = std::env::temp_dir().join(format!("diargos-trash-{}", std::process::id()));
+        let trash_dir = dir.join("Trash");
+        std::fs::create_dir_all(&dir).unwrap();
+
+        // Trashing a second file of the same name does not replace the first.
+        for contents in ["first", "second"] {
+            std::fs::write(dir.join("01.flac"), contents).unwrap();
+            super::move_into(&trash_dir, &dir.join("01.flac"), None).unwrap();
+        }
+
+        assert!(!dir.join("01.flac").exists());
+        assert_eq!(std::fs::read_to_string(trash_dir.join("files/01.flac")).unwrap(), "first");
+        assert_eq!(std::fs::read_to_string(trash_dir.join("files/01.flac 2")).unwrap(), "second");
+
+        let info = std::fs::read_to_string(trash_dir.join("info/01.flac 2.trashinfo")).unwrap();
+        let original = dir.canonicalize().unwrap().join("01.flac");
+        assert!(info.starts_with(&format!("[Trash Info]\nPath={}\nDeletionDate=", original.display())));
+
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn top_dir_trash() {
+        let top_dir = std::env::temp_dir().join(format!("diargos-top-trash-{}", std::process::id()));
+        std::fs::create_dir_all(&top_dir).unwrap();
+        let top_dir = top_dir.canonicalize().unwrap();
+        let uid = unsafe { libc::getuid() };
+
+        // Without a shared trash, each user gets a trash of their own.
+        let trash_dir = super::top_dir_trash_dir(&top_dir).unwrap();
+        assert_eq!(trash_dir, top_dir.join(format!(".Trash-{}", uid)));
+
+        std::fs::create_dir_all(top_dir.join("music")).unwrap();
+        std::fs::write(top_dir.join("music/01.flac"), "first").unwrap();
+        super::move_into(&trash_dir, &top_dir.join("music/01.flac"), Some(&top_dir)).unwrap();
+
+        let info = std::fs::read_to_string(trash_dir.join("info/01.flac.trashinfo")).unwrap();
+        assert!(info.starts_with("[Trash Info]\nPath=music/01.flac\nDeletionDate="));
+
+        // A shared trash is only used if it has the sticky bit set.
+        std::fs::create_dir(top_dir.join(".Trash")).unwrap();
+        assert_eq!(super::top_dir_trash_dir(&top_dir).unwrap(), trash_dir);
+
+        std::fs::set_permissions(top_dir.join(".Trash"), std::fs::Permissions::from_mode(0o1777)).unwrap();
+        assert_eq!(super::top_dir_trash_dir(&top_dir).unwrap(), top_dir.join(format!(".Trash/{}", uid)));
+
+        std::fs::remove_dir_all(&top_dir).unwrap();
+    }
+
+    #[test]
+    fn move_to_trash_in() {
+        // Forcing a file onto another filesystem than the home trash needs a
+        // RAM disk to put it on.
+        let home_dir = std::env::temp_dir().join(format!("diargos-home-{}", std::process::id()));
+        let other_dir = Path::new("/dev/shm").join(format!("diargos-volume-{}", std::process::id()));
+
+        let same_device = |a: &Path, b: &Path| Some(std::fs::metadata(a).ok()?.dev() == std::fs::metadata(b).ok()?.dev());
+        if same_device(&std::env::temp_dir(), Path::new("/dev/shm")) != Some(false) {
+            return;
+        }
+
+        std::fs::create_dir_all(&home_dir).unwrap();
+        std::fs::create_dir_all(&other_dir).unwrap();
+        std::fs::write(other_dir.join("01.flac"), "first").unwrap();
+
+        let top_dir = super::mount_point(&other_dir).unwrap();
+        let had_trash = top_dir.join(format!(".Trash-{}", unsafe { libc::getuid() })).exists();
+        let trash_dir = super::top_dir_trash_dir(&top_dir).unwrap();
+        let trash_name = format!("01.flac {}", std::process::id());
+
+        // The file name is made unique, as the trash outlives the test.
+        std::fs::rename(other_dir.join("01.flac"), other_dir.join(&trash_name)).unwrap();
+        super::move_to_trash_in(&home_dir.join("Trash"), &other_dir.join(&trash_name)).unwrap();
+
+        assert!(!other_dir.join(&trash_name).exists());
+        assert!(!home_dir.join("Trash/files").join(&trash_name).exists());
+        assert_eq!(std::fs::read_to_string(trash_dir.join("files").join(&trash_name)).unwrap(), "first");
+
+        std::fs::remove_file(trash_dir.join("files").join(&trash_name)).unwrap();
+        std::fs::remove_file(trash_dir.join("info").join(format!("{}.trashinfo", trash_name))).unwrap();
+        std::fs::remove_dir_all(&home_dir).unwrap();
+        std::fs::remove_dir_all(&other_dir).unwrap();
+
+        if !had_trash && trash_dir.starts_with(top_dir.join(format!(".Trash-{}", unsafe { libc::getuid() }))) {
+            std::fs::remove_dir_all(&trash_dir).unwrap();
+        }
+    }
+}
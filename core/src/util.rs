@@ -1,31 +1,44 @@
+//! Helpers shared across the crate: fitting text to the width of a cell,
+//! and finding, reading, and writing FLAC files.
 
 use std::collections::HashMap;
+use std::fs::OpenOptions;
 use std::io::Error as IoError;
 use std::path::Path;
+use std::path::PathBuf;
+use std::time::SystemTime;
 
 use globset::Glob;
 use metaflac::Tag;
 use metaflac::Block;
-use unicode_width::UnicodeWidthChar;
+use metaflac::BlockType;
+use metaflac::Error as MetaflacError;
+use unicode_segmentation::UnicodeSegmentation;
 use unicode_width::UnicodeWidthStr;
 
-use crate::consts::*;
 use crate::data::Column;
 use crate::data::ColumnKey;
 use crate::data::Record;
-use crate::data::Records;
+use crate::value::RATING_STARS;
+use crate::value::ValueKind;
 
+/// Whether text was cut short to fit a width.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum TrimStatus {
+    /// The text fits as is.
     Untrimmed,
+    /// The text was cut short, leaving some columns of padding where a wide
+    /// character did not fit, and with whether an ellipsis should follow.
     Trimmed(usize, bool),
 }
 
 impl TrimStatus {
+    /// Whether the text was cut short.
     pub fn is_trimmed(&self) -> bool {
         matches!(self, Self::Trimmed(..))
     }
 
+    /// The columns left empty after the cut text.
     pub fn padding(&self) -> usize {
         match self {
             Self::Untrimmed => 0,
@@ -33,6 +46,7 @@ impl TrimStatus {
         }
     }
 
+    /// Whether an ellipsis should follow the cut text.
     pub fn emit_ellipsis(&self) -> bool {
         match self {
             Self::Untrimmed => false,
@@ -41,27 +55,37 @@ impl TrimStatus {
     }
 }
 
+/// Text fit to a width.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub struct TrimOutput<'a> {
+    /// The part of the text that fits.
     pub display_str: &'a str,
+    /// How wide the part that fits is.
     pub output_width: usize,
+    /// How wide the whole text is.
     pub full_real_width: usize,
+    /// Whether and how the text was cut short.
     pub trim_status: TrimStatus,
 }
 
 impl<'a> TrimOutput<'a> {
+    /// Where an ellipsis goes after the text.
     pub fn ellipsis_offset(&self) -> usize {
         self.output_width + self.trim_status.padding()
     }
 }
 
+/// What part of a field a piece of text is.
 #[derive(Debug, Clone, Copy, PartialEq, Eq)]
 pub enum FigmentKind {
+    /// A value.
     Val,
+    /// The separator between two values.
     Sep,
 }
 
 impl FigmentKind {
+    /// Whether the text is a separator.
     pub fn is_sep(&self) -> bool {
         matches!(self, Self::Sep)
     }
@@ -124,6 +148,9 @@ enum FigOrWidth<'a> {
     Width(usize),
 }
 
+/// The pieces of the values of a field, joined by separators and fit to a
+/// width, each with its offset. An ellipsis follows if the values had to be
+/// cut short.
 pub struct MultiFigments<'a, S: AsRef<str>> {
     offset: usize,
     ellipsis: &'a str,
@@ -132,6 +159,7 @@ pub struct MultiFigments<'a, S: AsRef<str>> {
 }
 
 impl<'a, S: AsRef<str>> MultiFigments<'a, S> {
+    /// Fits values joined by a separator to a width.
     pub fn new(values: &'a [S], target_width: usize, separator: &'a str, ellipsis: &'a str) -> Self {
         // If the ellipsis is too wide for the target width, do not try and print it.
         let ellipsis_width =
@@ -280,13 +308,17 @@ impl<'a, S: AsRef<str> + Clone> Iterator for MultiFigments<'a, S> {
     }
 }
 
+/// Helpers for text widths and FLAC files.
 pub struct Util;
 
 impl Util {
+    /// Fits text to a width, cutting it short if need be.
     pub fn trim_display_str<'a>(original_str: &'a str, target_width: usize) -> TrimOutput<'a> {
         Self::trim_display_str_elided(original_str, target_width, 0)
     }
 
+    /// Fits text to a width, leaving room for an ellipsis of a given width if it
+    /// has to be cut short.
     pub fn trim_display_str_elided<'a>(
         original_str: &'a str,
         target_width: usize,
@@ -314,7 +346,7 @@ impl Util {
         let mut output_width = 0;
 
         // Padding is used for when the trim cutoff point occurs in the middle
-        // of a multiwidth character. The character cut in the middle will be
+        // of a multiwidth grapheme. The grapheme cut in the middle will be
         // trimmed, and padding will be calculated to fit the remining width.
         // This is not used if the string does not need trimming/eliding.
         let mut padding = 0;
@@ -323,10 +355,12 @@ impl Util {
         // this will be the width the original string will be trimmed to.
         let elided_width = target_width.saturating_sub(ellipsis_width);
 
-        for (i, ch) in original_str.char_indices() {
+        // Whole graphemes are kept or cut, so that combining marks always stay
+        // with the characters they combine with.
+        for (i, grapheme) in original_str.grapheme_indices(true) {
             let last_width = curr_width;
 
-            curr_width += ch.width().unwrap_or(0);
+            curr_width += grapheme.width();
 
             if !past_elision_point && curr_width > elided_width {
                 past_elision_point = true;
@@ -369,60 +403,162 @@ impl Util {
         }
     }
 
-    pub fn max_column_content_width(column: &Column, records: &Records) -> usize {
-        let mut max_seen = column.title.width();
-        let column_key = &column.key;
+    /// Calculates the total display width of multiple values joined by a separator.
+    pub fn multi_display_width<S: AsRef<str>>(values: &[S], separator: &str) -> usize {
+        let total_sep_width = values.len().saturating_sub(1) * separator.width();
+        let total_field_width = values.iter().map(|s| s.as_ref().width()).sum::<usize>();
+
+        total_field_width + total_sep_width
+    }
 
-        for record in records.iter() {
+    /// Writes out a count with commas between groups of three digits.
+    pub fn group_digits(n: usize) -> String {
+        let digits = n.to_string();
+        let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+
+        for (i, c) in digits.chars().enumerate() {
+            if i > 0 && (digits.len() - i).is_multiple_of(3) {
+                grouped.push(',');
+            }
+
+            grouped.push(c);
+        }
+
+        grouped
+    }
+
+    /// The width of the widest value in a column, or of its title. Values are
+    /// measured with markers for invisible characters when those are shown.
+    pub fn max_column_content_width<'a>(column: &Column, records: impl IntoIterator<Item = &'a Record>, reveal_invisible: bool) -> usize {
+        let mut max_seen = column.title.width();
+        for record in records {
             let curr_row_width =
                 match &column.key {
                     ColumnKey::Meta(meta_key) => {
                         record.get_meta(meta_key).map(|vals| {
-                            let total_sep_width = vals.len().saturating_sub(1) * FIELD_SEP_STR.width();
-                            let total_field_width = vals.iter().map(|s| s.width()).sum::<usize>();
-
-                            total_field_width + total_sep_width
+                            // Ratings are shown as a row of stars.
+                            if column.value_kind() == ValueKind::Rating {
+                                Self::multi_display_width(vals, column.separator()).max(RATING_STARS as usize)
+                            }
+                            else if reveal_invisible {
+                                let revealed = vals.iter().map(|v| crate::transform::reveal_invisible(v)).collect::<Vec<_>>();
+                                Self::multi_display_width(&revealed, column.separator())
+                            }
+                            else {
+                                Self::multi_display_width(vals, column.separator())
+                            }
                         }).unwrap_or(0)
                     },
                     ColumnKey::Info(info_key) => {
                         record.get_info(info_key).map(|s| s.width()).unwrap_or(0)
                     },
+                    ColumnKey::Command(command) => {
+                        record.get_command_output(command).map(|s| s.width()).unwrap_or(0)
+                    },
+                    ColumnKey::Computed(template) => {
+                        record.get_computed(template).map(|s| s.width()).unwrap_or(0)
+                    },
                 }
             ;
-            // let curr_row_width = record.get(column_key).map(|s| s.width()).unwrap_or(0);
             max_seen = max_seen.max(curr_row_width);
         }
 
         max_seen
     }
 
-    pub fn read_records_from_dir(working_dir: &Path) -> Result<Records, IoError> {
-        let glob = Glob::new("*.flac").unwrap().compile_matcher();
-        let mut records = Records::new();
+    /// The FLAC files directly in a directory.
+    pub fn list_flac_files(working_dir: &Path) -> Result<Vec<PathBuf>, IoError> {
+        let mut paths = Vec::new();
 
-        for entry in std::fs::read_dir(&working_dir)? {
+        for entry in std::fs::read_dir(working_dir)? {
             let path = entry?.path();
 
-            if glob.is_match(&path) {
-                let mut metadata = HashMap::new();
+            if Self::is_flac_file(&path) {
+                paths.push(path);
+            }
+        }
 
-                let tag = Tag::read_from_path(&path).unwrap();
+        Ok(paths)
+    }
 
-                for block in tag.blocks() {
-                    if let Block::VorbisComment(vc_map) = block {
-                        for (key, values) in vc_map.comments.iter() {
-                            metadata.insert(key.to_string(), values.clone());
-                        }
-                    }
+    /// When a file was last modified, if it can be told.
+    pub fn modified_time(path: &Path) -> Option<SystemTime> {
+        std::fs::metadata(path).and_then(|m| m.modified()).ok()
+    }
+
+    /// Whether a file can be opened for writing, which catches both its
+    /// permissions and a filesystem mounted read-only. Nothing is written.
+    pub fn is_writable(path: &Path) -> bool {
+        OpenOptions::new().write(true).open(path).is_ok()
+    }
+
+    /// Whether a path names a FLAC file, by its extension.
+    pub fn is_flac_file(path: &Path) -> bool {
+        Glob::new("*.flac").unwrap().compile_matcher().is_match(path)
+    }
+
+    /// Reads the metadata of a single file, with no pending edits.
+    pub fn read_record(path: PathBuf) -> Result<Record, MetaflacError> {
+        let mut metadata = HashMap::new();
+
+        let tag = Tag::read_from_path(&path)?;
+
+        for block in tag.blocks() {
+            if let Block::VorbisComment(vc_map) = block {
+                for (key, values) in vc_map.comments.iter() {
+                    metadata.insert(key.to_string(), values.clone());
                 }
+            }
+        }
+
+        let modified = Self::modified_time(&path);
+        let file_size = std::fs::metadata(&path).map(|m| m.len()).ok();
 
-                let record = Record::new(metadata, path);
+        // Streams of unknown length have a sample count of zero.
+        let duration =
+            tag.get_streaminfo()
+            .filter(|info| info.sample_rate > 0 && info.total_samples > 0)
+            .map(|info| info.total_samples / info.sample_rate as u64)
+        ;
+
+        let mut record = Record::new(metadata, path);
+        record.has_picture = tag.pictures().next().is_some();
+        record.file_size = file_size;
+        record.duration = duration;
+        record.modified = modified;
+        record.read_only = !Self::is_writable(&record.file_path);
+
+        Ok(record)
+    }
+
+    /// Writes the pending edits of a record to its file, leaving all other
+    /// metadata in the file untouched. The file can be made to keep the
+    /// modification time it had before.
+    pub fn write_record(record: &Record, preserve_mtime: bool) -> Result<(), MetaflacError> {
+        let modified = if preserve_mtime { Self::modified_time(&record.file_path) } else { None };
 
-                records.push(record);
+        let mut tag = Tag::read_from_path(&record.file_path)?;
+        let vorbis_comments = tag.vorbis_comments_mut();
+
+        for (meta_key, values) in record.edits.iter() {
+            match values {
+                Some(values) => vorbis_comments.set(meta_key.as_str(), values.clone()),
+                None => vorbis_comments.remove(meta_key),
             }
         }
 
-        Ok(records)
+        // A comment block left without tags is dropped altogether.
+        if vorbis_comments.comments.is_empty() {
+            tag.remove_blocks(BlockType::VorbisComment);
+        }
+
+        tag.save()?;
+
+        if let Some(modified) = modified {
+            OpenOptions::new().write(true).open(&record.file_path)?.set_modified(modified)?;
+        }
+
+        Ok(())
     }
 }
 
@@ -433,6 +569,14 @@ mod test {
     use crate::consts::ELLIPSIS_STR;
     use crate::consts::FIELD_SEP_STR;
 
+    #[test]
+    fn group_digits() {
+        assert_eq!(Util::group_digits(0), "0");
+        assert_eq!(Util::group_digits(999), "999");
+        assert_eq!(Util::group_digits(5432), "5,432");
+        assert_eq!(Util::group_digits(1234567), "1,234,567");
+    }
+
     #[test]
     fn trim_display_str_elided() {
         assert_eq!(
@@ -579,6 +723,38 @@ mod test {
                 trim_status: TrimStatus::Trimmed(0, true),
             },
         );
+
+        // A flag is two characters, which are never split up.
+        assert_eq!(
+            Util::trim_display_str_elided("🇯🇵 jp", 1, 0),
+            TrimOutput {
+                display_str: "",
+                output_width: 0,
+                full_real_width: 5,
+                trim_status: TrimStatus::Trimmed(1, false),
+            },
+        );
+        assert_eq!(
+            Util::trim_display_str_elided("🇯🇵 jp", 3, 1),
+            TrimOutput {
+                display_str: "🇯🇵",
+                output_width: 2,
+                full_real_width: 5,
+                trim_status: TrimStatus::Trimmed(0, true),
+            },
+        );
+
+        // Combining marks stay with the character before them, even when
+        // nothing else fits after it.
+        assert_eq!(
+            Util::trim_display_str_elided("y\u{306}\u{306}es", 2, 1),
+            TrimOutput {
+                display_str: "y\u{306}\u{306}",
+                output_width: 1,
+                full_real_width: 3,
+                trim_status: TrimStatus::Trimmed(0, true),
+            },
+        );
     }
 
     #[test]
@@ -0,0 +1,424 @@
+//! The types that tag values are read as, such as numbers, dates, and
+//! ratings.
+
+use std::cmp::Ordering;
+use std::fmt::Display;
+use std::fmt::Formatter;
+use std::fmt::Result as FmtResult;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+use crate::consts::*;
+
+/// The type that the values of a column are interpreted as.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ValueKind {
+    /// Plain text.
+    Text,
+    /// Text that often spans lines, such as lyrics and comments.
+    LongText,
+    /// A whole number out of a configured maximum, shown as stars.
+    Rating,
+    /// A whole number, such as a track number.
+    Integer,
+    /// A date, such as a release date.
+    Date,
+    /// A length of time in seconds, written as in `3:25`.
+    Duration,
+    /// Yes or no, written as in `1`, `true`, or `yes`.
+    Bool,
+}
+
+impl ValueKind {
+    /// Guesses the kind of well-known metadata keys.
+    pub fn infer(meta_key: &str) -> Self {
+        match meta_key {
+            "TRACKNUMBER" | "TRACKTOTAL" | "TOTALTRACKS"
+            | "DISCNUMBER" | "DISCTOTAL" | "TOTALDISCS"
+            | "BPM" => Self::Integer,
+            "DATE" | "ORIGINALDATE" | "RELEASEDATE" => Self::Date,
+            "LENGTH" => Self::Duration,
+            "COMPILATION" => Self::Bool,
+            "RATING" => Self::Rating,
+            "LYRICS" | "UNSYNCEDLYRICS" | "COMMENT" | "DESCRIPTION" => Self::LongText,
+            _ => Self::Text,
+        }
+    }
+
+    /// What values of this kind are, for messages about ones that aren't.
+    pub fn label(&self) -> &'static str {
+        match self {
+            Self::Text => "text",
+            Self::LongText => "long text",
+            Self::Rating => "rating",
+            Self::Integer => "whole number",
+            Self::Date => "date",
+            Self::Duration => "duration",
+            Self::Bool => "yes or no",
+        }
+    }
+
+    /// Checks if a raw string value can be read as this kind.
+    pub fn validate(&self, raw: &str) -> bool {
+        match self {
+            Self::Text | Self::LongText => true,
+            _ => !matches!(Value::parse(raw, *self), Value::Text(..)),
+        }
+    }
+
+    /// The first of some values that can't be read as this kind, if any.
+    /// Empty values are skipped, as they are dropped rather than stored.
+    pub fn first_invalid<'a>(&self, values: &'a [String]) -> Option<&'a str> {
+        values.iter().map(String::as_str).find(|v| !v.is_empty() && !self.validate(v))
+    }
+}
+
+/// How many stars the highest rating is shown as.
+pub const RATING_STARS: u32 = 5;
+
+/// The number of stars a rating out of `max` is worth, rounded, or `None` if
+/// it is not a whole number up to `max`.
+pub fn rating_stars(raw: &str, max: u32) -> Option<u32> {
+    let rating = raw.trim().parse::<u32>().ok().filter(|&r| max > 0 && r <= max)?;
+    Some((rating * RATING_STARS + max / 2) / max)
+}
+
+/// Draws a number of stars as filled stars followed by empty ones.
+pub fn draw_rating(stars: u32) -> String {
+    let stars = stars.min(RATING_STARS) as usize;
+    format!("{}{}", RATING_FULL_STR.repeat(stars), RATING_EMPTY_STR.repeat(RATING_STARS as usize - stars))
+}
+
+/// The rating out of `max` that is one star above or below a rating. A
+/// missing or unreadable rating counts as no stars.
+pub fn bump_rating(raw: Option<&str>, max: u32, up: bool) -> String {
+    let stars = raw.and_then(|raw| rating_stars(raw, max)).unwrap_or(0);
+    let stars = if up { (stars + 1).min(RATING_STARS) } else { stars.saturating_sub(1) };
+
+    ((stars * max + RATING_STARS / 2) / RATING_STARS).to_string()
+}
+
+/// A calendar date with optional month and day, as commonly found in tags.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash)]
+pub struct Date {
+    /// The year.
+    pub year: u16,
+    /// The month, from 1 to 12.
+    pub month: Option<u8>,
+    /// The day of the month, from 1.
+    pub day: Option<u8>,
+}
+
+impl Date {
+    fn days_in_month(year: u16, month: u8) -> u8 {
+        match month {
+            2 if year.is_multiple_of(4) && (!year.is_multiple_of(100) || year.is_multiple_of(400)) => 29,
+            2 => 28,
+            4 | 6 | 9 | 11 => 30,
+            _ => 31,
+        }
+    }
+
+    /// Parses dates in `YYYY`, `YYYY-MM`, or `YYYY-MM-DD` format.
+    pub fn parse(raw: &str) -> Option<Self> {
+        let mut parts = raw.split('-');
+
+        let mut parse_part = |len: usize| -> Option<Option<u16>> {
+            match parts.next() {
+                None => Some(None),
+                Some(p) if p.len() == len && p.bytes().all(|b| b.is_ascii_digit()) => Some(p.parse().ok()),
+                Some(..) => None,
+            }
+        };
+
+        let year = parse_part(4)??;
+        let month = parse_part(2)?.map(|m| m as u8);
+        let day = parse_part(2)?.map(|d| d as u8);
+
+        if parts.next().is_some() {
+            return None;
+        }
+
+        if let Some(month) = month {
+            if !(1..=12).contains(&month) { return None; }
+
+            if let Some(day) = day {
+                if day < 1 || day > Self::days_in_month(year, month) { return None; }
+            }
+        }
+
+        Some(Self { year, month, day })
+    }
+
+    /// Reads a date from separately entered parts. The day, or the month and
+    /// the day, may be left blank, and single digits are padded.
+    pub fn from_parts(year: &str, month: &str, day: &str) -> Option<Self> {
+        let pad = |part: &str| if part.len() == 1 { format!("0{}", part) } else { part.to_string() };
+        let parts = [year.trim().to_string(), pad(month.trim()), pad(day.trim())];
+
+        // A day without a month is not a date.
+        let num_given = parts.iter().take_while(|part| !part.is_empty()).count();
+        if parts[num_given..].iter().any(|part| !part.is_empty()) {
+            return None;
+        }
+
+        Self::parse(&parts[..num_given].join("-"))
+    }
+
+    /// The date a number of days after 1970-01-01, in the proleptic Gregorian
+    /// calendar.
+    pub fn from_days_since_epoch(days: i64) -> Self {
+        // Counts from 0000-03-01, so that leap days end each 400 year era.
+        let days = days + 719_468;
+        let era = days.div_euclid(146_097);
+        let day_of_era = days.rem_euclid(146_097);
+        let year_of_era = (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+        let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+        let shifted_month = (5 * day_of_year + 2) / 153;
+
+        let day = day_of_year - (153 * shifted_month + 2) / 5 + 1;
+        let month = if shifted_month < 10 { shifted_month + 3 } else { shifted_month - 9 };
+        let year = year_of_era + era * 400 + if month <= 2 { 1 } else { 0 };
+
+        Self { year: year as u16, month: Some(month as u8), day: Some(day as u8) }
+    }
+
+    /// The current date in UTC.
+    pub fn today() -> Self {
+        let secs = SystemTime::now().duration_since(UNIX_EPOCH).map(|d| d.as_secs()).unwrap_or(0);
+        Self::from_days_since_epoch((secs / 86_400) as i64)
+    }
+}
+
+impl Display for Date {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        write!(f, "{:04}", self.year)?;
+
+        if let Some(month) = self.month {
+            write!(f, "-{:02}", month)?;
+
+            if let Some(day) = self.day {
+                write!(f, "-{:02}", day)?;
+            }
+        }
+
+        Ok(())
+    }
+}
+
+/// A typed interpretation of the raw string values of a field. Raw values
+/// that can not be read as the desired kind are kept as text.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Value {
+    /// A single value, as text.
+    Text(String),
+    /// More than one value.
+    MultiText(Vec<String>),
+    /// A whole number.
+    Integer(i64),
+    /// A date.
+    Date(Date),
+    /// A length of time in seconds.
+    Duration(u64),
+    /// Yes or no.
+    Bool(bool),
+}
+
+impl Value {
+    /// Reads the values of a field as a kind. Fields with more than one value
+    /// stay text.
+    pub fn new<S: AsRef<str>>(raw_values: &[S], kind: ValueKind) -> Self {
+        match raw_values {
+            [raw] => Self::parse(raw.as_ref(), kind),
+            _ => Self::MultiText(raw_values.iter().map(|s| s.as_ref().to_string()).collect()),
+        }
+    }
+
+    /// Reads a single value as a kind, keeping it as text if it can't be read as
+    /// one.
+    pub fn parse(raw: &str, kind: ValueKind) -> Self {
+        let trimmed = raw.trim();
+
+        let parsed = match kind {
+            ValueKind::Text | ValueKind::LongText => None,
+            // Values such as "3/12" are common for track and disc numbers.
+            ValueKind::Integer | ValueKind::Rating => {
+                trimmed.split('/').next().and_then(|n| n.trim().parse().ok()).map(Self::Integer)
+            },
+            ValueKind::Date => Date::parse(trimmed).map(Self::Date),
+            ValueKind::Duration => Self::parse_duration(trimmed).map(Self::Duration),
+            ValueKind::Bool => {
+                match trimmed.to_ascii_lowercase().as_str() {
+                    "1" | "true" | "yes" => Some(Self::Bool(true)),
+                    "0" | "false" | "no" => Some(Self::Bool(false)),
+                    _ => None,
+                }
+            },
+        };
+
+        parsed.unwrap_or_else(|| Self::Text(raw.to_string()))
+    }
+
+    /// Parses durations as either plain seconds, `M:SS`, or `H:MM:SS`.
+    fn parse_duration(raw: &str) -> Option<u64> {
+        let mut total = 0u64;
+        let mut num_parts = 0;
+
+        for part in raw.split(':') {
+            let n: u64 = part.parse().ok()?;
+
+            // Only the leading part may be 60 or larger.
+            if num_parts > 0 && n >= 60 { return None; }
+
+            total = total.checked_mul(60)?.checked_add(n)?;
+            num_parts += 1;
+        }
+
+        if num_parts > 3 { None } else { Some(total) }
+    }
+
+    /// Ranks the variants so that mismatched kinds sort consistently, with
+    /// typed values sorting before text values.
+    fn rank(&self) -> u8 {
+        match self {
+            Self::Bool(..) => 0,
+            Self::Integer(..) => 1,
+            Self::Duration(..) => 2,
+            Self::Date(..) => 3,
+            Self::Text(..) => 4,
+            Self::MultiText(..) => 5,
+        }
+    }
+}
+
+impl Ord for Value {
+    fn cmp(&self, other: &Self) -> Ordering {
+        match (self, other) {
+            (Self::Text(a), Self::Text(b)) => a.cmp(b),
+            (Self::MultiText(a), Self::MultiText(b)) => a.cmp(b),
+            (Self::Integer(a), Self::Integer(b)) => a.cmp(b),
+            (Self::Date(a), Self::Date(b)) => a.cmp(b),
+            (Self::Duration(a), Self::Duration(b)) => a.cmp(b),
+            (Self::Bool(a), Self::Bool(b)) => a.cmp(b),
+            _ => self.rank().cmp(&other.rank()),
+        }
+    }
+}
+
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl Display for Value {
+    fn fmt(&self, f: &mut Formatter) -> FmtResult {
+        match self {
+            Self::Text(s) => write!(f, "{}", s),
+            Self::MultiText(vs) => write!(f, "{}", vs.join(FIELD_SEP_STR)),
+            Self::Integer(n) => write!(f, "{}", n),
+            Self::Date(d) => write!(f, "{}", d),
+            Self::Duration(secs) => {
+                let (h, m, s) = (secs / 3600, (secs / 60) % 60, secs % 60);
+                if h > 0 { write!(f, "{}:{:02}:{:02}", h, m, s) }
+                else { write!(f, "{}:{:02}", m, s) }
+            },
+            Self::Bool(b) => write!(f, "{}", if *b { "yes" } else { "no" }),
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn parse() {
+        assert_eq!(Value::parse("12", ValueKind::Integer), Value::Integer(12));
+        assert_eq!(Value::parse("3/12", ValueKind::Integer), Value::Integer(3));
+        assert_eq!(Value::parse("three", ValueKind::Integer), Value::Text(String::from("three")));
+
+        assert_eq!(
+            Value::parse("1999-02", ValueKind::Date),
+            Value::Date(Date { year: 1999, month: Some(2), day: None }),
+        );
+        assert_eq!(Value::parse("1999-02-29", ValueKind::Date), Value::Text(String::from("1999-02-29")));
+        assert_eq!(
+            Value::parse("2000-02-29", ValueKind::Date),
+            Value::Date(Date { year: 2000, month: Some(2), day: Some(29) }),
+        );
+        assert_eq!(Value::parse("99", ValueKind::Date), Value::Text(String::from("99")));
+
+        assert_eq!(Value::parse("1:02:03", ValueKind::Duration), Value::Duration(3723));
+        assert_eq!(Value::parse("4:61", ValueKind::Duration), Value::Text(String::from("4:61")));
+        assert_eq!(Value::Duration(3723).to_string(), "1:02:03");
+        assert_eq!(Value::Duration(62).to_string(), "1:02");
+
+        assert_eq!(Value::parse("Yes", ValueKind::Bool), Value::Bool(true));
+
+        assert_eq!(
+            Value::new(&["a", "b"], ValueKind::Integer),
+            Value::MultiText(vec![String::from("a"), String::from("b")]),
+        );
+    }
+
+    #[test]
+    fn ordering() {
+        let mut values = vec![
+            Value::parse("10", ValueKind::Integer),
+            Value::parse("n/a", ValueKind::Integer),
+            Value::parse("9", ValueKind::Integer),
+        ];
+
+        values.sort();
+
+        assert_eq!(
+            values,
+            vec![Value::Integer(9), Value::Integer(10), Value::Text(String::from("n/a"))],
+        );
+    }
+
+    #[test]
+    fn validate() {
+        assert!(ValueKind::Date.validate("2020-01-31"));
+        assert!(!ValueKind::Date.validate("2020-13"));
+        assert!(ValueKind::Text.validate("anything"));
+        assert!(ValueKind::LongText.validate("any\nthing"));
+        assert_eq!(ValueKind::infer("LYRICS"), ValueKind::LongText);
+        assert_eq!(ValueKind::Integer.label(), "whole number");
+        assert_eq!(ValueKind::Integer.first_invalid(&[String::from("3"), String::new(), String::from("x")]), Some("x"));
+    }
+
+    #[test]
+    fn ratings() {
+        assert_eq!(rating_stars("60", 100), Some(3));
+        assert_eq!(rating_stars("4", 5), Some(4));
+        assert_eq!(rating_stars("101", 100), None);
+        assert_eq!(rating_stars("good", 100), None);
+        assert_eq!(draw_rating(3), "★★★☆☆");
+
+        assert_eq!(bump_rating(Some("60"), 100, true), "80");
+        assert_eq!(bump_rating(Some("100"), 100, true), "100");
+        assert_eq!(bump_rating(Some("1"), 5, false), "0");
+        assert_eq!(bump_rating(None, 5, true), "1");
+        assert!(ValueKind::Rating.validate("3"));
+    }
+
+    #[test]
+    fn dates() {
+        assert_eq!(Date::from_parts("1999", "2", "3"), Date::parse("1999-02-03"));
+        assert_eq!(Date::from_parts(" 1999 ", "", ""), Date::parse("1999"));
+        assert_eq!(Date::from_parts("1999", "", "3"), None);
+        assert_eq!(Date::from_parts("1999", "2", "30"), None);
+        assert_eq!(Date::from_parts("", "", ""), None);
+
+        assert_eq!(Date::from_days_since_epoch(0).to_string(), "1970-01-01");
+        assert_eq!(Date::from_days_since_epoch(11_016).to_string(), "2000-02-29");
+        assert_eq!(Date::from_days_since_epoch(19_782).to_string(), "2024-02-29");
+        assert_eq!(Date::from_days_since_epoch(-1).to_string(), "1969-12-31");
+    }
+}
@@ -0,0 +1,112 @@
+//! Watching a directory for changes made by other programs.
+
+use std::path::Path;
+use std::sync::mpsc;
+use std::sync::mpsc::RecvTimeoutError;
+use std::time::Duration;
+
+use notify::Event;
+use notify::EventKind;
+use notify::RecommendedWatcher;
+use notify::RecursiveMode;
+use notify::Result as NotifyResult;
+use notify::Watcher;
+use notify::event::AccessKind;
+use notify::event::AccessMode;
+
+use crate::util::Util;
+
+/// How long a directory has to stay quiet before its changes are reported,
+/// since retagging a file usually touches it several times in a row.
+const QUIET_PERIOD: Duration = Duration::from_millis(300);
+
+/// Whether an event can change the wanted files of a directory. Plain reads,
+/// including our own, are not interesting.
+fn is_relevant(event: &Event, is_wanted: &dyn Fn(&Path) -> bool) -> bool {
+    let changes = matches!(
+        event.kind,
+        EventKind::Create(_)
+        | EventKind::Modify(_)
+        | EventKind::Remove(_)
+        | EventKind::Access(AccessKind::Close(AccessMode::Write))
+    );
+
+    changes && event.paths.iter().any(|path| is_wanted(path))
+}
+
+/// Watches a directory for files being added, removed, or changed, FLAC files
+/// unless told otherwise, and calls back from a background thread once things
+/// have settled down. Watching stops when this is dropped.
+pub struct DirWatcher {
+    _watcher: RecommendedWatcher,
+}
+
+impl DirWatcher {
+    /// Watches a directory for changes to its FLAC files.
+    pub fn new<F>(dir: &Path, on_change: F) -> NotifyResult<Self>
+    where
+        F: Fn() + Send + 'static,
+    {
+        Self::with_filter(dir, Util::is_flac_file, on_change)
+    }
+
+    /// Watches a directory for changes to the files a filter picks out.
+    pub fn with_filter<W, F>(dir: &Path, is_wanted: W, on_change: F) -> NotifyResult<Self>
+    where
+        W: Fn(&Path) -> bool + Send + 'static,
+        F: Fn() + Send + 'static,
+    {
+        let (sender, receiver) = mpsc::channel();
+
+        let mut watcher = notify::recommended_watcher(move |result: NotifyResult<Event>| {
+            if let Ok(event) = result {
+                if is_relevant(&event, &is_wanted) {
+                    sender.send(()).ok();
+                }
+            }
+        })?;
+
+        watcher.watch(dir, RecursiveMode::NonRecursive)?;
+
+        // The thread ends once the watcher, and with it the sender, is dropped.
+        std::thread::spawn(move || {
+            while receiver.recv().is_ok() {
+                loop {
+                    match receiver.recv_timeout(QUIET_PERIOD) {
+                        Ok(()) => continue,
+                        Err(RecvTimeoutError::Timeout) => break,
+                        Err(RecvTimeoutError::Disconnected) => return,
+                    }
+                }
+
+                on_change();
+            }
+        });
+
+        Ok(Self { _watcher: watcher })
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn changes_are_reported() {
+        let dir = std::env::temp_dir().join(format!("diargos-watch-{}", std::process::id()));
+        std::fs::create_dir_all(&dir).unwrap();
+
+        let (sender, receiver) = mpsc::channel();
+        let sender = std::sync::Mutex::new(sender);
+        let watcher = DirWatcher::new(&dir, move || { sender.lock().unwrap().send(()).ok(); }).unwrap();
+
+        std::fs::write(dir.join("notes.txt"), "ignored").unwrap();
+        assert!(receiver.recv_timeout(QUIET_PERIOD * 3).is_err());
+
+        std::fs::write(dir.join("01.flac"), "not really a FLAC").unwrap();
+        assert!(receiver.recv_timeout(QUIET_PERIOD * 10).is_ok());
+
+        drop(watcher);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
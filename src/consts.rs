@@ -1,12 +0,0 @@
-
-pub const ELLIPSIS_STR: &str = "⋯";
-pub const FIELD_SEP_STR: &str = "|";
-
-pub const MISSING_FILL: &str = "x";
-
-pub const COLUMN_SEP: &str = " │ ";
-// pub const COLUMN_HEADER_SEP: &str = "─┼─";
-pub const COLUMN_HEADER_SEP: &str = "═╪═";
-
-// pub const COLUMN_HEADER_BAR: &str = "─";
-pub const COLUMN_HEADER_BAR: &str = "═";
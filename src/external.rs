@@ -0,0 +1,138 @@
+use std::fs::File;
+use std::os::unix::io::AsRawFd;
+use std::process::Command as Process;
+use std::time::Duration;
+
+use cursive::Cursive;
+
+/// The line that separates values in the file, when a value spans lines.
+const VALUE_SEP_LINE: &str = "---";
+
+/// The editor used when neither `$VISUAL` nor `$EDITOR` is set.
+const DEFAULT_EDITOR: &str = "vi";
+
+pub type OnEdited = Box<dyn FnOnce(&mut Cursive, Result<Vec<String>, String>)>;
+
+/// Values to be edited in an external editor once the terminal UI has been
+/// suspended. This is kept as the user data of the `Cursive` instance.
+pub struct PendingEdit {
+    pub values: Vec<String>,
+    pub on_edited: OnEdited,
+}
+
+impl PendingEdit {
+    /// Stops the event loop so that the terminal can be handed over to the
+    /// editor. The callback is called once the UI is back.
+    pub fn request(siv: &mut Cursive, values: Vec<String>, on_edited: OnEdited) {
+        siv.set_user_data(Self { values, on_edited });
+        siv.quit();
+    }
+}
+
+/// Whether any of the values has a line break in it.
+pub fn spans_lines(values: &[String]) -> bool {
+    values.iter().any(|v| v.contains('\n'))
+}
+
+/// Writes values out to be edited, one per line, or separated by `---` lines
+/// if values can span lines.
+pub fn to_text(values: &[String], spans_lines: bool) -> String {
+    let sep = if spans_lines { format!("\n{}\n", VALUE_SEP_LINE) } else { String::from("\n") };
+
+    values.join(&sep)
+}
+
+/// Reads values back from edited text, in the layout `to_text` wrote them
+/// in. Empty values are dropped.
+pub fn from_text(text: &str, spans_lines: bool) -> Vec<String> {
+    let values: Vec<String> =
+        if spans_lines {
+            let mut blocks = vec![Vec::new()];
+
+            for line in text.lines() {
+                if line == VALUE_SEP_LINE { blocks.push(Vec::new()); }
+                else { blocks.last_mut().unwrap().push(line); }
+            }
+
+            blocks.into_iter().map(|lines| lines.join("\n").trim_end_matches('\n').to_string()).collect()
+        }
+        else {
+            text.lines().map(String::from).collect()
+        }
+    ;
+
+    values.into_iter().filter(|v| !v.is_empty()).collect()
+}
+
+fn editor_command() -> String {
+    std::env::var("VISUAL")
+    .or_else(|_| std::env::var("EDITOR"))
+    .ok()
+    .filter(|editor| !editor.trim().is_empty())
+    .unwrap_or_else(|| String::from(DEFAULT_EDITOR))
+}
+
+/// The input thread of the torn down terminal UI is still waiting on the
+/// terminal, and would take the first key meant for the editor. Feeding it a
+/// key of its own lets it see that the UI is gone and stop.
+fn release_input() {
+    if let Ok(tty) = File::open("/dev/tty") {
+        let key = 0u8;
+
+        unsafe { libc::ioctl(tty.as_raw_fd(), libc::TIOCSTI, &key); }
+
+        std::thread::sleep(Duration::from_millis(50));
+    }
+}
+
+/// Edits values in the editor from `$VISUAL` or `$EDITOR`, by way of a
+/// temporary file. The terminal must not be in use by the UI.
+pub fn edit_values(values: &[String]) -> Result<Vec<String>, String> {
+    let spans_lines = spans_lines(values);
+
+    let path = std::env::temp_dir().join(format!("diargos-edit-{}.txt", std::process::id()));
+    std::fs::write(&path, format!("{}\n", to_text(values, spans_lines))).map_err(|err| err.to_string())?;
+
+    release_input();
+
+    // The editor may come with arguments of its own, such as `code --wait`.
+    let editor = editor_command();
+    let mut words = editor.split_whitespace();
+    let program = words.next().unwrap_or(DEFAULT_EDITOR);
+
+    let status =
+        Process::new(program)
+        .args(words)
+        .arg(&path)
+        .status()
+        .map_err(|err| format!("Unable to run {}: {}", program, err))
+    ;
+
+    let text = std::fs::read_to_string(&path).map_err(|err| err.to_string());
+    std::fs::remove_file(&path).ok();
+
+    match status? {
+        status if status.success() => Ok(from_text(&text?, spans_lines)),
+        status => Err(format!("{} exited with {}", program, status)),
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    use str_macro::str;
+
+    #[test]
+    fn text_round_trip() {
+        let values = vec![str!("Artist A"), str!("Guest|Person")];
+        assert_eq!(to_text(&values, false), "Artist A\nGuest|Person");
+        assert_eq!(from_text("Artist A\n\nGuest|Person\nNew\n", false), vec![str!("Artist A"), str!("Guest|Person"), str!("New")]);
+
+        let values = vec![str!("Verse one\n\nVerse two"), str!("Other")];
+        let text = to_text(&values, spans_lines(&values));
+        assert_eq!(text, "Verse one\n\nVerse two\n---\nOther");
+        assert_eq!(from_text(&text, true), values);
+        assert_eq!(from_text("Only one\nvalue\n", true), vec![str!("Only one\nvalue")]);
+    }
+}
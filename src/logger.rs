@@ -0,0 +1,75 @@
+use std::fs::File;
+use std::fs::OpenOptions;
+use std::io::Error as IoError;
+use std::io::Write;
+use std::path::Path;
+use std::path::PathBuf;
+use std::sync::Mutex;
+use std::time::SystemTime;
+use std::time::UNIX_EPOCH;
+
+use log::LevelFilter;
+use log::Log;
+use log::Metadata;
+use log::Record;
+
+/// Appends log messages to a file, since the UI has the terminal to itself.
+struct FileLogger {
+    file: Mutex<File>,
+}
+
+impl Log for FileLogger {
+    fn enabled(&self, metadata: &Metadata) -> bool {
+        metadata.level() <= log::max_level()
+    }
+
+    fn log(&self, record: &Record) {
+        if !self.enabled(record.metadata()) {
+            return;
+        }
+
+        let timestamp = SystemTime::now().duration_since(UNIX_EPOCH).unwrap_or_default();
+        let mut file = self.file.lock().unwrap();
+
+        // There is nowhere left to report failing to log to.
+        writeln!(
+            file,
+            "{}.{:03} {:<5} {}: {}",
+            timestamp.as_secs(), timestamp.subsec_millis(), record.level(), record.target(), record.args(),
+        ).ok();
+    }
+
+    fn flush(&self) {
+        self.file.lock().unwrap().flush().ok();
+    }
+}
+
+/// Where the log is written if no other file is given, following the XDG base
+/// directory spec.
+pub fn default_path() -> Option<PathBuf> {
+    let state_dir =
+        std::env::var_os("XDG_STATE_HOME").map(PathBuf::from)
+        .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/state")))?
+    ;
+
+    Some(state_dir.join("diargos").join("diargos.log"))
+}
+
+/// Starts logging messages up to a level to a file. Nothing is logged, and no
+/// file is created, when the level is off.
+pub fn init(path: &Path, level: LevelFilter) -> Result<(), IoError> {
+    if level == LevelFilter::Off {
+        return Ok(());
+    }
+
+    if let Some(parent) = path.parent() {
+        std::fs::create_dir_all(parent)?;
+    }
+
+    let file = OpenOptions::new().create(true).append(true).open(path)?;
+
+    log::set_boxed_logger(Box::new(FileLogger { file: Mutex::new(file) })).map_err(IoError::other)?;
+    log::set_max_level(level);
+
+    Ok(())
+}
@@ -1,64 +1,388 @@
 
-mod config;
-mod consts;
-mod cursor;
-mod data;
-mod model;
-mod util;
+mod external;
+mod logger;
+mod places;
 mod views;
 
-use std::fs::File;
-use std::io::BufReader;
+use std::io::Write;
+use std::io::Error as IoError;
+use std::path::Path;
 use std::path::PathBuf;
+use std::rc::Rc;
 
 use clap::Clap;
 use cursive::Cursive;
 use cursive::CursiveExt;
+use cursive::traits::Nameable;
 use cursive::views::Dialog;
+use log::LevelFilter;
 
-use crate::config::Config;
-use crate::data::Data;
-use crate::model::Model;
-use crate::util::Util;
-use crate::views::TagRecordView;
+use diargos_core::command::Keymap;
+use diargos_core::command::ReferenceFormat;
+use diargos_core::config::Config;
+use diargos_core::config::ConfigLayer;
+use diargos_core::config::LayeredConfig;
+use diargos_core::error::Error;
+use crate::external::PendingEdit;
+use diargos_core::filter::Filter;
+use diargos_core::import::TagImport;
+use diargos_core::model::Model;
+use diargos_core::preset::Preset;
+use diargos_core::source::FlacDir;
+use diargos_core::source::FlacDirs;
+use diargos_core::source::RecordSource;
+use crate::views::TabsView;
+use crate::views::tabs::ConfigureModel;
+use crate::views::tabs::OpenModel;
+use crate::views::tabs::TABS_VIEW_NAME;
 
 #[derive(Clap)]
 struct Opts {
-    working_dir: Option<PathBuf>,
+    #[clap(subcommand)]
+    subcommand: Option<SubCommand>,
+
+    /// The directories to load into one table, or else the current one,
+    /// then optionally a config file, which takes precedence over the user
+    /// config and the `.diargos` config file of each directory.
+    paths: Vec<PathBuf>,
+
+    /// A column preset file to apply on top of the config.
+    #[clap(long)]
+    preset: Option<PathBuf>,
+
+    /// Starts by browsing for the directory to open.
+    #[clap(long)]
+    browse: bool,
+
+    /// Prints the columns for the directory as a plain text table and exits,
+    /// instead of starting the UI.
+    #[clap(long)]
+    print: bool,
+
+    /// With --print, prints a JSON object for each file instead of a table.
+    #[clap(long)]
+    json: bool,
+
+    /// Turns off every command that changes tags or files.
+    #[clap(long = "readonly")]
+    readonly: bool,
+
+    /// Makes saving show a diff of what would be written instead of writing.
+    #[clap(long)]
+    dry_run: bool,
+
+    /// How much to log: off, error, warn, info, debug, or trace.
+    #[clap(long, default_value = "off")]
+    log_level: LevelFilter,
+
+    /// The file to log to, instead of `diargos.log` in the XDG state directory.
+    #[clap(long)]
+    log_file: Option<PathBuf>,
+
+    /// Another directory to open in its own tab, can be given more than once.
+    #[clap(long = "tab")]
+    tabs: Vec<PathBuf>,
+
+    /// Shows a built-in library of fake records instead of reading files.
+    #[cfg(feature = "fixtures")]
+    #[clap(long)]
+    demo: bool,
+}
+
+#[derive(Clap)]
+enum SubCommand {
+    /// Prints the active keybindings and all commands.
+    Keymap(KeymapOpts),
+    /// Prints tags of the files in a directory without starting the UI, one
+    /// file per line.
+    Get(GetOpts),
+    /// Sets tags of the files in a directory and saves them without starting
+    /// the UI.
+    Set(SetOpts),
+    /// Applies a JSON or CSV edits file to the files in a directory and saves
+    /// them without starting the UI.
+    Apply(ApplyOpts),
+}
+
+#[derive(Clap)]
+struct KeymapOpts {
     config_file: Option<PathBuf>,
+
+    /// Either md or json.
+    #[clap(long, default_value = "md")]
+    format: ReferenceFormat,
 }
 
-fn main() {
-    let opts = Opts::parse();
+#[derive(Clap)]
+struct GetOpts {
+    /// The tags to print after the path of each file, then the directory.
+    #[clap(required = true, min_values = 2)]
+    args: Vec<String>,
+
+    /// Only prints files that match, such as `missing(ARTIST) or GENRE~rock`.
+    #[clap(long)]
+    filter: Option<String>,
+
+    /// Prints a JSON object for each file instead of tab-separated values.
+    #[clap(long)]
+    json: bool,
+
+    /// A config file, for its normalization and other settings.
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
+
+#[derive(Clap)]
+struct SetOpts {
+    /// Tags to set, as `KEY=value`, then the directory. A key given more
+    /// than once gets each value, and `KEY=` removes the tag.
+    #[clap(required = true, min_values = 2)]
+    args: Vec<String>,
+
+    /// Only changes files that match, such as `missing(ARTIST) or GENRE~rock`.
+    #[clap(long)]
+    filter: Option<String>,
+
+    /// Prints a diff of what would be written instead of saving.
+    #[clap(long)]
+    dry_run: bool,
+
+    /// A config file, for its normalization and other settings.
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
 
-    let working_dir =
-        match opts.working_dir {
-            None => std::env::current_dir().unwrap(),
-            Some(working_dir) => working_dir,
+#[derive(Clap)]
+struct ApplyOpts {
+    /// A JSON object from file path to field changes, such as
+    /// `{"01.flac": {"TITLE": "Song", "COMMENT": null}}`, or a CSV table like
+    /// the one read by import_csv.
+    edits: PathBuf,
+
+    directory: PathBuf,
+
+    /// Prints a diff of what would be written instead of saving.
+    #[clap(long)]
+    dry_run: bool,
+
+    /// A config file, for its normalization and other settings.
+    #[clap(long)]
+    config: Option<PathBuf>,
+}
+
+/// Reads the user config and the config file given on the command line, to
+/// be layered with the config of each directory that is opened.
+fn load_config(config_file: Option<PathBuf>) -> Result<LayeredConfig, Error> {
+    let command_line = config_file.map(|path| ConfigLayer::read_from_path(&path)).transpose()?.unwrap_or_default();
+    LayeredConfig::load(command_line)
+}
+
+/// Ends a subcommand that runs without the UI, with a failing exit status if
+/// it failed.
+fn exit_on_error(result: Result<(), String>) -> ! {
+    match result {
+        Ok(()) => std::process::exit(0),
+        Err(err) => {
+            eprintln!("error: {}", err);
+            std::process::exit(1);
+        },
+    }
+}
+
+fn print_lines(lines: Vec<String>) {
+    let stdout = std::io::stdout();
+    let mut stdout = stdout.lock();
+
+    for line in lines {
+        // Output piped into something like `head` may stop being read.
+        if writeln!(stdout, "{}", line).is_err() {
+            break;
         }
+    }
+}
+
+fn parse_filter(filter: Option<&str>) -> Result<Option<Filter>, String> {
+    filter.map(str::parse).transpose()
+}
+
+fn run_get(opts: GetOpts) -> Result<(), String> {
+    let filter = parse_filter(opts.filter.as_deref())?;
+    let config = load_config(opts.config).map_err(|err| err.to_string())?;
+    let (working_dir, meta_keys) = opts.args.split_last().unwrap();
+    let model = load_batch_model(&config, PathBuf::from(working_dir))?;
+
+    print_lines(diargos_core::batch::get(&model, meta_keys, filter.as_ref(), opts.json));
+    Ok(())
+}
+
+fn run_set(opts: SetOpts) -> Result<(), String> {
+    let (working_dir, assignments) = opts.args.split_last().unwrap();
+    let changes = diargos_core::batch::parse_assignments(assignments)?;
+    let filter = parse_filter(opts.filter.as_deref())?;
+    let mut config = load_config(opts.config).map_err(|err| err.to_string())?;
+    if opts.dry_run { config.command_line.dry_run = Some(true); }
+    let mut model = load_batch_model(&config, PathBuf::from(working_dir))?;
+
+    let (edited, total) = diargos_core::batch::set(&mut model, &changes, filter.as_ref());
+
+    save_batch(&mut model, &format!("{} of {} file(s) changed", edited, total))
+}
+
+fn run_apply(opts: ApplyOpts) -> Result<(), String> {
+    let import = TagImport::read_from_path(&opts.edits).map_err(|err| format!("{}: {}", opts.edits.display(), err))?;
+    let mut config = load_config(opts.config).map_err(|err| err.to_string())?;
+    if opts.dry_run { config.command_line.dry_run = Some(true); }
+    let mut model = load_batch_model(&config, opts.directory)?;
+
+    let summary = model.edit_records(format!("Apply {}", opts.edits.display()), |records| import.apply(records));
+
+    for unmatched in &summary.unmatched_rows {
+        eprintln!("{}: no unique file matches", unmatched);
+    }
+
+    save_batch(&mut model, &format!("{} of {} file(s) changed", summary.edited_records, summary.matched_rows))
+}
+
+/// Saves the edits staged by a batch subcommand, or prints a diff of them in
+/// dry-run mode, after reporting what changed.
+fn save_batch(model: &mut Model, changed: &str) -> Result<(), String> {
+    if model.dry_run {
+        print!("{}", model.pending_diff());
+        eprintln!("{}, nothing saved in dry-run mode", changed);
+        return Ok(());
+    }
+
+    let summary = model.save_all();
+
+    eprintln!("{}, {}", changed, summary);
+
+    for (file_path, err) in &summary.failures {
+        eprintln!("{}: {}", file_path.display(), err);
+    }
+
+    if summary.failures.is_empty() && summary.conflicts.is_empty() { Ok(()) }
+    else { Err(String::from("not every file could be saved")) }
+}
+
+/// Loads a directory for a subcommand that runs without the UI, warning about
+/// files that could not be read.
+fn load_batch_model(config: &LayeredConfig, working_dir: PathBuf) -> Result<Model, String> {
+    let config = config.resolve(Some(&working_dir)).map_err(|err| err.to_string())?;
+    let model =
+        load_model(&config, None, Box::new(FlacDir::new(working_dir.clone())))
+        .map_err(|err| Error::Dir(working_dir, err).to_string())?
     ;
 
-    let config =
-        match opts.config_file {
-            None => Config::default(),
-            Some(config_file_path) => {
-                let config_file = File::open(config_file_path).unwrap();
-                let reader = BufReader::new(config_file);
-                serde_json::from_reader(reader).unwrap()
-            },
+    for err in &model.load_failures {
+        eprintln!("warning: {}", err);
+    }
+
+    Ok(model)
+}
+
+/// Loads the records from a source into a model set up by the config.
+fn load_model(config: &Config, preset: Option<&Preset>, mut source: Box<dyn RecordSource>) -> Result<Model, IoError> {
+    source.set_show_hidden(config.show_hidden);
+
+    let mut model = Model::load(config.columns.clone(), source)?;
+    model.apply_config(config);
+
+    if let Some(preset) = preset {
+        preset.clone().apply(&mut model);
+    }
+
+    Ok(model)
+}
+
+fn main() {
+    let mut opts = Opts::parse();
+
+    // Not being able to log is not worth failing over.
+    if let Some(log_file) = opts.log_file.clone().or_else(crate::logger::default_path) {
+        if let Err(err) = crate::logger::init(&log_file, opts.log_level) {
+            eprintln!("warning: unable to log to {}: {}", log_file.display(), err);
         }
-    ;
+    }
+
+    log::info!("starting with {:?}", std::env::args().collect::<Vec<_>>());
+
+    match opts.subcommand.take() {
+        Some(SubCommand::Keymap(keymap_opts)) => exit_on_error(run_keymap(keymap_opts)),
+        Some(SubCommand::Get(get_opts)) => exit_on_error(run_get(get_opts)),
+        Some(SubCommand::Set(set_opts)) => exit_on_error(run_set(set_opts)),
+        Some(SubCommand::Apply(apply_opts)) => exit_on_error(run_apply(apply_opts)),
+        None => {},
+    }
+
+    // Errors are only returned before the UI starts, after which they are
+    // shown in dialogs instead.
+    if let Err(err) = run_ui(opts) {
+        eprintln!("error: {}", err);
+        std::process::exit(1);
+    }
+}
+
+fn run_keymap(opts: KeymapOpts) -> Result<(), String> {
+    let config = load_config(opts.config_file).and_then(|config| config.resolve(None)).map_err(|err| err.to_string())?;
+    let keymap = Keymap::with_overrides(&config.keys);
+
+    println!("{}", keymap.reference(opts.format));
+    Ok(())
+}
+
+/// Splits the paths given on the command line into the directories to load
+/// and the config file, which is the last path if there are several and it
+/// is not a directory. Directories given twice are only kept once.
+fn split_paths(mut paths: Vec<PathBuf>) -> (Vec<PathBuf>, Option<PathBuf>) {
+    let config_file = if paths.len() > 1 && paths.last().is_some_and(|p| !p.is_dir()) { paths.pop() } else { None };
+
+    let mut seen = Vec::new();
+    paths.retain(|dir| {
+        let canonical = dir.canonicalize().unwrap_or_else(|_| dir.clone());
+        let is_new = !seen.contains(&canonical);
+        seen.push(canonical);
+        is_new
+    });
+
+    (paths, config_file)
+}
+
+fn run_ui(opts: Opts) -> Result<(), Error> {
+    let (mut working_dirs, config_file) = split_paths(opts.paths);
+
+    if working_dirs.is_empty() {
+        working_dirs.push(std::env::current_dir().map_err(|err| Error::Dir(PathBuf::from("."), err))?);
+    }
+
+    // Say which of several directories could not be read.
+    if working_dirs.len() > 1 {
+        for dir in &working_dirs {
+            std::fs::read_dir(dir).map_err(|err| Error::Dir(dir.clone(), err))?;
+        }
+    }
+
+    let working_dir = working_dirs[0].clone();
 
-    let records = Util::read_records_from_dir(&working_dir).unwrap();
+    // The config files are read again for each directory opened and each
+    // reload, with the flags taking precedence over all of them. The config
+    // of the first directory is used for all of those loaded together.
+    let watched_config_file = config_file.clone();
+    let (readonly, dry_run) = (opts.readonly, opts.dry_run);
 
-    let columns = config.columns;
+    let resolve_config = Rc::new(move |working_dir: Option<&Path>| {
+        let mut layered_config = load_config(config_file.clone())?;
+        if readonly { layered_config.command_line.read_only = Some(true); }
+        if dry_run { layered_config.command_line.dry_run = Some(true); }
+
+        layered_config.resolve(working_dir)
+    });
 
     // use str_macro::str;
-    // use crate::data::Column;
-    // use crate::data::Columns;
-    // use crate::data::ColumnKey;
-    // use crate::data::InfoKind;
-    // use crate::data::Sizing;
+    // use diargos_core::data::Column;
+    // use diargos_core::data::Columns;
+    // use diargos_core::data::ColumnKey;
+    // use diargos_core::data::InfoKind;
+    // use diargos_core::data::Sizing;
 
     // let columns = vec![
     //     Column {
@@ -83,20 +407,125 @@ fn main() {
     //     },
     // ];
 
-    let data = Data::with_data(columns, records);
+    let preset =
+        opts.preset
+        .map(|preset_path| Preset::read_from_path(&preset_path).map_err(|err| Error::Settings(preset_path, err.to_string())))
+        .transpose()?
+    ;
+
+    let tab_dir = Some(working_dir.clone());
+    let (tab_name, source): (_, Box<dyn RecordSource>) = match working_dirs.len() {
+        1 => (TabsView::tab_name(&working_dir), Box::new(FlacDir::new(working_dir.clone()))),
+        n => (format!("{} +{}", TabsView::tab_name(&working_dir), n - 1), Box::new(FlacDirs::new(working_dirs))),
+    };
+
+    #[cfg(feature = "fixtures")]
+    let (tab_name, tab_dir, source): (_, _, Box<dyn RecordSource>) =
+        if opts.demo { (String::from("demo"), None, Box::new(diargos_core::fixtures::MemorySource::demo())) }
+        else { (tab_name, tab_dir, source) }
+    ;
+
+    let config = resolve_config(tab_dir.as_deref())?;
+    let mut model = load_model(&config, preset.as_ref(), source).map_err(|err| Error::Dir(working_dir, err))?;
+
+    if opts.print {
+        for err in &model.load_failures {
+            eprintln!("warning: {}", err);
+        }
 
-    let model = Model::with_data(data);
+        print_lines(diargos_core::batch::print_table(&mut model, opts.json));
+        return Ok(());
+    }
 
-    let main_view = TagRecordView::new(model);
+    if let Some(working_dir) = &tab_dir {
+        crate::places::remember_opened(working_dir);
+    }
+
+    let open_model: OpenModel = {
+        let resolve_config = resolve_config.clone();
+        let preset = preset.clone();
+
+        Rc::new(move |working_dir| {
+            let config = resolve_config(Some(&working_dir))?;
+            load_model(&config, preset.as_ref(), Box::new(FlacDir::new(working_dir.clone()))).map_err(|err| Error::Dir(working_dir, err))
+        })
+    };
+
+    let configure_model: ConfigureModel = Rc::new(move |working_dir, model| {
+        let mut config = resolve_config(working_dir)?;
+
+        // Columns from a preset stay in place of the configured ones.
+        if let Some(preset) = &preset {
+            config.columns = preset.columns.clone();
+        }
+
+        model.apply_config(&config);
+        Ok(())
+    });
 
     let mut siv = Cursive::default();
 
+    // The user config and the one given on the command line are watched for
+    // as long as the UI runs, the config of each directory by its tab.
+    let mut config_watchers = Vec::new();
+
+    if config.watch_config {
+        if let Some(user_config_dir) = LayeredConfig::user_config_dir() {
+            let is_config = |path: &Path| ConfigLayer::is_named(path, LayeredConfig::USER_CONFIG_NAME);
+            config_watchers.extend(crate::views::tabs::watch_config(&user_config_dir, is_config, siv.cb_sink().clone()));
+        }
+
+        if let Some(config_file) = watched_config_file.and_then(|path| path.canonicalize().ok()) {
+            let file_name = config_file.file_name().map(|name| name.to_os_string());
+            let is_config = move |path: &Path| path.file_name() == file_name.as_deref();
+
+            if let Some(config_dir) = config_file.parent() {
+                config_watchers.extend(crate::views::tabs::watch_config(config_dir, is_config, siv.cb_sink().clone()));
+            }
+        }
+    }
+
+    let mut main_view = TabsView::new(open_model, configure_model, siv.cb_sink().clone(), config.watch_files, config.watch_config);
+    main_view.add_tab(tab_name, tab_dir.clone(), model);
+
+    for working_dir in opts.tabs {
+        main_view.open(working_dir)?;
+    }
+
+    // Start on the first directory given.
+    main_view.select_tab(0);
+
     siv.add_fullscreen_layer(
         Dialog::around(
             main_view
+            .with_name(TABS_VIEW_NAME)
             // .fixed_size((60, 80))
         )
     );
 
+    if opts.browse {
+        crate::views::tabs::browse(&mut siv, tab_dir);
+    }
+
     siv.run();
+
+    // The editor needs the terminal to itself, so the UI is torn down around
+    // it and then put back together.
+    while let Some(pending) = siv.take_user_data::<PendingEdit>() {
+        let dump = siv.dump();
+        drop(siv);
+
+        let result = crate::external::edit_values(&pending.values);
+
+        siv = Cursive::default();
+        siv.restore(dump);
+        (pending.on_edited)(&mut siv, result);
+
+        siv.run();
+    }
+
+    // Quitting in the middle of saving finishes the files being written.
+    crate::views::save_queue::wait_for_writes();
+
+    Ok(())
 }
@@ -0,0 +1,144 @@
+use std::fs::File;
+use std::io::BufReader;
+use std::io::BufWriter;
+use std::io::Error as IoError;
+use std::io::ErrorKind as IoErrorKind;
+use std::path::Path;
+use std::path::PathBuf;
+
+use serde::Deserialize;
+use serde::Serialize;
+
+/// The most recently opened directories that are remembered.
+const MAX_RECENT: usize = 10;
+
+/// Bookmarked and recently opened directories, kept between sessions.
+#[derive(Debug, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Places {
+    #[serde(default)]
+    pub bookmarks: Vec<PathBuf>,
+
+    /// The most recently opened directory first.
+    #[serde(default)]
+    pub recent: Vec<PathBuf>,
+}
+
+impl Places {
+    /// Where places are stored, following the XDG base directory spec.
+    pub fn default_path() -> Option<PathBuf> {
+        let data_dir =
+            std::env::var_os("XDG_DATA_HOME").map(PathBuf::from)
+            .or_else(|| std::env::var_os("HOME").map(|home| PathBuf::from(home).join(".local/share")))?
+        ;
+
+        Some(data_dir.join("diargos").join("places.json"))
+    }
+
+    /// Reads places from a file, which is treated as empty if it does not exist.
+    pub fn read_from_path(path: &Path) -> Result<Self, IoError> {
+        let file = match File::open(path) {
+            Ok(file) => file,
+            Err(err) if err.kind() == IoErrorKind::NotFound => return Ok(Self::default()),
+            Err(err) => return Err(err),
+        };
+
+        serde_json::from_reader(BufReader::new(file)).map_err(IoError::from)
+    }
+
+    pub fn write_to_path(&self, path: &Path) -> Result<(), IoError> {
+        if let Some(parent) = path.parent() {
+            std::fs::create_dir_all(parent)?;
+        }
+
+        let writer = BufWriter::new(File::create(path)?);
+        serde_json::to_writer_pretty(writer, self).map_err(IoError::from)
+    }
+
+    /// Reads the stored places, changes them, and writes them back. Other
+    /// sessions may have changed them in the meantime, so they are not kept
+    /// around in memory.
+    pub fn update<F, R>(func: F) -> Result<R, IoError>
+    where
+        F: FnOnce(&mut Self) -> R,
+    {
+        let path = Self::default_path().ok_or_else(|| IoError::new(IoErrorKind::NotFound, "no home directory"))?;
+
+        let mut places = Self::read_from_path(&path)?;
+        let result = func(&mut places);
+        places.write_to_path(&path)?;
+
+        Ok(result)
+    }
+
+    pub fn load() -> Self {
+        Self::default_path()
+        .and_then(|path| Self::read_from_path(&path).ok())
+        .unwrap_or_default()
+    }
+
+    /// Moves a directory to the front of the recently opened ones.
+    pub fn add_recent(&mut self, dir: &Path) {
+        self.recent.retain(|d| d != dir);
+        self.recent.insert(0, dir.to_path_buf());
+        self.recent.truncate(MAX_RECENT);
+    }
+
+    /// Bookmarks a directory, or removes its bookmark. Returns whether the
+    /// directory is now bookmarked.
+    pub fn toggle_bookmark(&mut self, dir: &Path) -> bool {
+        let num_bookmarks = self.bookmarks.len();
+        self.bookmarks.retain(|d| d != dir);
+
+        if self.bookmarks.len() == num_bookmarks {
+            self.bookmarks.push(dir.to_path_buf());
+            true
+        }
+        else {
+            false
+        }
+    }
+}
+
+/// Records that a directory was opened, only logging failures since this is
+/// only a convenience.
+pub fn remember_opened(dir: &Path) {
+    let dir = dir.canonicalize().unwrap_or_else(|_| dir.to_path_buf());
+
+    if let Err(err) = Places::update(|places| places.add_recent(&dir)) {
+        log::warn!("unable to remember {} as opened: {}", dir.display(), err);
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn recent_and_bookmarks() {
+        let mut places = Places::default();
+
+        for i in 0..12 {
+            places.add_recent(Path::new(&format!("/music/{}", i)));
+        }
+
+        places.add_recent(Path::new("/music/5"));
+
+        assert_eq!(places.recent.len(), MAX_RECENT);
+        assert_eq!(places.recent[0], Path::new("/music/5"));
+        assert_eq!(places.recent[1], Path::new("/music/11"));
+        assert_eq!(places.recent.iter().filter(|d| *d == Path::new("/music/5")).count(), 1);
+
+        assert!(places.toggle_bookmark(Path::new("/music/a")));
+        assert!(places.toggle_bookmark(Path::new("/music/b")));
+        assert!(!places.toggle_bookmark(Path::new("/music/a")));
+        assert_eq!(places.bookmarks, vec![PathBuf::from("/music/b")]);
+    }
+
+    #[test]
+    fn deserialize() {
+        let places = serde_json::from_str::<Places>(r#"{"bookmarks": ["/music"]}"#).unwrap();
+
+        assert_eq!(places.bookmarks, vec![PathBuf::from("/music")]);
+        assert!(places.recent.is_empty());
+    }
+}
@@ -0,0 +1,38 @@
+use cursive::Cursive;
+use cursive::traits::Nameable;
+use cursive::views::Dialog;
+
+const BUSY_NAME: &str = "busy";
+
+/// Runs work on a background thread behind a dialog saying what is being
+/// waited on, so that the UI stays responsive. The done callback is only
+/// called if the work succeeds, otherwise the error is shown after the
+/// failure message.
+pub fn run<T, W, D>(siv: &mut Cursive, title: &str, message: &str, failure: &str, work: W, on_done: D)
+where
+    T: Send + 'static,
+    W: FnOnce() -> Result<T, String> + Send + 'static,
+    D: FnOnce(&mut Cursive, T) + Send + 'static,
+{
+    siv.add_layer(Dialog::text(message).title(title).with_name(BUSY_NAME));
+
+    let cb_sink = siv.cb_sink().clone();
+    let failure = failure.to_string();
+
+    std::thread::spawn(move || {
+        let result = work();
+
+        cb_sink.send(Box::new(move |siv| {
+            // The work cannot be cancelled, but the dialog can be dismissed.
+            match siv.screen_mut().find_layer_from_name(BUSY_NAME) {
+                Some(position) => { siv.screen_mut().remove_layer(position); },
+                None => return,
+            }
+
+            match result {
+                Ok(value) => on_done(siv, value),
+                Err(err) => siv.add_layer(Dialog::info(format!("{}: {}", failure, err))),
+            }
+        })).ok();
+    });
+}
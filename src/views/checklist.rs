@@ -0,0 +1,66 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use cursive::Cursive;
+use cursive::traits::Nameable;
+use cursive::traits::Resizable;
+use cursive::views::Checkbox;
+use cursive::views::Dialog;
+use cursive::views::LinearLayout;
+use cursive::views::ScrollView;
+use cursive::views::TextView;
+
+fn checkbox_name(index: usize) -> String {
+    format!("checklist_{}", index)
+}
+
+/// Creates a dialog with a checkbox for each item. The dialog is dismissed
+/// before the confirm callback is called with the indices of checked items.
+pub fn make<F>(title: &str, labels: Vec<String>, confirm_label: &str, on_confirm: F) -> Dialog
+where
+    F: Fn(&mut Cursive, Vec<usize>) + 'static,
+{
+    let checked = Rc::new(RefCell::new(vec![false; labels.len()]));
+
+    let mut list = LinearLayout::vertical();
+
+    for (i, label) in labels.iter().enumerate() {
+        let checked = checked.clone();
+
+        list.add_child(
+            LinearLayout::horizontal()
+            .child(
+                Checkbox::new()
+                .on_change(move |_, is_checked| { checked.borrow_mut()[i] = is_checked; })
+                .with_name(checkbox_name(i))
+            )
+            .child(TextView::new(format!(" {}", label)))
+        );
+    }
+
+    let num_items = labels.len();
+    let checked_for_all = checked.clone();
+
+    Dialog::around(ScrollView::new(list).max_height(20))
+    .title(title)
+    .button("Select All", move |siv| {
+        for i in 0..num_items {
+            siv.call_on_name(&checkbox_name(i), |view: &mut Checkbox| { view.set_checked(true); });
+        }
+
+        checked_for_all.borrow_mut().iter_mut().for_each(|c| *c = true);
+    })
+    .button(confirm_label, move |siv| {
+        let indices =
+            checked.borrow().iter()
+            .enumerate()
+            .filter(|(_, &c)| c)
+            .map(|(i, _)| i)
+            .collect()
+        ;
+
+        siv.pop_layer();
+        on_confirm(siv, indices);
+    })
+    .dismiss_button("Cancel")
+}
@@ -0,0 +1,61 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
+
+use cursive::CbSink;
+
+use diargos_core::model::Model;
+
+/// How often finished outputs are handed back to the model, at most, so that
+/// many quick commands do not flood the UI with redraws.
+const BATCH_INTERVAL: Duration = Duration::from_millis(200);
+
+/// Runs the commands of command columns that have no output yet, one at a
+/// time on a background thread, filling in the outputs as they finish. Stops
+/// early if the model goes away, as when its tab is closed.
+pub fn run_pending(cb_sink: &CbSink, shared_model: &Arc<Mutex<Model>>) {
+    let jobs = shared_model.lock().unwrap().take_command_jobs();
+
+    if jobs.is_empty() {
+        return;
+    }
+
+    let cb_sink = cb_sink.clone();
+    let shared_model = Arc::downgrade(shared_model);
+
+    std::thread::spawn(move || {
+        let num_jobs = jobs.len();
+        let mut finished = Vec::new();
+        let mut last_sent = Instant::now();
+
+        for (i, job) in jobs.into_iter().enumerate() {
+            if shared_model.strong_count() == 0 {
+                break;
+            }
+
+            let output = job.run();
+            finished.push((job, output));
+
+            if i + 1 < num_jobs && last_sent.elapsed() < BATCH_INTERVAL {
+                continue;
+            }
+
+            let outputs = std::mem::take(&mut finished);
+            let shared_model = shared_model.clone();
+
+            let sent = cb_sink.send(Box::new(move |_| {
+                if let Some(shared_model) = shared_model.upgrade() {
+                    shared_model.lock().unwrap().set_command_outputs(outputs);
+                }
+            }));
+
+            // The UI has shut down.
+            if sent.is_err() {
+                break;
+            }
+
+            last_sent = Instant::now();
+        }
+    });
+}
@@ -0,0 +1,250 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::theme::BaseColor;
+use cursive::theme::Color;
+use cursive::traits::Nameable;
+use cursive::traits::Resizable;
+use cursive::utils::markup::StyledString;
+use cursive::views::Dialog;
+use cursive::views::LinearLayout;
+use cursive::views::ScrollView;
+use cursive::views::SelectView;
+use cursive::views::TextView;
+use unicode_width::UnicodeWidthStr;
+
+use diargos_core::compare::FieldRow;
+use diargos_core::compare::MatchBy;
+use diargos_core::compare::Pair;
+use diargos_core::consts::*;
+use diargos_core::data::InfoKind;
+use diargos_core::data::Record;
+use diargos_core::model::Model;
+
+const PAIRS_NAME: &str = "compare_pairs";
+const FIELDS_NAME: &str = "compare_fields";
+const MATCH_BY_NAME: &str = "compare_match_by";
+
+/// One side of the comparison, which is the model of a tab.
+pub struct Side {
+    pub name: String,
+    pub model: Arc<Mutex<Model>>,
+}
+
+fn file_name(record: Option<&Record>) -> String {
+    record.and_then(|r| r.get_info(&InfoKind::FileName)).map_or_else(|| String::from(MISSING_VALUE_STR), |name| name.into_owned())
+}
+
+fn show_values(values: &Option<Vec<String>>) -> String {
+    values.as_ref().map_or_else(|| String::from(MISSING_VALUE_STR), |vs| vs.join(FIELD_SEP_STR))
+}
+
+/// Labels that differ are shown in red.
+fn label(text: String, differs: bool) -> StyledString {
+    if differs { StyledString::styled(text, Color::Light(BaseColor::Red)) }
+    else { StyledString::plain(text) }
+}
+
+struct State {
+    left: Side,
+    right: Side,
+    match_by: MatchBy,
+    pairs: Vec<Pair>,
+}
+
+impl State {
+    fn rematch(&mut self) {
+        let left = self.left.model.lock().unwrap();
+        let right = self.right.model.lock().unwrap();
+
+        self.pairs = diargos_core::compare::match_records(&left.data.records, &right.data.records, &self.match_by);
+    }
+
+    fn field_rows(&self, pair: Pair) -> Vec<FieldRow> {
+        let left = self.left.model.lock().unwrap();
+        let right = self.right.model.lock().unwrap();
+
+        diargos_core::compare::field_rows(
+            pair.left.and_then(|i| left.data.records.get(i)),
+            pair.right.and_then(|i| right.data.records.get(i)),
+        )
+    }
+
+    fn match_by_text(&self) -> String {
+        format!("{} ↔ {}, matched by {}", self.left.name, self.right.name, self.match_by)
+    }
+
+    /// Fills in the list of matched files, keeping the selection where possible.
+    fn fill_pairs(&self, view: &mut SelectView<Pair>) {
+        let left = self.left.model.lock().unwrap();
+        let right = self.right.model.lock().unwrap();
+
+        let names =
+            self.pairs.iter()
+            .map(|pair| {
+                let l = pair.left.and_then(|i| left.data.records.get(i));
+                let r = pair.right.and_then(|i| right.data.records.get(i));
+                let differs = diargos_core::compare::field_rows(l, r).iter().any(FieldRow::differs);
+
+                (file_name(l), file_name(r), differs)
+            })
+            .collect::<Vec<_>>()
+        ;
+
+        let width = names.iter().map(|(l, _, _)| l.width()).max().unwrap_or(0);
+        let selected = view.selected_id();
+
+        view.clear();
+
+        for ((l, r, differs), pair) in names.into_iter().zip(&self.pairs) {
+            let marker = if differs { "≠" } else { "=" };
+            let padding = " ".repeat(width - l.width());
+            view.add_item(label(format!("{}{} {} {}", l, padding, marker, r), differs), *pair);
+        }
+
+        if let Some(selected) = selected.filter(|&i| i < view.len()) {
+            view.set_selection(selected);
+        }
+    }
+
+    /// Fills in the fields of a pair of files.
+    fn fill_fields(&self, view: &mut SelectView<String>, pair: Option<Pair>) {
+        let rows = pair.map(|pair| self.field_rows(pair)).unwrap_or_default();
+
+        let key_width = rows.iter().map(|row| row.meta_key.width()).max().unwrap_or(0);
+        let left_width = rows.iter().map(|row| show_values(&row.left).width()).max().unwrap_or(0);
+
+        let selected = view.selected_id();
+
+        view.clear();
+
+        for row in rows {
+            let left = show_values(&row.left);
+            let text = format!(
+                "{}{}  {}{}  {}",
+                row.meta_key, " ".repeat(key_width - row.meta_key.width()),
+                left, " ".repeat(left_width - left.width()),
+                show_values(&row.right),
+            );
+
+            view.add_item(label(text, row.differs()), row.meta_key);
+        }
+
+        if let Some(selected) = selected.filter(|&i| i < view.len()) {
+            view.set_selection(selected);
+        }
+    }
+}
+
+fn selected_pair(siv: &mut Cursive) -> Option<Pair> {
+    siv.call_on_name(PAIRS_NAME, |view: &mut SelectView<Pair>| view.selection()).flatten().map(|pair| *pair)
+}
+
+fn refresh_fields(siv: &mut Cursive, state: &State) {
+    let pair = selected_pair(siv);
+    siv.call_on_name(FIELDS_NAME, |view: &mut SelectView<String>| state.fill_fields(view, pair));
+}
+
+fn refresh(siv: &mut Cursive, state: &State) {
+    siv.call_on_name(PAIRS_NAME, |view: &mut SelectView<Pair>| state.fill_pairs(view));
+    siv.call_on_name(MATCH_BY_NAME, |view: &mut TextView| view.set_content(state.match_by_text()));
+    refresh_fields(siv, state);
+}
+
+/// Copies the selected field of the selected pair of files from one side to
+/// the other, as an edit that can be undone from the target tab.
+fn copy_field(siv: &mut Cursive, state: &State, to_right: bool) {
+    let pair = selected_pair(siv);
+    let meta_key = siv.call_on_name(FIELDS_NAME, |view: &mut SelectView<String>| view.selection()).flatten();
+
+    let (pair, meta_key) = match (pair, meta_key) {
+        (Some(pair), Some(meta_key)) => (pair, meta_key),
+        _ => return,
+    };
+
+    let (from, to, from_index, to_index) =
+        if to_right { (&state.left, &state.right, pair.left, pair.right) }
+        else { (&state.right, &state.left, pair.right, pair.left) }
+    ;
+
+    let (from_index, to_index) = match (from_index, to_index) {
+        (Some(f), Some(t)) => (f, t),
+        _ => {
+            siv.add_layer(Dialog::info("This file has no match on the other side"));
+            return
+        },
+    };
+
+    let values = from.model.lock().unwrap().data.records[from_index].get_meta(&meta_key).map(<[String]>::to_vec);
+
+    {
+        let mut model = to.model.lock().unwrap();
+
+        if model.read_only {
+            drop(model);
+            siv.add_layer(Dialog::info("Fields can't be copied in read-only mode"));
+            return
+        }
+
+        let description = format!("Copy {} from {}", meta_key, from.name);
+
+        model.edit_records(description.clone(), |records| records[to_index].set_meta(&meta_key, values));
+        model.set_status(description);
+    }
+
+    refresh(siv, state);
+}
+
+/// Creates a dialog comparing the records of two tabs side by side.
+pub fn make(left: Side, right: Side) -> Dialog {
+    let mut state = State { left, right, match_by: MatchBy::FileName, pairs: Vec::new() };
+    state.rematch();
+
+    let mut pairs_view = SelectView::<Pair>::new();
+    state.fill_pairs(&mut pairs_view);
+
+    let mut fields_view = SelectView::<String>::new();
+    state.fill_fields(&mut fields_view, pairs_view.selection().map(|pair| *pair));
+
+    let match_by_view = TextView::new(state.match_by_text());
+
+    let state = Rc::new(RefCell::new(state));
+    let on_select_state = state.clone();
+
+    let pairs_view = pairs_view.on_select(move |siv, _| refresh_fields(siv, &on_select_state.borrow()));
+
+    let layout =
+        LinearLayout::vertical()
+        .child(match_by_view.with_name(MATCH_BY_NAME))
+        .child(ScrollView::new(pairs_view.with_name(PAIRS_NAME)).max_height(12))
+        .child(TextView::new(" "))
+        .child(ScrollView::new(fields_view.with_name(FIELDS_NAME)).max_height(12))
+    ;
+
+    let copy_left_state = state.clone();
+    let match_by_state = state.clone();
+
+    Dialog::around(layout)
+    .title("Compare")
+    .button("Copy →", move |siv| copy_field(siv, &state.borrow(), true))
+    .button("← Copy", move |siv| copy_field(siv, &copy_left_state.borrow(), false))
+    .button("Match By", move |siv| {
+        let state = match_by_state.clone();
+
+        siv.add_layer(
+            crate::views::prompt::make("Match By Tag (empty for file name)", "", move |siv, text| {
+                let text = text.trim();
+
+                let mut state = state.borrow_mut();
+                state.match_by = if text.is_empty() { MatchBy::FileName } else { MatchBy::Tag(text.to_uppercase()) };
+                state.rematch();
+
+                refresh(siv, &state);
+            })
+        );
+    })
+    .dismiss_button("Close")
+}
@@ -0,0 +1,20 @@
+use cursive::Cursive;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::ScrollView;
+use cursive::views::TextView;
+
+/// Creates a dialog asking to go ahead with an action. The dialog is
+/// dismissed before the confirm callback is called.
+pub fn make<F>(title: &str, message: String, confirm_label: &str, on_confirm: F) -> Dialog
+where
+    F: Fn(&mut Cursive) + 'static,
+{
+    Dialog::around(ScrollView::new(TextView::new(message)).max_height(20))
+    .title(title)
+    .button(confirm_label, move |siv| {
+        siv.pop_layer();
+        on_confirm(siv);
+    })
+    .dismiss_button("Cancel")
+}
@@ -0,0 +1,70 @@
+use cursive::Cursive;
+use cursive::traits::Nameable;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::EditView;
+use cursive::views::LinearLayout;
+use cursive::views::TextView;
+
+use diargos_core::value::Date;
+
+const YEAR_NAME: &str = "date_picker_year";
+const MONTH_NAME: &str = "date_picker_month";
+const DAY_NAME: &str = "date_picker_day";
+
+fn part(siv: &mut Cursive, name: &str) -> String {
+    siv.call_on_name(name, |view: &mut EditView| view.get_content().to_string()).unwrap_or_default()
+}
+
+fn set_parts(siv: &mut Cursive, date: Date) {
+    let month = date.month.map(|m| format!("{:02}", m)).unwrap_or_default();
+    let day = date.day.map(|d| format!("{:02}", d)).unwrap_or_default();
+
+    for (name, content) in [(YEAR_NAME, format!("{:04}", date.year)), (MONTH_NAME, month), (DAY_NAME, day)] {
+        siv.call_on_name(name, |view: &mut EditView| { view.set_content(content); });
+    }
+}
+
+/// Creates a dialog to enter a date as a year, and an optional month and day.
+/// Dates that do not exist are refused. The dialog is dismissed before the
+/// confirm callback is called with the date.
+pub fn make<F>(title: &str, initial: Option<Date>, on_confirm: F) -> Dialog
+where
+    F: Fn(&mut Cursive, Date) + 'static,
+{
+    let field = |name: &str, content: String, width: usize| {
+        EditView::new().content(content).max_content_width(width).with_name(name).fixed_width(width + 1)
+    };
+
+    let month = initial.and_then(|d| d.month).map(|m| format!("{:02}", m)).unwrap_or_default();
+    let day = initial.and_then(|d| d.day).map(|d| format!("{:02}", d)).unwrap_or_default();
+    let year = initial.map(|d| format!("{:04}", d.year)).unwrap_or_default();
+
+    Dialog::around(
+        LinearLayout::vertical()
+        .child(
+            LinearLayout::horizontal()
+            .child(field(YEAR_NAME, year, 4))
+            .child(TextView::new(" - "))
+            .child(field(MONTH_NAME, month, 2))
+            .child(TextView::new(" - "))
+            .child(field(DAY_NAME, day, 2))
+        )
+        .child(TextView::new("\nYYYY - MM - DD, the month and day may be left blank"))
+    )
+    .title(title)
+    .button("Today", |siv| set_parts(siv, Date::today()))
+    .button("OK", move |siv| {
+        let (year, month, day) = (part(siv, YEAR_NAME), part(siv, MONTH_NAME), part(siv, DAY_NAME));
+
+        match Date::from_parts(&year, &month, &day) {
+            Some(date) => {
+                siv.pop_layer();
+                on_confirm(siv, date);
+            },
+            None => siv.add_layer(Dialog::info("Not a valid date")),
+        }
+    })
+    .dismiss_button("Cancel")
+    .padding_lrtb(1, 1, 1, 0)
+}
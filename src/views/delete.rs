@@ -0,0 +1,58 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::views::Dialog;
+
+use diargos_core::model::Model;
+
+/// Asks to move the files of the target records to the trash, warning about
+/// any pending edits that would be lost, and drops their records.
+pub fn show(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let (file_paths, num_edited) = {
+        let model = shared_model.lock().unwrap();
+        let records = model.target_record_indices().into_iter().map(|i| &model.data.records[i]).collect::<Vec<_>>();
+
+        let file_paths = records.iter().map(|r| r.file_path.clone()).collect::<Vec<_>>();
+        let num_edited = records.iter().filter(|r| r.is_edited()).count();
+
+        (file_paths, num_edited)
+    };
+
+    if file_paths.is_empty() {
+        return siv.add_layer(Dialog::info("No files to delete"));
+    }
+
+    let mut message = format!("Move {} file(s) to the trash?", file_paths.len());
+
+    if num_edited > 0 {
+        message.push_str(&format!("\n\nThe pending edits of {} of them will be lost.", num_edited));
+    }
+
+    message.push('\n');
+
+    for file_path in &file_paths {
+        message.push_str(&format!("\n{}", file_path.display()));
+    }
+
+    siv.add_layer(
+        crate::views::confirm::make("Delete Files", message, "Delete", move |siv| {
+            let failures = {
+                let mut model = shared_model.lock().unwrap();
+                let failures = model.delete_files(&file_paths);
+                model.set_status(format!("Moved {} file(s) to the trash", file_paths.len() - failures.len()));
+                failures
+            };
+
+            if !failures.is_empty() {
+                let mut message = format!("Unable to delete {} file(s):", failures.len());
+
+                for (path, err) in failures {
+                    message.push_str(&format!("\n{}: {}", path.display(), err));
+                }
+
+                siv.add_layer(Dialog::info(message));
+            }
+        })
+    );
+}
@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::theme::BaseColor;
+use cursive::theme::Color;
+use cursive::traits::Resizable;
+use cursive::utils::markup::StyledString;
+use cursive::views::Dialog;
+use cursive::views::LinearLayout;
+use cursive::views::ScrollView;
+use cursive::views::TextView;
+
+use diargos_core::consts::*;
+use diargos_core::data::InfoKind;
+use diargos_core::model::Model;
+
+/// Lists the sets of records that look like the same track, with the near
+/// matches flagged, and offers to select all of them.
+pub fn show(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let (sets, mut list) = {
+        let model = shared_model.lock().unwrap();
+        let sets = model.find_duplicates();
+
+        let mut list = LinearLayout::vertical();
+
+        for set in &sets {
+            let heading =
+                if set.exact { StyledString::plain("Exact duplicates") }
+                else { StyledString::styled("⚠ Near duplicates", Color::Light(BaseColor::Red)) }
+            ;
+
+            list.add_child(TextView::new(heading));
+
+            for record in set.file_paths.iter().filter_map(|file_path| model.data.records.iter().find(|r| r.file_path == *file_path)) {
+                let value = |key: &str| record.get_meta(key).map(|v| v.join(FIELD_SEP_STR)).unwrap_or_default();
+
+                list.add_child(TextView::new(format!(
+                    "  {} - {}  ({})",
+                    value("ARTIST"),
+                    value("TITLE"),
+                    record.get_info(&InfoKind::FileName).unwrap_or_default(),
+                )));
+            }
+        }
+
+        (sets, list)
+    };
+
+    if sets.is_empty() {
+        return siv.add_layer(Dialog::info("No duplicates found"));
+    }
+
+    let num_near = sets.iter().filter(|set| !set.exact).count();
+
+    list.insert_child(0, TextView::new(format!("{} set(s) of duplicates, {} near\n ", sets.len(), num_near)));
+
+    siv.add_layer(
+        Dialog::around(ScrollView::new(list).max_height(20))
+        .title("Duplicates")
+        .button("Select All", move |siv| {
+            let mut model = shared_model.lock().unwrap();
+            let num_files = model.select_files(sets.iter().flat_map(|set| set.file_paths.iter()));
+            model.set_status(format!("Selected {} duplicate file(s)", num_files));
+
+            siv.pop_layer();
+        })
+        .dismiss_button("Close")
+    );
+}
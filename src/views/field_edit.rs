@@ -1,39 +1,205 @@
-
+use cursive::Cursive;
+use cursive::event::Event;
+use cursive::event::EventResult;
+use cursive::event::Key;
+use cursive::traits::Nameable;
 use cursive::traits::Resizable;
-use cursive::view::Margins;
+use cursive::view::View;
+use cursive::view::ViewWrapper;
 use cursive::views::Button;
 use cursive::views::Dialog;
 use cursive::views::EditView;
 use cursive::views::LinearLayout;
 use cursive::views::PaddedView;
+use cursive::views::ResizedView;
 use cursive::views::ScrollView;
 
+use diargos_core::value::ValueKind;
+
+const EDITOR_NAME: &str = "multi_field_edit";
+
+/// A value next to a button that removes it.
+type Row = PaddedView<LinearLayout>;
+
+/// The values that complete what was typed into a row, and which of them is
+/// shown.
+struct Completing {
+    matches: Vec<String>,
+    index: usize,
+}
+
+/// Edits the values of a multi-value tag, one value per line, in order.
 pub struct MultiFieldEditView {
-    first: EditView,
-    rest: Vec<EditView>,
+    rows: LinearLayout,
+    completions: Vec<String>,
+    completing: Option<Completing>,
 }
 
-pub fn make(values: Vec<String>) -> Dialog {
-    Dialog::around(
-        LinearLayout::vertical()
-        .child(
-            ScrollView::new({
-                let mut sub = LinearLayout::vertical();
-
-                for value in values {
-                    let edit_view = EditView::new().content(value).fixed_width(32);
-                    sub.add_child(PaddedView::lrtb(0, 0, 0, 1, edit_view));
-                }
+impl MultiFieldEditView {
+    pub fn new(values: Vec<String>, completions: Vec<String>) -> Self {
+        let mut rows = LinearLayout::vertical();
 
-                sub
-            })
-        )
-        .child(
+        for value in values {
+            rows.add_child(Self::row(value));
+        }
+
+        Self { rows, completions, completing: None }
+    }
+
+    fn row(value: String) -> Row {
+        PaddedView::lrtb(
+            0, 0, 0, 1,
             LinearLayout::horizontal()
-            .child(Button::new("OK", |_| {}))
-            .child(Button::new("Cancel", |_| {}))
-            .child(Button::new("Add Field", |_| {}))
+            .child(EditView::new().content(value).full_width())
+            // Clicking the button focuses its row first, so that is the one removed.
+            .child(Button::new("Remove", |siv| { call_on_editor(siv, |view| view.remove_focused()); })),
         )
+    }
+
+    /// The values in the order they are shown.
+    pub fn values(&self) -> Vec<String> {
+        (0..self.rows.len())
+        .filter_map(|i| {
+            let row = self.rows.get_child(i)?.downcast_ref::<Row>()?;
+            row.get_inner().get_child(0)?.downcast_ref::<ResizedView<EditView>>()
+        })
+        .map(|edit_view| edit_view.get_inner().get_content().to_string())
+        .collect()
+    }
+
+    /// Adds an empty value at the end, and focuses it.
+    pub fn add_value(&mut self) {
+        self.rows.add_child(Self::row(String::new()));
+        self.rows.set_focus_index(self.rows.len() - 1).ok();
+    }
+
+    /// Swaps the focused value with the one above or below it, keeping it
+    /// focused. Returns whether it moved.
+    pub fn move_focused(&mut self, up: bool) -> bool {
+        let from = self.rows.get_focus_index();
+
+        let to = match (up, from) {
+            (true, 0) => return false,
+            (true, from) => from - 1,
+            (false, from) if from + 1 >= self.rows.len() => return false,
+            (false, from) => from + 1,
+        };
+
+        self.rows.swap_children(from, to);
+        self.rows.set_focus_index(to).is_ok()
+    }
+
+    fn focused_edit_view(rows: &mut LinearLayout) -> Option<&mut EditView> {
+        let focus = rows.get_focus_index();
+        let row = rows.get_child_mut(focus)?.downcast_mut::<Row>()?.get_inner_mut();
+
+        // The remove button of the row may be focused instead.
+        if row.get_focus_index() != 0 {
+            return None;
+        }
+
+        row.get_child_mut(0)?.downcast_mut::<ResizedView<EditView>>().map(ResizedView::get_inner_mut)
+    }
+
+    /// Replaces the focused value with the next value that starts with what
+    /// was typed, ignoring case. Repeating this cycles through the matches.
+    /// Ignored if nothing was typed or nothing matches.
+    fn complete(&mut self) -> EventResult {
+        let completions = &self.completions;
+        let completing = &mut self.completing;
+
+        let edit_view = match Self::focused_edit_view(&mut self.rows) {
+            Some(edit_view) => edit_view,
+            None => return EventResult::Ignored,
+        };
+
+        let content = edit_view.get_content();
+
+        let is_cycling = completing.as_ref().is_some_and(|c| c.matches[c.index] == *content);
+
+        if !is_cycling {
+            let prefix = content.to_lowercase();
+            let matches =
+                completions.iter()
+                .filter(|value| value.to_lowercase().starts_with(&prefix) && **value != *content)
+                .cloned()
+                .collect::<Vec<_>>()
+            ;
+
+            if prefix.is_empty() || matches.is_empty() {
+                return EventResult::Ignored;
+            }
+
+            *completing = Some(Completing { matches, index: 0 });
+        }
+        else if let Some(c) = completing.as_mut() {
+            c.index = (c.index + 1) % c.matches.len();
+        }
+
+        let completion = completing.as_ref().map(|c| c.matches[c.index].clone()).unwrap_or_default();
+        EventResult::Consumed(Some(edit_view.set_content(completion)))
+    }
+
+    /// Removes the focused value. Returns whether there was one.
+    pub fn remove_focused(&mut self) -> bool {
+        self.rows.remove_child(self.rows.get_focus_index()).is_some()
+    }
+}
+
+impl ViewWrapper for MultiFieldEditView {
+    cursive::wrap_impl!(self.rows: LinearLayout);
+
+    fn wrap_on_event(&mut self, event: Event) -> EventResult {
+        match event {
+            Event::AltChar('k') => { self.move_focused(true); EventResult::Consumed(None) },
+            Event::AltChar('j') => { self.move_focused(false); EventResult::Consumed(None) },
+            Event::AltChar('d') => { self.remove_focused(); EventResult::Consumed(None) },
+            Event::Key(Key::Tab) => {
+                match self.complete() {
+                    EventResult::Ignored => self.rows.on_event(event),
+                    result => result,
+                }
+            },
+            event => self.rows.on_event(event),
+        }
+    }
+}
+
+fn call_on_editor<F, R>(siv: &mut Cursive, f: F) -> Option<R>
+where
+    F: FnOnce(&mut MultiFieldEditView) -> R,
+{
+    siv.call_on_name(EDITOR_NAME, f)
+}
+
+/// Creates a dialog to edit the values of a field. Values can be reordered
+/// with the buttons, or with Alt-K and Alt-J, and removed with Alt-D. Tab
+/// completes a value from the given completions. Values that can't be read
+/// as the kind of the field keep the dialog open, and otherwise it is
+/// dismissed before the confirm callback is called with the edited values.
+pub fn make<F>(title: &str, values: Vec<String>, completions: Vec<String>, kind: ValueKind, on_confirm: F) -> Dialog
+where
+    F: Fn(&mut Cursive, Vec<String>) + 'static,
+{
+    Dialog::around(
+        ScrollView::new(MultiFieldEditView::new(values, completions).with_name(EDITOR_NAME).fixed_width(42))
+        .max_height(20)
     )
+    .title(title)
+    .button("Move Up", |siv| { call_on_editor(siv, |view| view.move_focused(true)); })
+    .button("Move Down", |siv| { call_on_editor(siv, |view| view.move_focused(false)); })
+    .button("Add Field", |siv| { call_on_editor(siv, |view| view.add_value()); })
+    .button("OK", move |siv| {
+        let values = call_on_editor(siv, |view| view.values()).unwrap_or_default();
+
+        if let Some(invalid) = kind.first_invalid(&values) {
+            siv.add_layer(Dialog::info(format!("{} is not a valid {}", invalid, kind.label())));
+            return;
+        }
+
+        siv.pop_layer();
+        on_confirm(siv, values);
+    })
+    .dismiss_button("Cancel")
     .padding_lrtb(1, 1, 0, 0)
 }
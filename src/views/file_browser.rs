@@ -1,13 +1,28 @@
-
+use std::cell::RefCell;
 use std::cmp::Ordering;
 use std::fmt::Display;
 use std::fmt::Formatter;
 use std::fmt::Result as FmtResult;
 use std::io::Result as IoResult;
+use std::path::Path;
 use std::path::PathBuf;
+use std::rc::Rc;
+
+use cursive::Cursive;
+use cursive::traits::Nameable;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::LinearLayout;
+use cursive::views::ScrollView;
+use cursive::views::SelectView;
+use cursive::views::TextView;
 
-use cursive_tree_view::TreeView;
-use cursive_tree_view::Placement;
+use crate::places::Places;
+use crate::views::quick_open::BOOKMARK_MARKER;
+
+const LIST_NAME: &str = "file_browser_list";
+const SUMMARY_NAME: &str = "file_browser_summary";
+const DIALOG_NAME: &str = "file_browser_dialog";
 
 #[derive(Debug)]
 struct BrowserEntry {
@@ -21,7 +36,7 @@ impl Display for BrowserEntry {
     }
 }
 
-fn collect_entries(dir: &PathBuf, entries: &mut Vec<BrowserEntry>) -> IoResult<()> {
+fn collect_entries(dir: &Path, entries: &mut Vec<BrowserEntry>) -> IoResult<()> {
     if dir.is_dir() {
         for entry in std::fs::read_dir(dir)? {
             let entry = entry?;
@@ -49,37 +64,113 @@ fn collect_entries(dir: &PathBuf, entries: &mut Vec<BrowserEntry>) -> IoResult<(
     Ok(())
 }
 
-pub struct FileBrowserView {
-    tree_view: TreeView<BrowserEntry>,
-}
+/// Lists the bookmarked directories, a link to the parent of a directory, and
+/// its subdirectories, and describes how many FLAC files are in it.
+fn show_dir(siv: &mut Cursive, dir: &Path) {
+    let mut entries = Vec::new();
+    collect_entries(dir, &mut entries).ok();
 
-impl FileBrowserView {
-    fn expand_tree(&mut self, parent_row: usize, dir: &PathBuf) {
-        let mut entries = Vec::new();
-        collect_entries(dir, &mut entries).ok();
+    entries.sort_by(|a, b| {
+        match (a.dir.is_some(), b.dir.is_some()) {
+            (true, true) | (false, false) => a.name.cmp(&b.name),
+            (true, false) => Ordering::Less,
+            (false, true) => Ordering::Greater,
+        }
+    });
 
-        entries.sort_by(|a, b| {
-            match (a.dir.is_some(), b.dir.is_some()) {
-                (true, true) | (false, false) => a.name.cmp(&b.name),
-                (true, false) => Ordering::Less,
-                (false, true) => Ordering::Greater,
-            }
-        });
+    let num_flacs =
+        entries.iter()
+        .filter(|e| e.dir.is_none() && e.name.to_lowercase().ends_with(".flac"))
+        .count()
+    ;
+
+    siv.call_on_name(LIST_NAME, |view: &mut SelectView<PathBuf>| {
+        view.clear();
+
+        for bookmark in Places::load().bookmarks {
+            view.add_item(format!("{} {}", BOOKMARK_MARKER, bookmark.display()), bookmark);
+        }
+
+        if let Some(parent) = dir.parent() {
+            view.add_item("../", parent.to_path_buf());
+        }
 
         for entry in entries {
-            if entry.dir.is_some() {
-                self.tree_view.insert_container_item(
-                    entry,
-                    Placement::LastChild,
-                    parent_row,
-                );
-            } else {
-                self.tree_view.insert_item(
-                    entry,
-                    Placement::LastChild,
-                    parent_row,
-                );
+            if let Some(path) = &entry.dir {
+                view.add_item(format!("{}/", entry), path.clone());
             }
         }
-    }
+    });
+
+    siv.call_on_name(SUMMARY_NAME, |view: &mut TextView| {
+        view.set_content(format!("{} FLAC file(s) in this directory", num_flacs));
+    });
+
+    siv.call_on_name(DIALOG_NAME, |view: &mut Dialog| {
+        view.set_title(dir.to_string_lossy());
+    });
+}
+
+/// Shows a dialog for browsing to a directory, starting from `start`.
+/// Choosing a subdirectory moves into it, and the directory being shown is
+/// opened with the Open button. The dialog is dismissed before the open
+/// callback is called.
+pub fn show<F>(siv: &mut Cursive, start: PathBuf, on_open: F)
+where
+    F: Fn(&mut Cursive, PathBuf) + 'static,
+{
+    pick(siv, start, "Open", on_open)
+}
+
+/// Like `show`, but with a button of its own to choose the directory being
+/// shown with, such as for picking where to put files.
+pub fn pick<F>(siv: &mut Cursive, start: PathBuf, pick_label: &str, on_pick: F)
+where
+    F: Fn(&mut Cursive, PathBuf) + 'static,
+{
+    // Relative paths have no parent to go up to.
+    let start = start.canonicalize().unwrap_or(start);
+    let current = Rc::new(RefCell::new(start.clone()));
+    let on_submit_current = current.clone();
+
+    let list =
+        SelectView::<PathBuf>::new()
+        .on_submit(move |siv, dir: &PathBuf| {
+            *on_submit_current.borrow_mut() = dir.clone();
+            show_dir(siv, dir);
+        })
+        .with_name(LIST_NAME)
+    ;
+
+    let layout =
+        LinearLayout::vertical()
+        .child(TextView::new("").with_name(SUMMARY_NAME))
+        .child(ScrollView::new(list).min_width(40).max_height(20))
+    ;
+
+    let bookmark_current = current.clone();
+
+    let dialog =
+        Dialog::around(layout)
+        .button("Bookmark", move |siv| {
+            let dir = bookmark_current.borrow().clone();
+
+            if let Err(err) = Places::update(|places| places.toggle_bookmark(&dir)) {
+                siv.add_layer(Dialog::info(format!("Could not save bookmarks: {}", err)));
+            }
+
+            show_dir(siv, &dir);
+        })
+        .button(pick_label, move |siv| {
+            let dir = current.borrow().clone();
+
+            siv.pop_layer();
+            on_pick(siv, dir);
+        })
+        .dismiss_button("Cancel")
+        .with_name(DIALOG_NAME)
+    ;
+
+    siv.add_layer(dialog);
+    show_dir(siv, &start);
 }
@@ -0,0 +1,131 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::time::SystemTime;
+
+use cursive::Cursive;
+use cursive::traits::Nameable;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::LinearLayout;
+use cursive::views::ScrollView;
+use cursive::views::SelectView;
+use cursive::views::TextView;
+
+use diargos_core::history::Operation;
+use diargos_core::model::Model;
+
+const FILES_VIEW_NAME: &str = "history_files";
+
+/// The most files listed for the chosen operation.
+const MAX_FILES_SHOWN: usize = 8;
+
+fn describe_files(operation: Option<&Operation>) -> String {
+    let operation = match operation {
+        Some(operation) => operation,
+        None => return String::from("Before any edits"),
+    };
+
+    let file_paths = operation.file_paths();
+
+    let mut lines =
+        file_paths.iter()
+        .take(MAX_FILES_SHOWN)
+        .map(|p| p.file_name().unwrap_or(p.as_os_str()).to_string_lossy().into_owned())
+        .collect::<Vec<_>>()
+    ;
+
+    if file_paths.len() > MAX_FILES_SHOWN {
+        lines.push(format!("and {} more", file_paths.len() - MAX_FILES_SHOWN));
+    }
+
+    lines.join("\n")
+}
+
+/// Shows the edits that can be undone and redone, newest first, and goes back
+/// or forward to the one chosen. Undone edits are marked, and so is the
+/// current point.
+pub fn show(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    // Each entry is the number of operations that are done once it is chosen,
+    // along with the details shown for it.
+    let (entries, num_done) = {
+        let model = shared_model.lock().unwrap();
+        let (done, undone) = (model.history.done(), model.history.undone());
+
+        if done.is_empty() && undone.is_empty() {
+            drop(model);
+            siv.add_layer(Dialog::info("There are no edits to undo or redo"));
+            return;
+        }
+
+        let now = SystemTime::now();
+        let describe = |operation: &Operation| {
+            format!("{} ({} file(s), {})", operation.description, operation.file_paths().len(), operation.age(now))
+        };
+
+        let mut entries = Vec::new();
+
+        for (i, operation) in undone.iter().enumerate() {
+            entries.push((format!("  undone: {}", describe(operation)), done.len() + undone.len() - i, describe_files(Some(operation))));
+        }
+
+        for (i, operation) in done.iter().enumerate().rev() {
+            let marker = if i + 1 == done.len() { "▸" } else { " " };
+            entries.push((format!("{} {}", marker, describe(operation)), i + 1, describe_files(Some(operation))));
+        }
+
+        let marker = if done.is_empty() { "▸" } else { " " };
+        entries.push((format!("{} Start", marker), 0, describe_files(None)));
+
+        (entries, done.len())
+    };
+
+    let mut list = SelectView::<usize>::new();
+
+    for (i, (label, _, _)) in entries.iter().enumerate() {
+        list.add_item(label.clone(), i);
+    }
+
+    // Start at the current point.
+    let current = entries.iter().position(|(_, n, _)| *n == num_done).unwrap_or(0);
+    list.set_selection(current);
+
+    let files = entries.iter().map(|(_, _, files)| files.clone()).collect::<Vec<_>>();
+    let targets = entries.iter().map(|(_, n, _)| *n).collect::<Vec<_>>();
+    let current_files = files[current].clone();
+
+    let list =
+        list
+        .on_select(move |siv, i: &usize| {
+            let text = files[*i].clone();
+            siv.call_on_name(FILES_VIEW_NAME, |view: &mut TextView| view.set_content(text));
+        })
+        .on_submit(move |siv, i: &usize| {
+            siv.pop_layer();
+
+            let mut model = shared_model.lock().unwrap();
+            let target = targets[*i];
+            let forward = target > model.history.done().len();
+            let steps = model.go_to_history(target);
+
+            let status = match (steps, forward) {
+                (0, _) => String::from("Already at that point"),
+                (n, true) => format!("Went forward {} edit(s)", n),
+                (n, false) => format!("Went back {} edit(s)", n),
+            };
+
+            model.set_status(status);
+        })
+    ;
+
+    siv.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+            .child(ScrollView::new(list).max_height(16))
+            .child(TextView::new("\nFiles changed:"))
+            .child(TextView::new(current_files).with_name(FILES_VIEW_NAME))
+        )
+        .title("Edit History")
+        .dismiss_button("Cancel")
+        .max_width(80)
+    );
+}
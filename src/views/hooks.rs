@@ -0,0 +1,70 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::ScrollView;
+use cursive::views::SelectView;
+use cursive::views::TextView;
+
+use diargos_core::hook::Invocation;
+use diargos_core::model::Model;
+
+/// Runs a hook on the target records one after another, on a background
+/// thread, then shows what each run wrote out.
+fn run(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, name: &str) {
+    let invocations = {
+        let model = shared_model.lock().unwrap();
+
+        let command = match model.hooks.get(name) {
+            Some(command) => command,
+            None => return,
+        };
+
+        model.target_record_indices().into_iter().map(|i| Invocation::new(command, &model.data.records[i])).collect::<Vec<_>>()
+    };
+
+    if invocations.is_empty() {
+        return;
+    }
+
+    let title = format!("Hook: {}", name);
+    let message = format!("Running {} on {} file(s)…", name, invocations.len());
+
+    let work = move || Ok(invocations.into_iter().map(Invocation::run).collect::<Vec<_>>());
+    let report_title = title.clone();
+
+    crate::views::busy::run(siv, &title, &message, "Unable to run hook", work, move |siv, reports| {
+        siv.add_layer(
+            Dialog::around(ScrollView::new(TextView::new(reports.join("\n\n"))).max_size((100, 30)))
+            .title(report_title)
+            .dismiss_button("Close")
+        );
+    });
+}
+
+/// Lists the hooks from the config to pick one to run on the target records.
+pub fn choose(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let names = shared_model.lock().unwrap().hooks.keys().cloned().collect::<Vec<_>>();
+
+    if names.is_empty() {
+        siv.add_layer(Dialog::info("No hooks are set up, add commands under \"hooks\" in the config"));
+        return;
+    }
+
+    let list =
+        SelectView::<String>::new()
+        .with_all_str(names)
+        .on_submit(move |siv, name: &String| {
+            siv.pop_layer();
+            run(siv, shared_model.clone(), name);
+        })
+    ;
+
+    siv.add_layer(
+        Dialog::around(ScrollView::new(list).min_width(30).max_height(20))
+        .title("Run Hook")
+        .dismiss_button("Cancel")
+    );
+}
@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::ScrollView;
+use cursive::views::TextView;
+
+use diargos_core::model::Model;
+
+const NO_LYRICS_STR: &str = "No lyrics";
+
+/// Opens an editor for lyrics, saving them to the record of a file and then
+/// showing them again.
+fn edit(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, file_path: PathBuf, title: &str, initial: &str) {
+    siv.add_layer(crate::views::text_edit::make(title, initial, move |siv, text| {
+        let changed = shared_model.lock().unwrap().set_lyrics(&file_path, text);
+
+        if changed {
+            shared_model.lock().unwrap().set_status("Edited lyrics");
+        }
+
+        show_for(siv, shared_model.clone(), file_path.clone());
+    }));
+}
+
+/// Fetches lyrics for the record of a file, and opens them in an editor to be
+/// looked over before they are saved.
+fn fetch(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, file_path: PathBuf) {
+    let (provider, url) = {
+        let model = shared_model.lock().unwrap();
+        let record = model.data.records.iter().find(|r| r.file_path == file_path);
+
+        (model.lyrics_provider.clone(), record.and_then(|r| model.lyrics_provider.url_for(r)))
+    };
+
+    let url = match url {
+        Some(url) => url,
+        None => {
+            siv.add_layer(Dialog::info("The record is missing a tag needed to fetch its lyrics"));
+            return;
+        },
+    };
+
+    crate::views::busy::run(siv, "Lyrics", "Fetching lyrics…", "Unable to fetch lyrics", move || provider.fetch(&url), move |siv, lyrics| {
+        match lyrics {
+            Some(lyrics) => edit(siv, shared_model, file_path, "Fetched Lyrics", &lyrics),
+            None => siv.add_layer(Dialog::info("No lyrics found")),
+        }
+    });
+}
+
+fn show_for(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, file_path: PathBuf) {
+    let (name, lyrics, read_only) = {
+        let model = shared_model.lock().unwrap();
+
+        let record = match model.data.records.iter().find(|r| r.file_path == file_path) {
+            Some(record) => record,
+            None => return,
+        };
+
+        let name =
+            record.get_meta("TITLE").and_then(|values| values.first()).cloned()
+            .unwrap_or_else(|| file_path.file_name().unwrap_or_default().to_string_lossy().into_owned())
+        ;
+
+        (name, diargos_core::lyrics::lyrics(record), model.read_only)
+    };
+
+    let text = lyrics.clone().unwrap_or_else(|| String::from(NO_LYRICS_STR));
+
+    let shared_model_for_edit = shared_model.clone();
+    let file_path_for_edit = file_path.clone();
+
+    let mut dialog =
+        Dialog::around(ScrollView::new(TextView::new(text)).max_size((80, 24)))
+        .title(format!("Lyrics: {}", name))
+    ;
+
+    // Lyrics can only be looked at in read-only mode.
+    if !read_only {
+        dialog.add_button("Edit", move |siv| {
+            siv.pop_layer();
+            edit(siv, shared_model_for_edit.clone(), file_path_for_edit.clone(), "Edit Lyrics", lyrics.as_deref().unwrap_or_default());
+        });
+        dialog.add_button("Fetch", move |siv| {
+            siv.pop_layer();
+            fetch(siv, shared_model.clone(), file_path.clone());
+        });
+    }
+
+    siv.add_layer(dialog.dismiss_button("Close"));
+}
+
+/// Shows the lyrics of the record under the cursor, from which they can be
+/// edited or fetched.
+pub fn show(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let file_path = shared_model.lock().unwrap().current_record().map(|r| r.file_path.clone());
+
+    if let Some(file_path) = file_path {
+        show_for(siv, shared_model, file_path);
+    }
+}
@@ -1,6 +1,34 @@
-
 pub mod tag_record;
 pub mod file_browser;
 pub mod field_edit;
+pub mod prompt;
+pub mod confirm;
+pub mod checklist;
+pub mod snapshot_diff;
+pub mod refresh_conflict;
+pub mod tabs;
+pub mod compare;
+pub mod quick_open;
+pub mod rename;
+pub mod delete;
+pub mod transfer;
+pub mod template_preview;
+pub mod duplicates;
+pub mod transliterate;
+pub mod text_edit;
+pub mod date_picker;
+pub mod busy;
+pub mod musicbrainz;
+pub mod lyrics;
+pub mod replay_gain;
+pub mod hooks;
+pub mod scripts;
+pub mod tag_presets;
+pub mod command_columns;
+pub mod save_diff;
+pub mod skipped;
+pub mod history;
+pub mod save_queue;
 
 pub use self::tag_record::TagRecordView;
+pub use self::tabs::TabsView;
@@ -0,0 +1,98 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::ScrollView;
+use cursive::views::SelectView;
+
+use diargos_core::model::Model;
+use diargos_core::musicbrainz::Release;
+use diargos_core::musicbrainz::ReleaseSummary;
+use diargos_core::snapshot::Snapshot;
+
+const TITLE: &str = "MusicBrainz";
+const FAILURE: &str = "MusicBrainz lookup failed";
+
+/// Creates a dialog listing found releases. The dialog is dismissed before the
+/// choose callback is called.
+fn make_results<F>(releases: Vec<ReleaseSummary>, on_choose: F) -> Dialog
+where
+    F: Fn(&mut Cursive, &ReleaseSummary) + 'static,
+{
+    let mut list = SelectView::<ReleaseSummary>::new();
+
+    for release in releases {
+        list.add_item(release.to_string(), release);
+    }
+
+    let list = list.on_submit(move |siv, release: &ReleaseSummary| {
+        siv.pop_layer();
+        on_choose(siv, release);
+    });
+
+    Dialog::around(ScrollView::new(list).min_width(50).max_height(20))
+    .title("MusicBrainz Releases")
+    .dismiss_button("Cancel")
+}
+
+fn show_changes(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, release: Release) {
+    let comparison = {
+        let model = shared_model.lock().unwrap();
+        let records = model.current_album_indices().into_iter().map(|i| &model.data.records[i]).collect::<Vec<_>>();
+
+        let mut comparison = diargos_core::musicbrainz::proposed_tags(&release, &records).compare(&model.data.records);
+
+        // Records off the album are not part of the lookup.
+        comparison.added.clear();
+        comparison
+    };
+
+    if comparison.is_empty() {
+        siv.add_layer(Dialog::info("Tags already match the release"));
+        return;
+    }
+
+    let title = format!("Changes from {}", release.title);
+
+    siv.add_layer(
+        crate::views::snapshot_diff::make(&title, "Apply", comparison, move |siv, to_apply| {
+            let mut model = shared_model.lock().unwrap();
+            let applied = model.edit_records("Apply MusicBrainz release", |records| Snapshot::restore(to_apply, records));
+
+            siv.add_layer(Dialog::info(format!("Applied {} field(s)", applied)));
+        })
+    );
+}
+
+/// Searches MusicBrainz for the album of the record under the cursor, then
+/// shows the tags the chosen release would change on the album's records.
+pub fn look_up_album(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let (artist, album) = match shared_model.lock().unwrap().current_album_terms() {
+        Some(terms) => terms,
+        None => {
+            siv.add_layer(Dialog::info("The current record needs an artist and an album to look up"));
+            return;
+        },
+    };
+
+    let message = format!("Searching for {} by {}…", album, artist);
+
+    crate::views::busy::run(siv, TITLE, &message, FAILURE, move || diargos_core::musicbrainz::search(&artist, &album), move |siv, releases| {
+        if releases.is_empty() {
+            siv.add_layer(Dialog::info("No matching releases found"));
+            return;
+        }
+
+        siv.add_layer(make_results(releases, move |siv, summary| {
+            let shared_model = shared_model.clone();
+            let id = summary.id.clone();
+            let message = format!("Looking up {}…", summary.title);
+
+            crate::views::busy::run(siv, TITLE, &message, FAILURE, move || diargos_core::musicbrainz::lookup(&id), move |siv, release| {
+                show_changes(siv, shared_model, release);
+            });
+        }));
+    });
+}
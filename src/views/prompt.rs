@@ -0,0 +1,42 @@
+use std::rc::Rc;
+
+use cursive::Cursive;
+use cursive::traits::Nameable;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::EditView;
+
+const PROMPT_EDIT_NAME: &str = "prompt_edit";
+
+/// Creates a dialog with a single line of input. The dialog is dismissed
+/// before the submit callback is called with the entered text.
+pub fn make<F>(title: &str, initial: &str, on_submit: F) -> Dialog
+where
+    F: Fn(&mut Cursive, &str) + 'static,
+{
+    let on_submit = Rc::new(on_submit);
+    let on_ok = on_submit.clone();
+
+    Dialog::around(
+        EditView::new()
+        .content(initial)
+        .on_submit(move |siv, text| {
+            siv.pop_layer();
+            on_submit(siv, text);
+        })
+        .with_name(PROMPT_EDIT_NAME)
+        .fixed_width(48)
+    )
+    .title(title)
+    .button("OK", move |siv| {
+        let text =
+            siv.call_on_name(PROMPT_EDIT_NAME, |view: &mut EditView| view.get_content())
+            .unwrap_or_default()
+        ;
+
+        siv.pop_layer();
+        on_ok(siv, &text);
+    })
+    .dismiss_button("Cancel")
+    .padding_lrtb(1, 1, 1, 0)
+}
@@ -0,0 +1,41 @@
+use std::path::PathBuf;
+
+use cursive::Cursive;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::ScrollView;
+use cursive::views::SelectView;
+
+use crate::places::Places;
+
+pub const BOOKMARK_MARKER: &str = "★";
+
+/// Creates a dialog listing bookmarked directories, then recently opened ones.
+/// The dialog is dismissed before the open callback is called.
+pub fn make<F>(places: &Places, on_open: F) -> Dialog
+where
+    F: Fn(&mut Cursive, PathBuf) + 'static,
+{
+    if places.bookmarks.is_empty() && places.recent.is_empty() {
+        return Dialog::info("No bookmarked or recently opened directories yet")
+    }
+
+    let mut list = SelectView::<PathBuf>::new();
+
+    for dir in places.bookmarks.iter() {
+        list.add_item(format!("{} {}", BOOKMARK_MARKER, dir.display()), dir.clone());
+    }
+
+    for dir in places.recent.iter().filter(|d| !places.bookmarks.contains(d)) {
+        list.add_item(format!("  {}", dir.display()), dir.clone());
+    }
+
+    let list = list.on_submit(move |siv, dir: &PathBuf| {
+        siv.pop_layer();
+        on_open(siv, dir.clone());
+    });
+
+    Dialog::around(ScrollView::new(list).min_width(40).max_height(20))
+    .title("Quick Open")
+    .dismiss_button("Cancel")
+}
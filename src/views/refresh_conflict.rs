@@ -0,0 +1,185 @@
+use std::cell::RefCell;
+use std::collections::HashSet;
+use std::path::Path;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::traits::Resizable;
+use cursive::views::Checkbox;
+use cursive::views::Dialog;
+use cursive::views::LinearLayout;
+use cursive::views::ScrollView;
+use cursive::views::TextView;
+
+use diargos_core::consts::*;
+use diargos_core::error::Error;
+use diargos_core::model::Model;
+use diargos_core::refresh::Conflict;
+use diargos_core::refresh::FieldConflict;
+use diargos_core::refresh::Resolution;
+
+fn display_values(values: &Option<Vec<String>>) -> String {
+    match values {
+        Some(values) => values.join(FIELD_SEP_STR),
+        None => String::from(MISSING_VALUE_STR),
+    }
+}
+
+/// Re-reads the records of a model from disk, then walks through any
+/// conflicts. Reloads triggered by watching the directory stay quiet when
+/// nothing changed, such as after saving.
+pub fn reload(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, from_watcher: bool) {
+    let conflicts = {
+        let mut model = shared_model.lock().unwrap();
+
+        match model.refresh() {
+            Ok(mut summary) => {
+                let quiet = from_watcher && summary.is_empty();
+
+                if !quiet {
+                    let status = if from_watcher { format!("Updated from disk: {}", summary) } else { summary.to_string() };
+                    model.set_status(status);
+                }
+
+                // Files that still can't be read take the place of those
+                // skipped before, as any that can be read now were picked up.
+                model.load_failures = summary.failures.drain(..).map(|(file_path, err)| Error::Flac(file_path, err)).collect();
+
+                if quiet { return }
+
+                summary.conflicts
+            },
+            Err(err) => {
+                model.set_status(format!("Unable to re-scan directory: {}", err));
+                return
+            },
+        }
+    };
+
+    resolve_all(siv, shared_model, conflicts, Rc::new(|_| {}));
+}
+
+/// Walks through conflicts one at a time, asking how to settle each, then
+/// runs a callback once all of them are settled.
+pub fn resolve_all(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, mut conflicts: Vec<Conflict>, on_done: Rc<dyn Fn(&mut Cursive)>) {
+    if conflicts.is_empty() {
+        return on_done(siv);
+    }
+
+    let conflict = conflicts.remove(0);
+
+    let field_conflicts = {
+        let model = shared_model.lock().unwrap();
+
+        model.data.records.iter()
+        .find(|r| r.file_path == conflict.file_path)
+        .map(|record| conflict.field_conflicts(record))
+    };
+
+    // The record may have gone away in the meantime.
+    let field_conflicts = match field_conflicts {
+        Some(field_conflicts) => field_conflicts,
+        None => return resolve_all(siv, shared_model, conflicts, on_done),
+    };
+
+    let file_path = conflict.file_path.clone();
+
+    let on_resolve = move |siv: &mut Cursive, resolution: Resolution| {
+        shared_model.lock().unwrap().resolve_conflict(conflict.clone(), &resolution);
+        resolve_all(siv, shared_model.clone(), conflicts.clone(), on_done.clone());
+    };
+
+    siv.add_layer(make(&file_path, field_conflicts, on_resolve));
+}
+
+/// Creates a dialog offering to keep the pending edits of a file, take its
+/// version on disk, or merge the two field by field.
+pub fn make<F>(file_path: &Path, field_conflicts: Vec<FieldConflict>, on_resolve: F) -> Dialog
+where
+    F: Fn(&mut Cursive, Resolution) + 'static,
+{
+    let on_resolve = Rc::new(on_resolve);
+
+    let file_name = file_path.file_name().and_then(|f| f.to_str()).unwrap_or_default();
+
+    let message = format!(
+        "{} has pending edits, but was changed on disk since it was loaded.\n\n\
+        {} field(s) were changed on both sides.",
+        file_name,
+        field_conflicts.len(),
+    );
+
+    let on_keep = on_resolve.clone();
+    let on_take = on_resolve.clone();
+
+    Dialog::text(message)
+    .title("File Changed on Disk")
+    .button("Keep My Edits", move |siv| {
+        siv.pop_layer();
+        on_keep(siv, Resolution::KeepMine);
+    })
+    .button("Take Disk Version", move |siv| {
+        siv.pop_layer();
+        on_take(siv, Resolution::TakeDisk);
+    })
+    .button("Merge", move |siv| {
+        if field_conflicts.is_empty() {
+            siv.pop_layer();
+            on_resolve(siv, Resolution::Merge(HashSet::new()));
+        }
+        else {
+            siv.add_layer(make_merge(field_conflicts.clone(), on_resolve.clone()));
+        }
+    })
+}
+
+/// Lists the fields changed on both sides, each checked to keep the pending edit.
+fn make_merge<F>(field_conflicts: Vec<FieldConflict>, on_resolve: Rc<F>) -> Dialog
+where
+    F: Fn(&mut Cursive, Resolution) + 'static,
+{
+    let keep_mine = Rc::new(RefCell::new(vec![true; field_conflicts.len()]));
+
+    let mut list = LinearLayout::vertical();
+
+    for (i, field_conflict) in field_conflicts.iter().enumerate() {
+        let label = format!(
+            " {}: {} (mine) / {} (disk)",
+            field_conflict.meta_key,
+            display_values(&field_conflict.mine),
+            display_values(&field_conflict.theirs),
+        );
+
+        let keep_mine = keep_mine.clone();
+
+        list.add_child(
+            LinearLayout::horizontal()
+            .child(
+                Checkbox::new()
+                .checked()
+                .on_change(move |_, is_checked| { keep_mine.borrow_mut()[i] = is_checked; })
+            )
+            .child(TextView::new(label))
+        );
+    }
+
+    Dialog::around(ScrollView::new(list).max_height(20))
+    .title("Keep Which Edits?")
+    .button("Merge", move |siv| {
+        let keys =
+            field_conflicts.iter()
+            .zip(keep_mine.borrow().iter())
+            .filter(|(_, &k)| k)
+            .map(|(field_conflict, _)| field_conflict.meta_key.clone())
+            .collect()
+        ;
+
+        // Pop both this dialog and the one it was opened from.
+        siv.pop_layer();
+        siv.pop_layer();
+        on_resolve(siv, Resolution::Merge(keys));
+    })
+    .dismiss_button("Back")
+}
@@ -0,0 +1,165 @@
+use std::path::Path;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::views::Dialog;
+
+use diargos_core::data::InfoKind;
+use diargos_core::model::Model;
+use diargos_core::rename::Rename;
+use diargos_core::rename::Template;
+use crate::views::template_preview::PreviewRow;
+
+/// A path relative to the directory a file is renamed from.
+fn relative(rename_from: &Path, path: &Path) -> String {
+    let dir = rename_from.parent().unwrap_or_else(|| Path::new(""));
+    path.strip_prefix(dir).unwrap_or(path).display().to_string()
+}
+
+/// Asks for a template, then previews how the files of the target records
+/// would be renamed, and renames the ones left checked.
+pub fn show(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let initial = shared_model.lock().unwrap().rename_template.clone();
+
+    siv.add_layer(
+        crate::views::prompt::make("Rename Files From Template", &initial, move |siv, text| {
+            preview(siv, shared_model.clone(), text);
+        })
+    );
+}
+
+fn preview(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, text: &str) {
+    let template = match text.parse::<Template>() {
+        Ok(template) => template,
+        Err(err) => return siv.add_layer(Dialog::info(format!("Invalid template: {}", err))),
+    };
+
+    let renames = {
+        let mut model = shared_model.lock().unwrap();
+        model.rename_template = text.to_string();
+        model.plan_renames(&template)
+    };
+
+    review(siv, shared_model, renames);
+}
+
+/// Previews renames, and carries out the ones left checked.
+pub fn review(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, renames: Vec<Rename>) {
+    if renames.is_empty() {
+        return siv.add_layer(Dialog::info("No files need renaming"));
+    }
+
+    let rows =
+        renames.iter()
+        .map(|rename| PreviewRow {
+            before: relative(&rename.from, &rename.from),
+            after: Some(relative(&rename.from, &rename.to)),
+            warning: if rename.collided { Some(String::from("name taken, numbered")) } else { None },
+        })
+        .collect()
+    ;
+
+    siv.add_layer(
+        crate::views::template_preview::make("Review Renames", rows, "Rename", move |siv, indices| {
+            let renames = indices.into_iter().map(|i| renames[i].clone()).collect::<Vec<_>>();
+
+            let failures = {
+                let mut model = shared_model.lock().unwrap();
+                let failures = model.rename_files(&renames);
+                model.set_status(format!("Renamed {} file(s)", renames.len() - failures.len()));
+                failures
+            };
+
+            if !failures.is_empty() {
+                let mut message = format!("Unable to rename {} file(s):", failures.len());
+
+                for (path, err) in failures {
+                    message.push_str(&format!("\n{}: {}", path.display(), err));
+                }
+
+                siv.add_layer(Dialog::info(message));
+            }
+        })
+    );
+}
+
+/// Asks for a pattern, then previews the tags each target record would get
+/// from its file name, and stages the checked ones as one edit.
+pub fn show_tags_from_names(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let initial = shared_model.lock().unwrap().name_pattern.clone();
+
+    siv.add_layer(
+        crate::views::prompt::make("Tags From File Names", &initial, move |siv, text| {
+            preview_tags(siv, shared_model.clone(), text);
+        })
+    );
+}
+
+fn preview_tags(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, text: &str) {
+    let template = match text.parse::<Template>() {
+        Ok(template) => template,
+        Err(err) => return siv.add_layer(Dialog::info(format!("Invalid pattern: {}", err))),
+    };
+
+    let (plan, rows) = {
+        let mut model = shared_model.lock().unwrap();
+        model.name_pattern = text.to_string();
+
+        let plan = model.plan_tags_from_names(&template);
+
+        let rows =
+            plan.iter()
+            .map(|(i, inferred)| {
+                let before = model.data.records[*i].get_info(&InfoKind::FileName).unwrap_or_default().to_string();
+
+                let inferred = match inferred {
+                    Some(inferred) => inferred,
+                    None => return PreviewRow { before, after: None, warning: Some(String::from("does not match")) },
+                };
+
+                let after =
+                    Some(inferred.missing.iter().map(|(k, v)| format!("{} = {}", k, v)).collect::<Vec<_>>().join(", "))
+                    .filter(|after| !after.is_empty())
+                ;
+
+                let warning =
+                    Some(inferred.differing.join(", "))
+                    .filter(|differing| !differing.is_empty())
+                    .map(|differing| format!("kept {}", differing))
+                ;
+
+                PreviewRow { before, after, warning }
+            })
+            .collect::<Vec<_>>()
+        ;
+
+        (plan, rows)
+    };
+
+    if rows.iter().all(|row| row.after.is_none()) {
+        return siv.add_layer(Dialog::info("No missing tags found in file names"));
+    }
+
+    siv.add_layer(
+        crate::views::template_preview::make("Review Tags", rows, "Apply", move |_, indices| {
+            let fills =
+                indices.into_iter()
+                .filter_map(|i| Some((plan[i].0, plan[i].1.as_ref()?.missing.clone())))
+                .collect::<Vec<_>>()
+            ;
+
+            let mut model = shared_model.lock().unwrap();
+
+            model.edit_records("Tags from file names", |records| {
+                for (i, fields) in fills.iter() {
+                    for (meta_key, value) in fields {
+                        records[*i].set_meta(meta_key, Some(vec![value.clone()]));
+                    }
+                }
+            });
+
+            model.set_status(format!("Filled in tags of {} file(s) from their names", fills.len()));
+        })
+    );
+}
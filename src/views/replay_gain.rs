@@ -0,0 +1,105 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::sync::atomic::AtomicBool;
+use std::sync::atomic::Ordering;
+
+use cursive::Cursive;
+use cursive::traits::Nameable;
+use cursive::traits::Resizable;
+use cursive::utils::Counter;
+use cursive::views::Dialog;
+use cursive::views::LinearLayout;
+use cursive::views::ProgressBar;
+use cursive::views::TextView;
+
+use diargos_core::model::Model;
+
+const PROGRESS_NAME: &str = "replay_gain_progress";
+
+/// The most failed files listed once done.
+const MAX_LISTED_ERRORS: usize = 10;
+
+fn summary(num_changed: usize, num_files: usize, errors: &[(PathBuf, String)]) -> String {
+    let mut summary = format!("Tagged {} of {} file(s)", num_changed, num_files);
+
+    if !errors.is_empty() {
+        summary.push_str(&format!("\n\nUnable to analyze {} file(s):", errors.len()));
+
+        for (file_path, err) in errors.iter().take(MAX_LISTED_ERRORS) {
+            let file_name = file_path.file_name().unwrap_or_default().to_string_lossy();
+            summary.push_str(&format!("\n{}: {}", file_name, err));
+        }
+    }
+
+    summary
+}
+
+/// Analyzes the loudness of the target records on a background thread, with
+/// a progress bar, then stages their ReplayGain tags. Nothing is staged if
+/// the analysis is cancelled.
+pub fn analyze(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let file_paths = {
+        let model = shared_model.lock().unwrap();
+        model.target_record_indices().into_iter().map(|i| model.data.records[i].file_path.clone()).collect::<Vec<_>>()
+    };
+
+    if file_paths.is_empty() {
+        return;
+    }
+
+    let num_files = file_paths.len();
+    let counter = Counter::new(0);
+    let cancelled = Arc::new(AtomicBool::new(false));
+    let cancelled_for_button = cancelled.clone();
+
+    siv.add_layer(
+        Dialog::around(
+            LinearLayout::vertical()
+            .child(TextView::new(format!("Analyzing {} file(s)…", num_files)))
+            .child(ProgressBar::new().range(0, num_files).with_value(counter.clone()).fixed_width(40))
+        )
+        .title("ReplayGain")
+        .button("Cancel", move |_| cancelled_for_button.store(true, Ordering::Relaxed))
+        .with_name(PROGRESS_NAME)
+    );
+
+    // The progress bar is only redrawn on events otherwise.
+    siv.set_autorefresh(true);
+
+    let cb_sink = siv.cb_sink().clone();
+
+    std::thread::spawn(move || {
+        let mut analyzed = Vec::new();
+        let mut errors = Vec::new();
+
+        for file_path in file_paths {
+            if cancelled.load(Ordering::Relaxed) {
+                break;
+            }
+
+            match diargos_core::replaygain::analyze_file(&file_path, &cancelled) {
+                Ok(loudness) => analyzed.push((file_path, loudness)),
+                Err(err) => errors.push((file_path, err)),
+            }
+
+            counter.tick(1);
+        }
+
+        cb_sink.send(Box::new(move |siv| {
+            siv.set_autorefresh(false);
+
+            if let Some(position) = siv.screen_mut().find_layer_from_name(PROGRESS_NAME) {
+                siv.screen_mut().remove_layer(position);
+            }
+
+            if cancelled.load(Ordering::Relaxed) {
+                siv.add_layer(Dialog::info("ReplayGain analysis cancelled, no tags were changed"));
+                return;
+            }
+
+            let num_changed = shared_model.lock().unwrap().apply_replay_gain(&analyzed);
+            siv.add_layer(Dialog::info(summary(num_changed, num_files, &errors)));
+        })).ok();
+    });
+}
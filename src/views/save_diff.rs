@@ -0,0 +1,49 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::ScrollView;
+use cursive::views::TextView;
+
+use diargos_core::model::Model;
+
+/// Shows what saving would write in dry-run mode, without touching any file.
+/// The diff can also be written out as a report.
+pub fn show(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let (diff, num_edited) = {
+        let mut model = shared_model.lock().unwrap();
+        let num_edited = model.data.records.iter().filter(|r| r.is_edited()).count();
+        model.set_status(format!("Dry run: {} file(s) would be saved", num_edited));
+
+        (model.pending_diff(), num_edited)
+    };
+
+    if num_edited == 0 {
+        siv.add_layer(Dialog::info("Dry run: there are no edits to save"));
+        return;
+    }
+
+    let report = diff.clone();
+
+    siv.add_layer(
+        Dialog::around(ScrollView::new(TextView::new(diff)).max_size((100, 24)))
+        .title(format!("Dry Run: {} file(s) would change", num_edited))
+        .button("Write Report", move |siv| {
+            let report = report.clone();
+
+            siv.add_layer(
+                crate::views::prompt::make("Write Report", "save-diff.txt", move |siv, path| {
+                    let message = match std::fs::write(path, &report) {
+                        Ok(()) => format!("Report written to {}", path),
+                        Err(err) => format!("Unable to write report: {}", err),
+                    };
+
+                    siv.add_layer(Dialog::info(message));
+                })
+            );
+        })
+        .dismiss_button("Close")
+    );
+}
@@ -0,0 +1,133 @@
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
+use std::thread::JoinHandle;
+
+use cursive::Cursive;
+use cursive::views::Dialog;
+
+use diargos_core::data::Record;
+use diargos_core::model::Model;
+use diargos_core::model::SaveSummary;
+
+/// Picks the records to write from a model, marking them as being written.
+type Queue = Rc<dyn Fn(&mut Model) -> (Vec<Record>, SaveSummary)>;
+
+/// The threads writing files, which quitting waits for.
+static WRITERS: Mutex<Vec<JoinHandle<()>>> = Mutex::new(Vec::new());
+
+/// Writes all pending edits one file at a time on a background thread, so
+/// that the table stays usable, showing progress in the status line and
+/// listing any failures at the end. Files that changed on disk since loading
+/// are walked through, then saved once settled.
+pub fn save_all(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    save(siv, shared_model, Rc::new(Model::queue_writes));
+}
+
+/// Like `save_all`, but only writes the pending edits of one file.
+pub fn save_file(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, file_path: PathBuf) {
+    save(siv, shared_model, Rc::new(move |model: &mut Model| model.queue_file_write(&file_path)));
+}
+
+fn save(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, queue: Queue) {
+    let (records, mut summary, mut writer) = {
+        let mut model = shared_model.lock().unwrap();
+        let (records, summary) = queue(&mut model);
+        (records, summary, model.writer())
+    };
+
+    let conflicts = std::mem::take(&mut summary.conflicts);
+
+    if !conflicts.is_empty() {
+        let on_done_model = shared_model.clone();
+        let on_done = Rc::new(move |siv: &mut Cursive| save(siv, on_done_model.clone(), queue.clone()));
+
+        crate::views::refresh_conflict::resolve_all(siv, shared_model.clone(), conflicts, on_done);
+    }
+
+    if records.is_empty() {
+        return finish(siv, &shared_model, summary);
+    }
+
+    let num_files = records.len();
+    shared_model.lock().unwrap().set_status(format!("Saving {} file(s)", num_files));
+
+    let cb_sink = siv.cb_sink().clone();
+    let shared_summary = Arc::new(Mutex::new(summary));
+    let shared_model = Arc::downgrade(&shared_model);
+
+    // Files are written even if the tab is closed meanwhile, and quitting
+    // waits for them, so that no write is left half done.
+    let handle = std::thread::spawn(move || {
+        for (i, record) in records.into_iter().enumerate() {
+            let result = writer.write_record(&record);
+            let shared_model = shared_model.clone();
+            let shared_summary = shared_summary.clone();
+
+            let sent = cb_sink.send(Box::new(move |_| {
+                let shared_model = match shared_model.upgrade() {
+                    Some(shared_model) => shared_model,
+                    None => return,
+                };
+
+                let mut model = shared_model.lock().unwrap();
+                let mut summary = shared_summary.lock().unwrap();
+
+                match model.finish_write(&record, result) {
+                    Ok(()) => { summary.saved += 1; },
+                    Err(err) => { summary.failures.push((record.file_path.clone(), err)); },
+                }
+
+                let file_name = record.file_path.file_name().unwrap_or_default().to_string_lossy();
+                model.set_status(format!("Saving {} of {}: {}", i + 1, num_files, file_name));
+            }));
+
+            // The UI has shut down.
+            if sent.is_err() {
+                return;
+            }
+        }
+
+        cb_sink.send(Box::new(move |siv| {
+            if let Some(shared_model) = shared_model.upgrade() {
+                let summary = std::mem::take(&mut *shared_summary.lock().unwrap());
+                finish(siv, &shared_model, summary);
+            }
+        })).ok();
+    });
+
+    let mut writers = WRITERS.lock().unwrap();
+    writers.retain(|writer| !writer.is_finished());
+    writers.push(handle);
+}
+
+/// Blocks until every file queued for writing has been written. The UI must
+/// still be around, as the writers report to it after each file.
+pub fn wait_for_writes() {
+    let writers = std::mem::take(&mut *WRITERS.lock().unwrap());
+
+    if !writers.is_empty() {
+        log::info!("Waiting for {} writer(s) to finish", writers.len());
+    }
+
+    for writer in writers {
+        writer.join().ok();
+    }
+}
+
+/// Reports how saving went.
+fn finish(siv: &mut Cursive, shared_model: &Arc<Mutex<Model>>, summary: SaveSummary) {
+    log::info!("{}", summary);
+    shared_model.lock().unwrap().set_status(summary.to_string());
+
+    if !summary.failures.is_empty() {
+        let mut message = format!("Unable to save {} file(s):", summary.failures.len());
+
+        for (path, err) in summary.failures {
+            message.push_str(&format!("\n{}: {}", path.display(), err));
+        }
+
+        siv.add_layer(Dialog::info(message));
+    }
+}
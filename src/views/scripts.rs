@@ -0,0 +1,33 @@
+use cursive::Cursive;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::ScrollView;
+use cursive::views::SelectView;
+
+/// Lists the names of the scripts from the config to pick one to run on the
+/// target records. The list is dismissed before the callback is called with
+/// the name.
+pub fn choose<F>(siv: &mut Cursive, names: Vec<String>, on_choose: F)
+where
+    F: Fn(&mut Cursive, String) + 'static,
+{
+    if names.is_empty() {
+        siv.add_layer(Dialog::info("No scripts are set up, add them under \"scripts\" in the config"));
+        return;
+    }
+
+    let list =
+        SelectView::<String>::new()
+        .with_all_str(names)
+        .on_submit(move |siv, name: &String| {
+            siv.pop_layer();
+            on_choose(siv, name.clone());
+        })
+    ;
+
+    siv.add_layer(
+        Dialog::around(ScrollView::new(list).min_width(30).max_height(20))
+        .title("Run Script")
+        .dismiss_button("Cancel")
+    );
+}
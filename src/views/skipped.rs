@@ -0,0 +1,34 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::ScrollView;
+use cursive::views::TextView;
+
+use diargos_core::model::Model;
+
+/// Lists the files that could not be read when loading or last refreshing,
+/// along with why.
+pub fn show(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let model = shared_model.lock().unwrap();
+
+    if model.load_failures.is_empty() {
+        siv.add_layer(Dialog::info("No files were skipped"));
+        return;
+    }
+
+    let message =
+        model.load_failures.iter()
+        .map(|err| err.to_string())
+        .collect::<Vec<_>>()
+        .join("\n")
+    ;
+
+    siv.add_layer(
+        Dialog::around(ScrollView::new(TextView::new(message)).max_size((80, 20)))
+        .title(format!("{} File(s) Skipped", model.load_failures.len()))
+        .dismiss_button("OK")
+    );
+}
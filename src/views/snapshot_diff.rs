@@ -0,0 +1,96 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use cursive::Cursive;
+use cursive::traits::Nameable;
+use cursive::traits::Resizable;
+use cursive::views::Checkbox;
+use cursive::views::Dialog;
+use cursive::views::LinearLayout;
+use cursive::views::ScrollView;
+use cursive::views::TextView;
+
+use diargos_core::consts::*;
+use diargos_core::snapshot::Comparison;
+use diargos_core::snapshot::FieldDiff;
+
+fn display_values(values: &Option<Vec<String>>) -> String {
+    match values {
+        Some(values) => values.join(FIELD_SEP_STR),
+        None => String::from(MISSING_VALUE_STR),
+    }
+}
+
+fn checkbox_name(index: usize) -> String {
+    format!("snapshot_diff_{}", index)
+}
+
+/// Creates a dialog listing differences between a snapshot and the current
+/// state of the records. Each changed field can be checked, and the checked
+/// ones are passed to the confirm callback.
+pub fn make<F>(title: &str, confirm_label: &str, comparison: Comparison, on_confirm: F) -> Dialog
+where
+    F: Fn(&mut Cursive, Vec<FieldDiff>) + 'static,
+{
+    let Comparison { added, removed, changed: diffs } = comparison;
+
+    let checked = Rc::new(RefCell::new(vec![false; diffs.len()]));
+
+    let mut list = LinearLayout::vertical();
+
+    for (sigil, paths) in [("+", added), ("-", removed)] {
+        for path in paths {
+            list.add_child(TextView::new(format!("{} {}", sigil, path.display())));
+        }
+    }
+
+    for (i, diff) in diffs.iter().enumerate() {
+        let file_name = diff.file_path.file_name().and_then(|f| f.to_str()).unwrap_or_default();
+
+        let label = format!(
+            " {} {}: {} → {}",
+            file_name,
+            diff.meta_key,
+            display_values(&diff.current),
+            display_values(&diff.snapshot),
+        );
+
+        let checked = checked.clone();
+
+        list.add_child(
+            LinearLayout::horizontal()
+            .child(
+                Checkbox::new()
+                .on_change(move |_, is_checked| { checked.borrow_mut()[i] = is_checked; })
+                .with_name(checkbox_name(i))
+            )
+            .child(TextView::new(label))
+        );
+    }
+
+    let num_diffs = diffs.len();
+    let checked_for_all = checked.clone();
+
+    Dialog::around(ScrollView::new(list).max_height(20))
+    .title(format!("{} ({} changed)", title, num_diffs))
+    .button("Select All", move |siv| {
+        for i in 0..num_diffs {
+            siv.call_on_name(&checkbox_name(i), |view: &mut Checkbox| { view.set_checked(true); });
+        }
+
+        checked_for_all.borrow_mut().iter_mut().for_each(|c| *c = true);
+    })
+    .button(confirm_label, move |siv| {
+        let to_restore =
+            diffs.iter()
+            .zip(checked.borrow().iter())
+            .filter(|(_, &c)| c)
+            .map(|(diff, _)| diff.clone())
+            .collect()
+        ;
+
+        siv.pop_layer();
+        on_confirm(siv, to_restore);
+    })
+    .dismiss_button("Cancel")
+}
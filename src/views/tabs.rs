@@ -0,0 +1,380 @@
+use std::path::Path;
+use std::path::PathBuf;
+use std::rc::Rc;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::CbSink;
+use cursive::Cursive;
+use cursive::Printer;
+use cursive::XY;
+use cursive::direction::Direction;
+use cursive::event::Callback;
+use cursive::event::Event;
+use cursive::event::EventResult;
+use cursive::theme::ColorStyle;
+use cursive::view::View;
+use unicode_width::UnicodeWidthStr;
+
+use diargos_core::command::Command;
+use diargos_core::config::ConfigLayer;
+use diargos_core::config::LayeredConfig;
+use diargos_core::error::Error;
+use diargos_core::model::Model;
+use crate::places::Places;
+use crate::views::compare::Side;
+use crate::views::TagRecordView;
+use diargos_core::watch::DirWatcher;
+
+/// The name the tabs view is registered under, so dialogs can add tabs.
+pub const TABS_VIEW_NAME: &str = "tabs";
+
+/// Loads the model for a newly opened directory.
+pub type OpenModel = Rc<dyn Fn(PathBuf) -> Result<Model, Error>>;
+
+/// Applies the config files, as they are now, to the model of a tab with the
+/// directory it was opened at.
+pub type ConfigureModel = Rc<dyn Fn(Option<&Path>, &mut Model) -> Result<(), Error>>;
+
+const TAB_BAR_HEIGHT: usize = 1;
+
+struct Tab {
+    name: String,
+    /// The directory the records were read from, if any.
+    working_dir: Option<PathBuf>,
+    view: TagRecordView,
+    /// Reloads the records when files change on disk, if watching is on.
+    _watcher: Option<DirWatcher>,
+    /// Reloads the config when the directory's own config file changes, if
+    /// watching the config is on.
+    _config_watcher: Option<DirWatcher>,
+}
+
+/// Shows the file browser, and loads the chosen directory into the current
+/// tab. Browsing starts from a directory, or else the current one.
+pub fn browse(siv: &mut Cursive, start: Option<PathBuf>) {
+    let start = start.or_else(|| std::env::current_dir().ok()).unwrap_or_default();
+
+    crate::views::file_browser::show(siv, start, open_in_current);
+}
+
+/// Shows the bookmarked and recently opened directories, and loads the chosen
+/// one into the current tab.
+pub fn quick_open(siv: &mut Cursive) {
+    siv.add_layer(crate::views::quick_open::make(&Places::load(), open_in_current));
+}
+
+/// Watches a directory, reloading a model from a background thread whenever
+/// its files change. Models of closed tabs are left alone.
+fn watch(working_dir: &Path, shared_model: &Arc<Mutex<Model>>, cb_sink: CbSink) -> Option<DirWatcher> {
+    let shared_model = Arc::downgrade(shared_model);
+
+    let on_change = move || {
+        let shared_model = shared_model.clone();
+
+        cb_sink.send(Box::new(move |siv| {
+            if let Some(shared_model) = shared_model.upgrade() {
+                crate::views::refresh_conflict::reload(siv, shared_model, true);
+            }
+        })).ok();
+    };
+
+    // Not being able to watch is not worth failing over, a reload still works.
+    DirWatcher::new(working_dir, on_change)
+    .map_err(|err| log::warn!("unable to watch {}: {}", working_dir.display(), err))
+    .ok()
+}
+
+/// Watches a directory for changes to the config files a filter picks out,
+/// reloading the config of every tab when they change.
+pub fn watch_config<W>(dir: &Path, is_config: W, cb_sink: CbSink) -> Option<DirWatcher>
+where
+    W: Fn(&Path) -> bool + Send + 'static,
+{
+    let on_change = move || { cb_sink.send(Box::new(reload_config)).ok(); };
+
+    DirWatcher::with_filter(dir, is_config, on_change)
+    .map_err(|err| log::debug!("unable to watch {} for config changes: {}", dir.display(), err))
+    .ok()
+}
+
+/// Re-reads the config files, and applies them to the model of every tab.
+pub fn reload_config(siv: &mut Cursive) {
+    let result = siv.call_on_name(TABS_VIEW_NAME, |view: &mut TabsView| view.reload_config());
+
+    if let Some(Err(err)) = result {
+        siv.add_layer(cursive::views::Dialog::info(format!("Unable to reload the config: {}", err)));
+    }
+}
+
+fn open_in_current(siv: &mut Cursive, working_dir: PathBuf) {
+    let result = siv.call_on_name(TABS_VIEW_NAME, |view: &mut TabsView| view.open_in_current(working_dir));
+
+    if let Some(Err(err)) = result {
+        siv.add_layer(cursive::views::Dialog::info(err.to_string()));
+    }
+}
+
+/// Several `TagRecordView`s, each with its own model, with a line of tab
+/// names above them. The tab bar is only shown once there is more than one.
+pub struct TabsView {
+    tabs: Vec<Tab>,
+    current: usize,
+    open_model: OpenModel,
+    configure_model: ConfigureModel,
+    cb_sink: CbSink,
+    /// Whether opened directories should be watched for changes.
+    watch_files: bool,
+    /// Whether the config files of opened directories should be watched.
+    watch_config: bool,
+}
+
+impl TabsView {
+    pub fn new(open_model: OpenModel, configure_model: ConfigureModel, cb_sink: CbSink, watch_files: bool, watch_config: bool) -> Self {
+        Self { tabs: Vec::new(), current: 0, open_model, configure_model, cb_sink, watch_files, watch_config }
+    }
+
+    fn make_tab(&self, name: String, working_dir: Option<PathBuf>, model: Model) -> Tab {
+        let view = TagRecordView::new(model, self.cb_sink.clone());
+
+        let watcher = match &working_dir {
+            Some(working_dir) if self.watch_files => watch(working_dir, &view.shared_model(), self.cb_sink.clone()),
+            _ => None,
+        };
+
+        let config_watcher = match &working_dir {
+            Some(working_dir) if self.watch_config => {
+                watch_config(working_dir, |path| ConfigLayer::is_named(path, LayeredConfig::DIR_CONFIG_NAME), self.cb_sink.clone())
+            },
+            _ => None,
+        };
+
+        Tab { name, working_dir, view, _watcher: watcher, _config_watcher: config_watcher }
+    }
+
+    /// Adds a tab and switches to it.
+    pub fn add_tab(&mut self, name: String, working_dir: Option<PathBuf>, model: Model) {
+        let tab = self.make_tab(name, working_dir, model);
+        self.tabs.push(tab);
+        self.current = self.tabs.len() - 1;
+    }
+
+    /// Opens a directory in a new tab.
+    pub fn open(&mut self, working_dir: PathBuf) -> Result<(), Error> {
+        let model = (self.open_model)(working_dir.clone())?;
+        crate::places::remember_opened(&working_dir);
+        self.add_tab(Self::tab_name(&working_dir), Some(working_dir), model);
+        Ok(())
+    }
+
+    /// Loads a directory into the current tab, in place of its records.
+    pub fn open_in_current(&mut self, working_dir: PathBuf) -> Result<(), Error> {
+        let model = (self.open_model)(working_dir.clone())?;
+        crate::places::remember_opened(&working_dir);
+
+        let tab = self.make_tab(Self::tab_name(&working_dir), Some(working_dir), model);
+
+        match self.tabs.get_mut(self.current) {
+            Some(current) => *current = tab,
+            None => {
+                self.tabs.push(tab);
+                self.current = self.tabs.len() - 1;
+            },
+        }
+
+        Ok(())
+    }
+
+    /// Re-reads the config files, and applies them to the model of every tab.
+    /// Pending edits are kept.
+    pub fn reload_config(&mut self) -> Result<(), Error> {
+        for tab in &self.tabs {
+            let shared_model = tab.view.shared_model();
+            let mut model = shared_model.lock().unwrap();
+            (self.configure_model)(tab.working_dir.as_deref(), &mut model)?;
+        }
+
+        if let Some(tab) = self.tabs.get(self.current) {
+            tab.view.shared_model().lock().unwrap().set_status(format!("Reloaded the config of {} tab(s)", self.tabs.len()));
+        }
+
+        Ok(())
+    }
+
+    pub fn tab_name(working_dir: &Path) -> String {
+        working_dir.file_name()
+        .map(|name| name.to_string_lossy().into_owned())
+        .unwrap_or_else(|| working_dir.to_string_lossy().into_owned())
+    }
+
+    pub fn select_tab(&mut self, index: usize) {
+        if index < self.tabs.len() {
+            self.current = index;
+        }
+    }
+
+    /// Makes a callback that runs an action which replaces the records of
+    /// the current tab, first asking to confirm if it has unsaved edits.
+    fn replace_current<F>(tab: &Tab, action: F) -> Callback
+    where
+        F: Fn(&mut Cursive) + 'static,
+    {
+        let has_edits = tab.view.has_edits();
+
+        Callback::from_fn_once(move |siv| {
+            if has_edits {
+                siv.add_layer(
+                    crate::views::confirm::make(
+                        "Discard Edits?",
+                        String::from("This tab has unsaved edits, which will be lost when opening another directory."),
+                        "Discard",
+                        action,
+                    )
+                );
+            }
+            else {
+                action(siv);
+            }
+        })
+    }
+
+    fn cycle(&mut self, forward: bool) {
+        let n = self.tabs.len();
+
+        if n > 0 {
+            self.current = if forward { (self.current + 1) % n } else { (self.current + n - 1) % n };
+        }
+    }
+
+    fn tab_bar_height(&self) -> usize {
+        if self.tabs.len() > 1 { TAB_BAR_HEIGHT } else { 0 }
+    }
+
+    fn draw_tab_bar(&self, printer: &Printer) {
+        let mut offset_x = 0;
+
+        for (i, tab) in self.tabs.iter().enumerate() {
+            // Tabs with unsaved edits are marked, like in most editors.
+            let marker = if tab.view.has_edits() { "*" } else { "" };
+            let label = format!(" {}{} ", tab.name, marker);
+
+            let style = if i == self.current { ColorStyle::highlight() } else { ColorStyle::secondary() };
+            printer.with_color(style, |pr| { pr.print((offset_x, 0), &label); });
+
+            offset_x += label.width() + 1;
+        }
+    }
+}
+
+impl View for TabsView {
+    fn draw(&self, printer: &Printer<'_, '_>) {
+        let tab_bar_height = self.tab_bar_height();
+
+        if tab_bar_height > 0 {
+            self.draw_tab_bar(printer);
+        }
+
+        if let Some(tab) = self.tabs.get(self.current) {
+            tab.view.draw(&printer.offset((0, tab_bar_height)));
+        }
+    }
+
+    fn layout(&mut self, final_size: XY<usize>) {
+        let tab_bar_height = self.tab_bar_height();
+
+        if let Some(tab) = self.tabs.get_mut(self.current) {
+            tab.view.layout(final_size.saturating_sub((0, tab_bar_height)));
+        }
+    }
+
+    fn required_size(&mut self, hinted_size: XY<usize>) -> XY<usize> {
+        let tab_bar_height = self.tab_bar_height();
+
+        match self.tabs.get_mut(self.current) {
+            Some(tab) => tab.view.required_size(hinted_size.saturating_sub((0, tab_bar_height))) + (0, tab_bar_height),
+            None => XY::new(0, 0),
+        }
+    }
+
+    fn on_event(&mut self, event: Event) -> EventResult {
+        let num_tabs = self.tabs.len();
+
+        let tab = match self.tabs.get_mut(self.current) {
+            Some(tab) => tab,
+            None => return EventResult::Ignored,
+        };
+
+        match tab.view.command_for(&event) {
+            Some(Command::NextTab) => self.cycle(true),
+            Some(Command::PreviousTab) => self.cycle(false),
+            Some(Command::OpenTab) => {
+                let cb = Callback::from_fn(|siv| {
+                    siv.add_layer(
+                        crate::views::prompt::make("Open Directory in New Tab", "", |siv, text| {
+                            let result = siv.call_on_name(TABS_VIEW_NAME, |view: &mut TabsView| {
+                                view.open(PathBuf::from(text))
+                            });
+
+                            if let Some(Err(err)) = result {
+                                siv.add_layer(cursive::views::Dialog::info(err.to_string()));
+                            }
+                        })
+                    );
+                });
+
+                return EventResult::Consumed(Some(cb))
+            },
+            Some(Command::CompareTabs) => {
+                if num_tabs < 2 {
+                    tab.view.shared_model().lock().unwrap().set_status("Open another directory in a tab to compare with");
+                    return EventResult::Consumed(None)
+                }
+
+                let side = |tab: &Tab| Side { name: tab.name.clone(), model: tab.view.shared_model() };
+
+                let left = side(&self.tabs[self.current]);
+                let right = side(&self.tabs[(self.current + 1) % num_tabs]);
+
+                let cb = Callback::from_fn_once(move |siv| {
+                    siv.add_layer(crate::views::compare::make(left, right));
+                });
+
+                return EventResult::Consumed(Some(cb))
+            },
+            Some(Command::BrowseDirectory) => {
+                let start = tab.working_dir.clone();
+                return EventResult::Consumed(Some(Self::replace_current(tab, move |siv| browse(siv, start.clone()))))
+            },
+            Some(Command::QuickOpen) => {
+                return EventResult::Consumed(Some(Self::replace_current(tab, quick_open)))
+            },
+            Some(Command::ReloadConfig) => return EventResult::Consumed(Some(Callback::from_fn(reload_config))),
+            Some(Command::ToggleBookmark) => {
+                let status = match &tab.working_dir {
+                    None => String::from("Only directories can be bookmarked"),
+                    Some(working_dir) => {
+                        let dir = working_dir.canonicalize().unwrap_or_else(|_| working_dir.clone());
+
+                        match Places::update(|places| places.toggle_bookmark(&dir)) {
+                            Ok(true) => format!("Bookmarked {}", dir.display()),
+                            Ok(false) => format!("Removed bookmark for {}", dir.display()),
+                            Err(err) => format!("Could not save bookmarks: {}", err),
+                        }
+                    },
+                };
+
+                tab.view.shared_model().lock().unwrap().set_status(status);
+            },
+            _ => return tab.view.on_event(event),
+        }
+
+        EventResult::Consumed(None)
+    }
+
+    fn take_focus(&mut self, source: Direction) -> bool {
+        match self.tabs.get_mut(self.current) {
+            Some(tab) => tab.view.take_focus(source),
+            None => false,
+        }
+    }
+}
@@ -1,299 +1,279 @@
 
+use std::path::Path;
 use std::sync::Arc;
 use std::sync::Mutex;
+use std::time::Duration;
+use std::time::Instant;
 
+use cursive::CbSink;
+use cursive::Cursive;
 use cursive::Printer;
 use cursive::XY;
-use cursive::Rect;
 use cursive::direction::Direction;
 use cursive::event::Callback;
 use cursive::event::Event;
 use cursive::event::EventResult;
-use cursive::event::Key;
 use cursive::theme::ColorStyle;
 use cursive::view::View;
-use cursive::view::scroll::Scroller;
-use cursive::views::Canvas;
 use cursive::views::Dialog;
-use cursive::views::ScrollView;
+use cursive::views::SelectView;
 use unicode_width::UnicodeWidthStr;
 
-use crate::consts::*;
-use crate::data::ColumnKey;
-// use crate::data::Data;
-use crate::model::Model;
-use crate::util::Util;
-use crate::util::MultiFigments;
-
-enum Atom<'a> {
-    Single(&'a str, bool),
-    Multi(&'a [String], bool),
-    Missing(bool),
-    Header,
-}
+use diargos_core::clipboard::Clipboard;
+use diargos_core::command::Command;
+use diargos_core::command_line::LineCommand;
+use diargos_core::consts::*;
+use diargos_core::data::ColumnKey;
+use diargos_core::data::InfoKind;
+use crate::external::OnEdited;
+use crate::external::PendingEdit;
+use crate::views::transfer::Transfer;
+use diargos_core::import::TagImport;
+// use diargos_core::data::Data;
+use diargos_core::model::Model;
+use diargos_core::model::Numbering;
+use diargos_core::model::Scope;
+use diargos_core::playlist::PathStyle;
+use diargos_core::preset::Preset;
+use diargos_core::register::Registers;
+use diargos_core::snapshot::Snapshot;
+use diargos_core::transform::Case;
+use diargos_core::transform::Cleanup;
+use diargos_core::value::Date;
+use diargos_core::value::ValueKind;
 
-pub struct TagRecordView {
-    shared_model: Arc<Mutex<Model>>,
-    scroll_view: ScrollView<Canvas<Arc<Mutex<Model>>>>,
-}
+use diargos_core::table::TableView;
+use diargos_core::table::HEADER_HEIGHT;
 
-impl TagRecordView {
-    pub fn new(model: Model) -> Self {
-        // use std::fs::OpenOptions;
-        // use std::io::prelude::*;
+/// The line with the full value of the cell under the cursor, and the status line.
+const FOOTER_HEIGHT: usize = 2;
 
-        let shared_model = Arc::new(Mutex::new(model));
+/// The most lines the record detail pane takes up, including its title.
+const DETAIL_MAX_HEIGHT: usize = 12;
 
-        // first_visible_record = printer.content_offset.y
-        // num_visible_records = printer.output_size.y
-
-        let canvas =
-            Canvas::new(shared_model.clone())
-            .with_draw(|shared_model, printer| {
-                // let mut file =
-                //     OpenOptions::new()
-                //     .create(true)
-                //     .write(true)
-                //     .append(true)
-                //     .open("logs.txt")
-                //     .unwrap()
-                // ;
-
-                // let log = format!("{:?}, {:?}\n", printer.output_size, printer.content_offset);
-                // file.write_all(log.as_bytes()).unwrap();
-
-                let model = shared_model.lock().unwrap();
-                let data = &model.data;
-
-                for (offset_y, record) in data.records.iter().enumerate() {
-                    let atoms_and_widths =
-                        data.columns.iter()
-                        .enumerate()
-                        .map(|(x, col)| {
-                            let y = offset_y;
-                            let highlighted = model.is_cursor_at_cell(x, y);
-
-                            match &col.key {
-                                ColumnKey::Meta(meta_key) => {
-                                    match record.get_meta(meta_key) {
-                                        None => Atom::Missing(highlighted),
-                                        Some(vals) => Atom::Multi(vals, highlighted),
-                                    }
-                                },
-                                ColumnKey::Info(info_key) => {
-                                    match record.get_info(info_key) {
-                                        None => Atom::Missing(highlighted),
-                                        Some(val) => Atom::Single(val, highlighted),
-                                    }
-                                },
-                            }
-                        })
-                        .zip(model.iter_cached_widths())
-                    ;
+/// How long after the last typed character a type-ahead search starts over.
+const TYPE_AHEAD_TIMEOUT: Duration = Duration::from_millis(1000);
 
-                    Self::draw_delimited_row(printer, offset_y, COLUMN_SEP, atoms_and_widths);
-                }
-            })
-            .with_required_size(|shared_model, _constraints| {
-                let mut model = shared_model.lock().unwrap();
-                model.recache();
+pub struct TagRecordView {
+    shared_model: Arc<Mutex<Model>>,
+    table: TableView<Model>,
+    clipboard: Clipboard,
+    registers: Registers,
 
-                model.required_size(COLUMN_SEP.width())
-            })
-            .with_important_area(|shared_model, _final_size| {
-                let model = shared_model.lock().unwrap();
-
-                // Figure out the logical X and Y coordinates of the highlighted cell, if any.
-                let (lx, ly) = match model.cursor.to_xy() {
-                    // Return a view showing the entire visible canvas.
-                    (lx, None) => (lx, 0),
-                    (lx, Some(ly)) => (lx, ly),
-                };
+    /// Whether the next typed character names a register, as after `"` in Vim.
+    choosing_register: bool,
 
-                let tx = model.column_offset(lx, COLUMN_SEP.width()).unwrap_or(0);
-                let ty = ly;
+    /// The characters typed so far to jump to a row, and when the last one was typed.
+    type_ahead: String,
+    type_ahead_at: Option<Instant>,
 
-                let dx = model.cached_content_widths.get(lx).copied().unwrap_or(0);
-                let dy = 1;
+    /// Whether the next typed character starts a type-ahead search, even if it is bound.
+    starting_type_ahead: bool,
 
-                Rect::from_size((tx, ty), (dx, dy))
-            })
-        ;
+    /// The number of lines given to the record detail pane at the last layout.
+    detail_height: usize,
 
-        let mut scroll_view = ScrollView::new(canvas).scroll_x(true).scroll_y(true);
+    /// Sends back the outputs of command columns as they finish.
+    cb_sink: CbSink,
+}
 
-        // Set the scrollbar padding to be 0 on both axes.
-        let scroller = scroll_view.get_scroller_mut();
-        scroller.set_scrollbar_padding((0, 0));
+impl TagRecordView {
+    pub fn new(model: Model, cb_sink: CbSink) -> Self {
+        let shared_model = Arc::new(Mutex::new(model));
+        let table = TableView::new(shared_model.clone());
 
         Self {
             shared_model,
-            scroll_view,
+            table,
+            clipboard: Clipboard::new(),
+            registers: Registers::new(),
+            choosing_register: false,
+            type_ahead: String::new(),
+            type_ahead_at: None,
+            starting_type_ahead: false,
+            detail_height: 0,
+            cb_sink,
         }
     }
 
+    /// The command bound to an event in the keymap of this view's model.
+    pub fn command_for(&self, event: &Event) -> Option<Command> {
+        self.shared_model.lock().unwrap().keymap.command_for(event)
+    }
+
+    pub fn shared_model(&self) -> Arc<Mutex<Model>> {
+        self.shared_model.clone()
+    }
+
+    /// Whether any record has edits that have not been saved.
+    pub fn has_edits(&self) -> bool {
+        self.shared_model.lock().unwrap().data.records.iter().any(|r| r.is_edited())
+    }
+
     // pub fn from_data(data: Data) -> Self {
     //     Self::new(Model::with_data(data))
     // }
 
-    fn draw_delimited_row<'a>(
-        printer: &Printer,
-        offset_y: usize,
-        separator: &str,
-        atoms_and_widths: impl Iterator<Item = (Atom<'a>, usize)>,
-    )
+    fn copy_status(what: &str, copied_to_system: bool) -> String {
+        if copied_to_system { format!("Copied {} to clipboard", what) }
+        else { format!("Copied {} to internal register (no system clipboard)", what) }
+    }
+
+    /// Runs a bulk edit, first asking to confirm if it would touch more
+    /// files than configured.
+    fn run_bulk_edit<F>(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, num_files: usize, edit: F)
+    where
+        F: Fn(&mut Cursive, &mut Model) + 'static,
     {
-        let mut offset_x = 0;
-        let mut is_first_col = true;
-
-        for (atom, content_width) in atoms_and_widths {
-            if is_first_col { is_first_col = false; }
-            else {
-                printer.print((offset_x, offset_y), separator);
-                offset_x += separator.width();
-            }
+        let needs_confirm = shared_model.lock().unwrap().confirm.needs_bulk_edit_confirm(num_files);
 
-            match atom {
-                Atom::Missing(highlighted) => {
-                    // Print out a highlighted sentinel, to indicate a missing value.
-                    let color =
-                        if highlighted { ColorStyle::highlight() }
-                        else { ColorStyle::secondary() }
-                    ;
+        if !needs_confirm {
+            edit(siv, &mut shared_model.lock().unwrap());
+            return;
+        }
 
-                    printer.with_color(
-                        color,
-                        |pr| {
-                            pr.print_hline(
-                                (offset_x, offset_y),
-                                content_width,
-                                MISSING_FILL,
-                            );
-                        },
-                    );
+        let message = format!("This will edit up to {} files. Continue?", num_files);
 
-                },
-                Atom::Header => {
-                    printer.print_hline(
-                        (offset_x, offset_y),
-                        content_width,
-                        COLUMN_HEADER_BAR,
-                    );
-                },
-                Atom::Single(value, highlighted) => {
-                    let color =
-                        if highlighted { ColorStyle::highlight() }
-                        else { ColorStyle::primary() }
-                    ;
+        siv.add_layer(
+            crate::views::confirm::make("Bulk Edit", message, "Apply", move |siv| {
+                edit(siv, &mut shared_model.lock().unwrap());
+            })
+        );
+    }
 
-                    let trim_output = Util::trim_display_str_elided(
-                        value,
-                        content_width,
-                        ELLIPSIS_STR.width(),
-                    );
+    /// Stages values for the current column on the target records, as a
+    /// single operation that can be undone.
+    fn set_target_values(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, action: String, values: Option<Vec<String>>) {
+        let num_files = shared_model.lock().unwrap().target_record_indices().len();
 
-                    let display_str = trim_output.display_str;
-                    let emit_ellipsis = trim_output.trim_status.emit_ellipsis();
+        Self::run_bulk_edit(siv, shared_model, num_files, move |_, model| {
+            match model.set_target_values(&action, values.clone()) {
+                Some((edited, total)) => model.set_status(format!("{}: {} of {} record(s) changed", action, edited, total)),
+                None => model.set_status("Only metadata columns can be edited"),
+            };
+        });
+    }
 
-                    printer.with_color(
-                        color,
-                        move |pr| {
-                            pr.print((offset_x, offset_y), &display_str);
+    /// Asks to rename a field, saying how many records it is in and how many
+    /// of those already have the new one.
+    fn confirm_rename_key(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, from: String, to: String) {
+        let (num_having, num_merged) = shared_model.lock().unwrap().rename_key_counts(&from, &to);
 
-                            if emit_ellipsis {
-                                let ellipsis_offset = trim_output.ellipsis_offset();
+        if num_having == 0 {
+            return siv.add_layer(Dialog::info(format!("No records have {}", from)));
+        }
 
-                                pr.print((offset_x + ellipsis_offset, offset_y), ELLIPSIS_STR);
-                            }
-                        },
-                    );
-                },
-                Atom::Multi(values, highlighted) => {
-                    let color =
-                        if highlighted { ColorStyle::highlight() }
-                        else { ColorStyle::primary() }
-                    ;
+        let mut message = format!("Rename {} to {} in {} record(s)?", from, to.to_uppercase(), num_having);
 
-                    // let trim_output = Util::trim_display_str_elided(
-                    //     original_string,
-                    //     content_width,
-                    //     ELLIPSIS_STR.width(),
-                    // );
+        if num_merged > 0 {
+            message.push_str(&format!("\n\n{} of them already have {}, and keep its values ahead of the moved ones.", num_merged, to.to_uppercase()));
+        }
 
-                    let multi_figments = MultiFigments::new(values, content_width, FIELD_SEP_STR, ELLIPSIS_STR);
+        siv.add_layer(
+            crate::views::confirm::make("Rename Field", message, "Rename", move |_| {
+                let mut model = shared_model.lock().unwrap();
 
-                    // let display_str = trim_output.display_str;
-                    // let emit_ellipsis = trim_output.trim_status.emit_ellipsis();
+                match model.rename_key(&from, &to) {
+                    Some(edited) => model.set_status(format!("Renamed {} to {} in {} record(s)", from, to.to_uppercase(), edited)),
+                    None => model.set_status(format!("{} is not a valid field name", to)),
+                };
+            })
+        );
+    }
 
-                    for (offset, figment, figment_kind) in multi_figments {
-                        let used_color =
-                            if figment_kind.is_sep() { ColorStyle::title_primary() }
-                            else { color }
-                        ;
+    /// Draws every tag of the record under the cursor, one per line, below a
+    /// title line naming the file.
+    fn draw_detail(model: &Model, printer: &Printer, height: usize) {
+        let title = match model.current_record() {
+            Some(record) => format!("Tags of {}", record.get_info(&InfoKind::FileName).unwrap_or_default()),
+            None => String::from("No record under the cursor"),
+        };
 
-                        printer.with_color(
-                            used_color,
-                            move |pr| {
-                                pr.print((offset_x + offset, offset_y), &figment);
-                            },
-                        );
-                    }
+        printer.with_color(ColorStyle::title_primary(), |pr| { pr.print((0, 0), &title); });
 
-                    // printer.with_color(
-                    //     color,
-                    //     move |pr| {
-                    //         for (offset, figment, figment_kind) in multi_figments {
-                    //             pr.print((offset_x + offset, offset_y), &figment);
-                    //         }
-                    //         // pr.print((offset_x, offset_y), &display_str);
+        let lines = model.detail_lines();
+        let key_width = lines.iter().map(|(k, _, _)| k.width()).max().unwrap_or(0);
 
-                    //         // if emit_ellipsis {
-                    //         //     let ellipsis_offset = trim_output.ellipsis_offset();
+        // Keep the last line to say how many tags did not fit.
+        let room = height.saturating_sub(1);
+        let shown = if lines.len() > room { room.saturating_sub(1) } else { lines.len() };
 
-                    //         //     pr.print((offset_x + ellipsis_offset, offset_y), ELLIPSIS_STR);
-                    //         // }
-                    //     },
-                    // );
-                },
-            };
+        for (i, (meta_key, values, edited)) in lines.iter().take(shown).enumerate() {
+            let offset_y = i + 1;
+
+            printer.with_color(ColorStyle::secondary(), |pr| { pr.print((0, offset_y), meta_key); });
 
-            offset_x += content_width;
+            let style = if *edited { ColorStyle::highlight_inactive() } else { ColorStyle::primary() };
+            printer.with_color(style, |pr| { pr.print((key_width + 2, offset_y), values); });
+        }
+
+        if shown < lines.len() {
+            let more = format!("({} more)", lines.len() - shown);
+            printer.with_color(ColorStyle::secondary(), |pr| { pr.print((0, shown + 1), &more); });
         }
     }
 }
 
 impl View for TagRecordView {
     fn draw(&self, printer: &Printer<'_, '_>) {
-        let content_viewport = self.scroll_view.content_viewport();
-
         // This sub block is needed to avoid a deadlock.
-        {
+        let table_height = {
             let model = self.shared_model.lock().unwrap();
-            let data = &model.data;
 
-            // Draw the header and the header bar at the top vertical positions,
-            // but all the way to the left, so they scroll with the content.
-            let left_offset_printer = printer.content_offset((content_viewport.left(), 0));
+            // Draw the footer at the very bottom, independent of scrolling.
+            let footer_y = printer.size.y.saturating_sub(FOOTER_HEIGHT);
 
-            let atoms_and_widths =
-                data.columns.iter()
-                .enumerate()
-                .map(|(x, col)| {
-                    let highlighted = model.is_cursor_at_column(x);
-                    Atom::Single(&col.title, highlighted)
-                })
-                .zip(model.iter_cached_widths())
-            ;
+            if let Some(value_line) = model.current_value_line() {
+                printer.print((0, footer_y), &value_line);
+            }
 
-            Self::draw_delimited_row(&left_offset_printer, 0, COLUMN_SEP, atoms_and_widths);
+            // Show where the view is in the table at the right end of the value line.
+            if let Some(position) = model.scroll_position() {
+                let offset_x = printer.size.x.saturating_sub(position.width());
 
-            let atoms_and_widths = model.iter_cached_widths().map(|w| (Atom::Header, w));
+                printer.with_color(
+                    ColorStyle::secondary(),
+                    |pr| { pr.print((offset_x, footer_y), &position); },
+                );
+            }
 
-            Self::draw_delimited_row(&left_offset_printer, 1, COLUMN_HEADER_SEP, atoms_and_widths);
-        }
+            let offset_y = footer_y + 1;
+
+            if let Some(status) = &model.status {
+                printer.with_color(
+                    ColorStyle::secondary(),
+                    |pr| { pr.print((0, offset_y), status); },
+                );
+            }
+
+            if self.detail_height > 0 {
+                let offset_y = footer_y.saturating_sub(self.detail_height);
+                Self::draw_detail(&model, &printer.offset((0, offset_y)), self.detail_height);
+            }
+
+            // Show read-only and dry-run modes, a non-default batch scope, and
+            // any skipped files at the right end of the status line.
+            let mut indicators = Vec::new();
+            if model.read_only { indicators.push(String::from("[read-only]")); }
+            if model.dry_run { indicators.push(String::from("[dry run]")); }
+            if model.scope != Scope::default() { indicators.push(format!("[{}]", model.scope)); }
+            if !model.load_failures.is_empty() { indicators.push(format!("[{} file(s) skipped]", model.load_failures.len())); }
+            if model.is_writing() { indicators.push(String::from("[saving]")); }
 
-        // Draw the `ScrollView` starting two columns down.
-        self.scroll_view.draw(&printer.offset((0, 2)));
+            if !indicators.is_empty() {
+                let indicator = indicators.join(" ");
+                let offset_x = printer.size.x.saturating_sub(indicator.width());
+
+                printer.with_color(
+                    ColorStyle::title_secondary(),
+                    |pr| { pr.print((offset_x, offset_y), &indicator); },
+                );
+            }
+
+            footer_y.saturating_sub(self.detail_height)
+        };
+
+        self.table.draw(&printer.cropped((printer.size.x, table_height)));
     }
 
     fn layout(&mut self, final_size: XY<usize>) {
@@ -302,78 +282,1095 @@ impl View for TagRecordView {
             model.recache();
         }
 
-        let final_inner_size = final_size.saturating_sub((0, 2));
-        self.scroll_view.layout(final_inner_size);
+        // New records, changed files, and new columns all call for commands to be run.
+        crate::views::command_columns::run_pending(&self.cb_sink, &self.shared_model);
+
+        self.detail_height = {
+            let model = self.shared_model.lock().unwrap();
+
+            if model.show_detail { (model.detail_lines().len() + 1).min(DETAIL_MAX_HEIGHT) }
+            else { 0 }
+        };
+
+        let table_size = final_size.saturating_sub((0, FOOTER_HEIGHT + self.detail_height));
+        self.shared_model.lock().unwrap().viewport_height = table_size.y.saturating_sub(HEADER_HEIGHT);
+        self.table.layout(table_size);
+
+        // The cursor may have been moved from a dialog callback.
+        let scroll_pending = std::mem::take(&mut self.shared_model.lock().unwrap().scroll_pending);
+        if scroll_pending {
+            self.table.scroll_to_cursor();
+        }
     }
 
     fn required_size(&mut self, hinted_size: XY<usize>) -> XY<usize> {
-        let header_required_extra = XY::new(0, 2);
-        let inner_hinted_size = hinted_size.saturating_sub(header_required_extra);
-        self.scroll_view.required_size(inner_hinted_size) + header_required_extra
+        let footer_size = XY::new(0, FOOTER_HEIGHT + self.detail_height);
+        self.table.required_size(hinted_size.saturating_sub(footer_size)) + footer_size
     }
 
     fn on_event(&mut self, event: Event) -> EventResult {
+        // The mouse is only used to resize columns from the header.
+        if let Event::Mouse { .. } = event {
+            return self.table.on_event(event);
+        }
+
         {
             let mut model = self.shared_model.lock().unwrap();
             // let old_cursor = model.cursor;
 
+            // The key after choosing a register names the register, and any
+            // key other than a character cancels the choice.
+            if self.choosing_register {
+                self.choosing_register = false;
+
+                if let Event::Char(name) = event {
+                    if self.registers.choose(name) { model.set_status(format!("Register \"{} chosen", name)); }
+                    else { model.set_status(format!("No register named {}", name)); }
+
+                    return EventResult::Consumed(None)
+                }
+            }
+
+            // Typing on the sorted column jumps to the first matching row.
+            // Bound keys only start a search right after the type-ahead
+            // command, but can always continue one.
+            let starting = std::mem::take(&mut self.starting_type_ahead);
+
             match event {
-                Event::AltChar('x') => {
-                    let cb = Callback::from_fn(|siv| {
+                Event::Char(c) => {
+                    let expired = self.type_ahead_at.is_none_or(|at| at.elapsed() > TYPE_AHEAD_TIMEOUT);
+                    if expired || starting { self.type_ahead.clear(); }
+
+                    if starting || !self.type_ahead.is_empty() || model.keymap.command_for(&event).is_none() {
+                        let mut prefix = self.type_ahead.clone();
+                        prefix.push(c);
+
+                        if let Some(found) = model.type_ahead(&prefix) {
+                            if !found { model.set_status(format!("No value starts with: {}", prefix)); }
+
+                            self.type_ahead = prefix;
+                            self.type_ahead_at = Some(Instant::now());
+
+                            drop(model);
+                            self.table.scroll_to_cursor();
+
+                            return EventResult::Consumed(None)
+                        }
+                    }
+                },
+                _ => self.type_ahead.clear(),
+            }
+
+            let command = match model.keymap.command_for(&event) {
+                Some(command) => command,
+                None => return EventResult::Ignored,
+            };
+
+            if model.read_only && command.is_editing() {
+                model.set_status(format!("Read-only: {} is turned off", command.name()));
+                return EventResult::Consumed(None);
+            }
+
+            if command.stages_edits() {
+                if let Some(notice) = model.read_only_targets_notice() {
+                    model.set_status(notice);
+                    return EventResult::Consumed(None);
+                }
+            }
+
+            match command {
+                Command::EditField => {
+                    let meta_key = match model.current_column().map(|col| &col.key) {
+                        Some(ColumnKey::Meta(meta_key)) => meta_key.clone(),
+                        _ => {
+                            model.set_status("Only metadata columns can be edited");
+                            return EventResult::Consumed(None)
+                        },
+                    };
+
+                    let values = model.current_cell_values().flatten().unwrap_or_else(|| vec![String::new()]);
+
+                    let title = format!("Edit {} for {} Record(s)", meta_key, model.target_record_indices().len());
+                    let shared_model = self.shared_model.clone();
+
+                    let kind = model.current_column().map(|col| col.value_kind());
+
+                    if kind == Some(ValueKind::Date) {
+                        let initial = values.first().and_then(|v| Date::parse(v.trim()));
+
+                        let cb = Callback::from_fn(move |siv| {
+                            let shared_model = shared_model.clone();
+
+                            siv.add_layer(
+                                crate::views::date_picker::make(&title, initial, move |siv, date| {
+                                    let values = Some(vec![date.to_string()]);
+                                    Self::set_target_values(siv, shared_model.clone(), String::from("Edit field"), values);
+                                })
+                            );
+                        });
+
+                        return EventResult::Consumed(Some(cb))
+                    }
+
+                    // Values with line breaks get a text area, with any extra values after `---` lines.
+                    if kind == Some(ValueKind::LongText) || crate::external::spans_lines(&values) {
+                        let text = crate::external::to_text(&values, true);
+
+                        let cb = Callback::from_fn(move |siv| {
+                            let shared_model = shared_model.clone();
+
+                            siv.add_layer(
+                                crate::views::text_edit::make(&title, &text, move |siv, text| {
+                                    let values = Some(crate::external::from_text(text, true)).filter(|vs| !vs.is_empty());
+                                    Self::set_target_values(siv, shared_model.clone(), String::from("Edit field"), values);
+                                })
+                            );
+                        });
+
+                        return EventResult::Consumed(Some(cb))
+                    }
+
+                    let completions = model.column_completions();
+                    let kind = kind.unwrap_or(ValueKind::Text);
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
                         siv.add_layer(
-                            crate::views::field_edit::make(
-                                vec![
-                                    String::from("WOW"),
-                                    String::from("COOL"),
-                                    String::from("NEAT"),
-                                    String::from("RAD"),
-                                ]
-                            )
+                            crate::views::field_edit::make(&title, values.clone(), completions.clone(), kind, move |siv, values| {
+                                let values = values.into_iter().filter(|v| !v.is_empty()).collect::<Vec<_>>();
+                                let values = Some(values).filter(|vs| !vs.is_empty());
+
+                                Self::set_target_values(siv, shared_model.clone(), String::from("Edit field"), values);
+                            })
                         );
                     });
 
                     return EventResult::Consumed(Some(cb))
                 },
-                Event::AltChar('d') => {
+                Command::EditExternally => {
+                    if !matches!(model.current_column().map(|col| &col.key), Some(ColumnKey::Meta(..))) {
+                        model.set_status("Only metadata columns can be edited");
+                        return EventResult::Consumed(None)
+                    }
+
+                    let values = model.current_cell_values().flatten().unwrap_or_default();
+                    let kind = model.current_column().map_or(ValueKind::Text, |col| col.value_kind());
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn_once(move |siv| {
+                        let on_edited: OnEdited = Box::new(move |siv, result| {
+                            match result {
+                                Ok(values) if kind.first_invalid(&values).is_some() => {
+                                    let invalid = kind.first_invalid(&values).unwrap_or_default();
+                                    siv.add_layer(Dialog::info(format!("Not edited, {} is not a valid {}", invalid, kind.label())));
+                                },
+                                Ok(values) => {
+                                    let values = Some(values).filter(|vs| !vs.is_empty());
+                                    Self::set_target_values(siv, shared_model, String::from("Edit externally"), values);
+                                },
+                                Err(err) => siv.add_layer(Dialog::info(format!("Unable to edit externally: {}", err))),
+                            }
+                        });
+
+                        PendingEdit::request(siv, values, on_edited);
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::SortDescending => {
                     if let Some(col_idx) = model.cursor.column_index() {
                         model.sort_by_column_index(col_idx, true)
                     }
                 },
-                Event::AltChar('a') => {
+                Command::SortAscending => {
                     if let Some(col_idx) = model.cursor.column_index() {
                         model.sort_by_column_index(col_idx, false)
                     }
                 },
-                Event::Key(Key::Up) => {
+                Command::ToggleGrouping => {
+                    model.toggle_grouping(GROUP_BY_KEY);
+                },
+                Command::GroupByColumn => {
+                    let was_grouped = model.group_by.is_some();
+
+                    let status = match model.group_by_column() {
+                        Some(meta_key) => format!("Grouped by {}", meta_key),
+                        None if was_grouped && model.group_by.is_none() => String::from("Stopped grouping"),
+                        None => String::from("Only metadata columns can be grouped by"),
+                    };
+
+                    model.set_status(status);
+                },
+                Command::ToggleCollapse => {
+                    if !model.toggle_collapse() {
+                        model.set_status("Group records to collapse them");
+                    }
+                },
+                Command::ToggleCollapseAll => {
+                    if !model.toggle_collapse_all() {
+                        model.set_status("Group records to collapse them");
+                    }
+                },
+                Command::ExportPreset => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+                        siv.add_layer(
+                            crate::views::prompt::make("Export Preset", "preset.json", move |siv, path| {
+                                let preset = Preset::from_model(&shared_model.lock().unwrap());
+
+                                let message = match preset.write_to_path(Path::new(path)) {
+                                    Ok(()) => format!("Preset exported to {}", path),
+                                    Err(err) => format!("Unable to export preset: {}", err),
+                                };
+
+                                siv.add_layer(Dialog::info(message));
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ImportCsv => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+                        siv.add_layer(
+                            crate::views::prompt::make("Import CSV", "tags.csv", move |siv, path| {
+                                let import = match TagImport::read_from_path(Path::new(path)) {
+                                    Ok(import) => import,
+                                    Err(err) => {
+                                        siv.add_layer(Dialog::info(format!("Unable to import CSV: {}", err)));
+                                        return;
+                                    },
+                                };
+
+                                let num_files = import.matched_rows(&shared_model.lock().unwrap().data.records);
+                                let description = format!("Import {}", path);
+
+                                Self::run_bulk_edit(siv, shared_model.clone(), num_files, move |siv, model| {
+                                    let summary = model.edit_records(description.clone(), |records| import.apply(records));
+                                    siv.add_layer(Dialog::info(summary.to_string()));
+                                });
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::Save | Command::SaveCurrent if model.dry_run => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn_once(move |siv| crate::views::save_diff::show(siv, shared_model));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::Save => {
+                    let edited_paths =
+                        model.data.records.iter()
+                        .filter(|r| r.is_edited())
+                        .map(|r| r.file_path.display().to_string())
+                        .collect::<Vec<_>>()
+                    ;
+
+                    if model.confirm.needs_save_review(edited_paths.len()) {
+                        let shared_model = self.shared_model.clone();
+                        let message = format!("Save {} file(s)?\n\n{}", edited_paths.len(), edited_paths.join("\n"));
+
+                        let cb = Callback::from_fn(move |siv| {
+                            let shared_model = shared_model.clone();
+
+                            siv.add_layer(
+                                crate::views::confirm::make("Review Save", message.clone(), "Save", move |siv| {
+                                    crate::views::save_queue::save_all(siv, shared_model.clone());
+                                })
+                            );
+                        });
+
+                        return EventResult::Consumed(Some(cb))
+                    }
+
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn_once(move |siv| crate::views::save_queue::save_all(siv, shared_model));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::SaveCurrent => {
+                    let file_path = match model.current_record() {
+                        Some(record) if record.is_edited() => record.file_path.clone(),
+                        Some(_) => {
+                            model.set_status("The current record has no edits to save");
+                            return EventResult::Consumed(None)
+                        },
+                        None => return EventResult::Consumed(None),
+                    };
+
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn_once(move |siv| crate::views::save_queue::save_file(siv, shared_model, file_path));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::Reload => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn_once(move |siv| {
+                        crate::views::refresh_conflict::reload(siv, shared_model, false);
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::RenameFiles => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn_once(move |siv| crate::views::rename::show(siv, shared_model));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::MoveFiles | Command::CopyFiles => {
+                    let transfer = if command == Command::MoveFiles { Transfer::Move } else { Transfer::Copy };
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn_once(move |siv| crate::views::transfer::show(siv, shared_model, transfer));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::DeleteFiles => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn_once(move |siv| crate::views::delete::show(siv, shared_model));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::TagsFromNames => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn_once(move |siv| crate::views::rename::show_tags_from_names(siv, shared_model));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ExportSnapshot => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+                        siv.add_layer(
+                            crate::views::prompt::make("Export Snapshot", "snapshot.json", move |siv, path| {
+                                let snapshot = Snapshot::from_records(&shared_model.lock().unwrap().data.records);
+
+                                let message = match snapshot.write_to_path(Path::new(path)) {
+                                    Ok(()) => format!("Snapshot of {} file(s) written to {}", snapshot.entries.len(), path),
+                                    Err(err) => format!("Unable to write snapshot: {}", err),
+                                };
+
+                                siv.add_layer(Dialog::info(message));
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ExportPlaylist => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+                        siv.add_layer(
+                            crate::views::prompt::make("Export Playlist", "playlist.m3u8", move |siv, path| {
+                                let export = |style: PathStyle| {
+                                    let shared_model = shared_model.clone();
+                                    let path = path.to_string();
+
+                                    move |siv: &mut Cursive| {
+                                        let model = shared_model.lock().unwrap();
+                                        let records = model.data.records.iter().collect::<Vec<_>>();
+
+                                        let message = match diargos_core::playlist::write_to_path(&records, Path::new(&path), style) {
+                                            Ok(num_entries) => format!("Playlist of {} file(s) written to {}", num_entries, path),
+                                            Err(err) => format!("Unable to write playlist: {}", err),
+                                        };
+
+                                        siv.pop_layer();
+                                        siv.add_layer(Dialog::info(message));
+                                    }
+                                };
+
+                                siv.add_layer(
+                                    Dialog::text("Refer to files relative to the playlist, or by their absolute paths?")
+                                    .title("Export Playlist")
+                                    .button("Relative", export(PathStyle::Relative))
+                                    .button("Absolute", export(PathStyle::Absolute))
+                                    .dismiss_button("Cancel")
+                                );
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::CompareSnapshot => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+                        siv.add_layer(
+                            crate::views::prompt::make("Compare Snapshot", "snapshot.json", move |siv, path| {
+                                let snapshot = match Snapshot::read_from_path(Path::new(path)) {
+                                    Ok(snapshot) => snapshot,
+                                    Err(err) => {
+                                        siv.add_layer(Dialog::info(format!("Unable to read snapshot: {}", err)));
+                                        return;
+                                    },
+                                };
+
+                                let comparison = snapshot.compare(&shared_model.lock().unwrap().data.records);
+
+                                if comparison.is_empty() {
+                                    siv.add_layer(Dialog::info("No differences from snapshot"));
+                                    return;
+                                }
+
+                                let shared_model = shared_model.clone();
+                                siv.add_layer(
+                                    crate::views::snapshot_diff::make("Snapshot Differences", "Restore", comparison, move |siv, to_restore| {
+                                        let mut model = shared_model.lock().unwrap();
+                                        let restored = model.edit_records("Restore from snapshot", |records| Snapshot::restore(to_restore, records));
+
+                                        siv.add_layer(Dialog::info(format!("Restored {} field(s)", restored)));
+                                    })
+                                );
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::CopyCell => {
+                    let text =
+                        model.current_column()
+                        .zip(model.current_record())
+                        .and_then(|(column, record)| diargos_core::clipboard::cell_text(record, column))
+                    ;
+
+                    match text {
+                        Some(text) => {
+                            let copied_to_system = self.clipboard.set_text(text);
+                            model.set_status(Self::copy_status("cell", copied_to_system));
+                        },
+                        None => model.set_status("Nothing to copy"),
+                    };
+                },
+                Command::CopyRow | Command::CopyRowJson => {
+                    if let Some(record) = model.current_record() {
+                        let (text, format) =
+                            if command == Command::CopyRowJson { (diargos_core::clipboard::row_json(record, &model.data.columns), "row as JSON") }
+                            else { (diargos_core::clipboard::row_tsv(record, &model.data.columns), "row") }
+                        ;
+
+                        let copied_to_system = self.clipboard.set_text(text);
+                        model.set_status(Self::copy_status(format, copied_to_system));
+                    }
+                },
+                Command::Paste => {
+                    let values = self.clipboard.get_text().as_deref().and_then(diargos_core::clipboard::paste_values);
+
+                    match values {
+                        Some(values) => {
+                            let shared_model = self.shared_model.clone();
+                            let cb = Callback::from_fn_once(move |siv| {
+                                Self::set_target_values(siv, shared_model, String::from("Paste"), Some(values));
+                            });
+
+                            return EventResult::Consumed(Some(cb))
+                        },
+                        None => model.set_status("Nothing to paste"),
+                    };
+                },
+                Command::ChooseRegister => {
+                    self.choosing_register = true;
+                    model.set_status("Choose a register (a-z)");
+                },
+                Command::Yank => {
+                    if let Some(values) = model.current_cell_values() {
+                        let name = self.registers.yank(values);
+                        model.set_status(format!("Yanked cell into register \"{}", name));
+                    }
+                },
+                Command::Put => {
+                    let (name, values) = self.registers.put();
+
+                    match values.cloned() {
+                        Some(values) => {
+                            let shared_model = self.shared_model.clone();
+                            let action = format!("Put register \"{}", name);
+                            let cb = Callback::from_fn_once(move |siv| {
+                                Self::set_target_values(siv, shared_model, action, values);
+                            });
+
+                            return EventResult::Consumed(Some(cb))
+                        },
+                        None => model.set_status(format!("Register \"{} is empty", name)),
+                    };
+                },
+                Command::NumberTracks => {
+                    let num_files = model.target_record_indices().len();
+                    let title = format!("Number {} Track(s)", num_files);
+                    let labels = vec![
+                        String::from("Pad with zeros"),
+                        String::from("Fill in TRACKTOTAL"),
+                        String::from("Fill in TOTALDISCS"),
+                    ];
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
+                        siv.add_layer(
+                            crate::views::checklist::make(&title, labels.clone(), "Number", move |siv, indices| {
+                                let numbering = Numbering {
+                                    zero_pad: indices.contains(&0),
+                                    fill_total: indices.contains(&1),
+                                    fill_disc_total: indices.contains(&2),
+                                };
+
+                                Self::run_bulk_edit(siv, shared_model.clone(), num_files, move |_, model| {
+                                    let (edited, total) = model.number_tracks(numbering);
+                                    model.set_status(format!("Numbered {} of {} record(s)", edited, total));
+                                });
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::RateUp | Command::RateDown => {
+                    let num_files = model.target_record_indices().len();
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn_once(move |siv| {
+                        Self::run_bulk_edit(siv, shared_model, num_files, move |_, model| {
+                            let (edited, total) = model.bump_rating(command == Command::RateUp);
+                            let rating_key = model.rating_key.clone();
+                            model.set_status(format!("Rated {} of {} record(s) ({})", edited, total, rating_key));
+                        });
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ReplayGain => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| crate::views::replay_gain::analyze(siv, shared_model.clone()));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::LookUpAlbum => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| crate::views::musicbrainz::look_up_album(siv, shared_model.clone()));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::TogglePlayback => {
+                    let file_path = model.current_record().map(|record| record.file_path.clone());
+
+                    if let Some(file_path) = file_path {
+                        let file_name = file_path.file_name().unwrap_or_default().to_string_lossy().into_owned();
+
+                        let message = match model.player.toggle(&file_path) {
+                            Ok(true) => format!("Playing {}", file_name),
+                            Ok(false) => String::from("Stopped playing"),
+                            Err(err) => format!("Unable to play {}: {}", file_name, err),
+                        };
+
+                        model.set_status(message);
+                    }
+                },
+                Command::RunHook => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| crate::views::hooks::choose(siv, shared_model.clone()));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::RunScript => {
+                    let names = model.scripts.keys().cloned().collect::<Vec<_>>();
+                    let num_files = model.target_record_indices().len();
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
+                        // Failures are shown in a dialog, as they leave the records untouched.
+                        crate::views::scripts::choose(siv, names.clone(), move |siv, name| {
+                            Self::run_bulk_edit(siv, shared_model.clone(), num_files, move |siv, model| {
+                                match model.run_script(&name) {
+                                    Ok((edited, total)) => model.set_status(format!("Script {}: {} of {} record(s) changed", name, edited, total)),
+                                    Err(err) => siv.add_layer(Dialog::info(format!("Unable to run script {}: {}", name, err))),
+                                }
+                            });
+                        });
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ApplyTagPreset => {
+                    let names = model.tag_presets.keys().cloned().collect::<Vec<_>>();
+                    let num_files = model.target_record_indices().len();
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
+                        crate::views::tag_presets::choose(siv, names.clone(), move |siv, name| {
+                            Self::run_bulk_edit(siv, shared_model.clone(), num_files, move |_, model| {
+                                let status = match model.apply_tag_preset(&name) {
+                                    Ok((edited, total)) => format!("Tag preset {}: {} of {} record(s) changed", name, edited, total),
+                                    Err(err) => format!("Unable to apply tag preset {}: {}", name, err),
+                                };
+
+                                model.set_status(status);
+                            });
+                        });
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ShowHistory => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| crate::views::history::show(siv, shared_model.clone()));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ShowSkipped => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| crate::views::skipped::show(siv, shared_model.clone()));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ShowLyrics => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| crate::views::lyrics::show(siv, shared_model.clone()));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::FillAlbumFields => {
+                    let fills = model.plan_album_fills();
+
+                    if fills.is_empty() {
+                        model.set_status("No album fields to fill in");
+                        return EventResult::Consumed(None)
+                    }
+
+                    let title = format!("Fill In {} Album Field(s)", fills.len());
+                    let labels = fills.iter().map(ToString::to_string).collect::<Vec<_>>();
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+                        let fills = fills.clone();
+
+                        siv.add_layer(
+                            crate::views::checklist::make(&title, labels.clone(), "Fill", move |_, indices| {
+                                let chosen = indices.into_iter().map(|i| fills[i].clone()).collect::<Vec<_>>();
+
+                                let mut model = shared_model.lock().unwrap();
+                                let edited = model.fill_album_fields(&chosen);
+                                model.set_status(format!("Filled in album fields on {} record(s)", edited));
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::CleanTags => {
+                    let num_files = model.target_record_indices().len();
+                    let title = format!("Clean Tags of {} File(s)", num_files);
+                    let labels = Cleanup::ALL.iter().map(|c| String::from(c.label())).collect::<Vec<_>>();
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
+                        siv.add_layer(
+                            crate::views::checklist::make(&title, labels.clone(), "Clean", move |siv, indices| {
+                                let cleanups = indices.into_iter().map(|i| Cleanup::ALL[i]).collect::<Vec<_>>();
+
+                                Self::run_bulk_edit(siv, shared_model.clone(), num_files, move |_, model| {
+                                    let (edited, total) = model.clean_tags(&cleanups);
+                                    model.set_status(format!("Cleaned tags of {} of {} record(s)", edited, total));
+                                });
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::StripTags => {
+                    let num_files = model.target_record_indices().len();
+                    let mut message = format!("Remove every tag of {} file(s)?", num_files);
+
+                    if !model.strip_keep.is_empty() {
+                        message.push_str(&format!("\n\nKept: {}", model.strip_keep.join(", ")));
+                    }
+
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
+                        siv.add_layer(
+                            crate::views::confirm::make("Strip Tags", message.clone(), "Strip", move |_| {
+                                let mut model = shared_model.lock().unwrap();
+                                let (edited, total) = model.strip_tags();
+                                model.set_status(format!("Stripped tags of {} of {} record(s)", edited, total));
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ConvertCase => {
+                    let num_files = model.column_target_indices().len();
+
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
+                        let mut cases = SelectView::new();
+                        for case in Case::ALL.iter() {
+                            cases.add_item(case.label(), *case);
+                        }
+
+                        cases.set_on_submit(move |siv, case: &Case| {
+                            let case = *case;
+                            siv.pop_layer();
+
+                            Self::run_bulk_edit(siv, shared_model.clone(), num_files, move |_, model| {
+                                let status = match model.convert_case(case) {
+                                    Some((edited, total)) => format!("Converted {} of {} record(s) to {}", edited, total, case.label()),
+                                    None => String::from("Only metadata columns can be converted"),
+                                };
+
+                                model.set_status(status);
+                            });
+                        });
+
+                        siv.add_layer(
+                            Dialog::around(cases)
+                            .title(format!("Change Case of {} File(s)", num_files))
+                            .dismiss_button("Cancel")
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ConvertToAscii => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| crate::views::transliterate::show(siv, shared_model.clone()));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::CopyTags => {
+                    let (_, source_index) = model.cursor.to_xy();
+
+                    let num_files =
+                        model.target_record_indices().into_iter()
+                        .filter(|&i| Some(i) != source_index)
+                        .count()
+                    ;
+
+                    let source_index = match source_index {
+                        Some(source_index) if num_files > 0 => source_index,
+                        _ => {
+                            model.set_status("Select records to copy tags to, then move to the record to copy from");
+                            return EventResult::Consumed(None)
+                        },
+                    };
+
+                    let mut meta_keys = model.data.records[source_index].meta_keys().map(String::from).collect::<Vec<_>>();
+                    meta_keys.sort();
+
+                    let labels =
+                        meta_keys.iter()
+                        .map(|meta_key| {
+                            let values = model.data.records[source_index].get_meta(meta_key).unwrap_or_default();
+                            format!("{}: {}", meta_key, values.join(FIELD_SEP_STR))
+                        })
+                        .collect::<Vec<_>>()
+                    ;
+
+                    let title = format!("Copy Tags to {} Record(s)", num_files);
+                    let source_path = model.data.records[source_index].file_path.clone();
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+                        let meta_keys = meta_keys.clone();
+                        let source_path = source_path.clone();
+
+                        siv.add_layer(
+                            crate::views::checklist::make(&title, labels.clone(), "Copy", move |siv, indices| {
+                                let chosen = indices.into_iter().map(|i| meta_keys[i].clone()).collect::<Vec<_>>();
+
+                                let source_path = source_path.clone();
+
+                                Self::run_bulk_edit(siv, shared_model.clone(), num_files, move |_, model| {
+                                    match model.copy_tags(&source_path, &chosen) {
+                                        Some((edited, total)) => model.set_status(format!("Copied {} tag(s) to {} of {} record(s)", chosen.len(), edited, total)),
+                                        None => model.set_status(format!("Unable to copy tags, {} is no longer loaded", source_path.display())),
+                                    }
+                                });
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::CycleScope => {
+                    let scope = model.scope.next();
+                    model.scope = scope;
+                    model.set_status(format!("Batch operations now apply to: {}", scope));
+                },
+                Command::SetValue => {
+                    if !matches!(model.current_column().map(|col| &col.key), Some(ColumnKey::Meta(..))) {
+                        model.set_status("Only metadata columns can be edited");
+                        return EventResult::Consumed(None)
+                    }
+
+                    let initial =
+                        model.current_cell_values()
+                        .flatten()
+                        .map(|values| values.join(FIELD_SEP_STR))
+                        .unwrap_or_default()
+                    ;
+
+                    let title = format!("Set Value for {} Record(s)", model.target_record_indices().len());
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
+                        siv.add_layer(
+                            crate::views::prompt::make(&title, &initial, move |siv, text| {
+                                // Multiple values are separated the same way they are displayed.
+                                let values = text.split(FIELD_SEP_STR).map(String::from).collect::<Vec<_>>();
+                                let values = Some(values).filter(|vs| vs.iter().any(|v| !v.is_empty()));
+
+                                Self::set_target_values(siv, shared_model.clone(), String::from("Set value"), values);
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::RemoveField => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| {
+                        Self::set_target_values(siv, shared_model.clone(), String::from("Remove field"), None);
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::AddField => {
+                    let num_files = model.target_record_indices().len();
+                    let title = format!("Add Field to {} Record(s)", num_files);
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
+                        siv.add_layer(
+                            crate::views::prompt::make(&title, "", move |siv, meta_key| {
+                                let shared_model = shared_model.clone();
+                                let meta_key = meta_key.to_string();
+                                let title = format!("Value of {}", meta_key);
+
+                                siv.add_layer(
+                                    crate::views::prompt::make(&title, "", move |siv, text| {
+                                        // Multiple values are separated the same way they are displayed.
+                                        let values = text.split(FIELD_SEP_STR).map(String::from).collect::<Vec<_>>();
+                                        let meta_key = meta_key.clone();
+
+                                        Self::run_bulk_edit(siv, shared_model.clone(), num_files, move |_, model| {
+                                            match model.add_field(&meta_key, values.clone()) {
+                                                Some((edited, total)) => model.set_status(format!("Add field: {} of {} record(s) changed", edited, total)),
+                                                None => model.set_status(format!("{} is not a valid field name", meta_key)),
+                                            };
+                                        });
+                                    })
+                                );
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::RenameKey => {
+                    let initial = match model.current_column().map(|col| &col.key) {
+                        Some(ColumnKey::Meta(meta_key)) => meta_key.clone(),
+                        _ => String::new(),
+                    };
+
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
+                        siv.add_layer(
+                            crate::views::prompt::make("Rename Field", &initial, move |siv, from| {
+                                let shared_model = shared_model.clone();
+                                let from = from.to_string();
+                                let title = format!("New Name for {}", from);
+
+                                siv.add_layer(
+                                    crate::views::prompt::make(&title, "", move |siv, to| {
+                                        Self::confirm_rename_key(siv, shared_model.clone(), from.clone(), to.to_string());
+                                    })
+                                );
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::Undo => {
+                    match model.undo() {
+                        Some(description) => model.set_status(format!("Undid: {}", description)),
+                        None => model.set_status("Nothing to undo"),
+                    };
+                },
+                Command::Redo => {
+                    match model.redo() {
+                        Some(description) => model.set_status(format!("Redid: {}", description)),
+                        None => model.set_status("Nothing to redo"),
+                    };
+                },
+                Command::ToggleSelection => {
+                    model.toggle_selection();
+                    model.move_cursor_down(1);
+                },
+                Command::SelectAll => {
+                    model.select_all();
+                },
+                Command::ClearSelection => {
+                    model.clear_selection();
+                },
+                Command::ImportPreset => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+                        siv.add_layer(
+                            crate::views::prompt::make("Import Preset", "preset.json", move |siv, path| {
+                                match Preset::read_from_path(Path::new(path)) {
+                                    Ok(preset) => preset.apply(&mut shared_model.lock().unwrap()),
+                                    Err(err) => {
+                                        let message = format!("Unable to import preset: {}", err);
+                                        siv.add_layer(Dialog::info(message));
+                                    },
+                                }
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::MoveUp => {
                     model.move_cursor_up(1);
                 },
-                Event::Key(Key::Down) => {
+                Command::MoveDown => {
                     model.move_cursor_down(1);
                 },
-                Event::Key(Key::Left) => {
+                Command::MoveLeft => {
                     model.move_cursor_left(1);
                 },
-                Event::Key(Key::Right) => {
+                Command::MoveRight => {
                     model.move_cursor_right(1);
                 },
-                Event::Key(Key::PageUp) => {
-                    model.move_cursor_up(10);
+                Command::PageUp => {
+                    let n = model.page_size();
+                    model.move_cursor_up(n);
+                },
+                Command::PageDown => {
+                    let n = model.page_size();
+                    model.move_cursor_down(n);
+                },
+                Command::HalfPageUp => {
+                    let n = model.half_page_size();
+                    model.move_cursor_up(n);
+                },
+                Command::HalfPageDown => {
+                    let n = model.half_page_size();
+                    model.move_cursor_down(n);
                 },
-                Event::Key(Key::PageDown) => {
-                    model.move_cursor_down(10);
+                Command::ToggleRowMode => {
+                    model.toggle_row_mode();
+                },
+                Command::ToggleInvisible => {
+                    model.toggle_show_invisible();
+                },
+                Command::ToggleFitWidth => {
+                    model.toggle_fit_width();
+
+                    if model.fit_width { model.set_status("Fitting columns to the view"); }
+                    else { model.set_status("Showing columns at full width"); }
+                },
+                Command::ToggleHidden => {
+                    model.toggle_show_hidden();
+
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn_once(move |siv| {
+                        crate::views::refresh_conflict::reload(siv, shared_model, false);
+                    });
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::ToggleDetail => {
+                    model.show_detail = !model.show_detail;
+                },
+                // Tabs are handled by the enclosing `TabsView`.
+                Command::NextTab | Command::PreviousTab | Command::OpenTab | Command::CompareTabs
+                | Command::BrowseDirectory | Command::QuickOpen | Command::ToggleBookmark | Command::ReloadConfig => {
+                    return EventResult::Ignored
+                },
+                Command::TypeAhead => {
+                    if model.type_ahead_column().is_some() {
+                        self.starting_type_ahead = true;
+                        model.set_status("Type to search the sorted column");
+                    }
+                    else { model.set_status("Type-ahead only searches the column the records are sorted by"); }
+                },
+                Command::NextMissing | Command::PreviousMissing => {
+                    if !model.jump_to_missing(command == Command::NextMissing) {
+                        model.set_status("No missing values in this column");
+                    }
+                },
+                Command::FindDuplicates => {
+                    let shared_model = self.shared_model.clone();
+                    let cb = Callback::from_fn(move |siv| crate::views::duplicates::show(siv, shared_model.clone()));
+
+                    return EventResult::Consumed(Some(cb))
+                },
+                Command::OpenCommandLine => {
+                    let shared_model = self.shared_model.clone();
+
+                    let cb = Callback::from_fn(move |siv| {
+                        let shared_model = shared_model.clone();
+
+                        siv.add_layer(
+                            crate::views::prompt::make("Command", "", move |_, text| {
+                                let mut model = shared_model.lock().unwrap();
+
+                                let result = LineCommand::parse(text).and_then(|lc| model.run_line_command(lc));
+
+                                if let Err(message) = result {
+                                    model.set_status(message);
+                                }
+                            })
+                        );
+                    });
+
+                    return EventResult::Consumed(Some(cb))
                 },
-                _ => return EventResult::Ignored,
             };
         }
 
-        self.scroll_view.scroll_to_important_area();
+        self.table.scroll_to_cursor();
 
         EventResult::Consumed(None)
 
-        // self.scroll_view.on_event(event)
     }
 
     fn take_focus(&mut self, source: Direction) -> bool {
-        self.scroll_view.take_focus(source)
+        self.table.take_focus(source)
     }
 }
@@ -0,0 +1,127 @@
+use std::cell::RefCell;
+use std::rc::Rc;
+
+use cursive::Cursive;
+use cursive::theme::BaseColor;
+use cursive::theme::Color;
+use cursive::traits::Nameable;
+use cursive::traits::Resizable;
+use cursive::utils::markup::StyledString;
+use cursive::views::Checkbox;
+use cursive::views::Dialog;
+use cursive::views::LinearLayout;
+use cursive::views::ScrollView;
+use cursive::views::TextView;
+use unicode_width::UnicodeWidthStr;
+
+/// The widest the before column gets, so that the after column stays visible.
+const MAX_BEFORE_WIDTH: usize = 48;
+
+/// One row of a preview, for one file.
+#[derive(Debug, Clone)]
+pub struct PreviewRow {
+    pub before: String,
+    /// What the file will become, or `None` if nothing can be done with it.
+    pub after: Option<String>,
+    /// Why the row needs a closer look, if it does.
+    pub warning: Option<String>,
+}
+
+fn checkbox_name(index: usize) -> String {
+    format!("template_preview_{}", index)
+}
+
+fn pad(text: &str, width: usize) -> String {
+    format!("{}{}", text, " ".repeat(width.saturating_sub(text.width())))
+}
+
+fn label(row: &PreviewRow, before_width: usize) -> StyledString {
+    let mut text = format!(" {} → {}", pad(&row.before, before_width), row.after.as_deref().unwrap_or("(unchanged)"));
+
+    match &row.warning {
+        Some(warning) => {
+            text.push_str(&format!("  ⚠ {}", warning));
+            StyledString::styled(text, Color::Light(BaseColor::Red))
+        },
+        None => StyledString::plain(text),
+    }
+}
+
+fn set_all(siv: &mut Cursive, checked: &RefCell<Vec<bool>>, rows: &[PreviewRow], value: bool) {
+    for (i, _) in rows.iter().enumerate().filter(|(_, row)| row.after.is_some()) {
+        siv.call_on_name(&checkbox_name(i), |view: &mut Checkbox| { view.set_checked(value); });
+        checked.borrow_mut()[i] = value;
+    }
+}
+
+/// Creates a dialog showing what a template would do to each file, as a table
+/// of before and after with a checkbox per row to leave files out. Rows with
+/// warnings are flagged, and rows that nothing can be done with cannot be
+/// checked. The dialog is dismissed before the confirm callback is called
+/// with the indices of the checked rows.
+pub fn make<F>(title: &str, rows: Vec<PreviewRow>, confirm_label: &str, on_confirm: F) -> Dialog
+where
+    F: Fn(&mut Cursive, Vec<usize>) + 'static,
+{
+    let checked = Rc::new(RefCell::new(rows.iter().map(|row| row.after.is_some()).collect::<Vec<_>>()));
+
+    let before_width = rows.iter().map(|row| row.before.width()).max().unwrap_or(0).min(MAX_BEFORE_WIDTH);
+
+    let mut list = LinearLayout::vertical();
+
+    for (i, row) in rows.iter().enumerate() {
+        let mut checkbox = Checkbox::new();
+
+        if row.after.is_some() {
+            let checked = checked.clone();
+            checkbox.set_checked(true);
+            checkbox.set_on_change(move |_, is_checked| { checked.borrow_mut()[i] = is_checked; });
+        }
+        else {
+            checkbox.disable();
+        }
+
+        list.add_child(
+            LinearLayout::horizontal()
+            .child(checkbox.with_name(checkbox_name(i)))
+            .child(TextView::new(label(row, before_width)))
+        );
+    }
+
+    let num_changes = rows.iter().filter(|row| row.after.is_some()).count();
+    let num_warnings = rows.iter().filter(|row| row.warning.is_some()).count();
+
+    let mut summary = format!("{} of {} file(s) will change", num_changes, rows.len());
+    if num_warnings > 0 {
+        summary.push_str(&format!(", {} flagged", num_warnings));
+    }
+
+    let layout =
+        LinearLayout::vertical()
+        .child(TextView::new(summary))
+        .child(TextView::new(" "))
+        .child(ScrollView::new(list).max_height(20))
+    ;
+
+    let rows = Rc::new(rows);
+    let (all_checked, all_rows) = (checked.clone(), rows.clone());
+    let (none_checked, none_rows) = (checked.clone(), rows);
+
+    Dialog::around(layout)
+    .title(title)
+    .button("All", move |siv| set_all(siv, &all_checked, &all_rows, true))
+    .button("None", move |siv| set_all(siv, &none_checked, &none_rows, false))
+    .button(confirm_label, move |siv| {
+        let indices =
+            checked.borrow().iter()
+            .enumerate()
+            .filter(|(_, &c)| c)
+            .map(|(i, _)| i)
+            .collect()
+        ;
+
+        siv.pop_layer();
+        on_confirm(siv, indices);
+    })
+    .dismiss_button("Cancel")
+}
@@ -0,0 +1,35 @@
+use cursive::Cursive;
+use cursive::traits::Nameable;
+use cursive::traits::Resizable;
+use cursive::views::Dialog;
+use cursive::views::TextArea;
+
+const TEXT_AREA_NAME: &str = "text_edit_area";
+
+/// Creates a dialog to edit text that spans lines, which grows with its
+/// content up to most of the screen. The dialog is dismissed before the
+/// confirm callback is called with the edited text.
+pub fn make<F>(title: &str, initial: &str, on_confirm: F) -> Dialog
+where
+    F: Fn(&mut Cursive, &str) + 'static,
+{
+    Dialog::around(
+        TextArea::new()
+        .content(initial)
+        .with_name(TEXT_AREA_NAME)
+        .min_size((48, 8))
+        .max_size((100, 30))
+    )
+    .title(title)
+    .button("OK", move |siv| {
+        let text =
+            siv.call_on_name(TEXT_AREA_NAME, |view: &mut TextArea| view.get_content().to_string())
+            .unwrap_or_default()
+        ;
+
+        siv.pop_layer();
+        on_confirm(siv, &text);
+    })
+    .dismiss_button("Cancel")
+    .padding_lrtb(1, 1, 1, 0)
+}
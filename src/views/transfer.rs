@@ -0,0 +1,84 @@
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::views::Dialog;
+
+use diargos_core::model::Model;
+
+/// Whether files are moved or copied.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Transfer {
+    Move,
+    Copy,
+}
+
+impl Transfer {
+    fn verb(&self) -> &'static str {
+        match self {
+            Self::Move => "Move",
+            Self::Copy => "Copy",
+        }
+    }
+
+    fn past_tense(&self) -> &'static str {
+        match self {
+            Self::Move => "Moved",
+            Self::Copy => "Copied",
+        }
+    }
+}
+
+/// Picks a directory with the file browser, starting from the one the current
+/// record is in, then moves or copies the files of the target records into it.
+pub fn show(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>, transfer: Transfer) {
+    let (file_paths, start) = {
+        let model = shared_model.lock().unwrap();
+
+        let file_paths =
+            model.target_record_indices().into_iter()
+            .map(|i| model.data.records[i].file_path.clone())
+            .collect::<Vec<_>>()
+        ;
+
+        let start = model.current_record().and_then(|r| r.file_path.parent()).map(PathBuf::from);
+
+        (file_paths, start)
+    };
+
+    if file_paths.is_empty() {
+        return siv.add_layer(Dialog::info(format!("No files to {}", transfer.verb().to_lowercase())));
+    }
+
+    let start = start.or_else(|| std::env::current_dir().ok()).unwrap_or_default();
+    let label = format!("{} Here", transfer.verb());
+
+    crate::views::file_browser::pick(siv, start, &label, move |siv, dest_dir| {
+        let failures = {
+            let mut model = shared_model.lock().unwrap();
+
+            let failures = match transfer {
+                Transfer::Move => model.move_files(&file_paths, &dest_dir),
+                Transfer::Copy => model.copy_files(&file_paths, &dest_dir),
+            };
+
+            model.set_status(format!(
+                "{} {} file(s) to {}",
+                transfer.past_tense(), file_paths.len() - failures.len(), dest_dir.display(),
+            ));
+
+            failures
+        };
+
+        if !failures.is_empty() {
+            let mut message = format!("Unable to {} {} file(s):", transfer.verb().to_lowercase(), failures.len());
+
+            for (path, err) in failures {
+                message.push_str(&format!("\n{}: {}", path.display(), err));
+            }
+
+            siv.add_layer(Dialog::info(message));
+        }
+    });
+}
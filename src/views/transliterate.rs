@@ -0,0 +1,69 @@
+use std::sync::Arc;
+use std::sync::Mutex;
+
+use cursive::Cursive;
+use cursive::views::Dialog;
+
+use diargos_core::consts::*;
+use diargos_core::data::ColumnKey;
+use diargos_core::data::InfoKind;
+use diargos_core::model::Model;
+use diargos_core::transform::lacks_ascii_spelling;
+use crate::views::template_preview::PreviewRow;
+
+/// Previews the ASCII spelling of the values in the current column, or of
+/// the file names when on the file name column, and applies the ones left
+/// checked.
+pub fn show(siv: &mut Cursive, shared_model: Arc<Mutex<Model>>) {
+    let on_file_names = {
+        let model = shared_model.lock().unwrap();
+        matches!(model.current_column().map(|c| &c.key), Some(ColumnKey::Info(InfoKind::FileName)))
+    };
+
+    if on_file_names {
+        let renames = shared_model.lock().unwrap().plan_ascii_renames();
+        return crate::views::rename::review(siv, shared_model, renames);
+    }
+
+    let (meta_key, changes, rows) = {
+        let model = shared_model.lock().unwrap();
+
+        let (meta_key, changes) = match model.plan_ascii_values() {
+            Some(plan) => plan,
+            None => return siv.add_layer(Dialog::info("Only metadata and file name columns can be converted to ASCII")),
+        };
+
+        let rows =
+            changes.iter()
+            .map(|(i, values)| {
+                let before = model.data.records[*i].get_meta(&meta_key).unwrap_or_default().join(FIELD_SEP_STR);
+
+                let warning =
+                    if lacks_ascii_spelling(&before) { Some(String::from("some characters have no ASCII spelling")) }
+                    else { None }
+                ;
+
+                PreviewRow { before, after: Some(values.join(FIELD_SEP_STR)), warning }
+            })
+            .collect::<Vec<_>>()
+        ;
+
+        (meta_key, changes, rows)
+    };
+
+    if rows.is_empty() {
+        return siv.add_layer(Dialog::info(format!("All {} values are already ASCII", meta_key)));
+    }
+
+    let title = format!("Convert {} to ASCII", meta_key);
+
+    siv.add_layer(
+        crate::views::template_preview::make(&title, rows, "Convert", move |_, indices| {
+            let changes = indices.into_iter().map(|i| changes[i].clone()).collect::<Vec<_>>();
+
+            let mut model = shared_model.lock().unwrap();
+            let edited = model.stage_values("Convert to ASCII", &meta_key, &changes);
+            model.set_status(format!("Converted {} record(s) to ASCII", edited));
+        })
+    );
+}